@@ -1291,6 +1291,10 @@ impl App {
                     kind: NodeKind::App { dep },
                     pos,
                     ws,
+                    id: None,
+                    args: None,
+                    env: Vec::new(),
+                    size: None,
                 }));
             }
             PaletteCmd::GoTo(id) => {
@@ -1322,6 +1326,10 @@ impl App {
                     kind: NodeKind::Volume,
                     pos,
                     ws,
+                    id: None,
+                    args: None,
+                    env: Vec::new(),
+                    size: None,
                 }));
             }
             PaletteCmd::AddBindMount => {
@@ -1330,6 +1338,10 @@ impl App {
                     kind: NodeKind::BindMount,
                     pos,
                     ws,
+                    id: None,
+                    args: None,
+                    env: Vec::new(),
+                    size: None,
                 }));
             }
             PaletteCmd::AddPort => {
@@ -1338,6 +1350,10 @@ impl App {
                     kind: NodeKind::Port,
                     pos,
                     ws,
+                    id: None,
+                    args: None,
+                    env: Vec::new(),
+                    size: None,
                 }));
             }
             PaletteCmd::AddNetwork => {
@@ -1346,6 +1362,10 @@ impl App {
                     kind: NodeKind::Network,
                     pos,
                     ws,
+                    id: None,
+                    args: None,
+                    env: Vec::new(),
+                    size: None,
                 }));
             }
             PaletteCmd::AddGateway => {
@@ -1354,6 +1374,10 @@ impl App {
                     kind: NodeKind::Gateway,
                     pos,
                     ws,
+                    id: None,
+                    args: None,
+                    env: Vec::new(),
+                    size: None,
                 }));
             }
             PaletteCmd::AddIroh => {
@@ -1362,6 +1386,10 @@ impl App {
                     kind: NodeKind::Iroh,
                     pos,
                     ws,
+                    id: None,
+                    args: None,
+                    env: Vec::new(),
+                    size: None,
                 }));
             }
             PaletteCmd::AddVeilid => {
@@ -1370,6 +1398,10 @@ impl App {
                     kind: NodeKind::Veilid,
                     pos,
                     ws,
+                    id: None,
+                    args: None,
+                    env: Vec::new(),
+                    size: None,
                 }));
             }
             PaletteCmd::AddNote => {
@@ -1378,6 +1410,10 @@ impl App {
                     kind: NodeKind::Note,
                     pos,
                     ws,
+                    id: None,
+                    args: None,
+                    env: Vec::new(),
+                    size: None,
                 }));
             }
             PaletteCmd::AddCapture => {
@@ -1386,6 +1422,10 @@ impl App {
                     kind: NodeKind::Capture,
                     pos,
                     ws,
+                    id: None,
+                    args: None,
+                    env: Vec::new(),
+                    size: None,
                 }));
             }
             PaletteCmd::AddApi => {
@@ -1394,6 +1434,10 @@ impl App {
                     kind: NodeKind::Api,
                     pos,
                     ws,
+                    id: None,
+                    args: None,
+                    env: Vec::new(),
+                    size: None,
                 }));
             }
             PaletteCmd::AddMidiIn => {
@@ -1402,6 +1446,10 @@ impl App {
                     kind: NodeKind::MidiIn,
                     pos,
                     ws,
+                    id: None,
+                    args: None,
+                    env: Vec::new(),
+                    size: None,
                 }));
             }
             PaletteCmd::AddHostService => {
@@ -1410,6 +1458,10 @@ impl App {
                     kind: NodeKind::HostService,
                     pos,
                     ws,
+                    id: None,
+                    args: None,
+                    env: Vec::new(),
+                    size: None,
                 }));
             }
             PaletteCmd::NewWorkspace => self.new_workspace(),
@@ -4462,6 +4514,9 @@ impl App {
                     format!("{} (compiling…)", node.name)
                 } else if running {
                     node.name.clone()
+                } else if let Some(exit) = node.exit.lock().unwrap().as_ref() {
+                    // How it ended: a status, what it trapped on, or a stop.
+                    format!("{} ({})", node.name, exit.summary())
                 } else if node.finished.load(Ordering::Relaxed) {
                     format!("{} (exited)", node.name)
                 } else if runnable {
@@ -5957,9 +6012,13 @@ impl ApplicationHandler for App {
                         self.duplicate_focused();
                         return;
                     }
-                    // Cmd/Ctrl+Z undoes the last mutation.
+                    // Cmd/Ctrl+Z undoes the last mutation; with Shift, redoes it.
                     if pressed && app_chord && code == KeyCode::KeyZ {
-                        self.conn.send(Command::Undo);
+                        self.conn.send(if self.mods.shift_key() {
+                            Command::Redo
+                        } else {
+                            Command::Undo
+                        });
                         return;
                     }
                     // The filesystem inspector is modal: Escape backs out of a
@@ -6163,11 +6222,14 @@ mod inspect_tests {
             running: Arc::new(AtomicBool::new(false)),
             kill: Arc::new(AtomicBool::new(false)),
             setup: std::sync::OnceLock::new(),
-            env: Vec::new(),
+            image_env: Vec::new(),
+            env: Default::default(),
             layers: Vec::new(),
             capture_src: wk_server::capture::new_src(),
             exec_permit: wk_server::exec::new_permit(true),
             fs_serve: wk_server::vfs::ProviderConn::new(),
+            stats: wk_server::stats::new_counters(),
+            exit: Arc::new(std::sync::Mutex::new(None)),
         })
     }
 
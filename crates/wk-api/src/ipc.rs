@@ -12,8 +12,9 @@
 //! the boundary — the caller already has filesystem access to the user's
 //! session — so every connection is served with the admin-token handle the
 //! listener was started with. The message loop itself is the transport-neutral
-//! [`serve_client`](crate::serve_client); a networked transport supplies its
-//! own (stricter) authority decision and reuses that loop.
+//! [`serve_client`](crate::serve_client); the networked transport
+//! ([`crate::tcp`]) supplies its own (stricter) authority decision and reuses
+//! that loop.
 
 use std::io::{self, BufReader};
 use std::os::unix::fs::PermissionsExt;
@@ -5,22 +5,32 @@
 //! A transport's job is only to produce an authenticated connection: accept a
 //! stream, decide which bearer token the connection holds, attach it to a
 //! handle ([`ServerHandle::with_token`]), and pass the stream halves here. The
-//! local Unix socket ([`ipc`]) is the first transport — its trust decision is
-//! "local = admin". A networked transport makes a different decision (a token
-//! presented and verified during its handshake) and reuses this loop
-//! unchanged; all actual authorization happens inside the handle/server
-//! against that token, never in the transport.
+//! local Unix socket ([`ipc`]) trusts its caller — "local = admin". The
+//! networked transport ([`tcp`], TCP + TLS) makes a different decision: the
+//! client presents a token during its handshake, which is verified and bound
+//! to the connection, and then reuses this loop unchanged. The WebSocket
+//! transport ([`ws`]) does the same with the upgrade request's bearer token,
+//! framing each message as one WebSocket message for browsers. All actual
+//! authorization happens inside the handle/server against that token, never
+//! in the transport.
 
-use std::io::{self, BufRead, Write};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
-use wk_protocol::ipc::{read_msg, write_msg, ClientMsg, ServerMsg};
+use wk_protocol::ipc::{
+    read_msg, write_msg_as, Auth, ByteEncoding, ClientMsg, Hello, ServerMsg, FS_CHUNK,
+};
+use wk_protocol::Command;
 use wk_server::runtime::ServerHandle;
 
 pub mod ipc;
+pub mod tcp;
+pub mod ws;
 
 /// A live terminal attach: the node id (so the UI-detach flag can be cleared)
 /// and the pump thread streaming its output to the client.
@@ -38,20 +48,265 @@ impl Attach {
         if let Some(t) = self.pump.take() {
             let _ = t.join();
         }
-        handle.set_attached(self.node, false);
+        let _ = handle.set_attached(self.node, false);
+    }
+}
+
+/// A background stream to the client — a log follow or an event
+/// subscription: its pump thread and stop flag.
+struct Pump {
+    stop: Arc<AtomicBool>,
+    pump: Option<JoinHandle<()>>,
+}
+
+impl Pump {
+    /// Stop the pump (for a subscription, dropping its receiver unsubscribes
+    /// it server-side).
+    fn end(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(t) = self.pump.take() {
+            let _ = t.join();
+        }
+    }
+}
+
+/// A program started by [`ClientMsg::Exec`]: the switch that kills it, its
+/// terminal (with `tty`), and the pump streaming its output.
+struct Exec {
+    kill: Arc<AtomicBool>,
+    term: Option<wk_server::terminal::SharedTermIo>,
+    pump: Pump,
+}
+
+impl Exec {
+    /// Stop its pump, then kill the program (a no-op once it has exited).
+    fn end(self) {
+        self.pump.end();
+        self.kill.store(true, Ordering::Relaxed);
+        if let Some(term) = &self.term {
+            term.close();
+        }
+    }
+}
+
+/// How many chunks an [`Upload`]'s pipe holds before the connection waits
+/// for the unpacking to catch up.
+const UPLOAD_BACKLOG: usize = 16;
+
+/// An archive the client is streaming in ([`ClientMsg::FsExtract`]): the pipe
+/// its chunks go down (`None` marks the end), and the thread unpacking them
+/// into the node as they come.
+struct Upload {
+    chunks: SyncSender<Option<Vec<u8>>>,
+    unpack: JoinHandle<Result<(), String>>,
+}
+
+impl Upload {
+    /// Start unpacking whatever arrives into the directory `dir` of `fs`.
+    fn start(fs: wk_server::vfs::SharedFs, dir: String) -> Upload {
+        let (chunks, rx) = mpsc::sync_channel(UPLOAD_BACKLOG);
+        let pipe = Pipe {
+            rx,
+            chunk: Vec::new(),
+            at: 0,
+            ended: false,
+        };
+        let unpack = thread::spawn(move || wk_server::vfs::access::extract(&fs, &dir, pipe));
+        Upload { chunks, unpack }
+    }
+
+    /// Pass on the next chunk. Once the unpacking has stopped (it failed, or
+    /// the archive's own end came first) chunks are dropped; its verdict comes
+    /// at [`Upload::finish`].
+    fn feed(&self, chunk: Vec<u8>) {
+        let _ = self.chunks.send(Some(chunk));
+    }
+
+    /// Mark the end and wait for the unpacking's verdict.
+    fn finish(self) -> Result<(), String> {
+        let _ = self.chunks.send(None);
+        drop(self.chunks);
+        self.unpack
+            .join()
+            .unwrap_or_else(|_| Err("unpacking the archive failed".into()))
+    }
+
+    /// Abandon it: the unpacking reads the archive as cut short.
+    fn end(self) {
+        drop(self.chunks);
+        let _ = self.unpack.join();
+    }
+}
+
+/// The read side of an [`Upload`]'s pipe. The archive ends at the client's
+/// [`ClientMsg::FsEnd`]; a pipe closed without one is an error.
+struct Pipe {
+    rx: Receiver<Option<Vec<u8>>>,
+    chunk: Vec<u8>,
+    at: usize,
+    ended: bool,
+}
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.at == self.chunk.len() {
+            if self.ended {
+                return Ok(0);
+            }
+            match self.rx.recv() {
+                Ok(Some(chunk)) => (self.chunk, self.at) = (chunk, 0),
+                Ok(None) => self.ended = true,
+                Err(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "the archive was cut short",
+                    ))
+                }
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.at);
+        buf[..n].copy_from_slice(&self.chunk[self.at..self.at + n]);
+        self.at += n;
+        Ok(n)
+    }
+}
+
+/// A writer sending what it's given as [`ServerMsg::FsChunk`]s of up to
+/// [`FS_CHUNK`] bytes — how an archive streams out as it's built. `flush`
+/// sends the last, short one. Once `stop` is set every write fails, which
+/// ends the archive early.
+struct Chunks<W> {
+    out: Out<W>,
+    buf: Vec<u8>,
+    stop: Arc<AtomicBool>,
+}
+
+impl<W: Write> Write for Chunks<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.stop.load(Ordering::Relaxed) {
+            return Err(io::Error::other("the download was stopped"));
+        }
+        let n = data.len().min(FS_CHUNK - self.buf.len());
+        self.buf.extend_from_slice(&data[..n]);
+        if self.buf.len() == FS_CHUNK {
+            self.flush()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        self.out
+            .send(ServerMsg::FsChunk(std::mem::take(&mut self.buf)))
+    }
+}
+
+/// What runs on one channel of a connection (see [`ClientMsg::On`]): at most
+/// one attach, one exec, one log follow, one subscription, one archive
+/// upload and one archive download.
+#[derive(Default)]
+struct Channel {
+    attach: Option<Attach>,
+    exec: Option<Exec>,
+    follow: Option<Pump>,
+    events: Option<Pump>,
+    upload: Option<Upload>,
+    download: Option<Pump>,
+}
+
+impl Channel {
+    /// End everything running on the channel.
+    fn end(self, handle: &ServerHandle) {
+        if let Some(a) = self.attach {
+            a.end(handle);
+        }
+        if let Some(e) = self.exec {
+            e.end();
+        }
+        if let Some(u) = self.upload {
+            u.end();
+        }
+        for p in [self.follow, self.events, self.download]
+            .into_iter()
+            .flatten()
+        {
+            p.end();
+        }
+    }
+
+    /// The terminal [`ClientMsg::Input`] and [`ClientMsg::Resize`] go to: the
+    /// attached node's, else an interactive exec's.
+    fn term(&self) -> Option<&wk_server::terminal::SharedTermIo> {
+        match (&self.attach, &self.exec) {
+            (Some(a), _) => Some(&a.term),
+            (None, Some(e)) => e.term.as_ref(),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Where one channel's messages go: the connection's writer, each message
+/// wrapped in [`ServerMsg::On`] unless it's the connection's own channel 0.
+/// `base64` is the connection's byte encoding, shared so a hello switches
+/// every pump's at once.
+struct Out<W> {
+    writer: Arc<Mutex<W>>,
+    channel: u32,
+    base64: Arc<AtomicBool>,
+}
+
+impl<W> Clone for Out<W> {
+    fn clone(&self) -> Self {
+        Out {
+            writer: self.writer.clone(),
+            channel: self.channel,
+            base64: self.base64.clone(),
+        }
+    }
+}
+
+impl<W: Write> Out<W> {
+    fn send(&self, msg: ServerMsg) -> io::Result<()> {
+        let msg = match self.channel {
+            0 => msg,
+            channel => ServerMsg::On {
+                channel,
+                msg: Box::new(msg),
+            },
+        };
+        let bytes = if self.base64.load(Ordering::Relaxed) {
+            ByteEncoding::Base64
+        } else {
+            ByteEncoding::Array
+        };
+        write_msg_as(&mut *self.writer.lock().unwrap(), &msg, bytes)
     }
 }
 
 /// Serve one connected client: read framed [`ClientMsg`]s and reply with
 /// [`ServerMsg`]s until the client disconnects. While attached to a node, a
 /// pump thread streams that node's terminal output; this loop feeds its input.
+/// Log follows and event subscriptions stream from pump threads too, so the
+/// loop keeps serving requests meanwhile.
+///
+/// A connection multiplexes independent channels (see [`ClientMsg::On`]),
+/// each with its own attach, log follow and subscription; plain requests use
+/// channel 0. So one connection can watch several nodes at once.
 ///
 /// `handle` carries the connection's authority (its bearer token) — every
 /// read and command is authorized against it by the server. The `writer` is
-/// shared behind a lock because the attach pump writes concurrently with this
-/// loop's replies; the transport hands both halves in.
+/// shared behind a lock because the pumps write concurrently with this loop's
+/// replies; the transport hands both halves in.
+///
+/// A client that says [`ClientMsg::Hello`] is told this server's version and
+/// features, and if its hello lists `base64` every byte payload after the
+/// reply is written as base64 (see [`ByteEncoding`]). A request the server
+/// can't parse — a variant from a newer client — is answered with
+/// [`ServerMsg::Error`] and the connection carries on.
 pub fn serve_client<R, W>(
-    handle: ServerHandle,
+    mut handle: ServerHandle,
     mut reader: R,
     writer: Arc<Mutex<W>>,
 ) -> io::Result<()>
@@ -59,116 +314,525 @@ where
     R: BufRead,
     W: Write + Send + 'static,
 {
-    let send =
-        |w: &Mutex<W>, m: &ServerMsg| -> io::Result<()> { write_msg(&mut *w.lock().unwrap(), m) };
-
-    let mut attach: Option<Attach> = None;
-    while let Some(msg) = read_msg::<_, ClientMsg>(&mut reader)? {
+    let mut channels: HashMap<u32, Channel> = HashMap::new();
+    let base64 = Arc::new(AtomicBool::new(false));
+    loop {
+        let msg = match read_msg::<_, ClientMsg>(&mut reader) {
+            Ok(Some(msg)) => msg,
+            Ok(None) => break,
+            // The bad line was consumed whole, so the stream is still framed.
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                let out = Out {
+                    writer: writer.clone(),
+                    channel: 0,
+                    base64: base64.clone(),
+                };
+                out.send(ServerMsg::Error(format!("unsupported request: {e}")))?;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let (channel, msg) = match msg {
+            ClientMsg::On { channel, msg } => (channel, *msg),
+            msg => (0, msg),
+        };
+        let out = Out {
+            writer: writer.clone(),
+            channel,
+            base64: base64.clone(),
+        };
         match msg {
+            ClientMsg::On { .. } => out.send(ServerMsg::Error("channels don't nest".into()))?,
+            ClientMsg::Hello(theirs) => {
+                out.send(ServerMsg::Hello(Hello::ours()))?;
+                let bytes = theirs.byte_encoding();
+                base64.store(bytes == ByteEncoding::Base64, Ordering::Relaxed);
+            }
             ClientMsg::GetSnapshot => match handle.snapshot() {
-                Ok(snap) => send(&writer, &ServerMsg::Snapshot(snap))?,
-                Err(e) => send(&writer, &ServerMsg::Error(e))?,
+                Ok(snap) => out.send(ServerMsg::Snapshot(snap))?,
+                Err(e) => out.send(ServerMsg::Error(e))?,
             },
-            ClientMsg::Command(cmd) => {
-                handle.send(cmd);
-                send(&writer, &ServerMsg::Ok)?;
-            }
-            ClientMsg::Attach { node } => {
-                if let Some(a) = attach.take() {
-                    a.end(&handle);
+            ClientMsg::History => match handle.history() {
+                Ok((undo, redo)) => out.send(ServerMsg::History { undo, redo })?,
+                Err(e) => out.send(ServerMsg::Error(e))?,
+            },
+            ClientMsg::Stats => match handle.stats() {
+                Ok(stats) => out.send(ServerMsg::Stats(stats))?,
+                Err(e) => out.send(ServerMsg::Error(e))?,
+            },
+            ClientMsg::Commit { node, tag } => match handle.commit(node, &tag) {
+                Ok(image) => out.send(ServerMsg::Committed(image))?,
+                Err(e) => out.send(ServerMsg::Error(e))?,
+            },
+            ClientMsg::Diff { node } => match handle.diff(node) {
+                Ok(changes) => out.send(ServerMsg::Changes(changes))?,
+                Err(e) => out.send(ServerMsg::Error(e))?,
+            },
+            msg @ (ClientMsg::FsList { .. }
+            | ClientMsg::FsStat { .. }
+            | ClientMsg::FsRead { .. }
+            | ClientMsg::FsWrite { .. }
+            | ClientMsg::FsMkdir { .. }
+            | ClientMsg::FsRemove { .. }) => match serve_fs(&handle, msg) {
+                Ok(reply) => out.send(reply)?,
+                Err(e) => out.send(ServerMsg::Error(e))?,
+            },
+            // Built and sent a chunk at a time on its own thread, so a tree
+            // costs one file's bytes, not the whole archive's, and the
+            // connection keeps serving meanwhile.
+            ClientMsg::FsArchive { node, path } => {
+                let ch = channels.entry(channel).or_default();
+                if let Some(d) = ch.download.take() {
+                    d.end();
                 }
-                match handle.term_io(node) {
-                    Some(term) if handle.set_attached(node, true) => {
-                        let (cols, rows) = term.size();
-                        send(&writer, &ServerMsg::Attached { cols, rows })?;
+                match handle.node_fs(node, wk_protocol::Action::Read) {
+                    Ok(fs) => {
                         let stop = Arc::new(AtomicBool::new(false));
-                        let pump = spawn_pump(term.clone(), writer.clone(), stop.clone());
-                        attach = Some(Attach {
-                            node,
-                            term,
+                        let pump = spawn_archive_pump(fs, path, out.clone(), stop.clone());
+                        ch.download = Some(Pump {
                             stop,
                             pump: Some(pump),
                         });
                     }
-                    Some(_) => {
-                        handle.set_attached(node, false);
-                        send(&writer, &ServerMsg::Error("node is not a terminal".into()))?;
+                    Err(e) => out.send(ServerMsg::Error(e))?,
+                }
+            }
+            ClientMsg::FsExtract { node, path } => {
+                let ch = channels.entry(channel).or_default();
+                if let Some(u) = ch.upload.take() {
+                    u.end();
+                }
+                match handle.node_fs(node, wk_protocol::Action::Update) {
+                    Ok(fs) => {
+                        ch.upload = Some(Upload::start(fs, path));
+                        out.send(ServerMsg::Ok)?;
                     }
-                    None => send(&writer, &ServerMsg::Error("no such node".into()))?,
+                    Err(e) => out.send(ServerMsg::Error(e))?,
+                }
+            }
+            ClientMsg::FsChunk(bytes) => {
+                if let Some(u) = channels.get(&channel).and_then(|ch| ch.upload.as_ref()) {
+                    u.feed(bytes);
+                }
+            }
+            ClientMsg::FsEnd => match channels.get_mut(&channel).and_then(|ch| ch.upload.take()) {
+                Some(u) => match u.finish() {
+                    Ok(()) => out.send(ServerMsg::Ok)?,
+                    Err(e) => out.send(ServerMsg::Error(e))?,
+                },
+                None => out.send(ServerMsg::Error("no archive is being sent".into()))?,
+            },
+            // A command waits for the server to apply it, so the reply is its
+            // verdict — refused by the token, or a batch rolled back — and for
+            // a create (or batch) the ids it made, for an undo/redo the step
+            // it reversed.
+            ClientMsg::Command(cmd) => {
+                let created = matches!(
+                    cmd,
+                    Command::Create(_)
+                        | Command::Duplicate(_)
+                        | Command::Batch(_)
+                        | Command::Undo
+                        | Command::Redo
+                );
+                match handle.request(cmd) {
+                    Ok(made) if created => out.send(ServerMsg::Created(made))?,
+                    Ok(_) => out.send(ServerMsg::Ok)?,
+                    Err(e) => out.send(ServerMsg::Error(e))?,
+                }
+            }
+            ClientMsg::Attach { node } => {
+                let ch = channels.entry(channel).or_default();
+                if let Some(a) = ch.attach.take() {
+                    a.end(&handle);
+                }
+                match handle.term_io(node) {
+                    Some(term) => match handle.set_attached(node, true) {
+                        Ok(()) => {
+                            let (cols, rows) = term.size();
+                            out.send(ServerMsg::Attached { cols, rows })?;
+                            let stop = Arc::new(AtomicBool::new(false));
+                            let pump = spawn_pump(term.clone(), None, out.clone(), stop.clone());
+                            ch.attach = Some(Attach {
+                                node,
+                                term,
+                                stop,
+                                pump: Some(pump),
+                            });
+                        }
+                        Err(e) => out.send(ServerMsg::Error(e))?,
+                    },
+                    None => out.send(ServerMsg::Error("no such node".into()))?,
                 }
             }
             ClientMsg::Input(bytes) => {
-                if let Some(a) = &attach {
-                    a.term.feed_in(&bytes);
+                if let Some(term) = channels.get(&channel).and_then(Channel::term) {
+                    term.feed_in(&bytes);
                 }
             }
             ClientMsg::Resize { cols, rows } => {
-                if let Some(a) = &attach {
-                    a.term.set_size(cols, rows);
+                if let Some(term) = channels.get(&channel).and_then(Channel::term) {
+                    term.set_size(cols, rows);
+                }
+            }
+            ClientMsg::Exec {
+                node,
+                path,
+                argv,
+                env,
+                tty,
+            } => {
+                let ch = channels.entry(channel).or_default();
+                if let Some(e) = ch.exec.take() {
+                    e.end();
+                }
+                match start_exec(&handle, node, &path, argv, env, tty, &out) {
+                    Ok(exec) => ch.exec = Some(exec),
+                    Err(e) => out.send(ServerMsg::Error(e))?,
                 }
             }
             ClientMsg::Detach => {
-                if let Some(a) = attach.take() {
-                    a.end(&handle);
+                if let Some(ch) = channels.get_mut(&channel) {
+                    if let Some(a) = ch.attach.take() {
+                        a.end(&handle);
+                    }
+                    if let Some(e) = ch.exec.take() {
+                        e.end();
+                    }
+                    for p in [ch.follow.take(), ch.download.take()].into_iter().flatten() {
+                        p.end();
+                    }
                 }
-                send(&writer, &ServerMsg::Detached)?;
+                out.send(ServerMsg::Detached)?;
             }
             ClientMsg::Logs { node, follow } => {
                 let Some(term) = handle.term_io(node) else {
-                    send(&writer, &ServerMsg::Error("no such node".into()))?;
+                    out.send(ServerMsg::Error("no such node".into()))?;
                     continue;
                 };
+                let ch = channels.entry(channel).or_default();
+                if let Some(f) = ch.follow.take() {
+                    f.end();
+                }
                 // Send the current scrollback, then either finish or follow.
-                let (bytes, mut cursor) = term.log_read(0);
+                let (bytes, cursor) = term.log_read(0);
                 if !bytes.is_empty() {
-                    send(&writer, &ServerMsg::LogChunk(bytes))?;
+                    out.send(ServerMsg::LogChunk(bytes))?;
                 }
                 if !follow {
-                    send(&writer, &ServerMsg::LogEnd)?;
+                    out.send(ServerMsg::LogEnd)?;
                     continue;
                 }
-                // Follow: poll for new output (non-destructive) until disconnect.
-                loop {
-                    let (chunk, next) = term.log_read(cursor);
-                    if !chunk.is_empty() {
-                        if send(&writer, &ServerMsg::LogChunk(chunk)).is_err() {
-                            break;
+                let stop = Arc::new(AtomicBool::new(false));
+                let pump = spawn_log_pump(term, cursor, out.clone(), stop.clone());
+                ch.follow = Some(Pump {
+                    stop,
+                    pump: Some(pump),
+                });
+            }
+            ClientMsg::Subscribe => {
+                let ch = channels.entry(channel).or_default();
+                if let Some(s) = ch.events.take() {
+                    s.end();
+                }
+                match handle.subscribe() {
+                    Ok((base, rx)) => {
+                        out.send(ServerMsg::Snapshot(base))?;
+                        let stop = Arc::new(AtomicBool::new(false));
+                        let pump = spawn_event_pump(rx, out.clone(), stop.clone());
+                        ch.events = Some(Pump {
+                            stop,
+                            pump: Some(pump),
+                        });
+                    }
+                    Err(e) => out.send(ServerMsg::Error(e))?,
+                }
+            }
+            ClientMsg::Authenticate(Auth { token }) => {
+                let token = wk_server::workspace::hex_bytes(token.trim())
+                    .ok_or_else(|| "the token is not hex".to_string())
+                    .and_then(|token| handle.verify_token(&token).map(|()| token));
+                match token {
+                    Ok(token) => {
+                        // What the old authority started doesn't outlive it.
+                        for (_, ch) in channels.drain() {
+                            ch.end(&handle);
                         }
-                        cursor = next;
-                    } else if term.is_closed() {
-                        let _ = send(&writer, &ServerMsg::LogEnd);
-                        break;
-                    } else {
-                        thread::sleep(Duration::from_millis(50));
+                        handle = handle.with_token(token);
+                        out.send(ServerMsg::Ok)?;
                     }
+                    Err(e) => out.send(ServerMsg::Error(e))?,
                 }
             }
         }
     }
     // Client disconnected — release any attach so the UI reclaims the node.
-    if let Some(a) = attach.take() {
-        a.end(&handle);
+    for (_, ch) in channels.drain() {
+        ch.end(&handle);
     }
     Ok(())
 }
 
+/// Answer one of the `Fs` requests against the node's filesystem (see
+/// [`wk_server::vfs::access`]). Reads need node read and writes node update;
+/// a provider mount on the path may block this connection for up to its
+/// call timeout, like a guest's own access would.
+fn serve_fs(handle: &ServerHandle, msg: ClientMsg) -> Result<ServerMsg, String> {
+    use wk_protocol::ipc::FsEntry;
+    use wk_protocol::Action;
+    use wk_server::vfs::{access, DirEntry};
+
+    let entry = |e: DirEntry| FsEntry {
+        name: e.name,
+        dir: e.is_dir,
+        size: e.size as u64,
+    };
+    match msg {
+        ClientMsg::FsList { node, path } => {
+            let fs = handle.node_fs(node, Action::Read)?;
+            let list = access::list(&fs, &path)?;
+            Ok(ServerMsg::FsEntries(list.into_iter().map(entry).collect()))
+        }
+        ClientMsg::FsStat { node, path } => {
+            let fs = handle.node_fs(node, Action::Read)?;
+            Ok(ServerMsg::FsEntry(entry(access::stat(&fs, &path)?)))
+        }
+        ClientMsg::FsRead { node, path } => {
+            let fs = handle.node_fs(node, Action::Read)?;
+            Ok(ServerMsg::FsData(access::read_file(&fs, &path)?))
+        }
+        ClientMsg::FsWrite { node, path, data } => {
+            let fs = handle.node_fs(node, Action::Update)?;
+            access::write_file(&fs, &path, &data).map(|()| ServerMsg::Ok)
+        }
+        ClientMsg::FsMkdir { node, path } => {
+            let fs = handle.node_fs(node, Action::Update)?;
+            access::mkdir(&fs, &path).map(|()| ServerMsg::Ok)
+        }
+        ClientMsg::FsRemove { node, path } => {
+            let fs = handle.node_fs(node, Action::Update)?;
+            access::remove(&fs, &path).map(|()| ServerMsg::Ok)
+        }
+        _ => Err("not a filesystem request".into()),
+    }
+}
+
+/// Start a [`ClientMsg::Exec`] and the pump that streams it: with `tty`, the
+/// attach pump on the program's own terminal, which carries its exit status
+/// in before the detach; without, [`spawn_exec_pump`] on its two pipes.
+fn start_exec<W: Write + Send + 'static>(
+    handle: &ServerHandle,
+    node: wk_protocol::NodeId,
+    path: &str,
+    argv: Vec<String>,
+    env: Vec<(String, String)>,
+    tty: bool,
+    out: &Out<W>,
+) -> Result<Exec, String> {
+    use wk_server::exec::ExecIo;
+    use wk_server::execpipe::Pipe;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    if tty {
+        let term = wk_server::terminal::TermIo::new();
+        let child = handle.exec(node, path, argv, env, ExecIo::Tty(term.clone()))?;
+        let kill = child.kill_switch();
+        let (cols, rows) = term.size();
+        out.send(ServerMsg::Attached { cols, rows })
+            .map_err(|e| e.to_string())?;
+        // Closing the terminal once the program is gone is what ends the
+        // pump, after it has drained the last of the output.
+        let (tx, rx) = std::sync::mpsc::channel();
+        let closer = term.clone();
+        thread::spawn(move || {
+            let _ = tx.send(exit_msg(child.wait()));
+            closer.close();
+        });
+        let pump = spawn_pump(term.clone(), Some(rx), out.clone(), stop.clone());
+        return Ok(Exec {
+            kill,
+            term: Some(term),
+            pump: Pump {
+                stop,
+                pump: Some(pump),
+            },
+        });
+    }
+    let (stdout, stderr) = (Pipe::new(), Pipe::new());
+    let readers = (stdout.reader(), stderr.reader());
+    let child = handle.exec(node, path, argv, env, ExecIo::Pipes { stdout, stderr })?;
+    out.send(ServerMsg::Ok).map_err(|e| e.to_string())?;
+    let kill = child.kill_switch();
+    let pump = spawn_exec_pump(child, readers, out.clone(), stop.clone());
+    Ok(Exec {
+        kill,
+        term: None,
+        pump: Pump {
+            stop,
+            pump: Some(pump),
+        },
+    })
+}
+
+/// How an exec'd program ended, as the message that says so.
+fn exit_msg(status: Result<wk_server::exec::Output, String>) -> ServerMsg {
+    match status {
+        Ok(output) => ServerMsg::Exited(output.exit_code),
+        Err(e) => ServerMsg::Error(e),
+    }
+}
+
+/// Stream a piped exec's stdout and stderr as [`ServerMsg::Stdout`] and
+/// [`ServerMsg::Stderr`] until both reach end-of-file, then its
+/// [`ServerMsg::Exited`]. Stopped early (the client detached or left), it
+/// drops the readers, so a program still writing sees a broken pipe.
+fn spawn_exec_pump<W: Write + Send + 'static>(
+    child: wk_server::plugin::Child,
+    (stdout, stderr): (
+        wk_server::execpipe::PipeReader,
+        wk_server::execpipe::PipeReader,
+    ),
+    out: Out<W>,
+    stop: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut open = [Some(stdout), Some(stderr)];
+        while open.iter().any(Option::is_some) {
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            let mut idle = true;
+            for (i, end) in open.iter_mut().enumerate() {
+                let Some(reader) = end else { continue };
+                match reader.try_read(64 * 1024) {
+                    Some(bytes) if bytes.is_empty() => {}
+                    Some(bytes) => {
+                        idle = false;
+                        let msg = if i == 0 {
+                            ServerMsg::Stdout(bytes)
+                        } else {
+                            ServerMsg::Stderr(bytes)
+                        };
+                        if out.send(msg).is_err() {
+                            return;
+                        }
+                    }
+                    None => *end = None,
+                }
+            }
+            if idle {
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        let _ = out.send(exit_msg(child.wait()));
+    })
+}
+
+/// Forward a subscription's events to the client as [`ServerMsg::Event`]
+/// until stopped or the client is gone. Polls `stop` between events so a
+/// quiet workspace can't keep the thread alive after a disconnect.
+fn spawn_event_pump<W: Write + Send + 'static>(
+    rx: Receiver<wk_protocol::ipc::Event>,
+    out: Out<W>,
+    stop: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            match rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(ev) => {
+                    if out.send(ServerMsg::Event(ev)).is_err() {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    })
+}
+
+/// Send the tree at `path` in `fs` as a tar archive in
+/// [`ServerMsg::FsChunk`]s, then [`ServerMsg::FsEnd`] — or
+/// [`ServerMsg::Error`] if it can't be read. Stopped, it sends nothing more.
+fn spawn_archive_pump<W: Write + Send + 'static>(
+    fs: wk_server::vfs::SharedFs,
+    path: String,
+    out: Out<W>,
+    stop: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let chunks = Chunks {
+            out: out.clone(),
+            buf: Vec::new(),
+            stop: stop.clone(),
+        };
+        let sent = wk_server::vfs::access::archive(&fs, &path, chunks)
+            .and_then(|mut rest| rest.flush().map_err(|e| e.to_string()));
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        let _ = match sent {
+            Ok(()) => out.send(ServerMsg::FsEnd),
+            Err(e) => out.send(ServerMsg::Error(e)),
+        };
+    })
+}
+
+/// Follow a node's log from `cursor`: poll for new output (non-destructive)
+/// and send it as [`ServerMsg::LogChunk`]s until stopped, the client is gone,
+/// or the node exits — then [`ServerMsg::LogEnd`].
+fn spawn_log_pump<W: Write + Send + 'static>(
+    term: wk_server::terminal::SharedTermIo,
+    mut cursor: u64,
+    out: Out<W>,
+    stop: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            let (chunk, next) = term.log_read(cursor);
+            if !chunk.is_empty() {
+                if out.send(ServerMsg::LogChunk(chunk)).is_err() {
+                    break;
+                }
+                cursor = next;
+            } else if term.is_closed() {
+                let _ = out.send(ServerMsg::LogEnd);
+                break;
+            } else {
+                thread::sleep(Duration::from_millis(50));
+            }
+        }
+    })
+}
+
 /// Stream a node's terminal output to the client until stopped or the node
 /// exits. Drains `term` and writes [`ServerMsg::Term`]; on node exit sends
-/// [`ServerMsg::Detached`] so the client returns to its shell.
+/// [`ServerMsg::Detached`] so the client returns to its shell. An exec'd
+/// program's terminal passes `exit`, whose message (sent before the terminal
+/// closes) goes just ahead of the detach.
 fn spawn_pump<W: Write + Send + 'static>(
     term: wk_server::terminal::SharedTermIo,
-    writer: Arc<Mutex<W>>,
+    exit: Option<Receiver<ServerMsg>>,
+    out: Out<W>,
     stop: Arc<AtomicBool>,
 ) -> JoinHandle<()> {
     thread::spawn(move || {
         while !stop.load(Ordering::Relaxed) {
-            let out = term.drain_out();
-            if !out.is_empty() {
-                if write_msg(&mut *writer.lock().unwrap(), &ServerMsg::Term(out)).is_err() {
+            let drained = term.drain_out();
+            if !drained.is_empty() {
+                if out.send(ServerMsg::Term(drained)).is_err() {
                     break;
                 }
             } else if term.is_closed() {
-                let _ = write_msg(&mut *writer.lock().unwrap(), &ServerMsg::Detached);
+                // Whatever was written just before the close.
+                let rest = term.drain_out();
+                if !rest.is_empty() {
+                    let _ = out.send(ServerMsg::Term(rest));
+                }
+                if let Some(msg) = exit.as_ref().and_then(|rx| rx.try_recv().ok()) {
+                    let _ = out.send(msg);
+                }
+                let _ = out.send(ServerMsg::Detached);
                 break;
             } else {
                 thread::sleep(Duration::from_millis(10));
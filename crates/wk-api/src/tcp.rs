@@ -0,0 +1,443 @@
+//! The **networked transport**: the client API over TCP + TLS, so a laptop can
+//! drive a headless `wk run` on another machine (`wk run --listen`, then
+//! `wk --remote host:port --token <hex>`).
+//!
+//! Unlike the local socket ([`crate::ipc`]), reaching the port proves nothing,
+//! so nothing is trusted by default. The trust decision is the handshake: TLS
+//! first (the server's certificate authenticates it to the client; the channel
+//! keeps the token and traffic private), then the client's first line is an
+//! [`Auth`] carrying the Biscuit it bears. The listener verifies the token
+//! against the server's public key, binds it to the connection with
+//! [`ServerHandle::with_token`], and hands the stream to the transport-neutral
+//! [`serve_client`](crate::serve_client) — from there every read and command
+//! is authorized against that token, exactly as on any other transport. A bad
+//! token is refused with [`ServerMsg::Error`] and the connection closed.
+//!
+//! A TLS session is one stateful object, but `serve_client` reads on its own
+//! thread while pumps (attach, events) write on theirs. [`TlsStream`] shares
+//! the session behind a lock and reads with a short socket timeout, releasing
+//! the lock between attempts so a writer never waits on an idle reader.
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, ConnectionCommon, ServerConfig, ServerConnection};
+use rustls::{SideData, StreamOwned};
+use wk_protocol::ipc::{write_msg, Auth, ServerMsg};
+use wk_server::runtime::ServerHandle;
+
+/// How long a reader holds the session before letting a writer in.
+const POLL: Duration = Duration::from_millis(20);
+
+/// How long a peer gets to finish TLS and present (or accept) a token.
+const HANDSHAKE: Duration = Duration::from_secs(10);
+
+/// One TLS session over TCP, shared by every thread serving (or driving) the
+/// connection: clones are handles to the same session, each usable as a
+/// reader or writer. See the module docs for why reads poll.
+pub struct TlsStream<C> {
+    inner: Arc<Mutex<StreamOwned<C, TcpStream>>>,
+    /// This handle's read timeout (see [`Self::set_read_timeout`]).
+    timeout: Option<Duration>,
+}
+
+impl<C> Clone for TlsStream<C> {
+    fn clone(&self) -> Self {
+        TlsStream {
+            inner: self.inner.clone(),
+            timeout: self.timeout,
+        }
+    }
+}
+
+impl<C, S> TlsStream<C>
+where
+    C: DerefMut + Deref<Target = ConnectionCommon<S>>,
+    S: SideData,
+{
+    /// Share an established session (socket read timeout set to [`POLL`]).
+    fn new(stream: StreamOwned<C, TcpStream>) -> io::Result<Self> {
+        stream.sock.set_read_timeout(Some(POLL))?;
+        Ok(TlsStream {
+            inner: Arc::new(Mutex::new(stream)),
+            timeout: None,
+        })
+    }
+
+    /// Give up a read on this handle after `timeout` with no data (`None`,
+    /// the default, waits indefinitely) — `UnixStream::set_read_timeout` for a
+    /// shared session, per handle so a writer's clone is unaffected.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+}
+
+impl<C, S> Read for TlsStream<C>
+where
+    C: DerefMut + Deref<Target = ConnectionCommon<S>>,
+    S: SideData,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = Instant::now();
+        loop {
+            let res = self.inner.lock().unwrap().read(buf);
+            match res {
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    if self.timeout.is_some_and(|t| start.elapsed() >= t) {
+                        return Err(e);
+                    }
+                    // Let a waiting writer take the session before retrying.
+                    thread::sleep(Duration::from_millis(1));
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+impl<C, S> Write for TlsStream<C>
+where
+    C: DerefMut + Deref<Target = ConnectionCommon<S>>,
+    S: SideData,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.lock().unwrap().flush()
+    }
+}
+
+/// A client's session with a remote server (what [`connect`] returns).
+pub type ClientStream = TlsStream<ClientConnection>;
+
+/// Read one newline-terminated line byte by byte, so nothing past it is
+/// consumed from the session (the handshake reads its line before the
+/// message loop's own buffered reader takes over).
+fn read_line<R: Read>(r: &mut R) -> io::Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+    while r.read(&mut byte)? == 1 {
+        if byte[0] == b'\n' {
+            return String::from_utf8(line)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+        line.push(byte[0]);
+    }
+    Err(io::ErrorKind::UnexpectedEof.into())
+}
+
+/// The crypto provider for both sides. ring explicitly: more than one provider
+/// may be linked into the binary, which makes rustls's process default
+/// ambiguous.
+fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// A server TLS config from a PEM certificate chain and private key.
+pub fn server_config(cert: &Path, key: &Path) -> Result<Arc<ServerConfig>, String> {
+    let chain = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("{}: {e}", cert.display()))?;
+    let key = PrivateKeyDer::from_pem_file(key).map_err(|e| format!("{}: {e}", key.display()))?;
+    let config = ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_no_client_auth()
+        .with_single_cert(chain, key)
+        .map_err(|e| format!("tls certificate: {e}"))?;
+    Ok(Arc::new(config))
+}
+
+/// A client TLS config trusting exactly the certificates in the PEM file `ca`
+/// — the server's own self-signed certificate, or the CA that issued it.
+pub fn client_config(ca: &Path) -> Result<Arc<ClientConfig>, String> {
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(ca).map_err(|e| format!("{}: {e}", ca.display()))? {
+        let cert = cert.map_err(|e| format!("{}: {e}", ca.display()))?;
+        roots
+            .add(cert)
+            .map_err(|e| format!("{}: {e}", ca.display()))?;
+    }
+    if roots.is_empty() {
+        return Err(format!("{}: no certificates", ca.display()));
+    }
+    let config = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+/// Make sure a certificate + key exist at `cert`/`key`, generating a
+/// self-signed pair valid for `names` (host names or IP addresses) if either
+/// is missing. The certificate is what a client passes as `--ca`. An existing
+/// pair is left alone, so clients that copied it keep trusting the server
+/// across restarts.
+pub fn ensure_self_signed(cert: &Path, key: &Path, names: Vec<String>) -> Result<(), String> {
+    if cert.exists() && key.exists() {
+        return Ok(());
+    }
+    let mut params = rcgen::CertificateParams::new(names).map_err(|e| e.to_string())?;
+    params.extended_key_usages = vec![rcgen::ExtendedKeyUsagePurpose::ServerAuth];
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, "wk server");
+    let pair = rcgen::KeyPair::generate().map_err(|e| e.to_string())?;
+    let signed = params.self_signed(&pair).map_err(|e| e.to_string())?;
+    std::fs::write(cert, signed.pem()).map_err(|e| format!("{}: {e}", cert.display()))?;
+    std::fs::write(key, pair.serialize_pem()).map_err(|e| format!("{}: {e}", key.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(key, std::fs::Permissions::from_mode(0o600));
+    }
+    Ok(())
+}
+
+/// A running TCP + TLS listener. Drop or [`shutdown`](Self::shutdown) to stop
+/// accepting (connections already served run until their clients leave).
+pub struct TcpServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TcpServer {
+    /// Listen on `addr` (e.g. `0.0.0.0:7070`; port 0 picks a free one) and
+    /// serve each client that completes the handshake. `handle` must carry no
+    /// token of its own: each connection is served with the one it presents.
+    pub fn start(
+        handle: ServerHandle,
+        addr: &str,
+        tls: Arc<ServerConfig>,
+    ) -> io::Result<TcpServer> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        listener.set_nonblocking(true)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("wk-tcp".into())
+                .spawn(move || accept_loop(listener, handle, tls, stop))?
+        };
+        Ok(TcpServer {
+            addr,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// The bound address (with the real port, if 0 was asked for).
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stop accepting new connections.
+    pub fn shutdown(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(t) = self.thread.take() {
+            let _ = t.join();
+        }
+    }
+}
+
+impl Drop for TcpServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Accept connections until stopped, each handshaken and served on its own
+/// thread. Non-blocking, like the local socket's loop, to poll `stop`.
+fn accept_loop(
+    listener: TcpListener,
+    handle: ServerHandle,
+    tls: Arc<ServerConfig>,
+    stop: Arc<AtomicBool>,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                let (handle, tls) = (handle.clone(), tls.clone());
+                let _ = thread::Builder::new()
+                    .name("wk-tcp-conn".into())
+                    .spawn(move || {
+                        if let Err(e) = serve_conn(stream, handle, tls) {
+                            if e.kind() != io::ErrorKind::UnexpectedEof {
+                                eprintln!("wk: remote client {peer}: {e}");
+                            }
+                        }
+                    });
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(20));
+            }
+            Err(e) => {
+                eprintln!("wk: tcp accept error: {e}");
+                break;
+            }
+        }
+    }
+}
+
+/// Handshake one accepted connection (TLS, then the [`Auth`] line), then run
+/// the message loop bearing the presented token.
+fn serve_conn(stream: TcpStream, handle: ServerHandle, tls: Arc<ServerConfig>) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(HANDSHAKE))?;
+    let conn = ServerConnection::new(tls).map_err(io::Error::other)?;
+    let mut session = StreamOwned::new(conn, stream);
+
+    let token = serde_json::from_str::<Auth>(&read_line(&mut session)?)
+        .map_err(|e| format!("malformed handshake: {e}"))
+        .and_then(|auth| {
+            wk_server::workspace::hex_bytes(auth.token.trim())
+                .ok_or_else(|| "the token is not hex".to_string())
+        })
+        .and_then(|token| handle.verify_token(&token).map(|()| token));
+    let token = match token {
+        Ok(token) => token,
+        Err(e) => {
+            let _ = write_msg(&mut session, &ServerMsg::Error(e));
+            session.conn.send_close_notify();
+            let _ = session.flush();
+            return Ok(());
+        }
+    };
+    write_msg(&mut session, &ServerMsg::Ok)?;
+
+    let shared = TlsStream::new(session)?;
+    let reader = io::BufReader::new(shared.clone());
+    let writer = Arc::new(Mutex::new(shared));
+    crate::serve_client(handle.with_token(token), reader, writer)
+}
+
+/// Connect to a server's TCP listener at `addr` (`host:port`), verify it with
+/// `tls`, and present `token` (hex). Returns the session, ready for
+/// [`wk_protocol::ipc`] messages, or why the server refused it.
+pub fn connect(addr: &str, token: &str, tls: Arc<ClientConfig>) -> Result<ClientStream, String> {
+    // The certificate is checked against the host part of the address.
+    let host = addr
+        .rsplit_once(':')
+        .map(|(host, _)| host)
+        .unwrap_or(addr)
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let name = ServerName::try_from(host.to_string()).map_err(|e| format!("{host}: {e}"))?;
+    let stream = TcpStream::connect(addr).map_err(|e| format!("can't reach {addr}: {e}"))?;
+    stream.set_nodelay(true).map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(HANDSHAKE))
+        .map_err(|e| e.to_string())?;
+    let conn = ClientConnection::new(tls, name).map_err(|e| e.to_string())?;
+    let mut session = StreamOwned::new(conn, stream);
+
+    let auth = Auth {
+        token: token.trim().to_string(),
+    };
+    write_msg(&mut session, &auth).map_err(|e| format!("tls handshake with {addr}: {e}"))?;
+    let reply = read_line(&mut session).map_err(|e| format!("tls handshake with {addr}: {e}"))?;
+    match serde_json::from_str::<ServerMsg>(&reply).map_err(|e| e.to_string())? {
+        ServerMsg::Ok => TlsStream::new(session).map_err(|e| e.to_string()),
+        ServerMsg::Error(e) => Err(format!("{addr} refused the token: {e}")),
+        other => Err(format!("unexpected handshake reply: {other:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+    use wk_protocol::ipc::{read_msg, ClientMsg};
+    use wk_server::runtime::ServerRuntime;
+    use wk_server::workspace::{bytes_hex, Document};
+    use wk_token_service::TokenService;
+
+    /// A served listener with a fresh self-signed certificate, and the client
+    /// config trusting it.
+    fn listen(tag: &str, tokens: &TokenService) -> (ServerRuntime, TcpServer, Arc<ClientConfig>) {
+        let dir = std::env::temp_dir().join(format!("wk-tcp-{tag}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("workspace.wk");
+        std::fs::write(
+            &path,
+            "// vim: set filetype=kdl :\nworkspace \"01KXKGZ000000000000000WS00\" {\n}\n",
+        )
+        .unwrap();
+        let doc = Document::load_resolved(&path).unwrap();
+        let runtime = ServerRuntime::spawn(
+            &doc,
+            path,
+            tokens.public_key(),
+            tokens.mint_node_base().unwrap(),
+        )
+        .unwrap();
+        let (cert, key) = (dir.join("cert.pem"), dir.join("key.pem"));
+        ensure_self_signed(&cert, &key, vec!["localhost".into()]).unwrap();
+        let server = TcpServer::start(
+            runtime.handle(),
+            "127.0.0.1:0",
+            server_config(&cert, &key).unwrap(),
+        )
+        .unwrap();
+        (runtime, server, client_config(&cert).unwrap())
+    }
+
+    /// A token the server's key didn't sign is refused at the handshake; a
+    /// read-only one connects, reads, and is still refused writes.
+    #[test]
+    fn the_handshake_binds_the_presented_token() {
+        let tokens = TokenService::new();
+        let (runtime, server, tls) = listen("handshake", &tokens);
+        let addr = format!("localhost:{}", server.local_addr().port());
+
+        let stranger = bytes_hex(&TokenService::new().mint_admin().unwrap());
+        let err = connect(&addr, &stranger, tls.clone())
+            .err()
+            .expect("refused");
+        assert!(err.contains("refused the token"), "{err}");
+
+        let reader = tokens
+            .mint(&[(
+                wk_protocol::ResourceKind::Document,
+                wk_protocol::Action::Read,
+            )])
+            .unwrap();
+        let mut conn = connect(&addr, &bytes_hex(&reader), tls).expect("connects");
+        write_msg(&mut conn, &ClientMsg::GetSnapshot).unwrap();
+        let mut r = BufReader::new(conn.clone());
+        match read_msg::<_, ServerMsg>(&mut r).unwrap() {
+            Some(ServerMsg::Snapshot(s)) => assert_eq!(s.workspaces.len(), 1),
+            other => panic!("expected a snapshot, got {other:?}"),
+        }
+        let create = wk_protocol::Command::Create(wk_protocol::Resource::Workspace {
+            id: wk_protocol::NodeId::new(),
+        });
+        write_msg(&mut conn, &ClientMsg::Command(create)).unwrap();
+        match read_msg::<_, ServerMsg>(&mut r).unwrap() {
+            Some(ServerMsg::Error(e)) => assert!(e.contains("does not grant"), "{e}"),
+            other => panic!("expected a refusal, got {other:?}"),
+        }
+        server.shutdown();
+        runtime.shutdown();
+    }
+}
@@ -0,0 +1,345 @@
+//! The **WebSocket transport**: the client API for browser tooling and
+//! dashboards (`wk run --ws 7071`). A page can list nodes, subscribe to
+//! events, stream `Logs` and drive `Attach` sessions with nothing but the
+//! browser's `WebSocket`.
+//!
+//! It's the same protocol, message for message: each WebSocket text message
+//! carries exactly one [`ClientMsg`](wk_protocol::ipc::ClientMsg) or
+//! [`ServerMsg`] as JSON — the line the other transports frame with a newline.
+//! The adapters below turn messages into those lines and back, so
+//! [`serve_client`](crate::serve_client) runs unchanged.
+//!
+//! The listener binds like a HostPort — a bare port means `127.0.0.1` — and
+//! trusts nobody for reaching it (any web page may open a WebSocket to
+//! localhost). A connection bears a token, exactly as a node's `api:1337`
+//! connection bears the node's: the upgrade request presents it as
+//! `Authorization: Bearer <hex>`, or as `?token=<hex>` since a browser can't
+//! set headers on a WebSocket. An invalid token fails the upgrade with `401`;
+//! a valid one is bound to the connection with [`ServerHandle::with_token`]
+//! and authorizes every request from then on.
+
+use std::io::{self, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::{Message, WebSocket};
+use wk_server::runtime::ServerHandle;
+
+/// How long a reader holds the socket before letting a writer in (see
+/// [`crate::tcp`], which shares its session the same way).
+const POLL: Duration = Duration::from_millis(20);
+
+/// How long a peer gets to complete the upgrade.
+const HANDSHAKE: Duration = Duration::from_secs(10);
+
+type Shared = Arc<Mutex<WebSocket<TcpStream>>>;
+
+/// The token an upgrade request presents: its `Authorization: Bearer`
+/// header, else a `token` query parameter.
+fn bearer(req: &Request) -> Option<String> {
+    let header = req
+        .headers()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|t| t.trim().to_string());
+    header.or_else(|| {
+        req.uri().query().and_then(|q| {
+            q.split('&')
+                .find_map(|kv| kv.strip_prefix("token="))
+                .map(str::to_string)
+        })
+    })
+}
+
+/// Reads a connection's messages as newline-terminated lines.
+struct WsReader {
+    ws: Shared,
+    line: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for WsReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.line.len() {
+            let msg = self.ws.lock().unwrap().read();
+            let data = match msg {
+                Ok(Message::Text(t)) => t.as_str().as_bytes().to_vec(),
+                Ok(Message::Binary(b)) => b.to_vec(),
+                // Pings are answered by tungstenite itself.
+                Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_)) => continue,
+                Ok(Message::Close(_))
+                | Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                    return Ok(0)
+                }
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    // Let a waiting writer take the socket before retrying.
+                    thread::sleep(Duration::from_millis(1));
+                    continue;
+                }
+                Err(tungstenite::Error::Io(e)) => return Err(e),
+                Err(e) => return Err(io::Error::other(e)),
+            };
+            self.line = data;
+            self.line.push(b'\n');
+            self.pos = 0;
+        }
+        let n = buf.len().min(self.line.len() - self.pos);
+        buf[..n].copy_from_slice(&self.line[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Sends each complete line written to it as one text message.
+struct WsWriter {
+    ws: Shared,
+    pending: Vec<u8>,
+}
+
+impl Write for WsWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).take(end).collect();
+            let text = String::from_utf8(line).map_err(io::Error::other)?;
+            self.ws
+                .lock()
+                .unwrap()
+                .send(Message::text(text))
+                .map_err(io::Error::other)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A running WebSocket listener. Drop or [`shutdown`](Self::shutdown) to stop
+/// accepting (connections already served run until their clients leave).
+pub struct WsServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WsServer {
+    /// Listen on `addr` — `host:port`, or a bare port on `127.0.0.1` — and
+    /// serve each client whose upgrade presents a valid token. `handle` must
+    /// carry no token of its own.
+    pub fn start(handle: ServerHandle, addr: &str) -> io::Result<WsServer> {
+        let listener = match addr.parse::<u16>() {
+            Ok(port) => TcpListener::bind(("127.0.0.1", port))?,
+            Err(_) => TcpListener::bind(addr)?,
+        };
+        let addr = listener.local_addr()?;
+        listener.set_nonblocking(true)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("wk-ws".into())
+                .spawn(move || accept_loop(listener, handle, stop))?
+        };
+        Ok(WsServer {
+            addr,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// The bound address (with the real port, if 0 was asked for).
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stop accepting new connections.
+    pub fn shutdown(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(t) = self.thread.take() {
+            let _ = t.join();
+        }
+    }
+}
+
+impl Drop for WsServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Accept connections until stopped, each upgraded and served on its own
+/// thread. Non-blocking, like the other transports' loops, to poll `stop`.
+fn accept_loop(listener: TcpListener, handle: ServerHandle, stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                let handle = handle.clone();
+                let _ = thread::Builder::new()
+                    .name("wk-ws-conn".into())
+                    .spawn(move || {
+                        if let Err(e) = serve_conn(stream, handle) {
+                            if e.kind() != io::ErrorKind::UnexpectedEof {
+                                eprintln!("wk: websocket client {peer}: {e}");
+                            }
+                        }
+                    });
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(20));
+            }
+            Err(e) => {
+                eprintln!("wk: websocket accept error: {e}");
+                break;
+            }
+        }
+    }
+}
+
+/// Upgrade one connection, checking its bearer token, then run the message
+/// loop bearing it.
+fn serve_conn(stream: TcpStream, handle: ServerHandle) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(HANDSHAKE))?;
+
+    let mut token = None;
+    // The callback's signature is tungstenite's.
+    #[allow(clippy::result_large_err)]
+    let check = |req: &Request, resp: Response| -> Result<Response, ErrorResponse> {
+        let bytes = bearer(req)
+            .ok_or_else(|| "a bearer token is required".to_string())
+            .and_then(|hex| {
+                wk_server::workspace::hex_bytes(&hex)
+                    .ok_or_else(|| "the token is not hex".to_string())
+            })
+            .and_then(|bytes| handle.verify_token(&bytes).map(|()| bytes));
+        match bytes {
+            Ok(bytes) => {
+                token = Some(bytes);
+                Ok(resp)
+            }
+            Err(e) => {
+                let mut refusal = ErrorResponse::new(Some(e));
+                *refusal.status_mut() = tungstenite::http::StatusCode::UNAUTHORIZED;
+                Err(refusal)
+            }
+        }
+    };
+    let ws = tungstenite::accept_hdr(stream, check).map_err(|e| io::Error::other(e.to_string()))?;
+    let Some(token) = token else {
+        return Ok(());
+    };
+    ws.get_ref().set_read_timeout(Some(POLL))?;
+
+    let ws: Shared = Arc::new(Mutex::new(ws));
+    let reader = BufReader::new(WsReader {
+        ws: ws.clone(),
+        line: Vec::new(),
+        pos: 0,
+    });
+    let writer = Arc::new(Mutex::new(WsWriter {
+        ws,
+        pending: Vec::new(),
+    }));
+    crate::serve_client(handle.with_token(token), reader, writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tungstenite::client::IntoClientRequest;
+    use wk_protocol::ipc::{ClientMsg, ServerMsg};
+    use wk_server::runtime::ServerRuntime;
+    use wk_server::workspace::{bytes_hex, Document};
+    use wk_token_service::TokenService;
+
+    /// Open a WebSocket to `addr`, presenting `token` as a bearer header.
+    fn open(addr: SocketAddr, token: &str) -> Result<WebSocket<TcpStream>, tungstenite::Error> {
+        let mut req = format!("ws://{addr}/").into_client_request()?;
+        req.headers_mut()
+            .insert("Authorization", format!("Bearer {token}").parse().unwrap());
+        let stream = TcpStream::connect(addr)?;
+        tungstenite::client(req, stream)
+            .map(|(ws, _)| ws)
+            .map_err(|e| match e {
+                tungstenite::HandshakeError::Failure(e) => e,
+                tungstenite::HandshakeError::Interrupted(_) => {
+                    tungstenite::Error::Io(io::ErrorKind::WouldBlock.into())
+                }
+            })
+    }
+
+    /// Send one message and read the reply, each as a text message.
+    fn ask(ws: &mut WebSocket<TcpStream>, msg: &ClientMsg) -> ServerMsg {
+        ws.send(Message::text(serde_json::to_string(msg).unwrap()))
+            .unwrap();
+        match ws.read().unwrap() {
+            Message::Text(t) => serde_json::from_str(t.as_str()).unwrap(),
+            other => panic!("expected a text message, got {other:?}"),
+        }
+    }
+
+    /// A missing or foreign token fails the upgrade; a valid one is bound to
+    /// the connection — reads work, writes it doesn't grant are refused —
+    /// with one JSON message per WebSocket message.
+    #[test]
+    fn a_bearer_token_authorizes_the_websocket() {
+        let dir = std::env::temp_dir().join(format!("wk-ws-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("workspace.wk");
+        std::fs::write(
+            &path,
+            "// vim: set filetype=kdl :\nworkspace \"01KXKGZ000000000000000WS00\" {\n}\n",
+        )
+        .unwrap();
+        let doc = Document::load_resolved(&path).unwrap();
+        let tokens = TokenService::new();
+        let runtime = ServerRuntime::spawn(
+            &doc,
+            path,
+            tokens.public_key(),
+            tokens.mint_node_base().unwrap(),
+        )
+        .unwrap();
+        let server = WsServer::start(runtime.handle(), "0").unwrap();
+        let addr = server.local_addr();
+
+        let stranger = bytes_hex(&TokenService::new().mint_admin().unwrap());
+        assert!(open(addr, &stranger).is_err());
+        assert!(open(addr, "").is_err());
+
+        let reader = tokens
+            .mint(&[(
+                wk_protocol::ResourceKind::Document,
+                wk_protocol::Action::Read,
+            )])
+            .unwrap();
+        let mut ws = open(addr, &bytes_hex(&reader)).expect("upgrades");
+        match ask(&mut ws, &ClientMsg::GetSnapshot) {
+            ServerMsg::Snapshot(s) => assert_eq!(s.workspaces.len(), 1),
+            other => panic!("expected a snapshot, got {other:?}"),
+        }
+        let create = wk_protocol::Command::Create(wk_protocol::Resource::Workspace {
+            id: wk_protocol::NodeId::new(),
+        });
+        match ask(&mut ws, &ClientMsg::Command(create)) {
+            ServerMsg::Error(e) => assert!(e.contains("does not grant"), "{e}"),
+            other => panic!("expected a refusal, got {other:?}"),
+        }
+        server.shutdown();
+        runtime.shutdown();
+    }
+}
@@ -3,13 +3,14 @@
 
 use std::io::BufReader;
 use std::os::unix::net::UnixStream;
-use std::time::{Duration, Instant};
+use std::path::PathBuf;
+use std::time::Duration;
 
 use wk_api::ipc::{socket_path, IpcServer};
-use wk_protocol::ipc::{read_msg, write_msg, ClientMsg, ServerMsg};
+use wk_protocol::ipc::{read_msg, write_msg, Auth, ClientMsg, Event, Hello, ServerMsg};
 use wk_protocol::{Command, NodeKind, Resource};
 use wk_server::runtime::ServerRuntime;
-use wk_server::workspace::Document;
+use wk_server::workspace::{bytes_hex, Document};
 use wk_token_service::TokenService;
 
 fn snapshot(stream: &mut UnixStream) -> wk_protocol::ipc::Snapshot {
@@ -21,10 +22,12 @@ fn snapshot(stream: &mut UnixStream) -> wk_protocol::ipc::Snapshot {
     }
 }
 
-#[test]
-fn cli_reads_snapshot_and_applies_a_command() {
-    // A minimal one-workspace document written to a temp file.
-    let dir = std::env::temp_dir().join(format!("wk-ipc-test-{}", std::process::id()));
+/// Start a server with an admin-token CLI socket on a minimal one-workspace
+/// document written to a fresh temp dir (named by `tag`, so tests in this
+/// file don't share a socket). Also returns the token service, for minting
+/// narrower tokens.
+fn start_server(tag: &str) -> (PathBuf, PathBuf, ServerRuntime, IpcServer, TokenService) {
+    let dir = std::env::temp_dir().join(format!("wk-ipc-{tag}-{}", std::process::id()));
     std::fs::create_dir_all(&dir).unwrap();
     let path = dir.join("workspace.wk");
     std::fs::write(
@@ -44,6 +47,12 @@ fn cli_reads_snapshot_and_applies_a_command() {
     .unwrap();
     let handle = runtime.handle().with_token(tokens.mint_admin().unwrap());
     let ipc = IpcServer::start(handle, &path).unwrap();
+    (dir, path, runtime, ipc, tokens)
+}
+
+#[test]
+fn cli_reads_snapshot_and_applies_a_command() {
+    let (dir, path, runtime, ipc, _) = start_server("roundtrip");
 
     // Connect the way the CLI does: compute the socket path from the workspace.
     let sock = socket_path(&path);
@@ -54,34 +63,251 @@ fn cli_reads_snapshot_and_applies_a_command() {
     assert_eq!(snap.nodes.len(), 0, "empty workspace");
     let ws = snap.workspaces[0];
 
-    // Add a note node, exactly as the UI's palette would.
+    // Add a note node, exactly as the UI's palette would, sized up front.
     write_msg(
         &mut stream,
         &ClientMsg::Command(Command::Create(Resource::Node {
             kind: NodeKind::Note,
             pos: [10.0, 20.0],
             ws,
+            id: None,
+            args: None,
+            env: Vec::new(),
+            size: Some([240.0, 160.0]),
         })),
     )
     .unwrap();
+    // A create is acked once applied, with the id it made — no polling.
     let mut r = BufReader::new(stream.try_clone().unwrap());
+    let id = match read_msg::<_, ServerMsg>(&mut r).unwrap().unwrap() {
+        ServerMsg::Created(made) => {
+            assert_eq!(made.nodes.len(), 1, "one node created");
+            made.nodes[0]
+        }
+        other => panic!("expected Created, got {other:?}"),
+    };
+
+    let snap = snapshot(&mut stream);
+    let note = snap
+        .nodes
+        .iter()
+        .find(|n| n.id == id)
+        .expect("the reported id is live");
+    assert_eq!(note.kind, "note");
+    assert_eq!(note.pos, [10.0, 20.0]);
+    assert_eq!(note.size, [240.0, 160.0]);
+    assert_eq!(note.ws, ws);
+
+    ipc.shutdown();
+    runtime.shutdown();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// A subscribed client gets a baseline snapshot, then a pushed event for a
+/// change another connection makes — no polling.
+#[test]
+fn subscriber_is_pushed_a_created_node() {
+    let (dir, path, runtime, ipc, _) = start_server("events");
+    let sock = socket_path(&path);
+
+    let mut sub = UnixStream::connect(&sock).unwrap();
+    write_msg(&mut sub, &ClientMsg::Subscribe).unwrap();
+    let mut events = BufReader::new(sub.try_clone().unwrap());
+    let ws = match read_msg::<_, ServerMsg>(&mut events).unwrap().unwrap() {
+        ServerMsg::Snapshot(s) => s.workspaces[0],
+        other => panic!("expected the baseline snapshot, got {other:?}"),
+    };
+
+    let mut other = UnixStream::connect(&sock).unwrap();
+    write_msg(
+        &mut other,
+        &ClientMsg::Command(Command::Create(Resource::Node {
+            kind: NodeKind::Note,
+            pos: [0.0, 0.0],
+            ws,
+            id: None,
+            args: None,
+            env: Vec::new(),
+            size: None,
+        })),
+    )
+    .unwrap();
+
+    sub.set_read_timeout(Some(Duration::from_secs(3))).unwrap();
+    match read_msg::<_, ServerMsg>(&mut events).unwrap().unwrap() {
+        ServerMsg::Event(Event::NodeCreated(n)) => assert_eq!(n.kind, "note"),
+        other => panic!("expected a NodeCreated event, got {other:?}"),
+    }
+
+    ipc.shutdown();
+    runtime.shutdown();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// An admin-socket client can drop to a scoped token: a foreign one is
+/// refused (and the connection keeps its authority), a valid one then
+/// decides what the connection may do.
+#[test]
+fn authenticate_narrows_the_admin_socket() {
+    let (dir, path, runtime, ipc, tokens) = start_server("authenticate");
+    let mut stream = UnixStream::connect(socket_path(&path)).unwrap();
+    let mut r = BufReader::new(stream.try_clone().unwrap());
+    let mut ask = |stream: &mut UnixStream, msg: ClientMsg| {
+        write_msg(stream, &msg).unwrap();
+        read_msg::<_, ServerMsg>(&mut r).unwrap().unwrap()
+    };
+    let create = || {
+        ClientMsg::Command(Command::Create(Resource::Workspace {
+            id: wk_protocol::NodeId::new(),
+        }))
+    };
+
+    let stranger = bytes_hex(&TokenService::new().mint_admin().unwrap());
+    match ask(
+        &mut stream,
+        ClientMsg::Authenticate(Auth { token: stranger }),
+    ) {
+        ServerMsg::Error(e) => assert!(e.contains("invalid token"), "{e}"),
+        other => panic!("expected a refusal, got {other:?}"),
+    }
+    assert!(matches!(ask(&mut stream, create()), ServerMsg::Created(_)));
+
+    let reader = tokens
+        .mint(&[(
+            wk_protocol::ResourceKind::Document,
+            wk_protocol::Action::Read,
+        )])
+        .unwrap();
+    let token = bytes_hex(&reader);
     assert!(matches!(
-        read_msg::<_, ServerMsg>(&mut r).unwrap().unwrap(),
+        ask(&mut stream, ClientMsg::Authenticate(Auth { token })),
         ServerMsg::Ok
     ));
+    match ask(&mut stream, ClientMsg::GetSnapshot) {
+        ServerMsg::Snapshot(s) => assert_eq!(s.workspaces.len(), 2),
+        other => panic!("expected a snapshot, got {other:?}"),
+    }
+    match ask(&mut stream, create()) {
+        ServerMsg::Error(e) => assert!(e.contains("does not grant"), "{e}"),
+        other => panic!("expected a refusal, got {other:?}"),
+    }
+
+    ipc.shutdown();
+    runtime.shutdown();
+    let _ = std::fs::remove_dir_all(&dir);
+}
 
-    // The server applies commands on its tick; poll the snapshot until it lands.
-    let deadline = Instant::now() + Duration::from_secs(3);
-    let note = loop {
-        let snap = snapshot(&mut stream);
-        if let Some(n) = snap.nodes.iter().find(|n| n.kind == "note") {
-            break n.clone();
+/// A client's hello is answered with the server's version and features; a
+/// request the server can't parse is refused without losing the connection.
+#[test]
+fn hello_negotiates_and_unknown_requests_are_refused() {
+    use std::io::Write;
+
+    let (dir, path, runtime, ipc, _) = start_server("hello");
+    let mut stream = UnixStream::connect(socket_path(&path)).unwrap();
+    let mut r = BufReader::new(stream.try_clone().unwrap());
+
+    write_msg(&mut stream, &ClientMsg::Hello(Hello::ours())).unwrap();
+    match read_msg::<_, ServerMsg>(&mut r).unwrap().unwrap() {
+        ServerMsg::Hello(h) => {
+            assert_eq!(h.version, wk_protocol::ipc::PROTOCOL_VERSION);
+            assert!(h.supports("logs"), "{h:?}");
+            assert_eq!(h.byte_encoding(), wk_protocol::ipc::ByteEncoding::Base64);
         }
-        assert!(Instant::now() < deadline, "note never appeared");
-        std::thread::sleep(Duration::from_millis(20));
+        other => panic!("expected Hello, got {other:?}"),
+    }
+
+    // What a newer client might send.
+    stream
+        .write_all(b"{\"Teleport\":{\"to\":\"mars\"}}\n")
+        .unwrap();
+    match read_msg::<_, ServerMsg>(&mut r).unwrap().unwrap() {
+        ServerMsg::Error(e) => assert!(e.contains("unsupported request"), "{e}"),
+        other => panic!("expected a refusal, got {other:?}"),
+    }
+    assert_eq!(snapshot(&mut stream).workspaces.len(), 1);
+
+    ipc.shutdown();
+    runtime.shutdown();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// One connection, several channels: a subscription streams on its channel
+/// while plain requests and other channels are answered alongside it, each
+/// reply tagged with the channel that asked.
+#[test]
+fn channels_multiplex_one_connection() {
+    let (dir, path, runtime, ipc, _) = start_server("channels");
+    let mut stream = UnixStream::connect(socket_path(&path)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(3)))
+        .unwrap();
+    let mut r = BufReader::new(stream.try_clone().unwrap());
+    let on = |channel, msg| ClientMsg::On {
+        channel,
+        msg: Box::new(msg),
     };
-    assert_eq!(note.pos, [10.0, 20.0]);
-    assert_eq!(note.ws, ws);
+    let mut next = || read_msg::<_, ServerMsg>(&mut r).unwrap().unwrap();
+
+    write_msg(&mut stream, &on(1, ClientMsg::Subscribe)).unwrap();
+    let ws = match next() {
+        ServerMsg::On { channel: 1, msg } => match *msg {
+            ServerMsg::Snapshot(s) => s.workspaces[0],
+            other => panic!("expected the baseline snapshot, got {other:?}"),
+        },
+        other => panic!("expected channel 1's snapshot, got {other:?}"),
+    };
+
+    // A plain create is answered untagged; the subscription sees it on 1.
+    write_msg(
+        &mut stream,
+        &ClientMsg::Command(Command::Create(Resource::Node {
+            kind: NodeKind::Note,
+            pos: [0.0, 0.0],
+            ws,
+            id: None,
+            args: None,
+            env: Vec::new(),
+            size: None,
+        })),
+    )
+    .unwrap();
+    let (mut created, mut event) = (None, false);
+    while created.is_none() || !event {
+        match next() {
+            ServerMsg::Created(made) => created = Some(made.nodes[0]),
+            ServerMsg::On { channel: 1, msg } => {
+                assert!(matches!(*msg, ServerMsg::Event(Event::NodeCreated(_))));
+                event = true;
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    // Another channel's request is answered on that channel.
+    let note = created.unwrap();
+    write_msg(
+        &mut stream,
+        &on(
+            2,
+            ClientMsg::Logs {
+                node: note,
+                follow: true,
+            },
+        ),
+    )
+    .unwrap();
+    match next() {
+        ServerMsg::On { channel: 2, msg } => {
+            assert!(matches!(*msg, ServerMsg::Error(ref e) if e == "no such node"))
+        }
+        other => panic!("expected channel 2's refusal, got {other:?}"),
+    }
+    write_msg(&mut stream, &on(3, on(4, ClientMsg::GetSnapshot))).unwrap();
+    match next() {
+        ServerMsg::On { channel: 3, msg } => assert!(matches!(*msg, ServerMsg::Error(_))),
+        other => panic!("expected channel 3's refusal, got {other:?}"),
+    }
 
     ipc.shutdown();
     runtime.shutdown();
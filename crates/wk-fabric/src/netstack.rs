@@ -66,6 +66,19 @@ fn queue() -> Queue {
 pub struct VirtualNic {
     rx: Queue,
     tx: Queue,
+    /// What the hub has moved through this NIC so far.
+    pub traffic: Traffic,
+}
+
+/// Packets and bytes a [`VirtualNic`] has carried each way, counted as the hub
+/// moves them — what `wk stats` shows per node. A frame the node sends to
+/// itself (loopback) counts both ways, like on a real interface.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Traffic {
+    pub rx_packets: u64,
+    pub rx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_bytes: u64,
 }
 
 impl VirtualNic {
@@ -73,13 +86,19 @@ impl VirtualNic {
         VirtualNic {
             rx: queue(),
             tx: queue(),
+            traffic: Traffic::default(),
         }
     }
     /// Take everything this NIC has transmitted (for the hub to route).
-    fn drain_tx(&self) -> Vec<Frame> {
-        self.tx.lock().unwrap().drain(..).collect()
+    fn drain_tx(&mut self) -> Vec<Frame> {
+        let frames: Vec<Frame> = self.tx.lock().unwrap().drain(..).collect();
+        self.traffic.tx_packets += frames.len() as u64;
+        self.traffic.tx_bytes += frames.iter().map(|f| f.len() as u64).sum::<u64>();
+        frames
     }
-    fn deliver(&self, frame: Frame) {
+    fn deliver(&mut self, frame: Frame) {
+        self.traffic.rx_packets += 1;
+        self.traffic.rx_bytes += frame.len() as u64;
         self.rx.lock().unwrap().push_back(frame);
     }
 }
@@ -215,6 +234,12 @@ impl NodeStack {
         self.wakers.push(w);
     }
 
+    /// Sockets a live wasi resource still owns (not the ones draining after
+    /// a close).
+    pub fn open_sockets(&self) -> usize {
+        self.live.len()
+    }
+
     /// Record a freshly added socket handle as live (owned by a wasi resource),
     /// returning the generation to stamp on the owner and any derived streams.
     pub fn track(&mut self, h: SocketHandle) -> u64 {
@@ -651,6 +676,12 @@ mod tests {
             std::thread::sleep(Duration::from_millis(1));
         }
         assert_eq!(&got, b"hello v6 net");
+
+        // Each side's NIC counted the other's packets, payload included.
+        let c = client.lock().unwrap().device.traffic;
+        let s = server.lock().unwrap().device.traffic;
+        assert!(c.tx_bytes > 12 && s.rx_bytes > 12, "{c:?} {s:?}");
+        assert!(c.tx_packets > 0 && c.rx_packets > 0 && s.tx_packets > 0);
     }
 
     /// A v4-only listener (the family-scoped endpoints of `listen_endpoints`)
@@ -9,8 +9,26 @@
 //! - [`ClientMsg`]: what a client asks — read a [`Snapshot`], apply a
 //!   [`Command`] (the same vocabulary the UI uses), or attach to a node's
 //!   terminal and stream its I/O.
-//! - [`ServerMsg`]: the replies — a snapshot, an ack/error, or terminal bytes
-//!   while attached.
+//! - [`ServerMsg`]: the replies — a snapshot, an ack/error (or the ids a
+//!   create made), terminal bytes while attached, or [`Event`]s while
+//!   subscribed.
+//!
+//! The same streams run over a network transport (TCP + TLS), where the
+//! connection first presents its token as an [`Auth`] line.
+//!
+//! A client opens with [`ClientMsg::Hello`] and the server answers with its
+//! own [`Hello`]: the [`PROTOCOL_VERSION`] it speaks and the [`FEATURES`] it
+//! serves. Messages only ever grow by adding variants, so a client checks the
+//! server's features before sending a newer one — and can tell its user the
+//! server is too old, rather than have the connection die on a request the
+//! server can't parse. (A server that can't parse a request anyway refuses it
+//! with [`ServerMsg::Error`] and keeps the connection.)
+//!
+//! Byte payloads — terminal output, log chunks, keyboard input — are JSON
+//! number arrays by default, three to four times the size of the bytes. When
+//! both hellos list `base64`, each side writes them as base64 strings instead
+//! (see [`ByteEncoding`]); a reader takes either form, so the switch needs no
+//! synchronising, and a line stays one readable message.
 //!
 //! [`Snapshot`] is a plain-data projection of the server's live view: unlike the
 //! server's internal `View` (which holds shared runtime handles), it is
@@ -19,9 +37,150 @@
 
 use std::io::{self, BufRead, Write};
 
-use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Command, NodeId, RestartPolicy, Wire};
+
+/// The wire protocol's version. It changes only when an existing message
+/// changes meaning or shape; new messages are [`FEATURES`] instead.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// The additive parts of the protocol this build serves, by name — what a
+/// [`Hello`] advertises. Each names the requests it covers:
+///
+/// - `logs`: [`ClientMsg::Logs`].
+/// - `subscribe`: [`ClientMsg::Subscribe`] and its [`Event`]s.
+/// - `history`: [`ClientMsg::History`], [`Command::Redo`], and a [`Created`]
+///   reply to an undo or redo.
+/// - `authenticate`: [`ClientMsg::Authenticate`].
+/// - `channels`: [`ClientMsg::On`] and [`ServerMsg::On`].
+/// - `fs`: [`ClientMsg::FsList`] and the other `Fs` requests.
+/// - `exec`: [`ClientMsg::Exec`].
+/// - `stats`: [`ClientMsg::Stats`].
+/// - `restart`: [`crate::NodePatch::restart`] (an older server ignores it).
+/// - `limits`: [`crate::NodePatch::limits`] (an older server ignores it).
+/// - `env`: [`crate::NodePatch::env`] (an older server ignores it).
+/// - `commit`: [`ClientMsg::Commit`].
+/// - `diff`: [`ClientMsg::Diff`].
+/// - `base64`: byte payloads as base64 strings ([`ByteEncoding::Base64`]).
+///   Unlike the others it is a mode, not a request: it applies only when
+///   *both* hellos list it.
+pub const FEATURES: &[&str] = &[
+    "logs",
+    "subscribe",
+    "history",
+    "authenticate",
+    "channels",
+    "base64",
+    "fs",
+    "exec",
+    "stats",
+    "restart",
+    "limits",
+    "env",
+    "commit",
+    "diff",
+];
+
+/// Each side's opening message: the protocol version it speaks and the
+/// features it supports (see [`FEATURES`]).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hello {
+    pub version: u32,
+    pub features: Vec<String>,
+}
+
+impl Hello {
+    /// This build's hello.
+    pub fn ours() -> Hello {
+        Hello {
+            version: PROTOCOL_VERSION,
+            features: FEATURES.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    /// Whether the peer that sent this supports `feature`.
+    pub fn supports(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+
+    /// How to write byte payloads to the peer that sent this, given that
+    /// this side is the build [`Hello::ours`] describes.
+    pub fn byte_encoding(&self) -> ByteEncoding {
+        if self.supports("base64") {
+            ByteEncoding::Base64
+        } else {
+            ByteEncoding::Array
+        }
+    }
+}
+
+/// How [`write_msg_as`] writes a message's byte payloads ([`ClientMsg::Input`],
+/// [`ServerMsg::Term`], [`ServerMsg::LogChunk`], and the file bytes of the `Fs`
+/// messages). [`read_msg`] accepts both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ByteEncoding {
+    /// A JSON array of numbers — what every peer understands.
+    #[default]
+    Array,
+    /// A standard base64 string, for a peer whose hello lists `base64`.
+    Base64,
+}
+
+thread_local! {
+    /// The encoding [`write_msg_as`] is serializing with, for [`bytes`] to
+    /// read: serde gives a field no other way to see it.
+    static WRITING: Cell<ByteEncoding> = const { Cell::new(ByteEncoding::Array) };
+}
+
+/// Serde for a byte payload: written in the current [`ByteEncoding`], read
+/// from either form.
+mod bytes {
+    use std::fmt;
 
-use crate::{Command, NodeId};
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::de::{self, SeqAccess, Visitor};
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(b: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        match WRITING.with(Cell::get) {
+            ByteEncoding::Array => s.collect_seq(b),
+            ByteEncoding::Base64 => s.serialize_str(&STANDARD.encode(b)),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        struct Bytes;
+        impl<'de> Visitor<'de> for Bytes {
+            type Value = Vec<u8>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an array of bytes or a base64 string")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Vec<u8>, E> {
+                STANDARD.decode(v).map_err(E::custom)
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+                Ok(v.to_vec())
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+                let mut out = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(b) = seq.next_element()? {
+                    out.push(b);
+                }
+                Ok(out)
+            }
+        }
+        d.deserialize_any(Bytes)
+    }
+}
 
 /// One node as seen over the wire — enough for a CLI to list and target it.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -67,6 +226,47 @@ pub struct NodeInfo {
     /// An uplink node's live peer-connection count. `None` for other kinds.
     #[serde(default)]
     pub peers: Option<usize>,
+    /// How an app node's guest last ended. `None` until it has run and
+    /// ended once, and cleared each time it starts again.
+    #[serde(default)]
+    pub exit: Option<ExitInfo>,
+    /// An app node's restart policy (`No` for every other kind).
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    /// How many times in a row the server has re-run the node's guest under
+    /// its restart policy; back to 0 once a run stays up.
+    #[serde(default)]
+    pub restarts: u16,
+}
+
+/// How an app node's guest ended: it exited with a status, it trapped, or
+/// the server stopped it (neither a status nor a trap).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExitInfo {
+    /// Its exit status (0 = success); `None` if it didn't exit on its own.
+    pub code: Option<i32>,
+    /// What it trapped on (or the host error that ended it).
+    #[serde(default)]
+    pub trap: Option<String>,
+    /// The wasm backtrace at the trap, one frame per line, when there was one.
+    #[serde(default)]
+    pub backtrace: Option<String>,
+    /// When it ended, in milliseconds since the Unix epoch.
+    pub at_ms: u64,
+}
+
+impl ExitInfo {
+    /// The one-line form `wk ps` and the node chrome show: `exited 0`,
+    /// `trapped: <message>`, `stopped`.
+    pub fn summary(&self) -> String {
+        match (self.code, &self.trap) {
+            (Some(code), _) => format!("exited {code}"),
+            (None, Some(trap)) => {
+                format!("trapped: {}", trap.lines().next().unwrap_or_default())
+            }
+            (None, None) => "stopped".to_string(),
+        }
+    }
 }
 
 /// One wire between two nodes.
@@ -89,18 +289,169 @@ pub struct Snapshot {
     pub available: Vec<String>,
 }
 
+/// A change to a running workspace, pushed to a client that sent
+/// [`ClientMsg::Subscribe`]. Applied in order to the subscription's baseline
+/// [`Snapshot`], the deltas keep a client's view current without re-fetching.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Event {
+    /// A node was placed (created, duplicated, or brought back by undo).
+    NodeCreated(NodeInfo),
+    /// A node was removed.
+    NodeDeleted { id: NodeId },
+    /// An app node's guest started running.
+    NodeStarted { id: NodeId },
+    /// An app node's guest exited (finished, stopped, or trapped), and how.
+    NodeExited {
+        id: NodeId,
+        #[serde(default)]
+        exit: Option<ExitInfo>,
+    },
+    /// Two nodes were wired.
+    WireAdded(WireInfo),
+    /// A wire was removed.
+    WireRemoved(WireInfo),
+    /// An app node's effective capability token changed (hex, as in
+    /// [`NodeInfo::token`]).
+    TokenChanged { id: NodeId, token: Option<String> },
+    /// A node's surfaced problem was set, or cleared (`None`).
+    ErrorSet { id: NodeId, error: Option<String> },
+}
+
+/// One entry of a node's filesystem, as [`ClientMsg::FsList`] and
+/// [`ClientMsg::FsStat`] report it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FsEntry {
+    /// The entry's name in its directory (`/` for the root).
+    pub name: String,
+    pub dir: bool,
+    /// A file's length in bytes; 0 for a directory.
+    pub size: u64,
+}
+
+/// One path a node changed relative to its image, as [`ClientMsg::Diff`]
+/// reports it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FsChange {
+    pub kind: ChangeKind,
+    /// The path in the node's filesystem, from `/`.
+    pub path: String,
+}
+
+/// How a path changed: `docker diff`'s A, C and D.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeKind {
+    /// Not in the image.
+    Added,
+    /// In the image, but written over — or, for a directory, something in it
+    /// was added, changed or deleted.
+    Changed,
+    /// In the image, and deleted. Only the topmost path of a deleted tree is
+    /// listed.
+    Deleted,
+}
+
+impl ChangeKind {
+    /// The letter `docker diff` prints for it.
+    pub fn letter(self) -> char {
+        match self {
+            ChangeKind::Added => 'A',
+            ChangeKind::Changed => 'C',
+            ChangeKind::Deleted => 'D',
+        }
+    }
+}
+
+/// One app node's resource use, as [`ClientMsg::Stats`] reports it. The
+/// memory and child counts are what it holds now; frames and MIDI count up
+/// from when the server started it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeStats {
+    pub id: NodeId,
+    pub name: String,
+    /// Bytes of wasm linear memory held by its guest and the programs it runs
+    /// (0 while it isn't running).
+    pub memory: u64,
+    /// Files, directories and links in its filesystem, and the bytes of the
+    /// files it has written itself — image layers it only reads don't count.
+    pub fs_nodes: u64,
+    pub fs_bytes: u64,
+    /// Its fabric traffic and open sockets; `None` for a node that doesn't
+    /// do networking.
+    pub net: Option<NetStats>,
+    /// MIDI messages it sent, one per destination reached.
+    pub midi_sent: u64,
+    /// Frames it presented.
+    pub frames: u64,
+    /// `wk:exec` programs (its own and `wk exec`'s) running in it.
+    pub exec_children: u64,
+}
+
+/// A node's traffic on the fabric, counted at its virtual NIC.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetStats {
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+    pub sockets: u64,
+}
+
+/// What a create made, so a client can target it without re-fetching a
+/// [`Snapshot`] and guessing (the reply to a [`Command::Create`],
+/// [`Command::Duplicate`] or [`Command::Batch`] — for a batch, every node it
+/// placed and the last wire it made). [`Command::Undo`] and [`Command::Redo`]
+/// get it too, saying which step they reversed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Created {
+    /// The ids of the nodes the command placed, in creation order. Empty if
+    /// it placed none (e.g. an unknown dependency, or a wire/workspace create).
+    pub nodes: Vec<NodeId>,
+    /// For a wire create, the connection inferred from the two nodes' kinds —
+    /// whether new or already present. `None` if the nodes can't be wired.
+    pub wire: Option<Wire>,
+    /// For an undo or redo, the history label of the step it reversed (see
+    /// [`ServerMsg::History`]); `None` if there was nothing to reverse.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undone: Option<String>,
+}
+
+/// The first line a client sends on a *networked* connection: the Biscuit it
+/// bears, hex-encoded (the form `wk token mint` prints). The server verifies
+/// it against its public key and replies [`ServerMsg::Ok`] — every message
+/// after that is authorized against this token — or [`ServerMsg::Error`], and
+/// hangs up. The local socket has no handshake: it is trusted as admin, and a
+/// client that wants less sends [`ClientMsg::Authenticate`] instead.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Auth {
+    pub token: String,
+}
+
+/// The most archive bytes one [`ClientMsg::FsChunk`] or [`ServerMsg::FsChunk`]
+/// carries, so neither side holds a whole tree in memory.
+pub const FS_CHUNK: usize = 64 * 1024;
+
 /// A message from a client to the server.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ClientMsg {
+    /// Open the conversation: this client's version and features. The server
+    /// replies [`ServerMsg::Hello`] with its own. Optional — a client that
+    /// never says hello is served as before — but it's how a client learns
+    /// what it may send.
+    Hello(Hello),
     /// Request the current [`Snapshot`].
     GetSnapshot,
-    /// Apply a mutation — the same [`Command`] the UI issues.
+    /// Apply a mutation — the same [`Command`] the UI issues. The server
+    /// replies once it has applied it: [`ServerMsg::Created`] for a create,
+    /// batch, undo or redo, [`ServerMsg::Ok`] otherwise, or
+    /// [`ServerMsg::Error`] if the connection's token doesn't grant it.
     Command(Command),
     /// Attach to a node's terminal; the server replies [`ServerMsg::Attached`]
-    /// then streams [`ServerMsg::Term`] until [`ClientMsg::Detach`].
+    /// then streams [`ServerMsg::Term`] until [`ClientMsg::Detach`]. One
+    /// attach per node: while a client (or another channel) holds it, a second
+    /// is refused with [`ServerMsg::Error`].
     Attach { node: NodeId },
     /// Terminal input bytes (while attached).
-    Input(Vec<u8>),
+    Input(#[serde(with = "bytes")] Vec<u8>),
     /// The client terminal was resized (while attached).
     Resize { cols: u16, rows: u16 },
     /// Stop attaching (the node keeps running).
@@ -108,14 +459,121 @@ pub enum ClientMsg {
     /// Read a node's output log (a non-destructive scrollback). `follow` keeps
     /// the connection open, streaming new output as it arrives.
     Logs { node: NodeId, follow: bool },
+    /// Stream state changes: the server replies with a baseline
+    /// [`ServerMsg::Snapshot`], then pushes [`ServerMsg::Event`]s as they
+    /// happen, until the client disconnects. Other requests keep working on
+    /// the same connection meanwhile.
+    Subscribe,
+    /// List the undo/redo history; the server replies
+    /// [`ServerMsg::History`].
+    History,
+    /// List the directory `path` in a node's filesystem — the tree the node
+    /// itself sees, provider mounts included. Replies
+    /// [`ServerMsg::FsEntries`]. The `Fs` reads need node read; the writes
+    /// ([`ClientMsg::FsWrite`], [`ClientMsg::FsMkdir`],
+    /// [`ClientMsg::FsRemove`], [`ClientMsg::FsExtract`]) need node update
+    /// and reply [`ServerMsg::Ok`]. A failure is [`ServerMsg::Error`].
+    FsList { node: NodeId, path: String },
+    /// What `path` is; replies [`ServerMsg::FsEntry`].
+    FsStat { node: NodeId, path: String },
+    /// A whole file; replies [`ServerMsg::FsData`].
+    FsRead { node: NodeId, path: String },
+    /// Replace (or create) a file with `data`.
+    FsWrite {
+        node: NodeId,
+        path: String,
+        #[serde(with = "bytes")]
+        data: Vec<u8>,
+    },
+    /// Create a directory and any missing parents.
+    FsMkdir { node: NodeId, path: String },
+    /// Remove a file, or a directory and everything in it.
+    FsRemove { node: NodeId, path: String },
+    /// The tree at `path` as a tar archive, its entries named from the last
+    /// component. The server streams it as [`ServerMsg::FsChunk`]s ending
+    /// with [`ServerMsg::FsEnd`]; a failure part way ends it with
+    /// [`ServerMsg::Error`] instead. Other requests are served meanwhile; a
+    /// [`ClientMsg::Detach`] on the channel cuts it short, with neither.
+    FsArchive { node: NodeId, path: String },
+    /// Unpack a tar archive into the directory `path`, creating it. The server
+    /// replies [`ServerMsg::Ok`] and the client sends the archive on the same
+    /// channel as [`ClientMsg::FsChunk`]s, then [`ClientMsg::FsEnd`] — which
+    /// is answered [`ServerMsg::Ok`] once everything is unpacked, or
+    /// [`ServerMsg::Error`].
+    FsExtract { node: NodeId, path: String },
+    /// The next piece of the archive a [`ClientMsg::FsExtract`] unpacks, at
+    /// most [`FS_CHUNK`] bytes.
+    FsChunk(#[serde(with = "bytes")] Vec<u8>),
+    /// The end of that archive.
+    FsEnd,
+    /// Run the program at `path` in a node's filesystem against that live
+    /// filesystem, as the node's own `wk:exec` would — `docker exec`. `argv`
+    /// is its argv in full, `argv[0]` included (empty: just the path); `env`
+    /// layers over the node's. Needs node run and node update.
+    ///
+    /// Without `tty` the server replies [`ServerMsg::Ok`], streams
+    /// [`ServerMsg::Stdout`] and [`ServerMsg::Stderr`], and ends with
+    /// [`ServerMsg::Exited`]; stdin is empty. With `tty` the program gets a
+    /// terminal of its own and the channel is attached to it: the server
+    /// replies [`ServerMsg::Attached`], [`ClientMsg::Input`] and
+    /// [`ClientMsg::Resize`] reach it, and its [`ServerMsg::Term`] output ends
+    /// with [`ServerMsg::Exited`] then [`ServerMsg::Detached`]. A program that
+    /// traps ends with [`ServerMsg::Error`] instead of `Exited`, and
+    /// [`ClientMsg::Detach`] kills it.
+    Exec {
+        node: NodeId,
+        path: String,
+        #[serde(default)]
+        argv: Vec<String>,
+        #[serde(default)]
+        env: Vec<(String, String)>,
+        #[serde(default)]
+        tty: bool,
+    },
+    /// Each app node's resource use; replies [`ServerMsg::Stats`]. Needs node
+    /// read.
+    Stats,
+    /// Store what an app node has written to its filesystem since it started
+    /// as a new image, tagged `tag` — `docker commit`. The node must have been
+    /// started from an image; it keeps running. Replies
+    /// [`ServerMsg::Committed`]. Needs node read.
+    Commit { node: NodeId, tag: String },
+    /// What an app node has changed in its filesystem relative to the image
+    /// it started from (everything, for a node with no image): replies
+    /// [`ServerMsg::Changes`], sorted by path. Mounts are the canvas's, not
+    /// changes, and are left out. Needs node read.
+    Diff { node: NodeId },
+    /// Bear this token instead of the connection's current one, from now on.
+    /// The server verifies it like a networked handshake and replies
+    /// [`ServerMsg::Ok`], or [`ServerMsg::Error`] and keeps the old one. It's
+    /// how a client of the admin socket drops to a scoped capability (`wk
+    /// --token <hex> mcp`). Every channel's attach, log follow and
+    /// subscription is ended first, so nothing the old token started outlives
+    /// it.
+    Authenticate(Auth),
+    /// `msg` on a channel: an independent stream of this connection, so one
+    /// connection can hold several attaches, log follows and subscriptions
+    /// (one of each per channel) alongside ordinary requests. Everything sent
+    /// in answer — the reply and any stream it starts — comes back wrapped in
+    /// [`ServerMsg::On`] with the same `channel`; `Input`, `Resize` and
+    /// `Detach` act on that channel's attach (`Detach` also ends its log
+    /// follow). Plain requests are channel 0's. Channels don't nest.
+    On { channel: u32, msg: Box<ClientMsg> },
 }
 
 /// A message from the server to a client.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ServerMsg {
+    /// The server's version and features (in response to
+    /// [`ClientMsg::Hello`]).
+    Hello(Hello),
     Snapshot(Snapshot),
     /// A command was accepted.
     Ok,
+    /// A create, batch, undo or redo was applied (see [`Created`]). Unlike
+    /// [`ServerMsg::Ok`], sent only once the server has applied the command,
+    /// so the ids are live.
+    Created(Created),
     /// A request failed (bad target, unauthorized, not a terminal, ...).
     Error(String),
     /// Attach succeeded; the node's current terminal size.
@@ -124,19 +582,71 @@ pub enum ServerMsg {
         rows: u16,
     },
     /// Terminal output bytes from the attached node.
-    Term(Vec<u8>),
+    Term(#[serde(with = "bytes")] Vec<u8>),
     /// The attach ended (the node exited, or the client detached).
     Detached,
     /// A chunk of a node's output log (in response to [`ClientMsg::Logs`]).
-    LogChunk(Vec<u8>),
+    LogChunk(#[serde(with = "bytes")] Vec<u8>),
     /// End of the log stream (a non-following `Logs` request is complete).
     LogEnd,
+    /// A state change (in response to [`ClientMsg::Subscribe`]).
+    Event(Event),
+    /// The labels of the mutations [`Command::Undo`] and [`Command::Redo`]
+    /// would reverse (in response to [`ClientMsg::History`]), e.g.
+    /// `"move app vim (01J9Z3K4M5N6)"`. Each list runs oldest first, so the
+    /// next undo (redo) applies its *last* entry.
+    History {
+        undo: Vec<String>,
+        redo: Vec<String>,
+    },
+    /// A directory listing (in response to [`ClientMsg::FsList`]).
+    FsEntries(Vec<FsEntry>),
+    /// One entry (in response to [`ClientMsg::FsStat`]).
+    FsEntry(FsEntry),
+    /// A file's bytes (in response to [`ClientMsg::FsRead`]).
+    FsData(#[serde(with = "bytes")] Vec<u8>),
+    /// The next piece of an archive (in response to [`ClientMsg::FsArchive`]),
+    /// at most [`FS_CHUNK`] bytes.
+    FsChunk(#[serde(with = "bytes")] Vec<u8>),
+    /// The end of the archive.
+    FsEnd,
+    /// Output a program started by [`ClientMsg::Exec`] wrote to stdout.
+    Stdout(#[serde(with = "bytes")] Vec<u8>),
+    /// Output it wrote to stderr.
+    Stderr(#[serde(with = "bytes")] Vec<u8>),
+    /// It exited with this status (0 = success).
+    Exited(i32),
+    /// Per-node resource use (in response to [`ClientMsg::Stats`]), one entry
+    /// per app node.
+    Stats(Vec<NodeStats>),
+    /// The stored image's id (in response to [`ClientMsg::Commit`]).
+    Committed(String),
+    /// A node's filesystem changes (in response to [`ClientMsg::Diff`]).
+    Changes(Vec<FsChange>),
+    /// `msg` belongs to a channel (see [`ClientMsg::On`]).
+    On {
+        channel: u32,
+        msg: Box<ServerMsg>,
+    },
 }
 
 /// Write one message as a single JSON line. The newline frames it, so the peer
 /// reads with [`read_msg`].
 pub fn write_msg<W: Write, T: Serialize>(w: &mut W, msg: &T) -> io::Result<()> {
-    let mut line = serde_json::to_vec(msg)?;
+    write_msg_as(w, msg, ByteEncoding::Array)
+}
+
+/// [`write_msg`], with byte payloads in `bytes` — [`ByteEncoding::Base64`]
+/// only once both hellos have agreed to it.
+pub fn write_msg_as<W: Write, T: Serialize>(
+    w: &mut W,
+    msg: &T,
+    bytes: ByteEncoding,
+) -> io::Result<()> {
+    let before = WRITING.with(|c| c.replace(bytes));
+    let line = serde_json::to_vec(msg);
+    WRITING.with(|c| c.set(before));
+    let mut line = line?;
     line.push(b'\n');
     w.write_all(&line)?;
     w.flush()
@@ -156,7 +666,7 @@ pub fn read_msg<R: BufRead, T: for<'de> Deserialize<'de>>(r: &mut R) -> io::Resu
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{NodeKind, NodePatch, Resource, ResourceRef, Wire};
+    use crate::{NodeKind, NodePatch, Resource, ResourceRef, Restart, Wire};
 
     fn id(n: u128) -> NodeId {
         NodeId::from_u128(n)
@@ -170,6 +680,10 @@ mod tests {
                 kind: NodeKind::App { dep: 3 },
                 pos: [12.5, -4.0],
                 ws: id(1),
+                id: None,
+                args: Some(vec!["notes.txt".into()]),
+                env: vec![("EDITOR".into(), "vim".into())],
+                size: Some([640.0, 480.0]),
             }),
             Command::Create(Resource::Wire { a: id(2), b: id(3) }),
             Command::Update {
@@ -183,6 +697,8 @@ mod tests {
             Command::Delete(ResourceRef::Wire(Wire::Midi(id(5), id(6)))),
             Command::Run(id(7)),
             Command::Undo,
+            Command::Redo,
+            Command::Batch(vec![Command::Run(id(8)), Command::Stop(id(8))]),
         ];
         for c in cmds {
             let line = serde_json::to_string(&c).unwrap();
@@ -213,6 +729,9 @@ mod tests {
                 token: None,
                 ticket: None,
                 peers: None,
+                exit: None,
+                restart: RestartPolicy::No,
+                restarts: 0,
             }],
             wires: vec![WireInfo {
                 kind: "file".into(),
@@ -245,4 +764,456 @@ mod tests {
         assert!(matches!(msgs[1], ClientMsg::Attach { .. }));
         assert!(matches!(msgs[2], ClientMsg::Detach));
     }
+
+    /// The history reply round-trips with both lists in order.
+    #[test]
+    fn history_round_trips() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_msg(&mut buf, &ClientMsg::History).unwrap();
+        write_msg(
+            &mut buf,
+            &ServerMsg::History {
+                undo: vec!["create note".into(), "move note".into()],
+                redo: vec!["delete volume".into()],
+            },
+        )
+        .unwrap();
+        let mut r = std::io::BufReader::new(&buf[..]);
+        assert!(matches!(
+            read_msg::<_, ClientMsg>(&mut r).unwrap(),
+            Some(ClientMsg::History)
+        ));
+        match read_msg::<_, ServerMsg>(&mut r).unwrap() {
+            Some(ServerMsg::History { undo, redo }) => {
+                assert_eq!(undo, ["create note", "move note"]);
+                assert_eq!(redo, ["delete volume"]);
+            }
+            other => panic!("expected History, got {other:?}"),
+        }
+    }
+
+    /// The hello round-trips, and reports what it supports.
+    #[test]
+    fn hello_round_trips() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_msg(&mut buf, &ClientMsg::Hello(Hello::ours())).unwrap();
+        write_msg(&mut buf, &ServerMsg::Hello(Hello::ours())).unwrap();
+        let mut r = std::io::BufReader::new(&buf[..]);
+        match read_msg::<_, ClientMsg>(&mut r).unwrap() {
+            Some(ClientMsg::Hello(h)) => assert_eq!(h, Hello::ours()),
+            other => panic!("expected Hello, got {other:?}"),
+        }
+        match read_msg::<_, ServerMsg>(&mut r).unwrap() {
+            Some(ServerMsg::Hello(h)) => {
+                assert_eq!(h.version, PROTOCOL_VERSION);
+                assert!(h.supports("logs"));
+                assert!(!h.supports("teleport"));
+            }
+            other => panic!("expected Hello, got {other:?}"),
+        }
+    }
+
+    /// Channel envelopes wrap any message, on either side.
+    #[test]
+    fn channel_envelopes_round_trip() {
+        let mut buf: Vec<u8> = Vec::new();
+        let attach = ClientMsg::On {
+            channel: 7,
+            msg: Box::new(ClientMsg::Attach { node: id(1) }),
+        };
+        write_msg(&mut buf, &attach).unwrap();
+        let term = ServerMsg::On {
+            channel: 7,
+            msg: Box::new(ServerMsg::Term(b"hi".to_vec())),
+        };
+        write_msg(&mut buf, &term).unwrap();
+        let mut r = std::io::BufReader::new(&buf[..]);
+        match read_msg::<_, ClientMsg>(&mut r).unwrap() {
+            Some(ClientMsg::On { channel: 7, msg }) => {
+                assert!(matches!(*msg, ClientMsg::Attach { node } if node == id(1)))
+            }
+            other => panic!("expected a channel 7 Attach, got {other:?}"),
+        }
+        match read_msg::<_, ServerMsg>(&mut r).unwrap() {
+            Some(ServerMsg::On { channel: 7, msg }) => {
+                assert!(matches!(*msg, ServerMsg::Term(ref b) if b == b"hi"))
+            }
+            other => panic!("expected channel 7 output, got {other:?}"),
+        }
+    }
+
+    /// Authenticate carries its token as the same hex the handshake uses.
+    #[test]
+    fn authenticate_round_trips() {
+        let msg = ClientMsg::Authenticate(Auth {
+            token: "0a0b".into(),
+        });
+        let line = serde_json::to_string(&msg).unwrap();
+        assert_eq!(line, r#"{"Authenticate":{"token":"0a0b"}}"#);
+        match serde_json::from_str::<ClientMsg>(&line).unwrap() {
+            ClientMsg::Authenticate(auth) => assert_eq!(auth.token, "0a0b"),
+            other => panic!("expected Authenticate, got {other:?}"),
+        }
+    }
+
+    /// A create omitting the optional configuration (an older client) still
+    /// parses, defaulting it; a `Created` reply round-trips its ids and wire.
+    #[test]
+    fn create_defaults_and_created_reply_round_trip() {
+        let line = format!(
+            r#"{{"Create":{{"Node":{{"kind":"Note","pos":[0.0,0.0],"ws":"{}"}}}}}}"#,
+            id(1)
+        );
+        let cmd: Command = serde_json::from_str(&line).unwrap();
+        assert!(matches!(
+            cmd,
+            Command::Create(Resource::Node {
+                args: None,
+                ref env,
+                size: None,
+                ..
+            }) if env.is_empty()
+        ));
+
+        let reply = ServerMsg::Created(Created {
+            nodes: vec![id(2)],
+            wire: Some(Wire::Net(id(2), id(3))),
+            undone: None,
+        });
+        let back: ServerMsg =
+            serde_json::from_str(&serde_json::to_string(&reply).unwrap()).unwrap();
+        match back {
+            ServerMsg::Created(c) => {
+                assert_eq!(c.nodes, vec![id(2)]);
+                assert_eq!(c.wire, Some(Wire::Net(id(2), id(3))));
+                assert_eq!(c.undone, None);
+            }
+            other => panic!("expected Created, got {other:?}"),
+        }
+        let undid: Created =
+            serde_json::from_str(r#"{"nodes":[],"wire":null,"undone":"move note"}"#).unwrap();
+        assert_eq!(undid.undone.as_deref(), Some("move note"));
+    }
+
+    /// Byte payloads are arrays by default and base64 when asked, and read
+    /// back the same either way.
+    #[test]
+    fn byte_payloads_encode_as_negotiated() {
+        let term = ServerMsg::Term(b"\x1b[2Jhi".to_vec());
+        let mut buf: Vec<u8> = Vec::new();
+        write_msg(&mut buf, &term).unwrap();
+        assert_eq!(buf, b"{\"Term\":[27,91,50,74,104,105]}\n");
+        let at = buf.len();
+        write_msg_as(&mut buf, &term, ByteEncoding::Base64).unwrap();
+        assert_eq!(&buf[at..], b"{\"Term\":\"G1sySmhp\"}\n");
+        // The mode is per call: a plain write afterwards is arrays again.
+        write_msg(&mut buf, &ServerMsg::LogChunk(vec![1, 2])).unwrap();
+        assert!(buf.ends_with(b"{\"LogChunk\":[1,2]}\n"));
+
+        let mut r = std::io::BufReader::new(&buf[..]);
+        for _ in 0..2 {
+            match read_msg::<_, ServerMsg>(&mut r).unwrap() {
+                Some(ServerMsg::Term(b)) => assert_eq!(b, b"\x1b[2Jhi"),
+                other => panic!("expected Term, got {other:?}"),
+            }
+        }
+        assert!(matches!(
+            read_msg::<_, ServerMsg>(&mut r).unwrap(),
+            Some(ServerMsg::LogChunk(b)) if b == [1, 2]
+        ));
+
+        let input = ClientMsg::On {
+            channel: 1,
+            msg: Box::new(ClientMsg::Input(b":q\r".to_vec())),
+        };
+        let mut buf: Vec<u8> = Vec::new();
+        write_msg_as(&mut buf, &input, ByteEncoding::Base64).unwrap();
+        match read_msg::<_, ClientMsg>(&mut &buf[..]).unwrap() {
+            Some(ClientMsg::On { msg, .. }) => {
+                assert!(matches!(*msg, ClientMsg::Input(ref b) if b == b":q\r"))
+            }
+            other => panic!("expected channel input, got {other:?}"),
+        }
+        assert_eq!(Hello::ours().byte_encoding(), ByteEncoding::Base64);
+        let old = Hello {
+            version: PROTOCOL_VERSION,
+            features: vec!["logs".into()],
+        };
+        assert_eq!(old.byte_encoding(), ByteEncoding::Array);
+    }
+
+    /// File requests and replies round-trip, their bytes in either encoding.
+    #[test]
+    fn fs_messages_round_trip() {
+        let mut buf: Vec<u8> = Vec::new();
+        let put = ClientMsg::FsWrite {
+            node: id(1),
+            path: "/data/a.txt".into(),
+            data: b"hello".to_vec(),
+        };
+        write_msg_as(&mut buf, &put, ByteEncoding::Base64).unwrap();
+        let listing = ServerMsg::FsEntries(vec![FsEntry {
+            name: "a.txt".into(),
+            dir: false,
+            size: 5,
+        }]);
+        write_msg(&mut buf, &listing).unwrap();
+        let chunk = ClientMsg::FsChunk(b"ustar".to_vec());
+        write_msg_as(&mut buf, &chunk, ByteEncoding::Base64).unwrap();
+        write_msg(&mut buf, &ServerMsg::FsChunk(b"ustar".to_vec())).unwrap();
+        write_msg(&mut buf, &ServerMsg::FsEnd).unwrap();
+        let mut r = std::io::BufReader::new(&buf[..]);
+        match read_msg::<_, ClientMsg>(&mut r).unwrap() {
+            Some(ClientMsg::FsWrite { path, data, .. }) => {
+                assert_eq!(path, "/data/a.txt");
+                assert_eq!(data, b"hello");
+            }
+            other => panic!("expected FsWrite, got {other:?}"),
+        }
+        match read_msg::<_, ServerMsg>(&mut r).unwrap() {
+            Some(ServerMsg::FsEntries(list)) => assert_eq!(list[0].size, 5),
+            other => panic!("expected FsEntries, got {other:?}"),
+        }
+        assert!(matches!(
+            read_msg::<_, ClientMsg>(&mut r).unwrap(),
+            Some(ClientMsg::FsChunk(ref b)) if b == b"ustar"
+        ));
+        assert!(matches!(
+            read_msg::<_, ServerMsg>(&mut r).unwrap(),
+            Some(ServerMsg::FsChunk(ref b)) if b == b"ustar"
+        ));
+        assert!(matches!(
+            read_msg::<_, ServerMsg>(&mut r).unwrap(),
+            Some(ServerMsg::FsEnd)
+        ));
+    }
+
+    /// An exec request from an older client may leave out everything but the
+    /// node and path.
+    #[test]
+    fn exec_defaults_its_optional_fields() {
+        let line = format!(r#"{{"Exec":{{"node":"{}","path":"/bin/ls.wasm"}}}}"#, id(2));
+        match serde_json::from_str::<ClientMsg>(&line).unwrap() {
+            ClientMsg::Exec { argv, env, tty, .. } => {
+                assert!(argv.is_empty() && env.is_empty() && !tty)
+            }
+            other => panic!("expected Exec, got {other:?}"),
+        }
+    }
+
+    /// A stats reply round-trips; a node without networking has no `net`.
+    #[test]
+    fn stats_round_trip() {
+        let row = NodeStats {
+            id: id(4),
+            name: "web".into(),
+            memory: 2 << 20,
+            fs_nodes: 12,
+            fs_bytes: 4096,
+            net: Some(NetStats {
+                rx_bytes: 1500,
+                rx_packets: 3,
+                tx_bytes: 900,
+                tx_packets: 2,
+                sockets: 1,
+            }),
+            midi_sent: 0,
+            frames: 60,
+            exec_children: 1,
+        };
+        let quiet = NodeStats {
+            id: id(5),
+            name: "synth".into(),
+            ..NodeStats::default()
+        };
+        let reply = ServerMsg::Stats(vec![row.clone(), quiet.clone()]);
+        let line = serde_json::to_string(&reply).unwrap();
+        match serde_json::from_str::<ServerMsg>(&line).unwrap() {
+            ServerMsg::Stats(back) => assert_eq!(back, vec![row, quiet]),
+            other => panic!("expected Stats, got {other:?}"),
+        }
+    }
+
+    /// A commit request and its reply round-trip.
+    #[test]
+    fn commit_round_trips() {
+        let req = ClientMsg::Commit {
+            node: id(6),
+            tag: "py-with-numpy:1".into(),
+        };
+        let line = serde_json::to_string(&req).unwrap();
+        match serde_json::from_str::<ClientMsg>(&line).unwrap() {
+            ClientMsg::Commit { node, tag } => {
+                assert_eq!((node, tag.as_str()), (id(6), "py-with-numpy:1"))
+            }
+            other => panic!("expected Commit, got {other:?}"),
+        }
+        let line = serde_json::to_string(&ServerMsg::Committed("sha256-ab".into())).unwrap();
+        match serde_json::from_str::<ServerMsg>(&line).unwrap() {
+            ServerMsg::Committed(image) => assert_eq!(image, "sha256-ab"),
+            other => panic!("expected Committed, got {other:?}"),
+        }
+    }
+
+    /// A diff reply round-trips, each change printing as `docker diff` does.
+    #[test]
+    fn changes_round_trip() {
+        let changes = vec![
+            FsChange {
+                kind: ChangeKind::Changed,
+                path: "/etc".into(),
+            },
+            FsChange {
+                kind: ChangeKind::Added,
+                path: "/etc/pip.conf".into(),
+            },
+            FsChange {
+                kind: ChangeKind::Deleted,
+                path: "/tmp/build".into(),
+            },
+        ];
+        let line = serde_json::to_string(&ServerMsg::Changes(changes.clone())).unwrap();
+        match serde_json::from_str::<ServerMsg>(&line).unwrap() {
+            ServerMsg::Changes(back) => assert_eq!(back, changes),
+            other => panic!("expected Changes, got {other:?}"),
+        }
+        let letters: String = changes.iter().map(|c| c.kind.letter()).collect();
+        assert_eq!(letters, "CAD");
+    }
+
+    /// Each way a guest ends reads as its own summary; only a trap's first
+    /// line makes it in.
+    #[test]
+    fn exit_summaries() {
+        let ended = |code, trap: Option<&str>| ExitInfo {
+            code,
+            trap: trap.map(str::to_string),
+            backtrace: None,
+            at_ms: 0,
+        };
+        assert_eq!(ended(Some(0), None).summary(), "exited 0");
+        assert_eq!(ended(Some(3), None).summary(), "exited 3");
+        assert_eq!(
+            ended(None, Some("wasm `unreachable` instruction executed\nmore")).summary(),
+            "trapped: wasm `unreachable` instruction executed"
+        );
+        assert_eq!(ended(None, None).summary(), "stopped");
+    }
+
+    /// `on-failure` re-runs only a failed guest, `always` any exit, and no
+    /// policy re-runs a guest the server stopped.
+    #[test]
+    fn restart_policy_follows_how_the_guest_ended() {
+        let ended = |code, trap: Option<&str>| ExitInfo {
+            code,
+            trap: trap.map(str::to_string),
+            backtrace: None,
+            at_ms: 0,
+        };
+        let policy = |s: &str| s.parse::<Restart>().unwrap();
+        let (ok, failed, trapped, stopped) = (
+            ended(Some(0), None),
+            ended(Some(1), None),
+            ended(None, Some("unreachable")),
+            ended(None, None),
+        );
+        assert!(!policy("no").wants(&failed));
+        assert!(!policy("on-failure").wants(&ok));
+        assert!(policy("on-failure").wants(&failed));
+        assert!(policy("on-failure").wants(&trapped));
+        assert!(policy("always").wants(&ok));
+        assert!(!policy("always").wants(&stopped));
+    }
+
+    #[test]
+    fn restart_parses_docker_form_and_backs_off() {
+        let r: Restart = "on-failure:3".parse().unwrap();
+        assert_eq!(r.policy, RestartPolicy::OnFailure);
+        assert_eq!(r.to_string(), "on-failure:3");
+        assert!(r.allows(2) && !r.allows(3));
+        assert!("always".parse::<Restart>().unwrap().allows(1000));
+        assert!("no:2".parse::<Restart>().is_err());
+        assert!("sometimes".parse::<Restart>().is_err());
+        assert_eq!(r.delay_ms(0), 1000);
+        assert_eq!(r.delay_ms(2), 4000);
+        assert_eq!(r.delay_ms(40), Restart::MAX_BACKOFF_MS);
+        // A missing backoff reads as the default, not zero.
+        let r: Restart = serde_json::from_str(r#"{"policy":"Always"}"#).unwrap();
+        assert_eq!(r.backoff_ms, Restart::DEFAULT_BACKOFF_MS);
+    }
+
+    #[test]
+    fn limits_merge_and_parse_sizes() {
+        use crate::Limits;
+        let mut l = Limits {
+            memory: Some(64 << 20),
+            cpu: Some(50),
+            ..Limits::default()
+        };
+        l.merge(Limits {
+            cpu: Some(0),
+            fs_bytes: Some(1 << 20),
+            ..Limits::default()
+        });
+        assert_eq!(
+            l,
+            Limits {
+                memory: Some(64 << 20),
+                fs_bytes: Some(1 << 20),
+                ..Limits::default()
+            }
+        );
+        l.merge(Limits {
+            memory: Some(0),
+            fs_bytes: Some(0),
+            ..Limits::default()
+        });
+        assert!(l.is_empty());
+
+        assert_eq!(Limits::parse_bytes("4096"), Ok(4096));
+        assert_eq!(Limits::parse_bytes("512K"), Ok(512 << 10));
+        assert_eq!(Limits::parse_bytes("64MiB"), Ok(64 << 20));
+        assert_eq!(Limits::parse_bytes("2g"), Ok(2 << 30));
+        assert!(Limits::parse_bytes("64X").is_err());
+        assert!(Limits::parse_bytes("M").is_err());
+        assert!(Limits::parse_bytes("99999999999G").is_err());
+    }
+
+    #[test]
+    fn env_names_are_posix_names() {
+        use crate::is_env_name;
+        for ok in [
+            "PATH",
+            "_x",
+            "PHP_INI_DIR",
+            "a1",
+            "1A",
+            "A-B",
+            "A B",
+            "Ä",
+            "null",
+        ] {
+            assert!(is_env_name(ok), "{ok}");
+        }
+        for bad in ["", "A=B", "=", "A\0", "A\nB"] {
+            assert!(!is_env_name(bad), "{bad}");
+        }
+    }
+
+    /// Events serialize as one externally-tagged JSON object each — the line
+    /// format `wk events` prints.
+    #[test]
+    fn events_round_trip() {
+        let ev = ServerMsg::Event(Event::ErrorSet {
+            id: id(3),
+            error: Some("port 8080 in use".into()),
+        });
+        let line = serde_json::to_string(&ev).unwrap();
+        assert!(line.contains("ErrorSet"), "{line}");
+        let back: ServerMsg = serde_json::from_str(&line).unwrap();
+        assert!(matches!(
+            back,
+            ServerMsg::Event(Event::ErrorSet { error: Some(_), .. })
+        ));
+    }
 }
@@ -164,10 +164,27 @@ pub enum NodeKind {
 pub enum Resource {
     /// A node of `kind` at `pos` in workspace `ws`. Positions come *from* the
     /// client (it knows its camera) so the server never needs a view.
+    ///
+    /// `id` is an optional client-minted id for the new node (fresh if
+    /// absent), so later commands in the same [`Command::Batch`] can target
+    /// it. The rest is optional initial configuration, so creating a configured
+    /// node is one round trip instead of create-then-patch: `args` replaces
+    /// the dependency's default launch args (an uplink's peer ticket for an
+    /// Iroh/Veilid node), `env` is layered over an app's image ENV (names as
+    /// [`is_env_name`]), and `size` overrides the kind's default canvas size.
+    /// An app's args and env are in place before its guest first runs.
     Node {
         kind: NodeKind,
         pos: [f32; 2],
         ws: NodeId,
+        #[serde(default)]
+        id: Option<NodeId>,
+        #[serde(default)]
+        args: Option<Vec<String>>,
+        #[serde(default)]
+        env: Vec<(String, String)>,
+        #[serde(default)]
+        size: Option<[f32; 2]>,
     },
     /// A BindMount node already pointed at a host path (a file or a folder) —
     /// what dropping a file from the OS onto the canvas creates, in one
@@ -230,6 +247,228 @@ pub struct NodePatch {
     /// Point a HostService at a host `addr:port` (e.g. `127.0.0.1:8080`); the
     /// fabric side listens on the same port (requires `Update`).
     pub service_target: Option<String>,
+    /// Set an app node's restart policy (requires `Update`).
+    pub restart: Option<Restart>,
+    /// Change an app node's resource limits (requires `Update`). Only the
+    /// limits given change; `Some(0)` lifts one (see [`Limits::merge`]).
+    /// Boxed: rarely sent, and inline it would grow every `Command` by 64 bytes.
+    pub limits: Option<Box<Limits>>,
+    /// Set (`Some`) or unset (`None`) variables in an app node's own
+    /// environment, by name; the rest stay (requires `Update`). Takes effect
+    /// from the guest's next run. Names must pass [`is_env_name`].
+    pub env: Option<Vec<(String, Option<String>)>>,
+}
+
+/// Whether `name` may name a node's own environment variable: anything a
+/// POSIX environment holds — not empty and no `=` — short of control
+/// characters, which a `.wk` file's quoted node name can't carry. Names a
+/// shell can't expand (`HTTP-PROXY`, `null`) are still names to the guest.
+pub fn is_env_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains('=') && !name.chars().any(char::is_control)
+}
+
+/// Whether the server re-runs an app node's guest after it exits on its own.
+/// A guest the server stopped (`Stop`, a closed surface) is never re-run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RestartPolicy {
+    /// Leave it exited until someone runs it.
+    #[default]
+    No,
+    /// Re-run it after a nonzero exit status or a trap.
+    OnFailure,
+    /// Re-run it after any exit, a clean one included.
+    Always,
+}
+
+impl RestartPolicy {
+    /// The name the `.wk` file and `wk node set --restart` use.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RestartPolicy::No => "no",
+            RestartPolicy::OnFailure => "on-failure",
+            RestartPolicy::Always => "always",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "no" => Some(RestartPolicy::No),
+            "on-failure" => Some(RestartPolicy::OnFailure),
+            "always" => Some(RestartPolicy::Always),
+            _ => None,
+        }
+    }
+}
+
+/// An app node's restart policy, with its retry limit and backoff: the first
+/// re-run waits `backoff_ms`, and each consecutive one twice as long as the
+/// last, up to [`Restart::MAX_BACKOFF_MS`]. A run that stays up long enough
+/// resets the count (the server decides how long).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Restart {
+    pub policy: RestartPolicy,
+    /// Give up after this many consecutive re-runs; `0` never gives up.
+    #[serde(default)]
+    pub max_retries: u32,
+    /// The delay before the first re-run, in milliseconds.
+    #[serde(default = "Restart::default_backoff")]
+    pub backoff_ms: u64,
+}
+
+impl Default for Restart {
+    fn default() -> Self {
+        Self {
+            policy: RestartPolicy::No,
+            max_retries: 0,
+            backoff_ms: Self::DEFAULT_BACKOFF_MS,
+        }
+    }
+}
+
+impl Restart {
+    pub const DEFAULT_BACKOFF_MS: u64 = 1000;
+    /// The longest the doubling backoff grows to (a longer `backoff_ms` is
+    /// kept as given).
+    pub const MAX_BACKOFF_MS: u64 = 60_000;
+
+    fn default_backoff() -> u64 {
+        Self::DEFAULT_BACKOFF_MS
+    }
+
+    /// Whether a guest that ended as `exit` should be re-run.
+    pub fn wants(&self, exit: &ipc::ExitInfo) -> bool {
+        let failed = match (exit.code, &exit.trap) {
+            (Some(code), _) => code != 0,
+            (None, Some(_)) => true,
+            // Stopped by the server: never re-run.
+            (None, None) => return false,
+        };
+        match self.policy {
+            RestartPolicy::No => false,
+            RestartPolicy::OnFailure => failed,
+            RestartPolicy::Always => true,
+        }
+    }
+
+    /// Whether `attempt` (0-based, counting consecutive re-runs) is still
+    /// within the retry limit.
+    pub fn allows(&self, attempt: u32) -> bool {
+        self.max_retries == 0 || attempt < self.max_retries
+    }
+
+    /// How long to wait before re-run number `attempt` (0-based).
+    pub fn delay_ms(&self, attempt: u32) -> u64 {
+        let cap = Self::MAX_BACKOFF_MS.max(self.backoff_ms);
+        self.backoff_ms
+            .saturating_mul(1u64 << attempt.min(32))
+            .min(cap)
+    }
+}
+
+/// The `wk node set --restart` form, Docker's: `no`, `always` or
+/// `on-failure`, the last two optionally with `:<max-retries>`. The backoff is
+/// left at its default.
+impl std::str::FromStr for Restart {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (name, max) = match s.split_once(':') {
+            Some((name, max)) => (name, Some(max)),
+            None => (s, None),
+        };
+        let policy = RestartPolicy::parse(name).ok_or_else(|| {
+            format!("unknown restart policy {name:?} (expected no, on-failure or always)")
+        })?;
+        let max_retries = match max {
+            None => 0,
+            Some(_) if policy == RestartPolicy::No => {
+                return Err("`no` takes no retry limit".into());
+            }
+            Some(n) => n
+                .parse()
+                .map_err(|_| format!("bad retry limit {n:?} (expected a number)"))?,
+        };
+        Ok(Self {
+            policy,
+            max_retries,
+            ..Self::default()
+        })
+    }
+}
+
+impl std::fmt::Display for Restart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.policy.as_str())?;
+        if self.max_retries > 0 {
+            write!(f, ":{}", self.max_retries)?;
+        }
+        Ok(())
+    }
+}
+
+/// An app node's own resource limits, below the hard caps every node has.
+/// `None` leaves a resource at those caps. Going past one is refused, not
+/// fatal: memory growth fails, a filesystem write or create fails with
+/// `insufficient-space`, and a busy guest is paused — and the node's
+/// [`NodeInfo::error`](ipc::NodeInfo::error) says which.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    /// Bytes of linear memory the guest (with its `wk:exec` children) may
+    /// grow to.
+    pub memory: Option<u64>,
+    /// Percent of one core the guest (with its `wk:exec` children) may keep
+    /// busy, sliced per epoch tick and split evenly between them.
+    pub cpu: Option<u32>,
+    /// Bytes the node's filesystem may hold in its own files (layer bytes
+    /// shared with other nodes aren't counted).
+    pub fs_bytes: Option<u64>,
+    /// Files, directories and links the node's filesystem may hold.
+    pub fs_nodes: Option<u64>,
+}
+
+impl Limits {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Apply a [`NodePatch::limits`]: each limit the patch gives replaces
+    /// this one, and a `0` lifts it.
+    pub fn merge(&mut self, patch: Limits) {
+        fn set<T: Default + PartialEq>(slot: &mut Option<T>, new: Option<T>) {
+            if let Some(v) = new {
+                *slot = (v != T::default()).then_some(v);
+            }
+        }
+        set(&mut self.memory, patch.memory);
+        set(&mut self.cpu, patch.cpu);
+        set(&mut self.fs_bytes, patch.fs_bytes);
+        set(&mut self.fs_nodes, patch.fs_nodes);
+    }
+
+    /// A byte count as `wk node set` takes one: a number with an optional
+    /// binary suffix — `4096`, `512K`, `64M`, `2G` (`KiB`/`MiB`/`GiB` too).
+    pub fn parse_bytes(s: &str) -> Result<u64, String> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (num, unit) = s.split_at(split);
+        let shift = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "b" => 0,
+            "k" | "kb" | "kib" => 10,
+            "m" | "mb" | "mib" => 20,
+            "g" | "gb" | "gib" => 30,
+            _ => {
+                return Err(format!(
+                    "bad size {s:?} (expected e.g. 4096, 512K, 64M, 2G)"
+                ))
+            }
+        };
+        let n: u64 = num
+            .parse()
+            .map_err(|_| format!("bad size {s:?} (expected e.g. 4096, 512K, 64M, 2G)"))?;
+        n.checked_mul(1 << shift)
+            .ok_or_else(|| format!("size {s:?} is too large"))
+    }
 }
 
 /// A mutation a client asks the server to perform: create/update/delete on a
@@ -274,13 +513,25 @@ pub enum Command {
     Duplicate(NodeId),
     /// Undo the last undoable mutation.
     Undo,
+    /// Re-apply the last undone mutation. Any new undoable mutation clears
+    /// what can be redone.
+    Redo,
+    /// Apply these commands in order, all-or-nothing: if one can't be applied
+    /// (a missing target, a pair that can't be wired, a launch that fails) the
+    /// steps before it are rolled back and the batch reports the error. Each
+    /// inner command is authorized on its own; the whole batch is a single
+    /// undo step. Nodes created with a client-minted id can be targeted by
+    /// the commands after them.
+    Batch(Vec<Command>),
 }
 
 impl Command {
-    /// The `right(resource, action)` a client's token must grant for the server
-    /// to apply this command.
-    pub fn required(&self) -> (ResourceKind, Action) {
-        match self {
+    /// The `right(resource, action)`s a client's token must grant for the
+    /// server to apply this command: one for a plain command, every inner
+    /// command's for a [`Command::Batch`] (which confers no authority itself).
+    pub fn required(&self) -> Vec<(ResourceKind, Action)> {
+        let right = match self {
+            Command::Batch(cmds) => return cmds.iter().flat_map(Command::required).collect(),
             Command::Create(Resource::Node { .. } | Resource::HostMount { .. }) => {
                 (ResourceKind::Node, Action::Create)
             }
@@ -296,6 +547,9 @@ impl Command {
                     || patch.port_set.is_some()
                     || patch.host_path.is_some()
                     || patch.persist.is_some()
+                    || patch.restart.is_some()
+                    || patch.limits.is_some()
+                    || patch.env.is_some()
                 {
                     (ResourceKind::Node, Action::Update)
                 } else {
@@ -314,10 +568,12 @@ impl Command {
             Command::SetToken { .. } => (ResourceKind::Node, Action::Update),
             Command::Run(_) | Command::Stop(_) => (ResourceKind::Node, Action::Run),
             Command::Duplicate(_) => (ResourceKind::Node, Action::Create),
-            // Undo can restore or remove anything it previously recorded, so it
-            // needs document-wide write authority.
-            Command::Undo => (ResourceKind::Document, Action::Update),
-        }
+            // Undo can restore or remove anything it previously recorded (and
+            // redo anything it undid), so both need document-wide write
+            // authority.
+            Command::Undo | Command::Redo => (ResourceKind::Document, Action::Update),
+        };
+        vec![right]
     }
 }
 
@@ -28,6 +28,16 @@ fn authorized(a: &mut Authorizer) -> bool {
     a.authorize_with_limits(limits).is_ok()
 }
 
+/// Check that `token_bytes` is a well-formed token signed (through its whole
+/// attenuation chain) by `public_key`, without authorizing anything — a
+/// network transport's handshake uses this to turn away a stranger up front
+/// instead of serving a connection whose every request would be refused.
+pub fn verify(public_key: PublicKey, token_bytes: &[u8]) -> Result<(), String> {
+    Biscuit::from(token_bytes, public_key)
+        .map(|_| ())
+        .map_err(|e| format!("invalid token: {e}"))
+}
+
 /// Verify `token_bytes` against `public_key` and decide whether the holder may
 /// perform `action` on `resource`. Returns `false` on a bad signature, a
 /// malformed token, or an insufficient grant — the caller then drops the
@@ -0,0 +1,361 @@
+//! Registry credentials for pulls and pushes, found where `docker` would look
+//! plus two places of wk's own. In order:
+//!
+//! 1. `WK_REGISTRY_AUTH=registry=user:password[,…]` — for CI and one-off
+//!    runs, each login scoped to the registry it names so it's never handed
+//!    to another. Each entry splits at its first `=` and then its first `:`,
+//!    so a password may hold either; a comma or backslash in one is written
+//!    `\,` or `\\`.
+//! 2. `wk login <registry>`, stored in wk's cache dir (`auth.json`, readable
+//!    only by its owner) in the same shape as Docker's config.
+//! 3. Docker's `config.json` (`$DOCKER_CONFIG`, else `~/.docker`): a
+//!    registry's `credHelpers` entry, its `auths` entry, then the
+//!    `credsStore` helper — so a machine already logged in with `docker
+//!    login` needs nothing more.
+//!
+//! A registry with none of these is pulled from anonymously.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use base64::Engine;
+use oci_client::secrets::RegistryAuth;
+use serde_json::{json, Value};
+
+/// A user name and its password (or access token) for one registry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Login {
+    pub username: String,
+    pub password: String,
+}
+
+/// The auth to present to `registry` (a host, as in an image reference).
+pub(crate) fn resolve(registry: &str) -> Result<RegistryAuth, String> {
+    Ok(match lookup(registry)? {
+        Some(l) => RegistryAuth::Basic(l.username, l.password),
+        None => RegistryAuth::Anonymous,
+    })
+}
+
+/// The credentials for `registry`, from the first place that has some.
+pub fn lookup(registry: &str) -> Result<Option<Login>, String> {
+    if let Some(var) = std::env::var_os("WK_REGISTRY_AUTH") {
+        if let Some(login) = from_env(&var.to_string_lossy(), registry)? {
+            return Ok(Some(login));
+        }
+    }
+    if let Some(login) = from_config(&read_json(&logins_path()), registry)? {
+        return Ok(Some(login));
+    }
+    from_config(&read_json(&docker_config_path()), registry)
+}
+
+/// Store `login` for `registry`, for `wk login`. Replaces any earlier one.
+pub fn store(registry: &str, login: &Login) -> Result<(), String> {
+    let path = logins_path();
+    let mut config = read_json(&path);
+    if config.is_null() {
+        config = json!({});
+    }
+    let not_object = |what: &str| format!("{}: {what} is not a JSON object", path.display());
+    let auths = config
+        .as_object_mut()
+        .ok_or_else(|| not_object("the file"))?
+        .entry("auths")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .ok_or_else(|| not_object("\"auths\""))?;
+    let pair = format!("{}:{}", login.username, login.password);
+    let auth = base64::engine::general_purpose::STANDARD.encode(pair);
+    auths.insert(registry_key(registry), json!({ "auth": auth }));
+    let bytes = serde_json::to_vec_pretty(&config).expect("a JSON value encodes");
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("mkdir {}: {e}", dir.display()))?;
+    }
+    let mut open = std::fs::OpenOptions::new();
+    open.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut open, 0o600);
+    open.open(&path)
+        .and_then(|mut f| f.write_all(&bytes))
+        .map_err(|e| format!("write {}: {e}", path.display()))?;
+    // `mode` only applies to a file being created; tighten one that wasn't.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("chmod {}: {e}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Where `wk login` keeps what it is given.
+fn logins_path() -> PathBuf {
+    crate::oci::cache_dir().join("auth.json")
+}
+
+/// Docker's client config.
+fn docker_config_path() -> PathBuf {
+    std::env::var_os("DOCKER_CONFIG")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".docker")))
+        .unwrap_or_else(|| PathBuf::from(".docker"))
+        .join("config.json")
+}
+
+/// A JSON file, or `null` if it is missing or unreadable.
+fn read_json(path: &std::path::Path) -> Value {
+    std::fs::read(path)
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+/// How a registry is named for matching: just its host, with Docker Hub's
+/// several names folded into one. Docker writes Hub's key as
+/// `https://index.docker.io/v1/`, and an image reference says `docker.io`.
+fn registry_key(name: &str) -> String {
+    let host = name
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let host = host.split('/').next().unwrap_or(host);
+    match host {
+        "index.docker.io" | "registry-1.docker.io" => "docker.io".to_string(),
+        _ => host.to_string(),
+    }
+}
+
+/// `user:password`, split at the first colon (a password may hold more).
+fn split_pair(pair: &str) -> Option<Login> {
+    let (username, password) = pair.split_once(':')?;
+    (!username.is_empty()).then(|| Login {
+        username: username.to_string(),
+        password: password.to_string(),
+    })
+}
+
+/// The login `WK_REGISTRY_AUTH`'s value scopes to `registry`: comma-separated
+/// `registry=user:password` entries, matched by host like a config's keys.
+fn from_env(var: &str, registry: &str) -> Result<Option<Login>, String> {
+    let key = registry_key(registry);
+    let bad = || "WK_REGISTRY_AUTH: expected registry=user:password[,...]".to_string();
+    for entry in env_entries(var).iter().filter(|e| !e.trim().is_empty()) {
+        let (server, pair) = entry.split_once('=').ok_or_else(bad)?;
+        let login = split_pair(pair).ok_or_else(bad)?;
+        if registry_key(server.trim()) == key {
+            return Ok(Some(login));
+        }
+    }
+    Ok(None)
+}
+
+/// `WK_REGISTRY_AUTH`'s entries, split at each comma. A backslash stands for
+/// the character after it, so `\,` is a comma within an entry and `\\` a
+/// backslash.
+fn env_entries(var: &str) -> Vec<String> {
+    let mut entries = vec![String::new()];
+    let mut chars = var.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => entries.last_mut().unwrap().extend(chars.next()),
+            ',' => entries.push(String::new()),
+            c => entries.last_mut().unwrap().push(c),
+        }
+    }
+    entries
+}
+
+/// The credentials a Docker-shaped config holds for `registry`.
+fn from_config(config: &Value, registry: &str) -> Result<Option<Login>, String> {
+    let key = registry_key(registry);
+    // The config's own spelling of the registry, which a helper keys by.
+    let entry = |section: &str| {
+        config[section]
+            .as_object()
+            .and_then(|m| m.iter().find(|(k, _)| registry_key(k) == key))
+            .map(|(k, v)| (k.clone(), v.clone()))
+    };
+    if let Some((server, helper)) = entry("credHelpers") {
+        if let Some(helper) = helper.as_str() {
+            return helper_get(&format!("docker-credential-{helper}"), &server);
+        }
+    }
+    if let Some((_, auth)) = entry("auths") {
+        if let Some(b64) = auth["auth"].as_str().filter(|s| !s.is_empty()) {
+            let pair = base64::engine::general_purpose::STANDARD
+                .decode(b64)
+                .map_err(|e| format!("registry auth for {registry}: {e}"))?;
+            return split_pair(&String::from_utf8_lossy(&pair))
+                .map(Some)
+                .ok_or_else(|| format!("registry auth for {registry}: expected user:password"));
+        }
+        if let (Some(username), Some(password)) =
+            (auth["username"].as_str(), auth["password"].as_str())
+        {
+            return Ok(Some(Login {
+                username: username.to_string(),
+                password: password.to_string(),
+            }));
+        }
+    }
+    if let Some(store) = config["credsStore"].as_str().filter(|s| !s.is_empty()) {
+        let server = match entry("auths") {
+            Some((server, _)) => server,
+            None if key == "docker.io" => "https://index.docker.io/v1/".to_string(),
+            None => key,
+        };
+        return helper_get(&format!("docker-credential-{store}"), &server);
+    }
+    Ok(None)
+}
+
+/// Ask a Docker credential helper for `server`'s credentials: the helper
+/// reads the server on stdin and answers `{"Username": .., "Secret": ..}`.
+/// One that exits unsuccessfully has none to give, and so has one that isn't
+/// installed — a config naming it shouldn't stop an anonymous pull — though
+/// that gets a warning.
+fn helper_get(program: &str, server: &str) -> Result<Option<Login>, String> {
+    let spawned = Command::new(program)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            eprintln!("wk: credential helper {program}: {e}; going without credentials");
+            return Ok(None);
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(server.as_bytes());
+    }
+    let out = child
+        .wait_with_output()
+        .map_err(|e| format!("credential helper {program}: {e}"))?;
+    if !out.status.success() {
+        return Ok(None);
+    }
+    let reply: Value = serde_json::from_slice(&out.stdout)
+        .map_err(|e| format!("credential helper {program}: {e}"))?;
+    match (reply["Username"].as_str(), reply["Secret"].as_str()) {
+        (Some(username), Some(secret)) => Ok(Some(Login {
+            username: username.to_string(),
+            password: secret.to_string(),
+        })),
+        _ => Err(format!("credential helper {program}: no Username/Secret")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    fn login(u: &str, p: &str) -> Option<Login> {
+        Some(Login {
+            username: u.into(),
+            password: p.into(),
+        })
+    }
+
+    #[test]
+    fn docker_config_auths_match_by_host() {
+        let b64 = base64::engine::general_purpose::STANDARD.encode("ci:s3:cret");
+        let config = json!({
+            "auths": {
+                "https://index.docker.io/v1/": { "auth": b64 },
+                "localhost:5001": { "username": "dev", "password": "pw" },
+            }
+        });
+        assert_eq!(
+            from_config(&config, "docker.io").unwrap(),
+            login("ci", "s3:cret")
+        );
+        assert_eq!(
+            from_config(&config, "localhost:5001").unwrap(),
+            login("dev", "pw")
+        );
+        assert_eq!(from_config(&config, "ghcr.io").unwrap(), None);
+        assert_eq!(from_config(&Value::Null, "ghcr.io").unwrap(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn credential_helpers_are_asked_by_server() {
+        let dir = std::env::temp_dir().join("wk-cred-helper");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let helper = dir.join("docker-credential-test");
+        std::fs::write(
+            &helper,
+            "#!/bin/sh\nread server\n[ \"$server\" = ghcr.io ] || exit 1\n\
+             echo '{\"ServerURL\":\"ghcr.io\",\"Username\":\"bot\",\"Secret\":\"tok\"}'\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&helper, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let program = helper.to_string_lossy();
+        assert_eq!(
+            helper_get(&program, "ghcr.io").unwrap(),
+            login("bot", "tok")
+        );
+        assert_eq!(helper_get(&program, "quay.io").unwrap(), None, "not stored");
+        assert_eq!(
+            helper_get("/nonexistent/docker-credential-x", "ghcr.io").unwrap(),
+            None,
+            "a missing helper means no credentials"
+        );
+    }
+
+    #[test]
+    fn env_logins_are_scoped_to_their_registry() {
+        let var = "ghcr.io=bot:tok,https://index.docker.io/v1/=ci:s3=c:ret";
+        assert_eq!(from_env(var, "ghcr.io").unwrap(), login("bot", "tok"));
+        assert_eq!(from_env(var, "docker.io").unwrap(), login("ci", "s3=c:ret"));
+        assert_eq!(from_env(var, "quay.io").unwrap(), None);
+        assert!(from_env("bot:tok", "ghcr.io").is_err());
+        assert!(from_env("ghcr.io=nocolon", "ghcr.io").is_err());
+
+        // Commas and backslashes in a password are escaped.
+        let var = r"quay.io=ci:a\,b\\c,ghcr.io=bot:tok";
+        assert_eq!(from_env(var, "quay.io").unwrap(), login("ci", r"a,b\c"));
+        assert_eq!(from_env(var, "ghcr.io").unwrap(), login("bot", "tok"));
+    }
+
+    #[test]
+    fn logins_are_stored_docker_style() {
+        crate::oci::isolated_store("logins");
+        store("localhost:5001", &login("me", "first").unwrap()).unwrap();
+        // Left readable by someone else; storing again takes that back.
+        #[cfg(unix)]
+        std::fs::set_permissions(logins_path(), std::fs::Permissions::from_mode(0o644)).unwrap();
+        store("localhost:5001", &login("me", "second").unwrap()).unwrap();
+        #[cfg(unix)]
+        assert_eq!(
+            std::fs::metadata(logins_path())
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777,
+            0o600
+        );
+        let saved = read_json(&logins_path());
+        assert_eq!(
+            from_config(&saved, "localhost:5001").unwrap(),
+            login("me", "second")
+        );
+        assert_eq!(split_pair("nocolon"), None);
+        assert_eq!(split_pair(":pw"), None);
+
+        // A file that isn't Docker-shaped is refused, not clobbered or a panic.
+        std::fs::write(logins_path(), r#"{"auths": []}"#).unwrap();
+        let err = store("localhost:5001", &login("me", "third").unwrap()).unwrap_err();
+        assert!(err.contains(r#""auths" is not a JSON object"#), "{err}");
+        std::fs::write(logins_path(), "[]").unwrap();
+        assert!(store("localhost:5001", &login("me", "third").unwrap()).is_err());
+    }
+}
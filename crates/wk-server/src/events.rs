@@ -0,0 +1,253 @@
+//! The **event stream** behind `ClientMsg::Subscribe`: typed deltas of the
+//! server's state, pushed to subscribed clients as they happen instead of
+//! having every tool re-fetch the whole [`Snapshot`] in a loop.
+//!
+//! Events are derived, not hand-emitted: after each tick the server projects
+//! its [`Snapshot`] (only while someone is subscribed) and [`diff`]s it against
+//! the previous one. That catches every source of change uniformly — client
+//! commands, undo, guests exiting on their own threads, a HostPort failing to
+//! bind — without threading an emit call through each mutation path.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use wk_protocol::ipc::{Event, NodeInfo, Snapshot, WireInfo};
+use wk_protocol::NodeId;
+
+/// A new subscription: the baseline snapshot and the deltas that follow it.
+pub type Subscription = (Snapshot, Receiver<Event>);
+
+/// The subscribers of one server and the snapshot their deltas are relative
+/// to. Lives on the `Server`; published once per tick.
+#[derive(Default)]
+pub struct EventHub {
+    subs: Vec<Sender<Event>>,
+    /// The state the last published deltas brought every subscriber to. `None`
+    /// while nobody is subscribed (nothing is projected then).
+    last: Option<Snapshot>,
+}
+
+impl EventHub {
+    /// Whether any client is subscribed (the server skips projecting if not).
+    pub fn is_active(&self) -> bool {
+        !self.subs.is_empty()
+    }
+
+    /// Register a subscriber. `current` is the server's state right now, used
+    /// as the baseline when this is the first subscriber. Returns the baseline
+    /// the subscriber's deltas apply to, so a client can hold a complete view
+    /// without a race between its snapshot and its first event.
+    pub fn subscribe(&mut self, current: Snapshot) -> Subscription {
+        let (tx, rx) = mpsc::channel();
+        let base = self.last.get_or_insert(current).clone();
+        self.subs.push(tx);
+        (base, rx)
+    }
+
+    /// Diff `next` against the last published state and send the deltas to
+    /// every subscriber, dropping the ones whose client has gone away.
+    pub fn publish(&mut self, next: Snapshot) {
+        let events = match &self.last {
+            Some(prev) => diff(prev, &next),
+            None => Vec::new(),
+        };
+        if !events.is_empty() {
+            self.subs
+                .retain(|tx| events.iter().all(|e| tx.send(e.clone()).is_ok()));
+        }
+        if self.subs.is_empty() {
+            self.last = None;
+        } else {
+            self.last = Some(next);
+        }
+    }
+}
+
+/// The deltas that turn `prev` into `next`, in an order a client can apply
+/// blindly: nodes appear before the wires that join them, and wires go away
+/// before the nodes they joined.
+pub fn diff(prev: &Snapshot, next: &Snapshot) -> Vec<Event> {
+    let old: HashMap<NodeId, &NodeInfo> = prev.nodes.iter().map(|n| (n.id, n)).collect();
+    let new_ids: HashSet<NodeId> = next.nodes.iter().map(|n| n.id).collect();
+    let wire_key = |w: &WireInfo| (w.kind.clone(), w.a, w.b);
+    let old_wires: HashSet<_> = prev.wires.iter().map(wire_key).collect();
+    let new_wires: HashSet<_> = next.wires.iter().map(wire_key).collect();
+
+    let mut events = Vec::new();
+    for n in &next.nodes {
+        let Some(was) = old.get(&n.id) else {
+            events.push(Event::NodeCreated(n.clone()));
+            continue;
+        };
+        let exited = Event::NodeExited {
+            id: n.id,
+            exit: n.exit.clone(),
+        };
+        match (was.running, n.running) {
+            (false, true) => events.push(Event::NodeStarted { id: n.id }),
+            (true, false) => events.push(exited),
+            // Started and ended between two looks (a short CLI program): the
+            // run shows only as a new exit record.
+            (false, false) if n.exit.is_some() && n.exit != was.exit => {
+                events.push(Event::NodeStarted { id: n.id });
+                events.push(exited);
+            }
+            _ => {}
+        }
+        if was.token != n.token {
+            events.push(Event::TokenChanged {
+                id: n.id,
+                token: n.token.clone(),
+            });
+        }
+        if was.error != n.error {
+            events.push(Event::ErrorSet {
+                id: n.id,
+                error: n.error.clone(),
+            });
+        }
+    }
+    for w in &next.wires {
+        if !old_wires.contains(&wire_key(w)) {
+            events.push(Event::WireAdded(w.clone()));
+        }
+    }
+    for w in &prev.wires {
+        if !new_wires.contains(&wire_key(w)) {
+            events.push(Event::WireRemoved(w.clone()));
+        }
+    }
+    for n in &prev.nodes {
+        if !new_ids.contains(&n.id) {
+            events.push(Event::NodeDeleted { id: n.id });
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(n: u128) -> NodeInfo {
+        NodeInfo {
+            id: NodeId::from_u128(n),
+            kind: "app".into(),
+            name: "vim".into(),
+            ws: NodeId::from_u128(1),
+            pos: [0.0, 0.0],
+            size: [0.0, 0.0],
+            args: vec![],
+            running: false,
+            compiling: false,
+            runnable: true,
+            terminal: true,
+            attached: false,
+            error: None,
+            token: None,
+            ticket: None,
+            peers: None,
+            exit: None,
+            restart: wk_protocol::RestartPolicy::No,
+            restarts: 0,
+        }
+    }
+
+    fn snap(nodes: Vec<NodeInfo>, wires: Vec<WireInfo>) -> Snapshot {
+        Snapshot {
+            workspaces: vec![NodeId::from_u128(1)],
+            nodes,
+            wires,
+            available: vec![],
+        }
+    }
+
+    fn wire(a: u128, b: u128) -> WireInfo {
+        WireInfo {
+            kind: "net".into(),
+            a: NodeId::from_u128(a),
+            b: NodeId::from_u128(b),
+        }
+    }
+
+    /// A new node and its wire arrive node-first; removing them sends the
+    /// wire away first.
+    #[test]
+    fn creates_and_deletes_are_ordered_around_wires() {
+        let empty = snap(vec![], vec![]);
+        let full = snap(vec![node(2), node(3)], vec![wire(2, 3)]);
+
+        let up = diff(&empty, &full);
+        assert!(matches!(up[0], Event::NodeCreated(_)));
+        assert!(matches!(up[1], Event::NodeCreated(_)));
+        assert!(matches!(up[2], Event::WireAdded(_)));
+
+        let down = diff(&full, &empty);
+        assert!(matches!(down[0], Event::WireRemoved(_)));
+        assert!(matches!(down[1], Event::NodeDeleted { .. }));
+        assert_eq!(down.len(), 3);
+    }
+
+    /// Run state, token and error changes on an existing node each surface as
+    /// their own event; an unchanged snapshot yields nothing.
+    #[test]
+    fn state_changes_on_a_node_are_reported() {
+        let before = snap(vec![node(2)], vec![]);
+        assert!(diff(&before, &before).is_empty());
+
+        let mut n = node(2);
+        n.running = true;
+        n.token = Some("abcd".into());
+        n.error = Some("port in use".into());
+        let after = snap(vec![n], vec![]);
+        let ev = diff(&before, &after);
+        assert!(matches!(ev[0], Event::NodeStarted { .. }));
+        assert!(matches!(&ev[1], Event::TokenChanged { token: Some(t), .. } if t == "abcd"));
+        assert!(matches!(&ev[2], Event::ErrorSet { error: Some(_), .. }));
+
+        let back = diff(&after, &before);
+        assert!(matches!(back[0], Event::NodeExited { .. }));
+        assert!(matches!(back[2], Event::ErrorSet { error: None, .. }));
+    }
+
+    /// A guest that ran and ended between two snapshots still reports a
+    /// start and an exit, carrying its status.
+    #[test]
+    fn a_run_between_snapshots_is_reported() {
+        let before = snap(vec![node(2)], vec![]);
+        let mut n = node(2);
+        n.exit = Some(wk_protocol::ipc::ExitInfo {
+            code: Some(0),
+            trap: None,
+            backtrace: None,
+            at_ms: 1,
+        });
+        let after = snap(vec![n], vec![]);
+        let ev = diff(&before, &after);
+        assert!(matches!(ev[0], Event::NodeStarted { .. }));
+        assert!(matches!(
+            &ev[1],
+            Event::NodeExited { exit: Some(e), .. } if e.code == Some(0)
+        ));
+        assert!(diff(&after, &after).is_empty());
+    }
+
+    /// A subscriber gets the baseline, then deltas relative to it; once it
+    /// hangs up the hub forgets it (and stops projecting).
+    #[test]
+    fn hub_delivers_deltas_and_prunes_gone_subscribers() {
+        let mut hub = EventHub::default();
+        assert!(!hub.is_active());
+        let (base, rx) = hub.subscribe(snap(vec![], vec![]));
+        assert!(base.nodes.is_empty());
+        assert!(hub.is_active());
+
+        hub.publish(snap(vec![node(2)], vec![]));
+        assert!(matches!(rx.try_recv(), Ok(Event::NodeCreated(_))));
+        assert!(rx.try_recv().is_err(), "one event per change");
+
+        drop(rx);
+        hub.publish(snap(vec![], vec![]));
+        assert!(!hub.is_active());
+    }
+}
@@ -82,6 +82,9 @@ pub struct ExecCtx {
     pub depth: u32,
     /// Live token decision for this node's `exec` capability.
     pub permit: ExecPermit,
+    /// The node's counters, which its children's memory and number count
+    /// into.
+    pub stats: crate::stats::SharedCounters,
 }
 
 /// Register `wk:exec` on a linker. A store without an [`ExecCtx`] still links
@@ -135,6 +138,7 @@ impl wk::exec::process::Host for HostState {
             stdout,
             stderr,
             ctx.depth + 1,
+            &ctx.stats,
         ) {
             Ok(child) => Ok(Ok(self.table().push(ChildHandle(Some(child)))?)),
             Err(e) => Ok(Err(e)),
@@ -182,7 +186,7 @@ impl wk::exec::process::Host for HostState {
         };
         Ok(ctx
             .host
-            .run_program(&wasm, &argv, &env, &fs, stdin, ctx.depth + 1))
+            .run_program(&wasm, &argv, &env, &fs, stdin, ctx.depth + 1, &ctx.stats))
     }
 }
 
@@ -198,7 +202,12 @@ fn program_bytes(state: &HostState, path: &str) -> std::result::Result<(ExecCtx,
     if ctx.depth >= MAX_DEPTH {
         return Err(format!("too many nested wk:exec calls (limit {MAX_DEPTH})"));
     }
-    let fs = state.fs();
+    let wasm = load(&state.fs(), path)?;
+    Ok((ctx, wasm))
+}
+
+/// A program's bytes out of a node's filesystem, checked to be wasm.
+fn load(fs: &crate::vfs::SharedFs, path: &str) -> std::result::Result<Vec<u8>, String> {
     let wasm = match fs.lock().unwrap().read_file(path, usize::MAX) {
         Some(bytes) if !bytes.is_empty() => bytes,
         Some(_) => return Err(format!("{path}: empty file")),
@@ -207,7 +216,78 @@ fn program_bytes(state: &HostState, path: &str) -> std::result::Result<(ExecCtx,
     if !wasm.starts_with(b"\0asm") {
         return Err(format!("{path}: not a wasm program"));
     }
-    Ok((ctx, wasm))
+    Ok(wasm)
+}
+
+/// Where a program a *client* starts in a node (`wk exec`, see
+/// [`ServerHandle::exec`](crate::runtime::ServerHandle::exec)) reads and
+/// writes.
+pub enum ExecIo {
+    /// A terminal of its own for all three streams — `wk exec -it`. The
+    /// client attaches to it as to a terminal node: it types into it, sizes
+    /// it, and drains what the program draws.
+    Tty(crate::terminal::SharedTermIo),
+    /// No stdin; stdout and stderr each into a pipe whose reader the client
+    /// already holds, so it can stream them apart.
+    Pipes {
+        stdout: crate::execpipe::Pipe,
+        stderr: crate::execpipe::Pipe,
+    },
+}
+
+/// Start `path` from `node`'s filesystem for a client: `docker exec`.
+///
+/// The program runs as one of the node's own `wk:exec` children would — the
+/// node's live filesystem and nothing else — with the node's environment
+/// under `env`. Unlike a guest's `run`, the node's `exec` permit isn't
+/// consulted: the authority here is the client's token, which
+/// [`ServerHandle::exec`](crate::runtime::ServerHandle::exec) has checked.
+pub(crate) fn start(
+    host: &crate::plugin::PluginHost,
+    node: &crate::plugin::Node,
+    path: &str,
+    argv: Vec<String>,
+    env: Vec<(String, String)>,
+    io: ExecIo,
+) -> std::result::Result<crate::plugin::Child, String> {
+    let wasm = load(&node.fs, path)?;
+    // `execve`-style, as for `run`: an empty argv falls back to the path.
+    let argv = if argv.is_empty() {
+        vec![path.to_string()]
+    } else {
+        argv
+    };
+    let mut vars = node.environment();
+    vars.extend(env);
+    let (stdin, stdout, stderr) = match io {
+        ExecIo::Tty(term) => {
+            let (cols, rows) = term.size();
+            vars.push(("TERM".into(), "xterm-256color".into()));
+            vars.push(("COLUMNS".into(), cols.to_string()));
+            vars.push(("LINES".into(), rows.to_string()));
+            (
+                Stdin::Term(term.clone()),
+                Sink::Term(term.clone()),
+                Sink::Term(term),
+            )
+        }
+        ExecIo::Pipes { stdout, stderr } => (
+            Stdin::Empty,
+            Sink::Pipe(stdout.writer()),
+            Sink::Pipe(stderr.writer()),
+        ),
+    };
+    host.spawn_program(
+        &wasm,
+        &argv,
+        &vars,
+        &node.fs,
+        stdin,
+        stdout,
+        stderr,
+        1,
+        &node.stats,
+    )
 }
 
 impl wk::exec::process::HostPipe for HostState {
@@ -158,6 +158,13 @@ impl PipeReader {
     pub fn stream(self) -> OwnedReadEnd {
         OwnedReadEnd(self)
     }
+
+    /// Take up to `max` buffered bytes without waiting, for a reader on a
+    /// plain thread (the API streaming a `wk exec`'s output): empty means
+    /// "not yet", `None` is end-of-file.
+    pub fn try_read(&self, max: usize) -> Option<Vec<u8>> {
+        ReadEnd(self.0.clone()).read(max).ok().map(|b| b.to_vec())
+    }
 }
 
 impl PipeWriter {
@@ -364,6 +371,19 @@ mod tests {
         assert!(matches!(rd.read(64), Err(StreamError::Closed)));
     }
 
+    #[test]
+    fn try_read_drains_without_waiting_then_reports_eof() {
+        let pipe = Pipe::new();
+        let r = pipe.reader();
+        let w = pipe.writer();
+        assert_eq!(r.try_read(64), Some(Vec::new()));
+        w.p2_stream().write(Bytes::from_static(b"out")).unwrap();
+        assert_eq!(r.try_read(2), Some(b"ou".to_vec()));
+        drop(w);
+        assert_eq!(r.try_read(64), Some(b"t".to_vec()));
+        assert_eq!(r.try_read(64), None);
+    }
+
     #[test]
     fn writing_reports_remaining_room_and_fills_up() {
         let pipe = Pipe::new();
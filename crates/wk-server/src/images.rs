@@ -16,6 +16,7 @@ use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
 
 use wk_dockerfile::{self as dockerfile, Instr};
+use wk_protocol::ipc::{ChangeKind, FsChange};
 
 /// A stored image's manifest: what to mount and how to run it.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -117,18 +118,58 @@ fn write_creating_dirs(path: &Path, bytes: &[u8]) -> Result<(), String> {
 pub fn put_layer(tar: &[u8]) -> Result<String, String> {
     let digest = crate::oci::digest(tar);
     let path = layer_path(&digest);
-    if !path.exists() {
+    if path.exists() {
+        crate::prune::touch(&path);
+    } else {
         write_creating_dirs(&path, tar)?;
     }
     Ok(digest)
 }
 
+/// [`put_layer`] for a tarball read from `tar`, streamed to disk rather than
+/// held in memory: copied (and hashed) into a temporary file among the
+/// layers, then moved to its digest's name.
+pub(crate) fn put_layer_from(tar: impl std::io::Read) -> Result<String, String> {
+    static INCOMING: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    let n = INCOMING.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let tmp = store_dir()
+        .join("layers")
+        .join(format!("incoming-{}-{n}.tmp", std::process::id()));
+    let digest = match crate::oci::copy_hashed(tar, &tmp) {
+        Ok(digest) => digest,
+        Err(e) => {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e);
+        }
+    };
+    let path = layer_path(&digest);
+    if path.exists() {
+        crate::prune::touch(&path);
+        let _ = std::fs::remove_file(&tmp);
+    } else {
+        std::fs::rename(&tmp, &path).map_err(|e| format!("store layer {digest}: {e}"))?;
+    }
+    Ok(digest)
+}
+
 /// Persist an image manifest under `id`.
 pub fn save_image(id: &str, manifest: &ImageManifest) -> Result<(), String> {
     let json = serde_json::to_vec_pretty(manifest).map_err(|e| format!("encode manifest: {e}"))?;
     write_creating_dirs(&image_path(id), &json)
 }
 
+/// Store an image under its content-addressed id — a digest of the manifest
+/// (layers + config), so the same image always gets the same id — with `wasm`
+/// as its entrypoint component. Returns the id.
+pub(crate) fn store_image(manifest: &ImageManifest, wasm: &[u8]) -> Result<String, String> {
+    let manifest_json =
+        serde_json::to_vec(manifest).map_err(|e| format!("encode manifest: {e}"))?;
+    let id = crate::oci::digest(&manifest_json);
+    save_image(&id, manifest)?;
+    write_creating_dirs(&entrypoint_path(&id), wasm)?;
+    Ok(id)
+}
+
 /// Load the manifest for image `id`, if stored.
 pub fn load_image(id: &str) -> Option<ImageManifest> {
     let bytes = std::fs::read(image_path(id)).ok()?;
@@ -540,20 +581,39 @@ fn add_layer(
 /// `crate::oci::ensure_component`). A seam so image ingestion is testable.
 pub type AdaptFn = dyn Fn(&[u8]) -> Result<Vec<u8>, String>;
 
-/// Ingest a pulled OCI container image into the store: tar layers (gunzipped
-/// if compressed) become content-addressed layers, the image config's
-/// Entrypoint/Cmd/Env/WorkingDir/Labels become the manifest, and the
-/// entrypoint wasm is extracted from the rootfs — run through `adapt` (which
-/// componentizes a core module; see `crate::oci`) — and stored in the
-/// content-addressed blob store under the reference, so `Source::Oci` loads it
-/// unchanged. The image is stored under the sanitized reference, so
-/// `FROM <reference>` finds it.
+/// Ingest a pulled OCI container image into the store (see [`ingest`]). The
+/// entrypoint wasm goes in the content-addressed blob store under the
+/// reference, so `Source::Oci` loads it unchanged, and the image is stored
+/// under the sanitized reference, so `FROM <reference>` finds it.
 pub fn store_pulled_image(
     reference: &str,
     layers: &[(String, Vec<u8>)],
     config_json: &[u8],
     adapt: &AdaptFn,
 ) -> Result<String, String> {
+    let layers = layers
+        .iter()
+        .map(|(media, tar)| (media.clone(), tar.as_slice()));
+    let (manifest, wasm) = ingest(reference, layers, config_json, adapt)?;
+    let id = crate::oci::sanitize(reference);
+    save_image(&id, &manifest)?;
+    crate::oci::store_artifact(reference, &wasm)?;
+    Ok(id)
+}
+
+/// Take in an OCI container image — `what` names it in errors: tar layers
+/// (gunzipped if compressed) become content-addressed layers, the image
+/// config's Entrypoint/Cmd/Env/WorkingDir/Labels become the manifest, and the
+/// entrypoint wasm is extracted from the rootfs and run through `adapt` (which
+/// componentizes a core module; see `crate::oci`). Stores the layers, each
+/// read as it is stored; where the manifest and the wasm go is the caller's
+/// to decide.
+pub(crate) fn ingest<R: std::io::Read>(
+    what: &str,
+    layers: impl IntoIterator<Item = (String, R)>,
+    config_json: &[u8],
+    adapt: &AdaptFn,
+) -> Result<(ImageManifest, Vec<u8>), String> {
     let mut manifest = ImageManifest {
         layers: Vec::new(),
         entrypoint: Vec::new(),
@@ -562,27 +622,28 @@ pub fn store_pulled_image(
         workdir: None,
         labels: BTreeMap::new(),
     };
-    for (media, bytes) in layers {
+    for (media, layer) in layers {
+        use std::io::BufRead;
         if !media.contains("tar") {
-            return Err(format!("{reference}: unsupported layer media type {media}"));
+            return Err(format!("{what}: unsupported layer media type {media}"));
         }
         // Store decompressed, so every consumer reads plain tars.
-        let plain: Vec<u8> = if bytes.starts_with(&[0x1f, 0x8b]) {
-            use std::io::Read;
-            let mut out = Vec::new();
-            flate2::read::GzDecoder::new(bytes.as_slice())
-                .read_to_end(&mut out)
-                .map_err(|e| format!("{reference}: gunzip layer: {e}"))?;
-            out
+        let mut layer = std::io::BufReader::new(layer);
+        let head = layer
+            .fill_buf()
+            .map_err(|e| format!("{what}: read layer: {e}"))?;
+        let stored = if head.starts_with(&[0x1f, 0x8b]) {
+            put_layer_from(flate2::read::GzDecoder::new(layer))
+                .map_err(|e| format!("{what}: gunzip layer: {e}"))?
         } else {
-            bytes.clone()
+            put_layer_from(layer).map_err(|e| format!("{what}: layer: {e}"))?
         };
-        manifest.layers.push(put_layer(&plain)?);
+        manifest.layers.push(stored);
     }
 
     // The OCI/Docker image config: {"config": {"Entrypoint": [...], ...}}.
     let config: serde_json::Value = serde_json::from_slice(config_json)
-        .map_err(|e| format!("{reference}: parse image config: {e}"))?;
+        .map_err(|e| format!("{what}: parse image config: {e}"))?;
     let cfg = config.get("config").cloned().unwrap_or_default();
     let strings = |v: Option<&serde_json::Value>| -> Vec<String> {
         v.and_then(|v| v.as_array())
@@ -621,7 +682,7 @@ pub fn store_pulled_image(
         .first()
         .or(manifest.cmd.first())
         .cloned()
-        .ok_or_else(|| format!("{reference}: image config has no Entrypoint or Cmd"))?;
+        .ok_or_else(|| format!("{what}: image config has no Entrypoint or Cmd"))?;
     // The layers were just stored, so index them lazily from disk: only the
     // entrypoint's own bytes materialize, not the whole rootfs.
     let rootfs = crate::vfs::new_fs();
@@ -636,13 +697,56 @@ pub fn store_pulled_image(
         .lock()
         .unwrap()
         .read_file(&exe, usize::MAX)
-        .ok_or_else(|| format!("{reference}: entrypoint {exe:?} not found in the image"))?;
+        .ok_or_else(|| format!("{what}: entrypoint {exe:?} not found in the image"))?;
     let wasm = adapt(&wasm)?;
 
-    let id = crate::oci::sanitize(reference);
-    save_image(&id, &manifest)?;
-    crate::oci::store_artifact(reference, &wasm)?;
-    Ok(id)
+    Ok((manifest, wasm))
+}
+
+/// The OCI image config for `manifest` — the inverse of what [`ingest`]
+/// reads, so an image that leaves the store (saved, pushed) comes back as the
+/// same manifest. Layers are named by their uncompressed digests, which is
+/// what the store keys them by.
+pub(crate) fn image_config(manifest: &ImageManifest) -> Vec<u8> {
+    let mut config = serde_json::Map::new();
+    if !manifest.entrypoint.is_empty() {
+        config.insert("Entrypoint".into(), manifest.entrypoint.clone().into());
+    }
+    if !manifest.cmd.is_empty() {
+        config.insert("Cmd".into(), manifest.cmd.clone().into());
+    }
+    if !manifest.env.is_empty() {
+        let env: Vec<String> = manifest
+            .env
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect();
+        config.insert("Env".into(), env.into());
+    }
+    if let Some(dir) = &manifest.workdir {
+        config.insert("WorkingDir".into(), dir.clone().into());
+    }
+    if !manifest.labels.is_empty() {
+        let labels: serde_json::Map<String, serde_json::Value> = manifest
+            .labels
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone().into()))
+            .collect();
+        config.insert("Labels".into(), labels.into());
+    }
+    let diff_ids: Vec<String> = manifest.layers.iter().map(|d| oci_digest(d)).collect();
+    let body = serde_json::json!({
+        "architecture": "wasm",
+        "os": "wasi",
+        "config": config,
+        "rootfs": { "type": "layers", "diff_ids": diff_ids },
+    });
+    serde_json::to_vec(&body).expect("a JSON value encodes")
+}
+
+/// A store digest key (`sha256-<hex>`) as OCI writes it (`sha256:<hex>`).
+pub(crate) fn oci_digest(key: &str) -> String {
+    key.replacen('-', ":", 1)
 }
 
 /// Path of the alias file mapping a Dockerfile source to its built image id.
@@ -697,6 +801,21 @@ pub trait BuildRunner {
     ) -> Result<(), String>;
 }
 
+/// The paths of `before` that are gone from `after`, each deleted tree once
+/// by its topmost path. What a mount in `after` covers — or a directory now
+/// replaced by a file — is not listed: it is hidden, not deleted.
+fn deleted<'a>(
+    before: &'a BTreeMap<String, crate::vfs::PathKind>,
+    after: &'a BTreeMap<String, crate::vfs::PathKind>,
+) -> impl Iterator<Item = &'a String> + 'a {
+    before.keys().filter(move |path| {
+        !after.contains_key(*path)
+            && path
+                .rsplit_once('/')
+                .is_none_or(|(dir, _)| after.get(dir) == Some(&crate::vfs::PathKind::Dir))
+    })
+}
+
 /// Capture everything written to `fs` since `before` as a layer tarball:
 /// privately written files (created or copied-up), new directories, and
 /// deletions (as OCI whiteouts, topmost path only). `None` if nothing changed.
@@ -709,24 +828,15 @@ fn diff_layer(
     let after = g.snapshot();
     let mut b = tar::Builder::new(Vec::new());
     let mut changed = false;
-    for path in before.keys() {
-        if !after.contains_key(path) {
-            // Skip children of an already-whited-out directory.
-            let covered = path
-                .rsplit_once('/')
-                .is_some_and(|(dir, _)| before.contains_key(dir) && !after.contains_key(dir));
-            if covered {
-                continue;
-            }
-            let (dir, name) = path.rsplit_once('/').unwrap_or(("", path.as_str()));
-            let wh = if dir.is_empty() {
-                format!(".wh.{name}")
-            } else {
-                format!("{dir}/.wh.{name}")
-            };
-            tar_file(&mut b, &wh, b"")?;
-            changed = true;
-        }
+    for path in deleted(before, &after) {
+        let (dir, name) = path.rsplit_once('/').unwrap_or(("", path.as_str()));
+        let wh = if dir.is_empty() {
+            format!(".wh.{name}")
+        } else {
+            format!("{dir}/.wh.{name}")
+        };
+        tar_file(&mut b, &wh, b"")?;
+        changed = true;
     }
     for (path, kind) in &after {
         match kind {
@@ -763,6 +873,90 @@ fn diff_layer(
         .map_err(|e| format!("finish diff layer: {e}"))
 }
 
+/// Keep what a container wrote: diff `fs` — a node's filesystem, `base`'s
+/// layers mounted into it at start — against those layers alone, and store
+/// the result as a new image, `docker commit`. Copied-up and created files,
+/// new directories, changed links and deletions (as whiteouts) become one
+/// more layer on `base` (none if nothing changed); canvas mounts are not part
+/// of the image and are left out. `base` carries the config the image runs
+/// with, and the entrypoint component is taken from `fs` as it is now, so a
+/// container that rewrote its own program commits the new one. Returns the
+/// new image id.
+pub fn commit(fs: &crate::vfs::SharedFs, base: &ImageManifest) -> Result<String, String> {
+    let pristine = crate::vfs::new_fs();
+    mount(&pristine, &base.container_setup())?;
+    let before = pristine.lock().unwrap().snapshot();
+    let mut manifest = base.clone();
+    if let Some(tar) = diff_layer(&before, fs)? {
+        manifest.layers.push(put_layer(&tar)?);
+    }
+
+    let exe = manifest
+        .entrypoint
+        .first()
+        .or(manifest.cmd.first())
+        .cloned()
+        .ok_or("the image has no ENTRYPOINT (or CMD) to commit")?;
+    let wasm = fs
+        .lock()
+        .unwrap()
+        .read_file(&exe, usize::MAX)
+        .ok_or_else(|| format!("entrypoint {exe:?} is no longer in the node's filesystem"))?;
+
+    store_image(&manifest, &wasm)
+}
+
+/// What `fs` — a node's filesystem, `layers` mounted into it at start — has
+/// changed relative to those layers alone, for `wk diff`, sorted by path.
+/// Like `docker diff`, a directory holding a change is itself changed, and a
+/// copied-up file counts as changed whether or not its bytes differ. Mounts
+/// are the canvas's, not the node's changes, and are left out.
+pub fn changes(fs: &crate::vfs::SharedFs, layers: &[String]) -> Result<Vec<FsChange>, String> {
+    use crate::vfs::PathKind;
+    // A plain wasm node mounted nothing at start, not even `/dev`.
+    let pristine = crate::vfs::new_fs();
+    if !layers.is_empty() {
+        let setup = ContainerSetup {
+            layers: layers.to_vec(),
+            env: Vec::new(),
+        };
+        mount(&pristine, &setup)?;
+    }
+    let before = pristine.lock().unwrap().snapshot();
+    let after = fs.lock().unwrap().snapshot();
+
+    let mut out: BTreeMap<String, ChangeKind> = deleted(&before, &after)
+        .map(|path| (path.clone(), ChangeKind::Deleted))
+        .collect();
+    for (path, now) in &after {
+        let kind = match before.get(path) {
+            _ if *now == PathKind::Mounted => continue,
+            None => ChangeKind::Added,
+            Some(was) if was == now => continue,
+            // A layer file that is now private was copied up to be written.
+            Some(_) => ChangeKind::Changed,
+        };
+        out.insert(path.clone(), kind);
+    }
+    let touched: Vec<String> = out.keys().cloned().collect();
+    for path in &touched {
+        let mut at = path.as_str();
+        while let Some((dir, _)) = at.rsplit_once('/') {
+            if before.contains_key(dir) {
+                out.entry(dir.to_string()).or_insert(ChangeKind::Changed);
+            }
+            at = dir;
+        }
+    }
+    Ok(out
+        .into_iter()
+        .map(|(path, kind)| FsChange {
+            kind,
+            path: format!("/{path}"),
+        })
+        .collect())
+}
+
 /// [`build`], with a runner for `RUN` instructions. The rootfs is materialized
 /// live as layers apply, so each RUN sees the filesystem built so far and its
 /// writes are captured (via [`diff_layer`]) as the next layer.
@@ -1036,29 +1230,13 @@ pub fn build_with_runner(
         .read_file(&exe, usize::MAX)
         .ok_or_else(|| format!("entrypoint {exe:?} not found in the image rootfs"))?;
 
-    // Content-addressed image id over the manifest (layers + config).
-    let manifest_json =
-        serde_json::to_vec(&manifest).map_err(|e| format!("encode manifest: {e}"))?;
-    let id = crate::oci::digest(&manifest_json);
-    save_image(&id, &manifest)?;
-    write_creating_dirs(&entrypoint_path(&id), &wasm)?;
-    Ok(id)
+    store_image(&manifest, &wasm)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    /// Point this test's store at a fresh temp dir and return its root.
-    /// Thread-local (not an env var), so parallel tests under plain
-    /// `cargo test` can't redirect each other's stores.
-    fn isolated_store(name: &str) -> PathBuf {
-        let dir = std::env::temp_dir().join(format!("wk-image-store-{name}"));
-        let _ = std::fs::remove_dir_all(&dir);
-        std::fs::create_dir_all(&dir).unwrap();
-        crate::oci::set_test_cache_root(&dir);
-        dir
-    }
+    use crate::oci::isolated_store;
 
     #[test]
     fn layers_are_content_addressed_and_deduped() {
@@ -1148,6 +1326,183 @@ mod tests {
         assert!(!other.is_materialized(), "the unread sibling stays on disk");
     }
 
+    /// What a push or a save sends as the image config reads back — through
+    /// the same path a pull takes — as the manifest it was built from.
+    #[test]
+    fn image_config_reads_back_as_the_same_manifest() {
+        isolated_store("config");
+        let mut b = tar::Builder::new(Vec::new());
+        tar_file(&mut b, "bin/app.wasm", b"\0asm-config-roundtrip").unwrap();
+        tar_file(&mut b, "etc/app.toml", b"x = 1").unwrap();
+        let layer = b.into_inner().unwrap();
+        let manifest = ImageManifest {
+            layers: vec![put_layer(&layer).unwrap()],
+            entrypoint: vec!["/bin/app.wasm".into(), "serve".into()],
+            cmd: vec!["--port".into(), "80".into()],
+            env: vec![("B".into(), "2".into()), ("A".into(), "x=y".into())],
+            workdir: Some("/srv".into()),
+            labels: [(
+                "org.opencontainers.image.title".to_string(),
+                "app".to_string(),
+            )]
+            .into(),
+        };
+        let config = image_config(&manifest);
+        let layers = [(crate::layout::LAYER_MEDIA.to_string(), layer.as_slice())];
+        let (back, wasm) = ingest("test", layers, &config, &|b| Ok(b.to_vec())).unwrap();
+        assert_eq!(back, manifest);
+        assert_eq!(wasm, b"\0asm-config-roundtrip");
+
+        let parsed: serde_json::Value = serde_json::from_slice(&config).unwrap();
+        assert_eq!(parsed["os"], "wasi");
+        assert_eq!(
+            parsed["rootfs"]["diff_ids"][0],
+            oci_digest(&manifest.layers[0]).as_str()
+        );
+    }
+
+    #[test]
+    fn changes_read_like_docker_diff() {
+        isolated_store("changes");
+        let mut b = tar::Builder::new(Vec::new());
+        tar_file(&mut b, "etc/conf", b"changes-old").unwrap();
+        tar_file(&mut b, "etc/stale", b"changes-stale").unwrap();
+        tar_file(&mut b, "usr/lib/libx.so", b"changes-lib").unwrap();
+        tar_file(&mut b, "usr/share/doc", b"changes-doc").unwrap();
+        let layers = vec![put_layer(&b.into_inner().unwrap()).unwrap()];
+        let fs = crate::vfs::new_fs();
+        mount(
+            &fs,
+            &ContainerSetup {
+                layers: layers.clone(),
+                env: vec![],
+            },
+        )
+        .unwrap();
+        assert!(
+            changes(&fs, &layers).unwrap().is_empty(),
+            "fresh from its image"
+        );
+
+        {
+            let mut g = fs.lock().unwrap();
+            g.put_file_at("etc/conf", b"new".to_vec());
+            g.remove_path("etc/stale");
+            g.remove_path("usr");
+            g.put_file_at("tmp/build/out.o", b"obj".to_vec());
+        }
+        let volume = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        crate::vfs::mount_file(&fs, "/etc/notes.txt", volume, true);
+
+        let lines: Vec<String> = changes(&fs, &layers)
+            .unwrap()
+            .iter()
+            .map(|c| format!("{} {}", c.kind.letter(), c.path))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "C /etc",
+                "C /etc/conf",
+                "D /etc/stale",
+                "A /tmp",
+                "A /tmp/build",
+                "A /tmp/build/out.o",
+                "D /usr",
+            ]
+        );
+    }
+
+    #[test]
+    fn commit_keeps_what_a_container_wrote_as_one_more_layer() {
+        isolated_store("commit");
+        let mut b = tar::Builder::new(Vec::new());
+        tar_file(&mut b, "app.wasm", b"\0asm-commit-base").unwrap();
+        tar_file(&mut b, "etc/conf", b"old").unwrap();
+        tar_file(&mut b, "etc/stale", b"gone soon").unwrap();
+        let base = ImageManifest {
+            layers: vec![put_layer(&b.into_inner().unwrap()).unwrap()],
+            entrypoint: vec!["/app.wasm".into()],
+            cmd: vec![],
+            env: vec![("A".into(), "1".into())],
+            workdir: None,
+            labels: BTreeMap::new(),
+        };
+
+        // A node started from `base`, left untouched: nothing to add.
+        let node_fs = crate::vfs::new_fs();
+        mount(&node_fs, &base.container_setup()).unwrap();
+        let same = load_image(&commit(&node_fs, &base).unwrap()).unwrap();
+        assert_eq!(same.layers, base.layers, "no changes, no new layer");
+
+        // The node edits a layer file, deletes one, adds a package — and has a
+        // volume bound in, which is the canvas's, not the image's.
+        {
+            let mut g = node_fs.lock().unwrap();
+            g.put_file_at("etc/conf", b"new".to_vec());
+            g.remove_path("etc/stale");
+            g.put_file_at("site/pkg.py", b"print()".to_vec());
+        }
+        let volume = std::sync::Arc::new(std::sync::Mutex::new(b"notes".to_vec()));
+        crate::vfs::mount_file(&node_fs, "/data.txt", volume, true);
+
+        let id = commit(&node_fs, &base).unwrap();
+        let m = load_image(&id).unwrap();
+        assert_eq!(m.layers.len(), 2);
+        assert_eq!(m.layers[0], base.layers[0], "the base layers are shared");
+        assert_eq!(m.env, base.env);
+        assert!(entrypoint_path(&id).exists());
+
+        let rerun = crate::vfs::new_fs();
+        mount(&rerun, &m.container_setup()).unwrap();
+        let g = rerun.lock().unwrap();
+        assert_eq!(g.read_file("etc/conf", 64).as_deref(), Some(&b"new"[..]));
+        assert_eq!(
+            g.read_file("site/pkg.py", 64).as_deref(),
+            Some(&b"print()"[..])
+        );
+        assert!(
+            g.read_file("etc/stale", 64).is_none(),
+            "the deletion is a whiteout"
+        );
+        assert!(g.read_file("data.txt", 64).is_none(), "mounts stay out");
+    }
+
+    #[test]
+    fn commit_leaves_what_a_provider_mount_covers_in_place() {
+        isolated_store("commit-provider");
+        let mut b = tar::Builder::new(Vec::new());
+        tar_file(&mut b, "app.wasm", b"\0asm-commit-provider").unwrap();
+        tar_file(&mut b, "srv/www/index.html", b"<h1>layer</h1>").unwrap();
+        tar_file(&mut b, "srv/www/css/site.css", b"body{}").unwrap();
+        let base = ImageManifest {
+            layers: vec![put_layer(&b.into_inner().unwrap()).unwrap()],
+            entrypoint: vec!["/app.wasm".into()],
+            cmd: vec![],
+            env: vec![],
+            workdir: None,
+            labels: BTreeMap::new(),
+        };
+        let node_fs = crate::vfs::new_fs();
+        mount(&node_fs, &base.container_setup()).unwrap();
+        // A wk:fs provider served over the layer's directory hides it; it
+        // doesn't delete what's beneath.
+        let conn = crate::vfs::ProviderConn::new();
+        crate::vfs::mount_provider(&node_fs, "/srv/www", conn, true);
+
+        let m = load_image(&commit(&node_fs, &base).unwrap()).unwrap();
+        assert_eq!(m.layers, base.layers, "a mount is no change to the image");
+
+        let rerun = crate::vfs::new_fs();
+        mount(&rerun, &m.container_setup()).unwrap();
+        let g = rerun.lock().unwrap();
+        assert_eq!(
+            g.read_file("srv/www/index.html", 64).as_deref(),
+            Some(&b"<h1>layer</h1>"[..])
+        );
+        assert!(g.read_file("srv/www/css/site.css", 64).is_some());
+    }
+
     #[test]
     fn image_manifest_round_trips() {
         isolated_store("manifest");
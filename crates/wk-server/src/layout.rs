@@ -0,0 +1,356 @@
+//! OCI image-layout archives: `wk images save` and `wk images load`, so an
+//! image can move between machines — an air-gapped build host, say — without
+//! a registry. An archive is a tar of the standard layout: `oci-layout`,
+//! `index.json` naming each image, and `blobs/sha256/<hex>` holding every
+//! layer, image config and manifest by digest. Other OCI tools (skopeo,
+//! `oras`, a containerd-backed `docker load`) read and write the same thing.
+//!
+//! Layers are written as the store keeps them, uncompressed, so a layer's
+//! blob digest is its store key and a saved-then-loaded image gets back the
+//! same id. Loading takes in what any OCI tool writes, gzipped layers
+//! included, through the same path as a registry pull.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::images::{self, ImageManifest};
+
+/// The `oci-layout` file's contents.
+const LAYOUT_VERSION: &str = r#"{"imageLayoutVersion":"1.0.0"}"#;
+/// OCI media types for the documents and layers wk writes.
+pub(crate) const INDEX_MEDIA: &str = "application/vnd.oci.image.index.v1+json";
+pub(crate) const MANIFEST_MEDIA: &str = "application/vnd.oci.image.manifest.v1+json";
+pub(crate) const CONFIG_MEDIA: &str = "application/vnd.oci.image.config.v1+json";
+pub(crate) const LAYER_MEDIA: &str = "application/vnd.oci.image.layer.v1.tar";
+/// Index annotations naming an image: the OCI one, and containerd's, which
+/// `docker load` reads. Both carry the full `name:tag`.
+const REF_NAME: &str = "org.opencontainers.image.ref.name";
+const IMAGE_NAME: &str = "io.containerd.image.name";
+
+/// The OCI image manifest for `manifest`, whose config document is `config`
+/// (see [`images::image_config`]): each stored layer by digest and size.
+pub(crate) fn image_manifest(manifest: &ImageManifest, config: &[u8]) -> Result<Vec<u8>, String> {
+    let layers = manifest
+        .layers
+        .iter()
+        .map(|key| {
+            let size = std::fs::metadata(images::layer_path(key))
+                .map_err(|e| format!("layer {key}: {e}"))?
+                .len();
+            Ok(json!({
+                "mediaType": LAYER_MEDIA,
+                "digest": images::oci_digest(key),
+                "size": size,
+            }))
+        })
+        .collect::<Result<Vec<Value>, String>>()?;
+    let body = json!({
+        "schemaVersion": 2,
+        "mediaType": MANIFEST_MEDIA,
+        "config": {
+            "mediaType": CONFIG_MEDIA,
+            "digest": images::oci_digest(&crate::oci::digest(config)),
+            "size": config.len(),
+        },
+        "layers": layers,
+    });
+    Ok(serde_json::to_vec(&body).expect("a JSON value encodes"))
+}
+
+/// Append one archive member. Headers carry no timestamps or owners, so
+/// saving the same image twice writes the same bytes.
+fn append<W: std::io::Write>(
+    b: &mut tar::Builder<W>,
+    path: &str,
+    size: u64,
+    data: impl Read,
+) -> Result<(), String> {
+    let mut h = tar::Header::new_ustar();
+    h.set_entry_type(if path.ends_with('/') {
+        tar::EntryType::Directory
+    } else {
+        tar::EntryType::Regular
+    });
+    h.set_size(size);
+    h.set_mode(if path.ends_with('/') { 0o755 } else { 0o644 });
+    b.append_data(&mut h, path, data)
+        .map_err(|e| format!("write {path}: {e}"))
+}
+
+/// Where the blob for `digest` (`sha256:<hex>`) sits in the layout.
+fn blob_path(digest: &str) -> String {
+    format!("blobs/{}", digest.replacen(':', "/", 1))
+}
+
+/// Write the image `reference` (a tag, or an id / id-prefix) to `out` as an
+/// OCI image-layout tar. Saved by tag, the image is named by it in the index,
+/// and loading it restores the tag; saved by id, it loads untagged, as with
+/// `docker save`. Returns the image id.
+pub fn save(reference: &str, out: &Path) -> Result<String, String> {
+    let id = images::resolve_ref(reference).ok_or_else(|| format!("no image {reference:?}"))?;
+    let manifest = images::load_image(&id).ok_or_else(|| format!("no image {reference:?}"))?;
+    let tag = images::normalize_tag(reference);
+    let named = images::load_tags().contains_key(&tag);
+
+    let config = images::image_config(&manifest);
+    let doc = image_manifest(&manifest, &config)?;
+    let doc_digest = images::oci_digest(&crate::oci::digest(&doc));
+    let mut descriptor = json!({
+        "mediaType": MANIFEST_MEDIA,
+        "digest": doc_digest,
+        "size": doc.len(),
+    });
+    if named {
+        descriptor["annotations"] = json!({ REF_NAME: tag, IMAGE_NAME: tag });
+    }
+    let index = json!({
+        "schemaVersion": 2,
+        "mediaType": INDEX_MEDIA,
+        "manifests": [descriptor],
+    });
+    let index = serde_json::to_vec(&index).expect("a JSON value encodes");
+
+    let file = std::fs::File::create(out).map_err(|e| format!("create {}: {e}", out.display()))?;
+    let mut b = tar::Builder::new(std::io::BufWriter::new(file));
+    let layout = LAYOUT_VERSION.as_bytes();
+    append(&mut b, "oci-layout", layout.len() as u64, layout)?;
+    append(&mut b, "index.json", index.len() as u64, index.as_slice())?;
+    append(&mut b, "blobs/", 0, std::io::empty())?;
+    append(&mut b, "blobs/sha256/", 0, std::io::empty())?;
+    let mut written = std::collections::BTreeSet::new();
+    for key in &manifest.layers {
+        // An image may list one layer twice; the layout holds it once.
+        if !written.insert(key) {
+            continue;
+        }
+        let path = images::layer_path(key);
+        let layer = std::fs::File::open(&path).map_err(|e| format!("layer {key}: {e}"))?;
+        let size = layer
+            .metadata()
+            .map_err(|e| format!("layer {key}: {e}"))?
+            .len();
+        append(&mut b, &blob_path(&images::oci_digest(key)), size, layer)?;
+    }
+    let config_digest = images::oci_digest(&crate::oci::digest(&config));
+    append(
+        &mut b,
+        &blob_path(&config_digest),
+        config.len() as u64,
+        config.as_slice(),
+    )?;
+    append(
+        &mut b,
+        &blob_path(&doc_digest),
+        doc.len() as u64,
+        doc.as_slice(),
+    )?;
+    b.into_inner()
+        .and_then(|mut w| std::io::Write::flush(&mut w))
+        .map_err(|e| format!("write {}: {e}", out.display()))?;
+    Ok(id)
+}
+
+/// Where the blob a descriptor's `digest` names was unpacked, from a layout
+/// being loaded.
+fn blob<'a>(
+    blobs: &'a BTreeMap<String, PathBuf>,
+    digest: &Value,
+    what: &str,
+) -> Result<&'a Path, String> {
+    let digest = digest.as_str().unwrap_or_default();
+    blobs
+        .get(digest)
+        .map(PathBuf::as_path)
+        .ok_or_else(|| format!("{what}: blob {digest:?} is missing"))
+}
+
+/// A directory in the store that a layout's blobs are unpacked into while it
+/// loads, removed with everything in it once the load is done.
+struct Staging(PathBuf);
+
+impl Staging {
+    fn new() -> Self {
+        static LOADS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let n = LOADS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Staging(crate::oci::cache_dir().join(format!("loading-{}-{n}", std::process::id())))
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Store every image in the OCI image-layout tar at `archive`, and tag each
+/// one the index names. Each blob is checked against its digest first, as it
+/// is unpacked to disk, so no blob is held in memory whole.
+/// Returns each image's id and tag, in index order.
+pub fn load(archive: &Path) -> Result<Vec<(String, Option<String>)>, String> {
+    let what = archive.display().to_string();
+    let file = std::fs::File::open(archive).map_err(|e| format!("open {what}: {e}"))?;
+    let mut tar = tar::Archive::new(std::io::BufReader::new(file));
+    let staging = Staging::new();
+    let mut blobs: BTreeMap<String, PathBuf> = BTreeMap::new();
+    let mut index = None;
+    let mut layout = false;
+    for entry in tar.entries().map_err(|e| format!("read {what}: {e}"))? {
+        let mut entry = entry.map_err(|e| format!("read {what}: {e}"))?;
+        let path = entry
+            .path()
+            .map_err(|e| format!("read {what}: {e}"))?
+            .to_string_lossy()
+            .trim_start_matches("./")
+            .to_string();
+        if path == "oci-layout" {
+            layout = true;
+        } else if path == "index.json" {
+            let mut data = Vec::new();
+            entry
+                .read_to_end(&mut data)
+                .map_err(|e| format!("read {what}: {path}: {e}"))?;
+            index = Some(data);
+        } else if let Some(hex) = path.strip_prefix("blobs/sha256/").filter(|h| !h.is_empty()) {
+            // The name becomes a file name in the store: hex digits only.
+            if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(format!("{what}: {path} is not named by a sha256 digest"));
+            }
+            let unpacked = staging.0.join(hex);
+            let key = crate::oci::copy_hashed(&mut entry, &unpacked)
+                .map_err(|e| format!("read {what}: {path}: {e}"))?;
+            if key != format!("sha256-{hex}") {
+                return Err(format!(
+                    "{what}: blob sha256:{hex} does not match its digest"
+                ));
+            }
+            blobs.insert(format!("sha256:{hex}"), unpacked);
+        }
+    }
+    if !layout {
+        return Err(format!(
+            "{what}: not an OCI image layout (no oci-layout file)"
+        ));
+    }
+    let index = index.ok_or_else(|| format!("{what}: the layout has no index.json"))?;
+    let index: Value =
+        serde_json::from_slice(&index).map_err(|e| format!("{what}: parse index.json: {e}"))?;
+
+    let blob = |digest: &Value| blob(&blobs, digest, &what);
+    let json = |digest: &Value| -> Result<Vec<u8>, String> {
+        let path = blob(digest)?;
+        std::fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))
+    };
+    let mut loaded = Vec::new();
+    for desc in index["manifests"].as_array().into_iter().flatten() {
+        if desc["mediaType"] == INDEX_MEDIA {
+            return Err(format!(
+                "{what}: a nested image index (a multi-platform image) is not supported"
+            ));
+        }
+        let doc: Value = serde_json::from_slice(&json(&desc["digest"])?)
+            .map_err(|e| format!("{what}: parse image manifest: {e}"))?;
+        let config = json(&doc["config"]["digest"])?;
+        let layers = doc["layers"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|l| {
+                let media = l["mediaType"].as_str().unwrap_or_default().to_string();
+                let path = blob(&l["digest"])?;
+                let file = std::fs::File::open(path)
+                    .map_err(|e| format!("open {}: {e}", path.display()))?;
+                Ok((media, file))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let (manifest, wasm) =
+            images::ingest(&what, layers, &config, &crate::oci::ensure_component)?;
+        let id = images::store_image(&manifest, &wasm)?;
+        let annotations = &desc["annotations"];
+        let tag = annotations[IMAGE_NAME]
+            .as_str()
+            .or(annotations[REF_NAME].as_str())
+            .filter(|t| !t.is_empty());
+        if let Some(tag) = tag {
+            images::set_tag(tag, &id)?;
+        }
+        loaded.push((id, tag.map(images::normalize_tag)));
+    }
+    if loaded.is_empty() {
+        return Err(format!("{what}: the index lists no images"));
+    }
+    Ok(loaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oci::isolated_store;
+
+    #[test]
+    fn saved_images_load_back_as_the_same_image_elsewhere() {
+        let home = isolated_store("save");
+        let wasm = b"\0asm\x0d\x00\x01\x00layout-test".to_vec();
+        let mut b = tar::Builder::new(Vec::new());
+        let mut h = tar::Header::new_gnu();
+        h.set_size(wasm.len() as u64);
+        h.set_mode(0o644);
+        b.append_data(&mut h, "bin/tool.wasm", wasm.as_slice())
+            .unwrap();
+        let manifest = ImageManifest {
+            layers: vec![images::put_layer(&b.into_inner().unwrap()).unwrap()],
+            entrypoint: vec!["/bin/tool.wasm".into(), "--serve".into()],
+            cmd: vec!["8080".into()],
+            env: vec![
+                ("PATH".into(), "/bin".into()),
+                ("MODE".into(), "a=b".into()),
+            ],
+            workdir: Some("/srv".into()),
+            labels: [("org.example.team".to_string(), "build".to_string())].into(),
+        };
+        let id = images::store_image(&manifest, &wasm).unwrap();
+        images::set_tag("tool:1", &id).unwrap();
+
+        let by_tag = home.join("tool.tar");
+        let by_id = home.join("anon.tar");
+        assert_eq!(save("tool:1", &by_tag).unwrap(), id);
+        save(&id, &by_id).unwrap();
+        let again = home.join("again.tar");
+        save("tool:1", &again).unwrap();
+        assert_eq!(
+            std::fs::read(&by_tag).unwrap(),
+            std::fs::read(&again).unwrap(),
+            "saving is deterministic"
+        );
+
+        // Another machine: an empty store.
+        isolated_store("load");
+        assert_eq!(
+            load(&by_tag).unwrap(),
+            [(id.clone(), Some("tool:1".to_string()))]
+        );
+        assert_eq!(images::load_image(&id).unwrap(), manifest);
+        assert_eq!(images::resolve_ref("tool:1").as_deref(), Some(id.as_str()));
+        assert_eq!(std::fs::read(images::entrypoint_path(&id)).unwrap(), wasm);
+        assert_eq!(load(&by_id).unwrap(), [(id, None)]);
+    }
+
+    #[test]
+    fn a_tampered_blob_is_refused() {
+        let home = isolated_store("tamper");
+        let mut b = tar::Builder::new(Vec::new());
+        let layout = LAYOUT_VERSION.as_bytes();
+        append(&mut b, "oci-layout", layout.len() as u64, layout).unwrap();
+        let fake = format!("blobs/sha256/{}", "0".repeat(64));
+        append(&mut b, &fake, 4, &b"evil"[..]).unwrap();
+        let path = home.join("bad.tar");
+        std::fs::write(&path, b.into_inner().unwrap()).unwrap();
+        let err = load(&path).unwrap_err();
+        assert!(err.contains("does not match its digest"), "{err}");
+
+        std::fs::write(&path, b"not a tar at all, just bytes").unwrap();
+        assert!(load(&path).is_err());
+    }
+}
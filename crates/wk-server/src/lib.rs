@@ -11,21 +11,28 @@
 pub mod audio;
 pub mod auth;
 pub mod capture;
+pub mod credentials;
+pub mod events;
 pub mod exec;
 pub mod execpipe;
 pub mod fsprov;
 pub mod http;
 pub mod images;
+pub mod layout;
+pub mod lock;
 pub mod midi;
 pub mod midihw;
 pub mod oci;
 pub mod options;
 pub mod plugin;
+pub mod prune;
 pub mod runtime;
 pub mod scene;
 pub mod server;
+pub mod signing;
 pub mod sockets;
 pub mod sockets_p3;
+pub mod stats;
 pub mod terminal;
 pub mod tty;
 pub mod wiring;
@@ -0,0 +1,291 @@
+//! A workspace's lock: what each dependency of a `.wk` file resolved to, so a
+//! teammate's `wk run` starts exactly what ours did. Beside `home.wk` sits
+//! `home.lock`, recording per dependency:
+//!
+//! - `manifest` — the registry manifest digest an `oci://` reference was
+//!   pulled as. A moved tag doesn't move the workspace: [`Source::ensure`]
+//!   pulls the pinned manifest instead.
+//! - `wasm` — the sha256 of the component that runs.
+//! - `image` — the stored image a `docker://`, `image://` or pulled container
+//!   image resolved to. A Dockerfile whose context has changed builds a
+//!   different image, and is refused until the lock is updated.
+//!
+//! A local `.wasm` path is pinned too, but only warned about — it is the file
+//! you're working on. `wk lock --update` rewrites the lock from what the
+//! dependencies resolve to now; `wk lock` checks it.
+//!
+//! [`Source::ensure`]: crate::workspace::Source::ensure
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::workspace::{Dependency, Document, Source};
+
+/// A `.wk` file's lock: the pin for each dependency, by name.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lock {
+    pub dependencies: BTreeMap<String, Pin>,
+}
+
+/// What one dependency resolved to.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Pin {
+    /// The dependency's source as the `.wk` file spells it; a pin for a
+    /// different source no longer applies.
+    pub source: String,
+    /// Registry manifest digest (`sha256:<hex>`), for an `oci://` source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<String>,
+    /// The runnable component's digest (`sha256:<hex>`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm: Option<String>,
+    /// The stored image id, for a container source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// The lock file this pin was read from, for messages; empty for one
+    /// that wasn't read from a file.
+    #[serde(skip)]
+    pub lock_file: PathBuf,
+}
+
+impl Pin {
+    /// What `source` resolves to in the local cache and store right now.
+    pub fn current(source: &Source) -> Pin {
+        Pin {
+            source: source.to_kdl(),
+            manifest: match source {
+                Source::Oci(reference) => crate::oci::manifest_digest(reference),
+                _ => None,
+            },
+            wasm: wasm_digest(&source.local_path()),
+            image: source.image_id(),
+            lock_file: PathBuf::new(),
+        }
+    }
+
+    /// How `current` differs from this pin, as `field: pinned -> current`
+    /// lines; empty when it matches. The source is assumed to match.
+    pub fn differences(&self, current: &Pin) -> Vec<String> {
+        let fields = [
+            ("manifest", &self.manifest, &current.manifest),
+            ("wasm", &self.wasm, &current.wasm),
+            ("image", &self.image, &current.image),
+        ];
+        fields
+            .into_iter()
+            .filter(|(_, pinned, _)| pinned.is_some())
+            .filter(|(_, pinned, now)| pinned != now)
+            .map(|(field, pinned, now)| {
+                let now = now.as_deref().unwrap_or("(none)");
+                format!("{field}: {} -> {now}", pinned.as_deref().unwrap_or(""))
+            })
+            .collect()
+    }
+}
+
+/// The sha256 of the file at `path` (`sha256:<hex>`), if it exists.
+pub fn wasm_digest(path: &Path) -> Option<String> {
+    let bytes = std::fs::read(path).ok()?;
+    Some(crate::images::oci_digest(&crate::oci::digest(&bytes)))
+}
+
+/// Where the lock for the `.wk` file `wk` lives.
+pub fn path_for(wk: &Path) -> PathBuf {
+    wk.with_extension("lock")
+}
+
+impl Lock {
+    /// The lock beside `wk`, if there is one.
+    pub fn load(wk: &Path) -> Result<Option<Lock>, String> {
+        let path = path_for(wk);
+        let Ok(bytes) = std::fs::read(&path) else {
+            return Ok(None);
+        };
+        let mut lock: Lock =
+            serde_json::from_slice(&bytes).map_err(|e| format!("{}: {e}", path.display()))?;
+        for pin in lock.dependencies.values_mut() {
+            pin.lock_file = path.clone();
+        }
+        Ok(Some(lock))
+    }
+
+    pub fn save(&self, wk: &Path) -> Result<(), String> {
+        let path = path_for(wk);
+        let mut json = serde_json::to_vec_pretty(self).map_err(|e| format!("encode lock: {e}"))?;
+        json.push(b'\n');
+        std::fs::write(&path, json).map_err(|e| format!("write {}: {e}", path.display()))
+    }
+
+    /// The pin for `dep`, if the lock has one for its current source.
+    pub fn pin(&self, dep: &Dependency) -> Option<&Pin> {
+        self.dependencies
+            .get(&dep.name)
+            .filter(|p| p.source == dep.source.to_kdl())
+    }
+}
+
+/// `wk lock --update`: make every dependency of `path` runnable (pulling
+/// what's missing, rebuilding Dockerfiles) and pin what each resolved to.
+/// An `oci://` reference cached before wk recorded manifest digests has the
+/// registry asked which manifest its tag names, and that one re-pulled, so
+/// the pin and the cache agree.
+pub fn update(path: &Path) -> Result<(), String> {
+    let doc = Document::load_resolved(path)?;
+    let mut lock = Lock::default();
+    for dep in &doc.dependencies {
+        dep.ensure(None)?;
+        let mut pin = Pin::current(&dep.source);
+        if let (Source::Oci(reference), None) = (&dep.source, &pin.manifest) {
+            let digest = crate::oci::remote_manifest_digest(reference)?;
+            println!("pulling {reference} at {digest} ...");
+            crate::oci::pull_pinned(reference, &digest)?;
+            pin = Pin::current(&dep.source);
+        }
+        println!(
+            "  {}  {}",
+            dep.name,
+            pin.wasm.as_deref().unwrap_or("(no wasm)")
+        );
+        lock.dependencies.insert(dep.name.clone(), pin);
+    }
+    lock.save(path)?;
+    println!("wrote {}", path_for(path).display());
+    Ok(())
+}
+
+/// `wk lock`: check the lock against what `path`'s dependencies resolve to
+/// in the local cache now, without pulling or building anything.
+pub fn check(path: &Path) -> Result<(), String> {
+    let doc = Document::load_resolved(path)?;
+    let lock = Lock::load(path)?.ok_or_else(|| {
+        format!(
+            "no {}; create it with `wk lock --update`",
+            path_for(path).display()
+        )
+    })?;
+    let mut stale = 0;
+    for dep in &doc.dependencies {
+        let Some(pin) = lock.pin(dep) else {
+            println!("  {}  not locked", dep.name);
+            stale += 1;
+            continue;
+        };
+        let differences = pin.differences(&Pin::current(&dep.source));
+        if differences.is_empty() {
+            println!("  {}  ok", dep.name);
+        } else {
+            stale += 1;
+            for d in differences {
+                println!("  {}  {d}", dep.name);
+            }
+        }
+    }
+    match stale {
+        0 => Ok(()),
+        n => Err(format!(
+            "{n} of {} dependencies differ from {} (`wk run` pulls the pinned \
+             versions; `wk lock --update` accepts the new ones)",
+            doc.dependencies.len(),
+            path_for(path).display()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_lock_pins_each_dependency_to_what_it_resolved_to() {
+        let dir = crate::oci::isolated_store("pins");
+        let wasm = dir.join("synth.wasm");
+        std::fs::write(&wasm, b"\0asm v1").unwrap();
+        let wk = dir.join("home.wk");
+        std::fs::write(
+            &wk,
+            format!("dependencies {{\n  synth \"{}\"\n}}\n", wasm.display()),
+        )
+        .unwrap();
+
+        update(&wk).unwrap();
+        assert!(dir.join("home.lock").is_file());
+        let lock = Lock::load(&wk).unwrap().unwrap();
+        let dep = &Document::load_resolved(&wk).unwrap().dependencies[0];
+        let pin = lock.pin(dep).expect("pinned");
+        assert_eq!(pin.wasm, wasm_digest(&wasm));
+        assert!(pin.wasm.as_deref().unwrap().starts_with("sha256:"));
+        check(&wk).unwrap();
+
+        std::fs::write(&wasm, b"\0asm v2").unwrap();
+        let err = check(&wk).unwrap_err();
+        assert!(err.contains("1 of 1 dependencies differ"), "{err}");
+        let now = Pin::current(&dep.source);
+        assert_eq!(pin.differences(&now).len(), 1);
+        assert!(pin.differences(&now)[0].starts_with("wasm: sha256:"));
+        dep.ensure(Some(&lock))
+            .expect("a changed local path is only warned about");
+
+        // A different source isn't covered by the old pin.
+        let moved = Dependency {
+            source: Source::Path(dir.join("other.wasm")),
+            ..dep.clone()
+        };
+        assert!(lock.pin(&moved).is_none());
+    }
+
+    /// A container source that resolves to something other than its pin is
+    /// refused, naming the lock: an `image://` tag moved to another image,
+    /// and a Dockerfile whose context has changed.
+    #[test]
+    fn a_pin_refuses_a_moved_tag_and_a_rebuilt_image() {
+        let dir = crate::oci::isolated_store("refuse");
+        let image = |wasm: &[u8]| {
+            let manifest = crate::images::ImageManifest {
+                layers: vec![crate::images::put_layer(wasm).unwrap()],
+                entrypoint: vec!["/app.wasm".into()],
+                cmd: Vec::new(),
+                env: Vec::new(),
+                workdir: None,
+                labels: BTreeMap::new(),
+            };
+            crate::images::store_image(&manifest, wasm).unwrap()
+        };
+        let pinned = |source: &Source| Pin {
+            lock_file: dir.join("home.lock"),
+            ..Pin::current(source)
+        };
+
+        crate::images::set_tag("app", &image(b"\0asm v1")).unwrap();
+        let tagged = Source::Image("app".into());
+        let pin = pinned(&tagged);
+        tagged.ensure(Some(&pin)).unwrap();
+        crate::images::set_tag("app", &image(b"\0asm v2")).unwrap();
+        let err = tagged.ensure(Some(&pin)).unwrap_err();
+        assert!(
+            err.contains("home.lock") && err.contains("image: "),
+            "{err}"
+        );
+
+        let ctx = dir.join("ctx");
+        std::fs::create_dir_all(&ctx).unwrap();
+        std::fs::write(ctx.join("app.wasm"), b"\0asm v1").unwrap();
+        let dockerfile = ctx.join("Dockerfile");
+        std::fs::write(
+            &dockerfile,
+            "FROM scratch\nCOPY app.wasm /app.wasm\nENTRYPOINT [\"/app.wasm\"]\n",
+        )
+        .unwrap();
+        let built = Source::Dockerfile(dockerfile);
+        built.ensure(None).unwrap();
+        let pin = pinned(&built);
+        built.ensure(Some(&pin)).unwrap();
+        std::fs::write(ctx.join("app.wasm"), b"\0asm v2").unwrap();
+        let err = built.ensure(Some(&pin)).unwrap_err();
+        assert!(
+            err.contains("home.lock") && err.contains("image: "),
+            "{err}"
+        );
+    }
+}
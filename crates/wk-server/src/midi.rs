@@ -80,12 +80,15 @@ impl Routes {
         }
     }
 
-    fn send(&self, src: NodeId, msg: &Message) {
-        if let Some(v) = self.links.get(&src) {
-            for (_, inbox) in v {
-                inbox.lock().unwrap().push(msg.clone());
-            }
+    /// Deliver `msg` to each of `src`'s destinations; how many there were.
+    fn send(&self, src: NodeId, msg: &Message) -> usize {
+        let Some(v) = self.links.get(&src) else {
+            return 0;
+        };
+        for (_, inbox) in v {
+            inbox.lock().unwrap().push(msg.clone());
         }
+        v.len()
     }
 
     /// Inject a message from a non-guest source (a hardware MIDI device node),
@@ -145,7 +148,8 @@ impl wk::midi::midi::HostOutput for HostState {
     }
 
     fn send(&mut self, _this: Resource<MidiOutput>, data: Vec<u8>) -> Result<()> {
-        self.midi_router.lock().unwrap().send(self.node_id, &data);
+        let routed = self.midi_router.lock().unwrap().send(self.node_id, &data);
+        self.meter.midi_routed(routed);
         Ok(())
     }
 
@@ -178,12 +182,12 @@ mod tests {
 
         // Idempotent connect doesn't duplicate delivery.
         routes.connect(kbd, synth, to_synth.clone());
-        routes.send(kbd, &vec![0x80, 60, 0]);
+        assert_eq!(routes.send(kbd, &vec![0x80, 60, 0]), 1, "one delivery");
         assert_eq!(len(&to_synth), 2);
 
         // Disconnecting stops delivery.
         routes.disconnect(kbd, synth);
-        routes.send(kbd, &vec![0x90, 62, 100]);
+        assert_eq!(routes.send(kbd, &vec![0x90, 62, 100]), 0);
         assert_eq!(len(&to_synth), 2);
 
         // Removing the source node also stops delivery.
@@ -12,13 +12,15 @@
 //! network the first time.
 
 use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use sha2::Digest;
 
 use oci_client::client::{ClientConfig, ClientProtocol, Config, ImageLayer};
+use oci_client::errors::{OciDistributionError, OciErrorCode};
+use oci_client::manifest::{OciDescriptor, OciImageManifest, OCI_IMAGE_MEDIA_TYPE};
 use oci_client::secrets::RegistryAuth;
-use oci_client::{Client, Reference};
+use oci_client::{Client, Reference, RegistryOperation};
 
 /// The Wasm OCI Artifact layer media type.
 const WASM_LAYER: &str = "application/wasm";
@@ -28,14 +30,21 @@ const TAR_LAYERS: [&str; 3] = [
     "application/vnd.oci.image.layer.v1.tar+gzip",
     "application/vnd.docker.image.rootfs.diff.tar.gzip",
 ];
+/// A wk signature: the artifact type of its referrer manifest and the media
+/// type of its one layer (a [`crate::signing::Signature`] as JSON).
+const SIGNATURE_MEDIA: &str = "application/vnd.wk.signature.v1+json";
+/// The OCI empty descriptor, the config of an artifact that needs none.
+const EMPTY_MEDIA: &str = "application/vnd.oci.empty.v1+json";
 /// The Wasm OCI Artifact config media type, and a minimal config body.
 const WASM_CONFIG: &str = "application/vnd.wasm.config.v0+json";
 const WASM_CONFIG_BODY: &str = r#"{"architecture":"wasm","os":"wasi"}"#;
 
-/// A client for `image`'s registry. A `localhost` registry is served over plain
-/// HTTP (the common local-testing setup, e.g. `registry:2` in compose.yml).
-fn client_for(image: &Reference) -> Client {
+/// A client for `image`'s registry, and the credentials to present to it (see
+/// [`crate::credentials`]). A `localhost` registry is served over plain HTTP
+/// (the common local-testing setup, e.g. `registry:2` in compose.yml).
+fn client_for(image: &Reference) -> Result<(Client, RegistryAuth), String> {
     let registry = image.registry().to_string();
+    let auth = crate::credentials::resolve(&registry)?;
     let mut config = ClientConfig::default();
     if registry.starts_with("localhost") || registry.starts_with("127.0.0.1") {
         config.protocol = ClientProtocol::HttpsExcept(vec![registry]);
@@ -53,7 +62,7 @@ fn client_for(image: &Reference) -> Client {
             .or_else(|| entries.first())
             .map(|e| e.digest.clone())
     }));
-    Client::new(config)
+    Ok((Client::new(config), auth))
 }
 
 // Test-only per-thread override of the cache root. Plain `cargo test` runs
@@ -73,6 +82,19 @@ pub(crate) fn set_test_cache_root(root: &std::path::Path) {
     TEST_CACHE_ROOT.with(|r| *r.borrow_mut() = Some(root.to_path_buf()));
 }
 
+/// Point this test's cache/store at a fresh temp dir named for it, and
+/// return the dir. Thread-local (not an env var), so parallel tests under
+/// plain `cargo test` can't redirect each other's stores; `name` only has to
+/// be unique among the crate's tests.
+#[cfg(test)]
+pub(crate) fn isolated_store(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wk-store-{name}"));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    set_test_cache_root(&dir);
+    dir
+}
+
 pub(crate) fn cache_dir() -> PathBuf {
     #[cfg(test)]
     if let Some(root) = TEST_CACHE_ROOT.with(|r| r.borrow().clone()) {
@@ -103,15 +125,45 @@ pub(crate) fn sanitize(reference: &str) -> String {
 /// The content digest key for `bytes` (`sha256-<hex>`), the naming scheme the
 /// whole store uses (blobs here, layer tars and image ids in `crate::images`).
 pub(crate) fn digest(bytes: &[u8]) -> String {
-    let hex: String = sha2::Sha256::digest(bytes)
-        .iter()
-        .map(|b| format!("{b:02x}"))
-        .collect();
+    digest_key(sha2::Sha256::new_with_prefix(bytes))
+}
+
+/// The digest key for what `hash` has taken in.
+fn digest_key(hash: sha2::Sha256) -> String {
+    let hex: String = hash.finalize().iter().map(|b| format!("{b:02x}")).collect();
     format!("sha256-{hex}")
 }
 
+/// Copy `from` into a new file at `to` (its directory created) and return
+/// the digest key of what was copied, hashed on the way, so a blob of any
+/// size costs a buffer's worth of memory.
+pub(crate) fn copy_hashed(mut from: impl std::io::Read, to: &Path) -> Result<String, String> {
+    use std::io::Write;
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("mkdir {}: {e}", parent.display()))?;
+    }
+    let file = std::fs::File::create(to).map_err(|e| format!("create {}: {e}", to.display()))?;
+    let mut file = std::io::BufWriter::new(file);
+    let mut hash = sha2::Sha256::new();
+    let mut buf = vec![0; 64 << 10];
+    loop {
+        let n = match from.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(format!("read: {e}")),
+        };
+        hash.update(&buf[..n]);
+        file.write_all(&buf[..n])
+            .map_err(|e| format!("write {}: {e}", to.display()))?;
+    }
+    file.flush()
+        .map_err(|e| format!("write {}: {e}", to.display()))?;
+    Ok(digest_key(hash))
+}
+
 /// Where the blob for `digest` lives.
-fn blob_path(digest: &str) -> PathBuf {
+pub(crate) fn blob_path(digest: &str) -> PathBuf {
     cache_dir().join("blobs").join(format!("{digest}.wasm"))
 }
 
@@ -121,7 +173,9 @@ fn blob_path(digest: &str) -> PathBuf {
 pub(crate) fn put_blob(bytes: &[u8]) -> Result<String, String> {
     let digest = digest(bytes);
     let path = blob_path(&digest);
-    if !path.exists() {
+    if path.exists() {
+        crate::prune::touch(&path);
+    } else {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)
                 .map_err(|e| format!("mkdir {}: {e}", parent.display()))?;
@@ -138,22 +192,228 @@ fn refs_path() -> PathBuf {
     cache_dir().join("refs.json")
 }
 
-fn load_refs() -> BTreeMap<String, String> {
-    std::fs::read(refs_path())
+/// Path of the reference → manifest-digest index: which registry manifest
+/// each reference was last pulled as, for a workspace's lock to pin.
+fn manifests_path() -> PathBuf {
+    cache_dir().join("manifests.json")
+}
+
+fn load_index(path: &std::path::Path) -> BTreeMap<String, String> {
+    std::fs::read(path)
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(path: &std::path::Path, index: &BTreeMap<String, String>) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(index).map_err(|e| format!("encode index: {e}"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("mkdir {}: {e}", parent.display()))?;
+    }
+    std::fs::write(path, json).map_err(|e| format!("write {}: {e}", path.display()))
+}
+
+pub(crate) fn load_refs() -> BTreeMap<String, String> {
+    load_index(&refs_path())
+}
+
+pub(crate) fn save_refs(refs: &BTreeMap<String, String>) -> Result<(), String> {
+    save_index(&refs_path(), refs)
+}
+
+/// The registry manifest digest (`sha256:<hex>`) `reference` was last pulled
+/// as, if it has been pulled since wk started recording them.
+pub fn manifest_digest(reference: &str) -> Option<String> {
+    load_index(&manifests_path()).remove(reference)
+}
+
+/// The registry manifest digest `reference`'s tag names now, asked with a
+/// HEAD — for pinning a reference pulled before wk recorded digests.
+pub fn remote_manifest_digest(reference: &str) -> Result<String, String> {
+    let image: Reference = reference
+        .parse()
+        .map_err(|e| format!("invalid OCI reference {reference:?}: {e}"))?;
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("tokio runtime: {e}"))?;
+    rt.block_on(async {
+        let (client, auth) = client_for(&image)?;
+        client
+            .fetch_manifest_digest(&image, &auth)
+            .await
+            .map_err(|e| format!("failed to resolve {reference}: {e}"))
+    })
+}
+
+/// Where the signatures fetched for blob `digest` are cached.
+fn signatures_path(digest: &str) -> PathBuf {
+    cache_dir()
+        .join("signatures")
+        .join(format!("{digest}.json"))
+}
+
+fn cached_signatures(digest: &str) -> Vec<crate::signing::Signature> {
+    std::fs::read(signatures_path(digest))
         .ok()
         .and_then(|b| serde_json::from_slice(&b).ok())
         .unwrap_or_default()
 }
 
-fn save_refs(refs: &BTreeMap<String, String>) -> Result<(), String> {
-    let json = serde_json::to_vec_pretty(refs).map_err(|e| format!("encode refs: {e}"))?;
-    let path = refs_path();
+/// Cache `sig` for blob `digest`, replacing an earlier one by the same key.
+fn store_signature(digest: &str, sig: crate::signing::Signature) -> Result<(), String> {
+    let mut sigs = cached_signatures(digest);
+    sigs.retain(|s| s.key != sig.key);
+    sigs.push(sig);
+    let json = serde_json::to_vec_pretty(&sigs).map_err(|e| format!("encode signatures: {e}"))?;
+    let path = signatures_path(digest);
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent).map_err(|e| format!("mkdir {}: {e}", parent.display()))?;
     }
     std::fs::write(&path, json).map_err(|e| format!("write {}: {e}", path.display()))
 }
 
+/// The cached wasm for `reference`, provided it carries a valid signature by
+/// `trusted` (`ed25519:<hex>`) over its exact digest — see `crate::signing`.
+/// The bytes on disk are hashed too, so a blob swapped or corrupted in the
+/// cache doesn't pass for the one that was signed.
+pub fn verified_artifact(reference: &str, trusted: &str) -> Result<PathBuf, String> {
+    let (blob, path) =
+        cached_blob(reference).ok_or_else(|| format!("{reference} is not pulled"))?;
+    crate::signing::verify(
+        &format!("oci://{reference}"),
+        &crate::images::oci_digest(&blob),
+        &cached_signatures(&blob),
+        trusted,
+    )?;
+    let bytes = std::fs::read(&path).map_err(|e| format!("read {}: {e}", path.display()))?;
+    if digest(&bytes) != blob {
+        return Err(format!(
+            "{}: the cached bytes don't match their digest {blob}; pull oci://{reference} again",
+            path.display()
+        ));
+    }
+    Ok(path)
+}
+
+/// The tag a signature for the manifest `subject` is pushed under, for
+/// registries without the referrers API: `sha256-<hex>.sig`, as cosign does.
+fn signature_tag(image: &Reference, subject: &str) -> Result<Reference, String> {
+    let tag = format!(
+        "{}/{}:{}.sig",
+        image.registry(),
+        image.repository(),
+        subject.replacen(':', "-", 1)
+    );
+    tag.parse()
+        .map_err(|e| format!("invalid OCI reference {tag:?}: {e}"))
+}
+
+/// `image`'s repository at the manifest `digest`.
+fn at_digest(image: &Reference, digest: &str) -> Result<Reference, String> {
+    let pinned = format!("{}/{}@{digest}", image.registry(), image.repository());
+    pinned
+        .parse()
+        .map_err(|e| format!("invalid OCI reference {pinned:?}: {e}"))
+}
+
+/// Whether `e` is the registry saying it has no such manifest (or repository,
+/// or API), as opposed to failing to answer.
+fn not_found(e: &OciDistributionError) -> bool {
+    match e {
+        OciDistributionError::ImageManifestNotFoundError(_) => true,
+        OciDistributionError::ServerError { code, .. } => *code == 404,
+        OciDistributionError::RegistryError { envelope, .. } => envelope.errors.iter().any(|e| {
+            matches!(
+                e.code,
+                OciErrorCode::ManifestUnknown | OciErrorCode::NameUnknown
+            )
+        }),
+        _ => false,
+    }
+}
+
+/// Fetch the signatures attached to `image`'s manifest `subject` and cache
+/// those over the pulled blob `blob`. The registry's referrers API is asked
+/// first; one without it, or with nothing listed there, is asked for the
+/// `sha256-<hex>.sig` tag instead. A registry with none to give leaves the
+/// component unsigned, but one that can't be asked is an error — not a
+/// verdict that the component is unsigned.
+fn fetch_signature(
+    rt: &tokio::runtime::Runtime,
+    image: &Reference,
+    subject: &str,
+    blob: &str,
+) -> Result<(), String> {
+    let sigs = rt.block_on(async {
+        let (client, auth) = client_for(image)?;
+        let subject_ref = at_digest(image, subject)?;
+        client
+            .auth(&subject_ref, &auth, RegistryOperation::Pull)
+            .await
+            .map_err(|e| format!("failed to sign in to {}: {e}", image.registry()))?;
+        let mut sigs = Vec::new();
+        match client
+            .pull_referrers(&subject_ref, Some(SIGNATURE_MEDIA))
+            .await
+        {
+            Ok(index) => {
+                for entry in index.manifests {
+                    let referrer = at_digest(image, &entry.digest)?;
+                    sigs.extend(pull_signature(&client, &auth, &referrer).await?);
+                }
+            }
+            Err(e) if not_found(&e) => {}
+            Err(e) => return Err(format!("failed to list {image}'s referrers: {e}")),
+        }
+        if sigs.is_empty() {
+            let tag = signature_tag(image, subject)?;
+            sigs.extend(pull_signature(&client, &auth, &tag).await?);
+        }
+        Ok::<_, String>(sigs)
+    })?;
+    let signed = crate::images::oci_digest(blob);
+    for sig in sigs.into_iter().filter(|s| s.digest == signed) {
+        store_signature(blob, sig)?;
+    }
+    Ok(())
+}
+
+/// The wk signature the manifest at `at` carries; `None` if there is no
+/// such manifest, or it is some other kind of referrer.
+async fn pull_signature(
+    client: &Client,
+    auth: &RegistryAuth,
+    at: &Reference,
+) -> Result<Option<crate::signing::Signature>, String> {
+    match client.pull(at, auth, vec![SIGNATURE_MEDIA]).await {
+        Ok(data) => Ok(data
+            .layers
+            .first()
+            .and_then(|l| serde_json::from_slice(&l.data).ok())),
+        Err(OciDistributionError::IncompatibleLayerMediaTypeError(_)) => Ok(None),
+        Err(e) if not_found(&e) => Ok(None),
+        Err(e) => Err(format!("failed to fetch the signature at {at}: {e}")),
+    }
+}
+
+/// Re-fetch the signature for the wasm cached for `reference` — for one pulled
+/// before its signature was published, or before wk fetched signatures.
+pub fn refresh_signature(reference: &str) -> Result<(), String> {
+    let (Some(subject), Some((blob, _))) = (manifest_digest(reference), cached_blob(reference))
+    else {
+        return Ok(());
+    };
+    let image: Reference = reference
+        .parse()
+        .map_err(|e| format!("invalid OCI reference {reference:?}: {e}"))?;
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("tokio runtime: {e}"))?;
+    fetch_signature(&rt, &image, &subject, &blob)
+}
+
 /// Store pulled wasm for `reference`: the bytes by content in the blob store,
 /// and a ref-index entry pointing the reference at them. Returns the blob path.
 pub(crate) fn store_artifact(reference: &str, wasm: &[u8]) -> Result<PathBuf, String> {
@@ -170,17 +430,22 @@ pub(crate) fn store_artifact(reference: &str, wasm: &[u8]) -> Result<PathBuf, St
 /// to its content-addressed blob. A cache file from the pre-index layout
 /// (`<sanitized-ref>.wasm`) is migrated into the blob store on first touch.
 pub fn cached_artifact(reference: &str) -> Option<PathBuf> {
-    if let Some(digest) = load_refs().get(reference) {
-        let path = blob_path(digest);
+    cached_blob(reference).map(|(_, path)| path)
+}
+
+/// [`cached_artifact`], with the blob's digest (its store key).
+fn cached_blob(reference: &str) -> Option<(String, PathBuf)> {
+    if let Some(digest) = load_refs().remove(reference) {
+        let path = blob_path(&digest);
         if path.exists() {
-            return Some(path);
+            return Some((digest, path));
         }
     }
     let legacy = legacy_ref_path(reference);
     let bytes = std::fs::read(&legacy).ok()?;
     let stored = store_artifact(reference, &bytes).ok()?;
     let _ = std::fs::remove_file(&legacy);
-    Some(stored)
+    Some((digest(&bytes), stored))
 }
 
 /// The pre-index cache location for `reference` (keyed by sanitized name).
@@ -200,8 +465,8 @@ pub fn name_for(reference: &str) -> String {
         .unwrap_or_else(|| "plugin".to_string())
 }
 
-/// Pull the wasm component bytes for `reference` from its OCI registry
-/// (anonymously). Blocking — runs a small Tokio runtime internally.
+/// Pull the wasm component bytes for `reference` from its OCI registry.
+/// Blocking — runs a small Tokio runtime internally.
 pub fn pull(reference: &str) -> Result<Vec<u8>, String> {
     let image: Reference = reference
         .parse()
@@ -213,9 +478,9 @@ pub fn pull(reference: &str) -> Result<Vec<u8>, String> {
         .map_err(|e| format!("tokio runtime: {e}"))?;
 
     rt.block_on(async {
-        let client = client_for(&image);
+        let (client, auth) = client_for(&image)?;
         let data = client
-            .pull(&image, &RegistryAuth::Anonymous, vec![WASM_LAYER])
+            .pull(&image, &auth, vec![WASM_LAYER])
             .await
             .map_err(|e| format!("failed to pull {reference}: {e}"))?;
         let layer = data
@@ -288,20 +553,34 @@ pub fn pull_into_cache(reference: &str) -> Result<(), String> {
     let image: Reference = reference
         .parse()
         .map_err(|e| format!("invalid OCI reference {reference:?}: {e}"))?;
+    pull_from(reference, &image)
+}
+
+/// Pull the registry manifest `digest` of `reference`'s repository and cache
+/// it under `reference` — what a workspace's lock pins, wherever the tag
+/// points now.
+pub fn pull_pinned(reference: &str, digest: &str) -> Result<(), String> {
+    let image: Reference = reference
+        .parse()
+        .map_err(|e| format!("invalid OCI reference {reference:?}: {e}"))?;
+    pull_from(reference, &at_digest(&image, digest)?)
+}
 
+/// Pull `image` and store it as `reference`.
+fn pull_from(reference: &str, image: &Reference) -> Result<(), String> {
     let rt = tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()
         .map_err(|e| format!("tokio runtime: {e}"))?;
 
     let data = rt.block_on(async {
-        let client = client_for(&image);
+        let (client, auth) = client_for(image)?;
         let mut accepted = vec![WASM_LAYER];
         accepted.extend(TAR_LAYERS);
         client
-            .pull(&image, &RegistryAuth::Anonymous, accepted)
+            .pull(image, &auth, accepted)
             .await
-            .map_err(|e| format!("failed to pull {reference}: {e}"))
+            .map_err(|e| format!("failed to pull {image}: {e}"))
     })?;
 
     let is_artifact = data.layers.len() == 1
@@ -312,19 +591,35 @@ pub fn pull_into_cache(reference: &str) -> Result<(), String> {
     if is_artifact {
         let wasm = ensure_component(&data.layers[0].data)?;
         store_artifact(reference, &wasm)?;
-        return Ok(());
+        if let Some(subject) = &data.digest {
+            fetch_signature(&rt, image, subject, &digest(&wasm))?;
+        }
+    } else {
+        // A container image: tar layers + config.
+        let layers: Vec<(String, Vec<u8>)> = data
+            .layers
+            .into_iter()
+            .map(|l| (l.media_type, l.data.to_vec()))
+            .collect();
+        crate::images::store_pulled_image(
+            reference,
+            &layers,
+            &data.config.data,
+            &ensure_component,
+        )?;
+    }
+    // Which manifest the reference now stands for, once its content is in.
+    if let Some(digest) = data.digest {
+        let mut manifests = load_index(&manifests_path());
+        if manifests.get(reference) != Some(&digest) {
+            manifests.insert(reference.to_string(), digest);
+            save_index(&manifests_path(), &manifests)?;
+        }
     }
-    // A container image: tar layers + config.
-    let layers: Vec<(String, Vec<u8>)> = data
-        .layers
-        .into_iter()
-        .map(|l| (l.media_type, l.data.to_vec()))
-        .collect();
-    crate::images::store_pulled_image(reference, &layers, &data.config.data, &ensure_component)?;
     Ok(())
 }
 
-/// Push `wasm` to `reference` as a Wasm OCI Artifact (anonymously). Blocking.
+/// Push `wasm` to `reference` as a Wasm OCI Artifact. Blocking.
 pub fn push(reference: &str, wasm: &[u8]) -> Result<(), String> {
     let image: Reference = reference
         .parse()
@@ -336,7 +631,7 @@ pub fn push(reference: &str, wasm: &[u8]) -> Result<(), String> {
         .map_err(|e| format!("tokio runtime: {e}"))?;
 
     rt.block_on(async {
-        let client = client_for(&image);
+        let (client, auth) = client_for(&image)?;
         let layer = ImageLayer::new(wasm.to_vec(), WASM_LAYER.to_string(), None);
         let config = Config::new(
             WASM_CONFIG_BODY.as_bytes().to_vec(),
@@ -346,14 +641,150 @@ pub fn push(reference: &str, wasm: &[u8]) -> Result<(), String> {
         // `manifest: None` lets oci-client build the OCI manifest from the
         // config + layer (digests and sizes filled in).
         client
-            .push(
-                &image,
-                std::slice::from_ref(&layer),
-                config,
-                &RegistryAuth::Anonymous,
-                None,
+            .push(&image, std::slice::from_ref(&layer), config, &auth, None)
+            .await
+            .map_err(|e| format!("failed to push {reference}: {e}"))?;
+        Ok(())
+    })
+}
+
+/// A signature's referrer manifest: its one layer and config, and the manifest
+/// itself as the exact bytes that go up, with their digest.
+struct SignatureManifest {
+    layer: ImageLayer,
+    config: Config,
+    body: Vec<u8>,
+    digest: String,
+}
+
+/// The referrer manifest carrying `sig` for the artifact manifest
+/// `subject_digest` (`subject_size` bytes): of artifact type
+/// `SIGNATURE_MEDIA`, with the artifact's manifest as its `subject`. It is
+/// serialized once, so its digest is over the very bytes that are pushed.
+fn signature_manifest(
+    sig: &crate::signing::Signature,
+    subject_digest: &str,
+    subject_size: usize,
+) -> Result<SignatureManifest, String> {
+    let json = serde_json::to_vec(sig).map_err(|e| format!("encode signature: {e}"))?;
+    let layer = ImageLayer::new(json, SIGNATURE_MEDIA.to_string(), None);
+    let config = Config::new(b"{}".to_vec(), EMPTY_MEDIA.to_string(), None);
+    let mut manifest = OciImageManifest::build(std::slice::from_ref(&layer), &config, None);
+    manifest.artifact_type = Some(SIGNATURE_MEDIA.to_string());
+    manifest.subject = Some(OciDescriptor {
+        media_type: OCI_IMAGE_MEDIA_TYPE.to_string(),
+        digest: subject_digest.to_string(),
+        size: subject_size as i64,
+        ..Default::default()
+    });
+    let body =
+        serde_json::to_vec(&manifest).map_err(|e| format!("encode signature manifest: {e}"))?;
+    let digest = crate::images::oci_digest(&digest(&body));
+    Ok(SignatureManifest {
+        layer,
+        config,
+        body,
+        digest,
+    })
+}
+
+/// Attach `sig` to the artifact at `reference` (see [`push`]) by the OCI
+/// referrers convention (see [`signature_manifest`]). A registry with the
+/// referrers API gets it by its own digest and lists it; one without gets it
+/// under the artifact's `sha256-<hex>.sig` tag, where [`pull_into_cache`]
+/// looks next. Blocking.
+pub fn push_signature(reference: &str, sig: &crate::signing::Signature) -> Result<(), String> {
+    let image: Reference = reference
+        .parse()
+        .map_err(|e| format!("invalid OCI reference {reference:?}: {e}"))?;
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("tokio runtime: {e}"))?;
+    rt.block_on(async {
+        let (client, auth) = client_for(&image)?;
+        let (subject, subject_digest) = client
+            .pull_manifest_raw(&image, &auth, &[OCI_IMAGE_MEDIA_TYPE])
+            .await
+            .map_err(|e| format!("failed to read {reference}'s manifest: {e}"))?;
+        let m = signature_manifest(sig, &subject_digest, subject.len())?;
+        let sig_ref = match client
+            .pull_referrers(&at_digest(&image, &subject_digest)?, None)
+            .await
+        {
+            Ok(_) => at_digest(&image, &m.digest)?,
+            Err(e) if not_found(&e) => signature_tag(&image, &subject_digest)?,
+            Err(e) => return Err(format!("failed to list {reference}'s referrers: {e}")),
+        };
+        // Blobs first, then the manifest as `m.body` byte for byte: `push`
+        // would serialize the manifest again.
+        let failed =
+            |e: OciDistributionError| format!("failed to push the signature for {reference}: {e}");
+        client
+            .auth(&image, &auth, RegistryOperation::Push)
+            .await
+            .map_err(failed)?;
+        for (data, digest) in [
+            (&m.layer.data, m.layer.sha256_digest()),
+            (&m.config.data, m.config.sha256_digest()),
+        ] {
+            client
+                .push_blob(&image, data.to_vec(), &digest)
+                .await
+                .map_err(failed)?;
+        }
+        client
+            .push_manifest_raw(
+                &sig_ref,
+                m.body,
+                hyper::header::HeaderValue::from_static(OCI_IMAGE_MEDIA_TYPE),
             )
             .await
+            .map_err(failed)?;
+        Ok(())
+    })
+}
+
+/// Push the stored container image `manifest` to `reference`: every layer
+/// tar, an OCI image config built from the manifest (see
+/// [`crate::images::image_config`]) and the OCI manifest tying them together
+/// — what [`pull_into_cache`] reads back, on any machine, as the same image.
+/// Layers go up uncompressed, as the store keeps them, so their digests are
+/// their store keys. Blocking.
+pub fn push_image(reference: &str, manifest: &crate::images::ImageManifest) -> Result<(), String> {
+    let image: Reference = reference
+        .parse()
+        .map_err(|e| format!("invalid OCI reference {reference:?}: {e}"))?;
+    let layers = manifest
+        .layers
+        .iter()
+        .map(|key| {
+            let tar = std::fs::read(crate::images::layer_path(key))
+                .map_err(|e| format!("layer {key}: {e}"))?;
+            Ok(ImageLayer::new(
+                tar,
+                crate::layout::LAYER_MEDIA.to_string(),
+                None,
+            ))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let config = Config::new(
+        crate::images::image_config(manifest),
+        crate::layout::CONFIG_MEDIA.to_string(),
+        None,
+    );
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("tokio runtime: {e}"))?;
+    rt.block_on(async {
+        let (client, auth) = client_for(&image)?;
+        // As for an artifact, oci-client builds the manifest from the config
+        // and layers (digests and sizes filled in).
+        client
+            .push(&image, &layers, config, &auth, None)
+            .await
             .map_err(|e| format!("failed to push {reference}: {e}"))?;
         Ok(())
     })
@@ -380,20 +811,9 @@ mod tests {
         assert!(!a.file_name().unwrap().to_string_lossy().contains('/'));
     }
 
-    /// Point this test's cache at a fresh temp dir. Thread-local (not an env
-    /// var), so parallel tests under plain `cargo test` can't redirect each
-    /// other's caches.
-    fn isolated_cache(name: &str) -> PathBuf {
-        let dir = std::env::temp_dir().join(format!("wk-oci-cache-{name}"));
-        let _ = std::fs::remove_dir_all(&dir);
-        std::fs::create_dir_all(&dir).unwrap();
-        set_test_cache_root(&dir);
-        dir
-    }
-
     #[test]
     fn blobs_are_content_addressed_and_deduped() {
-        isolated_cache("blobs");
+        isolated_store("blobs");
         let a = put_blob(b"same bytes").unwrap();
         let b = put_blob(b"same bytes").unwrap();
         let c = put_blob(b"other bytes").unwrap();
@@ -406,7 +826,7 @@ mod tests {
 
     #[test]
     fn two_references_to_the_same_bytes_share_one_blob() {
-        isolated_cache("sharedblob");
+        isolated_store("sharedblob");
         let a = store_artifact("ghcr.io/org/foo:1.0", b"\0asm-shared").unwrap();
         let b = store_artifact("ghcr.io/org/bar:2.0", b"\0asm-shared").unwrap();
         assert_eq!(a, b, "both refs resolve to the same blob");
@@ -416,7 +836,7 @@ mod tests {
 
     #[test]
     fn repulling_a_moved_tag_repoints_the_index() {
-        isolated_cache("movedtag");
+        isolated_store("movedtag");
         let old = store_artifact("ghcr.io/org/app:latest", b"\0asm-v1").unwrap();
         let new = store_artifact("ghcr.io/org/app:latest", b"\0asm-v2").unwrap();
         assert_ne!(old, new);
@@ -427,7 +847,7 @@ mod tests {
 
     #[test]
     fn legacy_ref_keyed_files_migrate_into_the_blob_store() {
-        isolated_cache("migrate");
+        isolated_store("migrate");
         let reference = "ghcr.io/org/old:1.0";
         let legacy = legacy_ref_path(reference);
         std::fs::create_dir_all(legacy.parent().unwrap()).unwrap();
@@ -443,7 +863,57 @@ mod tests {
 
     #[test]
     fn unpulled_reference_resolves_to_nothing() {
-        isolated_cache("unpulled");
+        isolated_store("unpulled");
         assert_eq!(cached_artifact("ghcr.io/org/nope:1.0"), None);
     }
+
+    #[test]
+    fn only_a_trusted_signature_over_the_cached_bytes_verifies() {
+        let dir = isolated_store("signed");
+        let key = crate::signing::load_or_create(&dir.join("wk.key")).unwrap();
+        let trusted = crate::signing::key_string(&key.verifying_key());
+        let reference = "ghcr.io/org/synth:1.0";
+        store_artifact(reference, b"synth component").unwrap();
+        let err = verified_artifact(reference, &trusted).unwrap_err();
+        assert!(err.contains("unsigned"), "{err}");
+
+        let blob = digest(b"synth component");
+        let sig = crate::signing::sign(&key, &crate::images::oci_digest(&blob));
+        store_signature(&blob, sig.clone()).unwrap();
+        assert_eq!(
+            verified_artifact(reference, &trusted).unwrap(),
+            blob_path(&blob)
+        );
+
+        // Bytes swapped under the signed blob's name don't pass for it.
+        std::fs::write(blob_path(&blob), b"tampered").unwrap();
+        let err = verified_artifact(reference, &trusted).unwrap_err();
+        assert!(err.contains("don't match their digest"), "{err}");
+
+        // The tag moves to other bytes; the old signature doesn't cover them.
+        store_artifact(reference, b"something else").unwrap();
+        store_signature(&digest(b"something else"), sig).unwrap();
+        let err = verified_artifact(reference, &trusted).unwrap_err();
+        assert!(err.contains("doesn't match"), "{err}");
+    }
+
+    /// A signature's referrer manifest is pushed as exactly the bytes its
+    /// digest was taken over, pointing at the artifact it signs.
+    #[test]
+    fn a_signature_manifest_is_addressed_by_its_own_bytes() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let sig = crate::signing::sign(&key, "sha256:00ff");
+        let subject = format!("sha256:{}", "ab".repeat(32));
+        let m = signature_manifest(&sig, &subject, 512).unwrap();
+        assert_eq!(m.digest, crate::images::oci_digest(&digest(&m.body)));
+        let uploaded: OciImageManifest = serde_json::from_slice(&m.body).unwrap();
+        assert_eq!(uploaded.artifact_type.as_deref(), Some(SIGNATURE_MEDIA));
+        let pointed = uploaded.subject.unwrap();
+        assert_eq!(
+            (pointed.digest.as_str(), pointed.size),
+            (subject.as_str(), 512)
+        );
+        assert_eq!(uploaded.layers[0].digest, m.layer.sha256_digest());
+        assert_eq!(uploaded.config.digest, m.config.sha256_digest());
+    }
 }
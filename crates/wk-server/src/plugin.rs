@@ -154,8 +154,12 @@ pub struct Node {
     /// The compiled component and its wiring, filled in by the background compile
     /// thread. `None` while the node is still compiling.
     pub setup: OnceLock<NodeSetup>,
-    /// Environment for the guest (a container image's ENV), applied on run.
-    pub env: Vec<(String, String)>,
+    /// The container image's ENV (empty for a plain wasm node).
+    pub image_env: Vec<(String, String)>,
+    /// The node's own variables, layered over `image_env` whenever its guest
+    /// or a `wk:exec` child starts — so a change applies from the next run
+    /// (see [`Node::environment`]).
+    pub env: Mutex<Vec<(String, String)>>,
     /// The container image's layer digests mounted into `fs` (empty for a
     /// plain wasm node) — the file inspector shows the count and badges
     /// layer-backed entries.
@@ -171,6 +175,12 @@ pub struct Node {
     /// into. Exists on every node so a consumer can be wired before the
     /// provider runs — calls fail fast (EIO) until a serve loop attaches.
     pub fs_serve: Arc<wk_vfs::ProviderConn>,
+    /// What the node's stores count as they run — memory, frames, MIDI, live
+    /// children — for `wk stats` (see [`crate::stats`]).
+    pub stats: crate::stats::SharedCounters,
+    /// How the guest last ended — status, trap, or stop — set by its thread
+    /// as it ends and cleared when it runs again.
+    pub exit: Arc<Mutex<Option<wk_protocol::ipc::ExitInfo>>>,
 }
 
 /// A node's compiled component plus how to run and wire it — published once the
@@ -240,6 +250,42 @@ impl Node {
     pub fn is_runnable(&self) -> bool {
         self.setup.get().is_some_and(|s| s.run.is_some())
     }
+    /// The environment a guest started now gets: the image's ENV with the
+    /// node's own variables layered over it (see [`layer_env`]).
+    pub fn environment(&self) -> Vec<(String, String)> {
+        layer_env(self.image_env.clone(), &self.env.lock().unwrap())
+    }
+    /// Apply the node's resource limits: memory and CPU to every store that
+    /// runs for it (from its next memory growth or epoch tick), the quotas to
+    /// its filesystem.
+    pub fn set_limits(&self, limits: &wk_protocol::Limits) {
+        self.stats.set_limits(limits.memory, limits.cpu);
+        self.fs.lock().unwrap().set_quota(crate::vfs::Quota {
+            nodes: limits
+                .fs_nodes
+                .map(|n| usize::try_from(n).unwrap_or(usize::MAX)),
+            bytes: limits.fs_bytes,
+        });
+    }
+    /// The limit the node last ran into, as its error: whatever a store held
+    /// its guest to, else a filesystem quota its guest hit.
+    pub fn limit_error(&self) -> Option<String> {
+        if let Some(e) = self.stats.exceeded.lock().unwrap().clone() {
+            return Some(e);
+        }
+        let fs = self.fs.lock().unwrap();
+        let quota = fs.quota();
+        match fs.quota_hit()? {
+            crate::vfs::QuotaHit::Nodes => Some(format!(
+                "filesystem quota reached ({} entries)",
+                quota.nodes.unwrap_or_default()
+            )),
+            crate::vfs::QuotaHit::Bytes => Some(format!(
+                "filesystem quota reached ({})",
+                crate::stats::limit_size(quota.bytes.unwrap_or_default())
+            )),
+        }
+    }
     /// A `wasi:cli/command` guest (a terminal app), as opposed to a graphical or
     /// http node — the nodes a CLI client can `attach` to.
     pub fn is_command(&self) -> bool {
@@ -374,6 +420,10 @@ pub struct HostState {
     /// [`GatedHttpHooks`]).
     http_hooks: GatedHttpHooks,
     gpu: Arc<wgpu_core::global::Global>,
+    /// Counts this store's linear memory (and the rest of [`crate::stats`])
+    /// into the node it runs for; a throwaway set for stores that aren't a
+    /// node's.
+    pub(crate) meter: crate::stats::Meter,
 }
 
 impl wasmtime_wasi_http::p2::WasiHttpView for HostState {
@@ -762,7 +812,9 @@ impl wasi::graphics_context::graphics_context::HostContext for HostState {
 
     fn present(&mut self, _self_: Resource<ContextState>) -> Result<()> {
         // Decoupled compositing: the pixels were already written via the
-        // frame-buffer; the server reads the latest buffer each frame.
+        // frame-buffer; the server reads the latest buffer each frame. All
+        // that's left to do is count it.
+        self.meter.frame();
         Ok(())
     }
 
@@ -955,6 +1007,7 @@ impl PluginHost {
     /// filesystem and nothing else — no surfaces, no MIDI, no capture, no
     /// network, and no `wk:exec` of its own beyond `depth`. It therefore can't
     /// reach anything its parent couldn't.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn run_program(
         &self,
         wasm: &[u8],
@@ -963,6 +1016,7 @@ impl PluginHost {
         fs: &crate::vfs::SharedFs,
         stdin: Vec<u8>,
         depth: u32,
+        stats: &crate::stats::SharedCounters,
     ) -> std::result::Result<crate::exec::Output, String> {
         self.spawn_program(
             wasm,
@@ -973,6 +1027,7 @@ impl PluginHost {
             Sink::Capture,
             Sink::Capture,
             depth,
+            stats,
         )?
         .wait()
     }
@@ -1000,6 +1055,7 @@ impl PluginHost {
         stdout: Sink,
         stderr: Sink,
         depth: u32,
+        stats: &crate::stats::SharedCounters,
     ) -> std::result::Result<Child, String> {
         use wasmtime_wasi::p2::pipe::{MemoryInputPipe, MemoryOutputPipe};
 
@@ -1009,6 +1065,12 @@ impl PluginHost {
             .build_linker()
             .map_err(|e| format!("link program: {e:#}"))?;
 
+        // A child on a terminal drives it as a node's guest would: its
+        // `wk:tty/control` calls (raw mode, window size) reach that terminal.
+        let term_io = match (&stdin, &stdout) {
+            (Stdin::Term(io), _) | (_, Sink::Term(io)) => io.clone(),
+            _ => crate::terminal::TermIo::new(),
+        };
         // Only a captured sink has bytes to hand back at `wait`; a piped one
         // has already delivered them to whoever is reading the other end.
         let mut b = WasiCtxBuilder::new();
@@ -1017,6 +1079,7 @@ impl PluginHost {
             Stdin::Empty => b.stdin(MemoryInputPipe::new(Vec::new())),
             Stdin::Bytes(bytes) => b.stdin(MemoryInputPipe::new(bytes)),
             Stdin::Pipe(r) => b.stdin(r),
+            Stdin::Term(io) => b.stdin(crate::terminal::stdin(&io)),
         };
         let out = match stdout {
             Sink::Capture => {
@@ -1028,6 +1091,10 @@ impl PluginHost {
                 b.stdout(w);
                 None
             }
+            Sink::Term(io) => {
+                b.stdout(crate::terminal::stdout(&io));
+                None
+            }
         };
         let err = match stderr {
             Sink::Capture => {
@@ -1039,6 +1106,10 @@ impl PluginHost {
                 b.stderr(w);
                 None
             }
+            Sink::Term(io) => {
+                b.stderr(crate::terminal::stdout(&io));
+                None
+            }
         };
         for (k, v) in env {
             b.env(k, v);
@@ -1049,7 +1120,7 @@ impl PluginHost {
             registry: Arc::new(Mutex::new(Vec::new())),
             node_id: NodeId::nil(),
             fs: fs.clone(),
-            term_io: crate::terminal::TermIo::new(),
+            term_io,
             capture_src: crate::capture::new_src(),
             capture_seq: 0,
             // Children may nest further, up to exec::MAX_DEPTH.
@@ -1057,6 +1128,7 @@ impl PluginHost {
                 host: Arc::new(self.clone()),
                 depth,
                 permit: crate::exec::new_permit(true),
+                stats: stats.clone(),
             }),
             midi_in: crate::midi::new_inbox(),
             midi_router: self.midi.clone(),
@@ -1068,9 +1140,15 @@ impl PluginHost {
             http_ctx: wasmtime_wasi_http::WasiHttpCtx::new(),
             http_hooks: GatedHttpHooks { stack: None },
             gpu: Arc::clone(&self.gpu),
+            meter: crate::stats::Meter::new(stats.clone()),
         };
         let engine = self.engine.clone();
         let name = argv[0].clone();
+        let kill = Arc::new(AtomicBool::new(false));
+        let kill_cb = kill.clone();
+        let mut slicer = crate::stats::Slicer::new(stats.clone(), kill.clone());
+        // Counted live from here until its thread ends, however it ends.
+        let live = crate::stats::Live::new(stats.clone());
         // The caller is *already* inside a tokio runtime (the guest's own
         // async host call), so the child gets its own thread: nesting
         // `block_on` in a running runtime panics. Handing back the join handle
@@ -1080,11 +1158,21 @@ impl PluginHost {
         let join = std::thread::Builder::new()
             .name("wk-exec-child".into())
             .spawn(move || {
+                let _live = live;
                 let mut store = Store::new(&engine, state);
+                store.limiter(|s| &mut s.meter);
                 // Epochs kill runaway *nodes*; a child inherits that budget
-                // rather than being cut off by a tick meant for its parent.
+                // rather than being cut off by a tick meant for its parent,
+                // and stops only when its own kill switch is thrown.
                 store.set_epoch_deadline(1);
-                store.epoch_deadline_callback(|_| Ok(wasmtime::UpdateDeadline::Continue(1)));
+                store.epoch_deadline_callback(move |_| {
+                    slicer.tick();
+                    if kill_cb.load(Ordering::Relaxed) {
+                        Ok(UpdateDeadline::Interrupt)
+                    } else {
+                        Ok(UpdateDeadline::Continue(1))
+                    }
+                });
                 let rt = tokio::runtime::Builder::new_current_thread()
                     .enable_time()
                     .build()
@@ -1101,6 +1189,7 @@ impl PluginHost {
             out,
             err,
             name,
+            kill,
         })
     }
 }
@@ -1114,6 +1203,8 @@ pub enum Stdin {
     /// The reading end of a pipe — whatever the other end writes, as it is
     /// written.
     Pipe(crate::execpipe::PipeReader),
+    /// A terminal's keyboard queue (`wk exec -it`).
+    Term(crate::terminal::SharedTermIo),
 }
 
 /// Where a spawned child's stdout or stderr goes.
@@ -1123,6 +1214,8 @@ pub enum Sink {
     /// The writing end of a pipe. Dropped when the child exits, which is what
     /// gives the reader its end-of-file.
     Pipe(crate::execpipe::PipeWriter),
+    /// A terminal's screen, as a node's guest writes to it (`wk exec -it`).
+    Term(crate::terminal::SharedTermIo),
 }
 
 /// A running program. Dropping it detaches; [`wait`](Self::wait) collects.
@@ -1131,9 +1224,17 @@ pub struct Child {
     out: Option<wasmtime_wasi::p2::pipe::MemoryOutputPipe>,
     err: Option<wasmtime_wasi::p2::pipe::MemoryOutputPipe>,
     name: String,
+    kill: Arc<AtomicBool>,
 }
 
 impl Child {
+    /// The child's kill switch: set it and the program traps at the next
+    /// epoch tick, the way a stopped node does. Taken before [`wait`](Self::wait)
+    /// so another thread can end a program that is being waited for.
+    pub fn kill_switch(&self) -> Arc<AtomicBool> {
+        self.kill.clone()
+    }
+
     /// Block until it exits, then report its status and any captured output.
     pub fn wait(self) -> std::result::Result<crate::exec::Output, String> {
         let status = self
@@ -1206,6 +1307,7 @@ impl crate::images::BuildRunner for PluginHost {
                 host: Arc::new(self.clone()),
                 depth: 0,
                 permit: crate::exec::new_permit(true),
+                stats: crate::stats::new_counters(),
             }),
             midi_in: crate::midi::new_inbox(),
             midi_router: self.midi.clone(),
@@ -1220,6 +1322,7 @@ impl crate::images::BuildRunner for PluginHost {
             // builds hermetic.
             http_hooks: GatedHttpHooks { stack: None },
             gpu: Arc::clone(&self.gpu),
+            meter: crate::stats::Meter::new(crate::stats::new_counters()),
         };
         let mut store = Store::new(&self.engine, state);
         // The engine runs with epoch interruption (for killing runaway nodes);
@@ -1439,6 +1542,7 @@ impl PluginHost {
             // denied — an incoming-handler can't proxy to arbitrary hosts.
             http_hooks: GatedHttpHooks { stack: None },
             gpu: gpu.clone(),
+            meter: crate::stats::Meter::new(crate::stats::new_counters()),
         };
         let engine = self.engine.clone();
         std::thread::spawn(move || {
@@ -1496,7 +1600,8 @@ impl PluginHost {
     /// `setup` is published and, for a non-networked non-http node, its guest
     /// starts. A **networked** node (imports wasi:sockets) stays idle so it can be
     /// wired onto a Network/Gateway before it runs; an **http** server node stays
-    /// idle until served on a Port.
+    /// idle until served on a Port. `env` is the node's own environment,
+    /// layered over the container image's ENV.
     #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         &self,
@@ -1508,6 +1613,7 @@ impl PluginHost {
         nodes: NodeRegistry,
         initial_options: Vec<f32>,
         container: Option<crate::images::ContainerSetup>,
+        env: &[(String, String)],
     ) -> Result<()> {
         let node = Arc::new(Node {
             id,
@@ -1522,10 +1628,11 @@ impl PluginHost {
             running: Arc::new(AtomicBool::new(false)),
             kill: Arc::new(AtomicBool::new(false)),
             setup: OnceLock::new(),
-            env: container
+            image_env: container
                 .as_ref()
                 .map(|c| c.env.clone())
                 .unwrap_or_default(),
+            env: Mutex::new(env.to_vec()),
             layers: container
                 .as_ref()
                 .map(|c| c.layers.clone())
@@ -1535,6 +1642,8 @@ impl PluginHost {
             // before the guest does).
             exec_permit: crate::exec::new_permit(true),
             fs_serve: wk_vfs::ProviderConn::new(),
+            stats: crate::stats::new_counters(),
+            exit: Arc::new(Mutex::new(None)),
         });
         nodes.lock().unwrap().push(node.clone());
 
@@ -1652,6 +1761,8 @@ impl PluginHost {
         }
         node.finished.store(false, Ordering::Relaxed);
         node.kill.store(false, Ordering::Relaxed);
+        *node.exit.lock().unwrap() = None;
+        node.stats.exceeded.lock().unwrap().take();
         // Re-open stdin in case a previous `stop` closed it (EOF).
         node.term_io.reopen();
 
@@ -1678,9 +1789,9 @@ impl PluginHost {
             .stderr(crate::terminal::stdout(&node.term_io))
             .stdin(crate::terminal::stdin(&node.term_io))
             .args(&argv);
-        // A container image's ENV first, then the terminal vars (so TERM etc.
-        // reflect the actual terminal even if the image sets them).
-        for (k, v) in &node.env {
+        // The image ENV + node overrides first, then the terminal vars (so TERM
+        // etc. reflect the actual terminal even if the image sets them).
+        for (k, v) in &node.environment() {
             ctx_builder.env(k, v);
         }
         ctx_builder
@@ -1702,6 +1813,7 @@ impl PluginHost {
                 host: Arc::new(self.clone()),
                 depth: 0,
                 permit: node.exec_permit.clone(),
+                stats: node.stats.clone(),
             }),
             midi_in: node.midi_in.clone(),
             midi_router: self.midi.clone(),
@@ -1716,12 +1828,16 @@ impl PluginHost {
             http_ctx: wasmtime_wasi_http::WasiHttpCtx::new(),
             http_hooks: GatedHttpHooks { stack: http_stack },
             gpu: Arc::clone(&self.gpu),
+            meter: crate::stats::Meter::new(node.stats.clone()),
         };
         let mut store = Store::new(&self.engine, state);
+        store.limiter(|s| &mut s.meter);
         // Trap the instance once it has been killed; otherwise keep running.
         store.set_epoch_deadline(1);
         let kill_cb = node.kill.clone();
+        let mut slicer = crate::stats::Slicer::new(node.stats.clone(), node.kill.clone());
         store.epoch_deadline_callback(move |_| {
+            slicer.tick();
             if kill_cb.load(Ordering::Relaxed) {
                 Ok(UpdateDeadline::Interrupt)
             } else {
@@ -1733,6 +1849,7 @@ impl PluginHost {
         let finished = node.finished.clone();
         let running = node.running.clone();
         let kill = node.kill.clone();
+        let exit = node.exit.clone();
         // A provider node's conduit accepts consumer calls only while its
         // serve loop can answer them; otherwise they fail fast (EIO).
         let fs_conn = node.serves_fs().then(|| node.fs_serve.clone());
@@ -1747,19 +1864,17 @@ impl PluginHost {
                 .enable_time()
                 .build()
                 .expect("tokio runtime");
-            let result: Result<()> = rt.block_on(async move {
+            let result: Result<i32> = rt.block_on(async move {
                 if is_command {
                     // Either WASI generation, by the `wasi:cli/run` version the
                     // component exports. A clean `exit()` (incl. `main`
                     // returning) or a non-zero status is a normal end, not a
                     // host error.
-                    run_command(&mut store, &component, &linker)
-                        .await
-                        .map(|_| ())
+                    run_command(&mut store, &component, &linker).await
                 } else {
                     let compositor =
                         Compositor::instantiate_async(&mut store, &component, &linker).await?;
-                    compositor.call_run(&mut store).await
+                    compositor.call_run(&mut store).await.map(|()| 0)
                 }
             });
             // The serve loop is gone with the guest: fail in-flight consumer
@@ -1767,10 +1882,13 @@ impl PluginHost {
             if let Some(conn) = &fs_conn {
                 conn.end_serving();
             }
+            // Recorded before `running` drops, so whoever sees the node stop
+            // also sees how.
+            *exit.lock().unwrap() = Some(exit_info(&result, kill.load(Ordering::Relaxed)));
             finished.store(true, Ordering::Relaxed);
             running.store(false, Ordering::Relaxed);
             match result {
-                Ok(()) => {}
+                Ok(_) => {}
                 // A clean close (surface closed, or the kill switch tripped):
                 // exit quietly.
                 Err(_) if kill.load(Ordering::Relaxed) => {}
@@ -1782,10 +1900,95 @@ impl PluginHost {
     }
 }
 
+/// How a guest's run ended, for [`Node::exit`]. Stopping it — the kill
+/// switch, or its surface closing — is neither a status nor a trap.
+fn exit_info(result: &Result<i32>, killed: bool) -> wk_protocol::ipc::ExitInfo {
+    let at_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let mut info = wk_protocol::ipc::ExitInfo {
+        code: None,
+        trap: None,
+        backtrace: None,
+        at_ms,
+    };
+    match result {
+        Ok(code) => info.code = Some(*code),
+        Err(_) if killed => {}
+        Err(e) if e.downcast_ref::<SurfaceClosed>().is_some() => {}
+        Err(e) => {
+            // A trap names itself; anything else is a host error, reported
+            // whole.
+            info.trap = Some(match e.downcast_ref::<wasmtime::Trap>() {
+                Some(trap) => trap.to_string(),
+                None => format!("{e:#}"),
+            });
+            info.backtrace = e
+                .downcast_ref::<wasmtime::WasmBacktrace>()
+                .map(|bt| bt.to_string())
+                .filter(|bt| !bt.trim().is_empty());
+        }
+    }
+    info
+}
+
+/// `base` (a container image's ENV) with `over` (a node's own variables)
+/// layered on top: a key in `over` replaces the base value in place, new keys
+/// append. Merged rather than concatenated because a guest's `getenv` returns
+/// the *first* match, so a duplicate key would silently keep the image value.
+pub fn layer_env(
+    mut base: Vec<(String, String)>,
+    over: &[(String, String)],
+) -> Vec<(String, String)> {
+    for (k, v) in over {
+        match base.iter_mut().find(|(bk, _)| bk == k) {
+            Some(slot) => slot.1 = v.clone(),
+            None => base.push((k.clone(), v.clone())),
+        }
+    }
+    base
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Node variables override the image's by key, keeping its order; new
+    /// ones append.
+    #[test]
+    fn node_env_layers_over_image_env() {
+        let kv = |k: &str, v: &str| (k.to_string(), v.to_string());
+        let merged = layer_env(
+            vec![kv("PATH", "/bin"), kv("LANG", "C")],
+            &[kv("LANG", "en_US.UTF-8"), kv("EDITOR", "vim")],
+        );
+        assert_eq!(
+            merged,
+            vec![
+                kv("PATH", "/bin"),
+                kv("LANG", "en_US.UTF-8"),
+                kv("EDITOR", "vim")
+            ]
+        );
+    }
+
+    /// A status, a trap and a stop each record as themselves; a stop is
+    /// neither status nor trap even though the guest errored out of it.
+    #[test]
+    fn exit_info_tells_status_trap_and_stop_apart() {
+        let exited = exit_info(&Ok(3), false);
+        assert_eq!((exited.code, exited.trap.as_deref()), (Some(3), None));
+
+        let trapped = exit_info(&Err(wasmtime::Trap::UnreachableCodeReached.into()), false);
+        assert_eq!(trapped.code, None);
+        assert!(trapped.trap.as_deref().unwrap().contains("unreachable"));
+
+        let stopped = exit_info(&Err(wasmtime::Trap::Interrupt.into()), true);
+        assert_eq!((stopped.code, stopped.trap), (None, None));
+        assert!(stopped.at_ms > 0);
+    }
+
     /// wk is a WASI 0.3 host: the standard `@0.3.0` interfaces link onto a
     /// `Linker<HostState>` (proving `HostState: WasiView` satisfies p3), and the
     /// 0.2 and 0.3 generations coexist in one linker without a name clash.
@@ -1845,6 +2048,7 @@ mod tests {
             http_ctx: wasmtime_wasi_http::WasiHttpCtx::new(),
             http_hooks: GatedHttpHooks { stack: None },
             gpu: Arc::clone(&host.gpu),
+            meter: crate::stats::Meter::new(crate::stats::new_counters()),
         };
 
         let res = HostSurface::new(
@@ -1938,6 +2142,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
 
@@ -2092,6 +2297,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
 
@@ -2236,6 +2442,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
 
@@ -2362,6 +2569,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
 
@@ -2514,6 +2722,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
 
@@ -2606,6 +2815,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
 
@@ -2766,6 +2976,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
         let node = {
@@ -2940,6 +3151,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
         let node = {
@@ -3161,6 +3373,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
         let node = {
@@ -3326,6 +3539,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
 
@@ -3569,6 +3783,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
         // bash imports wasi:sockets (/dev/tcp), so it's a networked node and
@@ -3685,6 +3900,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
 
@@ -3938,6 +4154,7 @@ mod tests {
                     ("HOME".into(), "/root".into()),
                 ],
             }),
+            &[],
         )
         .expect("spawn python");
         let python = nodes
@@ -4132,6 +4349,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
 
@@ -4258,6 +4476,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
         // curl imports wasi:sockets, so it waits to be Run; the first-ever
@@ -4388,6 +4607,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
 
@@ -4543,10 +4763,7 @@ mod tests {
 
         // An isolated image store (thread-local), so the build neither reads
         // nor pollutes the user's.
-        let store = std::env::temp_dir().join("wk-image-store-doctools-exec");
-        let _ = std::fs::remove_dir_all(&store);
-        std::fs::create_dir_all(&store).unwrap();
-        crate::oci::set_test_cache_root(&store);
+        crate::oci::isolated_store("doctools-exec");
 
         let host = PluginHost::new().expect("host");
         let bash_id =
@@ -4569,8 +4786,16 @@ mod tests {
 
         let run = |cmd: &str| {
             let argv = vec!["bash".to_string(), "-c".to_string(), cmd.to_string()];
-            host.run_program(&sh, &argv, &m.env, &fs, Vec::new(), 0)
-                .unwrap_or_else(|e| panic!("bash -c {cmd:?}: {e}"))
+            host.run_program(
+                &sh,
+                &argv,
+                &m.env,
+                &fs,
+                Vec::new(),
+                0,
+                &crate::stats::new_counters(),
+            )
+            .unwrap_or_else(|e| panic!("bash -c {cmd:?}: {e}"))
         };
 
         let out = run("pandoc --version");
@@ -4663,6 +4888,7 @@ mod tests {
             nodes.clone(),
             Vec::new(),
             None,
+            &[],
         )
         .expect("spawn");
 
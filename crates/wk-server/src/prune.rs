@@ -0,0 +1,352 @@
+//! Garbage collection for the local image store and blob cache (`wk images
+//! prune`). Layer tars are shared between images and pulled blobs between
+//! references, so removing an image or repointing a tag never deletes them;
+//! this does, mark-and-sweep:
+//!
+//! - **Mark** the images worth keeping — tagged, built from a Dockerfile
+//!   (aliased), pulled (stored under a `refs.json` reference), or used by a
+//!   dependency of a known `.wk` file — then every layer their manifests list
+//!   and every blob `refs.json` points at. With `--all`, only what a known
+//!   `.wk` file uses is kept; tags, aliases and references no workspace
+//!   depends on go too.
+//! - **Sweep** every other image, layer tar and blob, and the signatures
+//!   fetched for blobs that go — save what was written
+//!   in the last [`GRACE`], which may belong to a build or pull still running.
+//!
+//! A `.wk` file is known once wk has run it or added or pulled a dependency
+//! into it ([`remember`]); the list lives beside the store, and files that
+//! have since been deleted drop out of it.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::workspace::{Document, Source};
+
+/// What a prune removed — or, for a dry run, would remove.
+#[derive(Debug, Default)]
+pub struct Pruned {
+    /// Image ids removed.
+    pub images: Vec<String>,
+    /// `refs.json` references dropped (`--all` only).
+    pub refs: Vec<String>,
+    /// Layer tars and blobs removed, by store key.
+    pub blobs: Vec<String>,
+    /// Cached signatures removed, by the key of the blob they signed.
+    pub signatures: Vec<String>,
+    /// Bytes reclaimed.
+    pub bytes: u64,
+}
+
+/// How long what the store gains is safe from a prune. A build or pull writes
+/// its layers and blobs before the image or reference that reaches them, and
+/// tags an image only after storing it, so a prune running alongside would see
+/// them unreached; anything written (or reused, see [`touch`]) this recently
+/// is left for the next prune instead.
+const GRACE: Duration = Duration::from_secs(60 * 60);
+
+/// Mark `path`, a layer or blob that a build or pull is reusing, as just
+/// written, so a prune running alongside leaves it be. Best effort.
+pub(crate) fn touch(path: &Path) {
+    if let Ok(f) = std::fs::File::options().append(true).open(path) {
+        let _ = f.set_modified(SystemTime::now());
+    }
+}
+
+/// Whether `path` was written after `cutoff`.
+fn newer(path: &Path, cutoff: SystemTime) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|t| t > cutoff)
+}
+
+/// Path of the known `.wk` file list.
+fn known_path() -> PathBuf {
+    crate::oci::cache_dir().join("workspaces.json")
+}
+
+fn load_known() -> BTreeSet<PathBuf> {
+    std::fs::read(known_path())
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+fn save_known(known: &BTreeSet<PathBuf>) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(known).map_err(|e| format!("encode workspaces: {e}"))?;
+    let path = known_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("mkdir {}: {e}", parent.display()))?;
+    }
+    std::fs::write(&path, json).map_err(|e| format!("write {}: {e}", path.display()))
+}
+
+/// Record `file` as a known `.wk` file, whose dependencies a prune keeps.
+/// Best effort: a file that can't be resolved, or a list that can't be
+/// written, only means a prune has one workspace fewer to protect.
+pub fn remember(file: &Path) {
+    let Ok(file) = file.canonicalize() else {
+        return;
+    };
+    let mut known = load_known();
+    if known.insert(file) {
+        let _ = save_known(&known);
+    }
+}
+
+/// What a workspace's dependencies use from the store: image ids and
+/// `refs.json` references.
+#[derive(Default)]
+struct Used {
+    images: BTreeSet<String>,
+    refs: BTreeSet<String>,
+}
+
+impl Used {
+    fn add(&mut self, doc: &Document) {
+        for dep in &doc.dependencies {
+            match &dep.source {
+                Source::Oci(reference) => {
+                    self.refs.insert(reference.clone());
+                    self.images.insert(crate::oci::sanitize(reference));
+                }
+                Source::Dockerfile(p) => {
+                    if let Some((id, _)) = crate::images::aliased_image(p) {
+                        self.images.insert(id);
+                    }
+                }
+                Source::Image(reference) => {
+                    if let Some(id) = crate::images::resolve_ref(reference) {
+                        self.images.insert(id);
+                    }
+                }
+                Source::Path(_) => {}
+            }
+        }
+    }
+}
+
+/// The files in the store directory `dir` named `<key>.<ext>`, by key.
+fn keys_in(dir: &Path, ext: &str) -> BTreeMap<String, PathBuf> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            let key = name.strip_suffix(ext)?.strip_suffix('.')?.to_string();
+            Some((key, e.path()))
+        })
+        .collect()
+}
+
+fn size_of(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Prune the store. `workspaces` are `.wk` files to protect besides the known
+/// ones (the current `-f` file, say). A known file that no longer parses is an
+/// error rather than something to guess past — pruning what it uses would
+/// break it. With `dry_run`, nothing is removed; the report is what would be.
+pub fn prune(workspaces: &[PathBuf], all: bool, dry_run: bool) -> Result<Pruned, String> {
+    let cutoff = SystemTime::now() - GRACE;
+    let mut known = load_known();
+    let before = known.len();
+    known.retain(|p| p.is_file());
+    if known.len() != before && !dry_run {
+        save_known(&known)?;
+    }
+    let mut used = Used::default();
+    for file in known
+        .iter()
+        .chain(workspaces.iter().filter(|p| p.is_file()))
+    {
+        used.add(&Document::load_resolved(file)?);
+    }
+
+    let cache = crate::oci::cache_dir();
+    let images_dir = cache.join("images");
+    let aliases = keys_in(&images_dir, "alias");
+    let mut refs = crate::oci::load_refs();
+
+    // Mark.
+    let mut keep = used.images.clone();
+    if !all {
+        keep.extend(crate::images::load_tags().into_values());
+        keep.extend(
+            aliases
+                .values()
+                .filter_map(|p| std::fs::read_to_string(p).ok())
+                .map(|id| id.trim().to_string()),
+        );
+        keep.extend(refs.keys().map(|r| crate::oci::sanitize(r)));
+    }
+    let mut out = Pruned::default();
+    if all {
+        out.refs = refs
+            .keys()
+            .filter(|r| !used.refs.contains(*r))
+            .cloned()
+            .collect();
+        refs.retain(|r, _| used.refs.contains(r));
+    }
+    let images = crate::images::list_images();
+    let mut live_layers: BTreeSet<&String> = BTreeSet::new();
+    for (id, manifest) in &images {
+        if keep.contains(id) || newer(&images_dir.join(format!("{id}.json")), cutoff) {
+            live_layers.extend(manifest.layers.iter());
+        } else {
+            out.images.push(id.clone());
+        }
+    }
+    let live_blobs: BTreeSet<&String> = refs.values().collect();
+
+    // Sweep.
+    let mut doomed: Vec<PathBuf> = Vec::new();
+    for id in &out.images {
+        out.bytes += size_of(&images_dir.join(format!("{id}.json")));
+        out.bytes += size_of(&crate::images::entrypoint_path(id));
+    }
+    for (_, path) in aliases.iter().filter(|(_, p)| {
+        std::fs::read_to_string(p).is_ok_and(|id| out.images.contains(&id.trim().to_string()))
+    }) {
+        out.bytes += size_of(path);
+        doomed.push(path.clone());
+    }
+    for (dir, ext, live) in [
+        ("layers", "tar", &live_layers),
+        ("blobs", "wasm", &live_blobs),
+    ] {
+        for (key, path) in keys_in(&cache.join(dir), ext) {
+            if !live.contains(&key) && !newer(&path, cutoff) {
+                out.bytes += size_of(&path);
+                out.blobs.push(key);
+                doomed.push(path);
+            }
+        }
+    }
+    // A blob's signatures are worth keeping as long as the blob is.
+    for (key, path) in keys_in(&cache.join("signatures"), "json") {
+        if !live_blobs.contains(&key) && !newer(&path, cutoff) {
+            out.bytes += size_of(&path);
+            out.signatures.push(key);
+            doomed.push(path);
+        }
+    }
+    if dry_run {
+        return Ok(out);
+    }
+    if !out.refs.is_empty() {
+        crate::oci::save_refs(&refs)?;
+    }
+    for id in &out.images {
+        crate::images::remove_image(id);
+    }
+    for path in doomed {
+        std::fs::remove_file(&path).map_err(|e| format!("remove {}: {e}", path.display()))?;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::images::{self, ImageManifest};
+    use crate::oci::isolated_store;
+
+    /// A stored image whose one layer is `content`.
+    fn image(content: &[u8]) -> (String, String) {
+        let layer = images::put_layer(content).unwrap();
+        let manifest = ImageManifest {
+            layers: vec![layer.clone()],
+            entrypoint: vec!["/app.wasm".into()],
+            cmd: Vec::new(),
+            env: Vec::new(),
+            workdir: None,
+            labels: BTreeMap::new(),
+        };
+        (images::store_image(&manifest, b"\0asm").unwrap(), layer)
+    }
+
+    /// Age everything under `dir` past [`GRACE`], as if written long ago.
+    fn age(dir: &Path) {
+        let long_ago = SystemTime::now() - 2 * GRACE;
+        for entry in std::fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                age(&path);
+            } else {
+                let f = std::fs::File::options().append(true).open(&path).unwrap();
+                f.set_modified(long_ago).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn prune_sweeps_only_what_nothing_reaches() {
+        let dir = isolated_store("sweep");
+        let (tagged, tagged_layer) = image(b"tagged layer");
+        let (dangling, dangling_layer) = image(b"dangling layer");
+        let (in_use, in_use_layer) = image(b"in-use layer");
+        images::set_tag("kept", &tagged).unwrap();
+        crate::oci::store_artifact("localhost:5001/synth:1.0", b"synth wasm").unwrap();
+        let orphan = crate::oci::put_blob(b"repointed away").unwrap();
+        let signatures = crate::oci::cache_dir().join("signatures");
+        std::fs::create_dir_all(&signatures).unwrap();
+        let signed = crate::oci::digest(b"synth wasm");
+        for blob in [&signed, &orphan] {
+            std::fs::write(signatures.join(format!("{blob}.json")), b"[]").unwrap();
+        }
+        let wk = dir.join("app.wk");
+        std::fs::write(
+            &wk,
+            format!("dependencies {{\n  app \"image://{in_use}\"\n}}\n"),
+        )
+        .unwrap();
+        remember(&wk);
+        let reused = images::put_layer(b"reused layer").unwrap();
+        age(&dir);
+        // Written, or written again, by a build that hasn't tagged its image.
+        let (building, building_layer) = image(b"mid-build layer");
+        images::put_layer(b"reused layer").unwrap();
+
+        let dry = prune(&[], false, true).unwrap();
+        assert_eq!(dry.images, vec![dangling.clone()]);
+        assert_eq!(dry.blobs, vec![dangling_layer.clone(), orphan.clone()]);
+        assert!(!dry.blobs.contains(&reused));
+        assert_eq!(dry.signatures, vec![orphan.clone()]);
+        assert!(dry.bytes > 0);
+        assert!(
+            images::load_image(&dangling).is_some(),
+            "a dry run keeps it"
+        );
+
+        let done = prune(&[], false, false).unwrap();
+        assert_eq!(done.bytes, dry.bytes);
+        assert!(images::load_image(&dangling).is_none());
+        assert!(!images::layer_path(&dangling_layer).exists());
+        assert!(!crate::oci::blob_path(&orphan).exists());
+        assert!(!signatures.join(format!("{orphan}.json")).exists());
+        assert!(signatures.join(format!("{signed}.json")).exists());
+        assert!(images::layer_path(&tagged_layer).exists());
+        assert!(images::layer_path(&in_use_layer).exists());
+        assert!(images::layer_path(&building_layer).exists());
+        assert!(images::load_image(&building).is_some());
+        assert!(crate::oci::cached_artifact("localhost:5001/synth:1.0").is_some());
+
+        // --all keeps only what a known workspace uses.
+        let all = prune(&[], true, false).unwrap();
+        assert_eq!(all.images, vec![tagged.clone()]);
+        assert_eq!(all.refs, vec!["localhost:5001/synth:1.0".to_string()]);
+        assert!(images::resolve_ref("kept").is_none(), "its tag goes too");
+        assert!(!images::layer_path(&tagged_layer).exists());
+        assert!(crate::oci::cached_artifact("localhost:5001/synth:1.0").is_none());
+        assert!(images::load_image(&in_use).is_some());
+
+        // A workspace that's gone protects nothing.
+        std::fs::remove_file(&wk).unwrap();
+        let gone = prune(&[], true, false).unwrap();
+        assert_eq!(gone.images, vec![in_use]);
+        assert!(load_known().is_empty());
+    }
+}
@@ -16,6 +16,7 @@ use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
 use biscuit_auth::PublicKey;
+use wk_protocol::ipc::Created;
 use wk_protocol::{Action, Command, ResourceKind};
 
 use crate::auth;
@@ -25,10 +26,15 @@ use crate::workspace::Document;
 /// How often the server loop drains commands and ticks (~60 Hz).
 const STEP: Duration = Duration::from_millis(16);
 
-/// A message on the command channel: the bearer's token and the command it
-/// authorizes. The token travels with every command so the server can verify it
-/// independently — exactly as a networked client would send it.
-type Envelope = (Vec<u8>, Command);
+/// What the server tells a [`ServerHandle::request`] once its command is
+/// applied: what it created, or why it was refused.
+type Reply = Sender<Result<Created, String>>;
+
+/// A message on the command channel: the bearer's token, the command it
+/// authorizes, and (for a [`ServerHandle::request`]) where to reply. The token
+/// travels with every command so the server can verify it independently —
+/// exactly as a networked client would send it.
+type Envelope = (Vec<u8>, Command, Option<Reply>);
 
 /// A client's connection to a running server. Cloneable and `Send`, so every
 /// attached client (local UI, MCP bridge, network peer) holds its own. A client
@@ -52,11 +58,35 @@ impl ServerHandle {
         self
     }
 
+    /// Check a token a client presented (see [`auth::verify`]) before binding
+    /// it with [`Self::with_token`]. A server with no auth configured accepts
+    /// any token, as it authorizes everything anyway.
+    pub fn verify_token(&self, token: &[u8]) -> Result<(), String> {
+        match self.server.lock().unwrap().auth_public_key() {
+            Some(key) => auth::verify(key, token),
+            None => Ok(()),
+        }
+    }
+
     /// Queue a command (with this connection's token) for the server to apply on
     /// its next step, if the token authorizes it. Never blocks on the server;
     /// dropping the server makes this a no-op.
     pub fn send(&self, cmd: Command) {
-        let _ = self.cmds.send((self.token.as_ref().clone(), cmd));
+        let _ = self.cmds.send((self.token.as_ref().clone(), cmd, None));
+    }
+
+    /// Queue a command like [`Self::send`], but block until the server has
+    /// applied it (at most one step) and return what it created — the new node
+    /// ids, or the wire a `Create(Wire)` inferred. Errors if the token doesn't
+    /// authorize the command (every inner one, for a batch), a batch was
+    /// rolled back, or the server has stopped.
+    pub fn request(&self, cmd: Command) -> Result<Created, String> {
+        let (tx, rx) = mpsc::channel();
+        self.cmds
+            .send((self.token.as_ref().clone(), cmd, Some(tx)))
+            .map_err(|_| "the server has stopped".to_string())?;
+        rx.recv()
+            .map_err(|_| "the server has stopped".to_string())?
     }
 
     /// Whether this connection's token grants `(resource, action)` — the same
@@ -87,6 +117,25 @@ impl ServerHandle {
         Ok(self.server.lock().unwrap().ipc_snapshot())
     }
 
+    /// The undo/redo history's labels (see [`Server::history`]). Requires
+    /// document read, like [`Self::snapshot`].
+    pub fn history(&self) -> Result<(Vec<String>, Vec<String>), String> {
+        if !self.allowed(ResourceKind::Document, Action::Read) {
+            return Err("this connection's token does not grant document read".into());
+        }
+        Ok(self.server.lock().unwrap().history())
+    }
+
+    /// Subscribe to state changes (see [`crate::events`]): the baseline
+    /// snapshot and a channel of deltas relative to it. Requires document
+    /// read, like [`Self::snapshot`] — the events carry the same data.
+    pub fn subscribe(&self) -> Result<crate::events::Subscription, String> {
+        if !self.allowed(ResourceKind::Document, Action::Read) {
+            return Err("this connection's token does not grant document read".into());
+        }
+        Ok(self.server.lock().unwrap().subscribe())
+    }
+
     /// Look up a node's terminal I/O by id (for `attach`/`logs`), if it's an
     /// app node. Requires the token to grant node read (the stream carries the
     /// node's output).
@@ -101,13 +150,104 @@ impl ServerHandle {
             .map(|n| n.term_io.clone())
     }
 
+    /// A node's filesystem, for a client to browse (`action` [`Action::Read`])
+    /// or change ([`Action::Update`]) through [`crate::vfs::access`]. The
+    /// token must grant node `action`.
+    pub fn node_fs(
+        &self,
+        id: wk_protocol::NodeId,
+        action: Action,
+    ) -> Result<crate::vfs::SharedFs, String> {
+        if !self.allowed(ResourceKind::Node, action) {
+            return Err(format!(
+                "this connection's token does not grant node {}",
+                action.as_str()
+            ));
+        }
+        self.server
+            .lock()
+            .unwrap()
+            .app_node(id)
+            .map(|n| n.fs.clone())
+            .ok_or_else(|| "no such node".to_string())
+    }
+
+    /// Each app node's resource use (see [`Server::node_stats`]). Requires
+    /// node read.
+    pub fn stats(&self) -> Result<Vec<wk_protocol::ipc::NodeStats>, String> {
+        if !self.allowed(ResourceKind::Node, Action::Read) {
+            return Err("this connection's token does not grant node read".into());
+        }
+        Ok(self.server.lock().unwrap().node_stats())
+    }
+
+    /// Keep what app node `id` has written as a new image tagged `tag`, for
+    /// `wk commit` (see [`crate::images::commit`]); returns the image id.
+    /// Requires node read and node update: it copies the node's files out,
+    /// and though nothing changes on the canvas, it writes the image store
+    /// and repoints `tag`. The diff runs outside the server lock.
+    pub fn commit(&self, id: wk_protocol::NodeId, tag: &str) -> Result<String, String> {
+        for action in [Action::Read, Action::Update] {
+            if !self.allowed(ResourceKind::Node, action) {
+                return Err(format!(
+                    "this connection's token does not grant node {}",
+                    action.as_str()
+                ));
+            }
+        }
+        let (node, base) = self.server.lock().unwrap().commit_base(id)?;
+        let image = crate::images::commit(&node.fs, &base)?;
+        crate::images::set_tag(tag, &image)?;
+        Ok(image)
+    }
+
+    /// What app node `id` has changed in its filesystem relative to the image
+    /// it started from, for `wk diff` (see [`crate::images::changes`]).
+    /// Requires node read.
+    pub fn diff(&self, id: wk_protocol::NodeId) -> Result<Vec<wk_protocol::ipc::FsChange>, String> {
+        if !self.allowed(ResourceKind::Node, Action::Read) {
+            return Err("this connection's token does not grant node read".into());
+        }
+        let node = self.server.lock().unwrap().app_node(id);
+        let node = node.ok_or("no such node")?;
+        crate::images::changes(&node.fs, &node.layers)
+    }
+
+    /// Start a program from a node's filesystem, talking over `io`, for
+    /// `wk exec`. The token must grant node run and node update — the
+    /// program can change the node's files as freely as its guest can.
+    pub fn exec(
+        &self,
+        id: wk_protocol::NodeId,
+        path: &str,
+        argv: Vec<String>,
+        env: Vec<(String, String)>,
+        io: crate::exec::ExecIo,
+    ) -> Result<crate::plugin::Child, String> {
+        for action in [Action::Run, Action::Update] {
+            if !self.allowed(ResourceKind::Node, action) {
+                return Err(format!(
+                    "this connection's token does not grant node {}",
+                    action.as_str()
+                ));
+            }
+        }
+        let (host, node) = {
+            let server = self.server.lock().unwrap();
+            let node = server.app_node(id).ok_or("no such node")?;
+            (server.host.clone(), node)
+        };
+        crate::exec::start(&host, &node, path, argv, env, io)
+    }
+
     /// Mark (or clear) a node as externally attached by a CLI client, so the UI
-    /// yields its terminal. Returns whether it is a streamable terminal node.
-    /// Attaching requires node update (an attached client injects input);
-    /// releasing is always allowed, so cleanup can't be blocked by a token.
-    pub fn set_attached(&self, id: wk_protocol::NodeId, on: bool) -> bool {
+    /// yields its terminal. Refused unless it is a terminal node no other
+    /// client is attached to. Attaching requires node update (an attached
+    /// client injects input); releasing is always allowed, so cleanup can't be
+    /// blocked by a token.
+    pub fn set_attached(&self, id: wk_protocol::NodeId, on: bool) -> Result<(), String> {
         if on && !self.allowed(ResourceKind::Node, Action::Update) {
-            return false;
+            return Err("this connection's token does not grant node update".into());
         }
         self.server.lock().unwrap().set_attached(id, on)
     }
@@ -214,12 +354,29 @@ fn serve(
     while !stop.load(Ordering::Relaxed) {
         {
             let mut s = server.lock().unwrap();
-            while let Ok((token, cmd)) = rx.try_recv() {
-                let (resource, action) = cmd.required();
-                if auth::authorize(public_key, &token, resource, action) {
-                    s.apply(cmd);
-                } else {
-                    eprintln!("wk: rejected unauthorized command ({resource:?} {action:?})");
+            while let Ok((token, cmd, reply)) = rx.try_recv() {
+                // Every right the command needs — each inner command's, for a
+                // batch — or none of it runs.
+                let denied = cmd.required().into_iter().find(|&(resource, action)| {
+                    !auth::authorize(public_key, &token, resource, action)
+                });
+                let result = match denied {
+                    None => s.try_apply(cmd),
+                    Some((resource, action)) => Err(format!(
+                        "this connection's token does not grant {} {}",
+                        resource.as_str(),
+                        action.as_str()
+                    )),
+                };
+                match reply {
+                    Some(reply) => {
+                        let _ = reply.send(result);
+                    }
+                    None => {
+                        if let Err(e) = result {
+                            eprintln!("wk: rejected command: {e}");
+                        }
+                    }
                 }
             }
             // Advance the epoch so any runaway guest re-checks its kill switch,
@@ -260,7 +417,7 @@ mod tests {
         assert!(bare.view().workspaces.is_empty(), "empty view");
         assert!(bare.snapshot().is_err(), "snapshot refused");
         assert!(bare.term_io(wk_protocol::NodeId::new()).is_none());
-        assert!(!bare.set_attached(wk_protocol::NodeId::new(), true));
+        assert!(bare.set_attached(wk_protocol::NodeId::new(), true).is_err());
 
         // Document read: views and snapshots, but attach still needs update.
         let reader = runtime.handle().with_token(
@@ -273,13 +430,114 @@ mod tests {
             "a real view (the document always has one workspace)"
         );
         assert!(reader.snapshot().is_ok());
-        assert!(!reader.set_attached(wk_protocol::NodeId::new(), true));
+        let err = reader
+            .set_attached(wk_protocol::NodeId::new(), true)
+            .unwrap_err();
+        assert!(err.contains("does not grant node update"), "{err}");
 
-        // Admin: everything (attach on a nonexistent node still returns false,
-        // but for the not-a-terminal reason, not authorization — verified by
-        // the reader/admin split above).
+        // Admin: everything (attach on a nonexistent node is still refused,
+        // but for want of the node, not authorization).
         let admin = runtime.handle().with_token(tokens.mint_admin().unwrap());
         assert!(admin.snapshot().is_ok());
+        assert_eq!(
+            admin.set_attached(wk_protocol::NodeId::new(), true),
+            Err("no such node".to_string())
+        );
+        runtime.shutdown();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Committing writes an image and moves a tag, so reading the node isn't
+    /// enough: a read-only token is refused before anything is copied.
+    #[test]
+    fn commit_needs_node_update() {
+        let tokens = TokenService::new();
+        let dir = std::env::temp_dir().join("wk-commit-auth-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let runtime = ServerRuntime::spawn(
+            &Document::empty(),
+            dir.join("t.wk"),
+            tokens.public_key(),
+            tokens.mint_node_base().unwrap(),
+        )
+        .expect("spawns");
+
+        let reader = runtime.handle().with_token(
+            tokens
+                .mint(&[
+                    (ResourceKind::Node, Action::Read),
+                    (ResourceKind::Document, Action::Read),
+                ])
+                .unwrap(),
+        );
+        let err = reader
+            .commit(wk_protocol::NodeId::new(), "mine:latest")
+            .unwrap_err();
+        assert!(err.contains("does not grant node update"), "{err}");
+
+        // With update it gets as far as looking the node up.
+        let admin = runtime.handle().with_token(tokens.mint_admin().unwrap());
+        assert_eq!(
+            admin.commit(wk_protocol::NodeId::new(), "mine:latest"),
+            Err("no such node".to_string())
+        );
+        runtime.shutdown();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// A batch is authorized per inner command: one step the token doesn't
+    /// grant refuses the whole batch, so none of it lands.
+    #[test]
+    fn a_batch_needs_every_inner_right() {
+        use wk_protocol::{NodeKind, Resource};
+        let tokens = TokenService::new();
+        let dir = std::env::temp_dir().join("wk-batch-auth-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let runtime = ServerRuntime::spawn(
+            &Document::empty(),
+            dir.join("t.wk"),
+            tokens.public_key(),
+            tokens.mint_node_base().unwrap(),
+        )
+        .expect("spawns");
+        let creator = runtime.handle().with_token(
+            tokens
+                .mint(&[
+                    (ResourceKind::Node, Action::Create),
+                    (ResourceKind::Document, Action::Read),
+                ])
+                .unwrap(),
+        );
+        let ws = creator.snapshot().unwrap().workspaces[0];
+        let note = wk_protocol::NodeId::new();
+        let create = Command::Create(Resource::Node {
+            kind: NodeKind::Note,
+            pos: [0.0, 0.0],
+            ws,
+            id: Some(note),
+            args: None,
+            env: Vec::new(),
+            size: None,
+        });
+
+        // Create is granted, delete isn't: the batch is refused outright.
+        let err = creator
+            .request(Command::Batch(vec![
+                create.clone(),
+                Command::Delete(wk_protocol::ResourceRef::Node(note)),
+            ]))
+            .unwrap_err();
+        assert!(err.contains("node delete"), "{err}");
+        assert!(
+            creator.snapshot().unwrap().nodes.is_empty(),
+            "nothing landed"
+        );
+
+        // The granted step alone goes through, under the client-minted id.
+        let made = creator.request(Command::Batch(vec![create])).unwrap();
+        assert_eq!(made.nodes, vec![note]);
         runtime.shutdown();
         let _ = std::fs::remove_dir_all(&dir);
     }
@@ -12,13 +12,18 @@ use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use crate::plugin::{NodeRegistry, PluginHost, SharedNode, SharedSurface, SurfaceRegistry};
 use crate::wiring::{self, NodeClass};
 use crate::workspace::{
     secret_bytes, secret_hex, Dependency, Document, NodeSnap, SnapKind, Workspace,
 };
-use wk_protocol::{Command, NodeId, NodeKind, Resource, ResourceRef, Wire};
+use wk_protocol::ipc::Created;
+use wk_protocol::{
+    is_env_name, Command, Limits, NodeId, NodeKind, Resource, ResourceRef, Restart, RestartPolicy,
+    Wire,
+};
 
 /// Default canvas size of a file / port / network node, in canvas pixels.
 pub const FILE_W: f32 = 130.0;
@@ -415,10 +420,28 @@ pub fn host_file_name(path: &std::path::Path) -> String {
         .unwrap_or_else(|| "hostfile".to_string())
 }
 
-/// Longest undo history kept.
+/// Longest undo (and redo) history kept.
 const UNDO_CAP: usize = 200;
 
-/// A recorded inverse of one mutation, applied by [`Command::Undo`].
+/// Push onto an undo/redo stack, dropping its oldest entry past [`UNDO_CAP`].
+fn push_capped(stack: &mut Vec<Step>, step: Step) {
+    stack.push(step);
+    if stack.len() > UNDO_CAP {
+        stack.remove(0);
+    }
+}
+
+/// One entry of the undo or redo history: the inverse to apply, and a
+/// human-readable label for the mutation it reverses (what `wk history`
+/// lists). The label is fixed when the entry is recorded — by the time it's
+/// listed, the node it names may be gone.
+struct Step {
+    label: String,
+    undo: Undo,
+}
+
+/// A recorded inverse of one mutation, applied by [`Command::Undo`] (and,
+/// inverted again, by [`Command::Redo`]).
 enum Undo {
     Pos(NodeId, [f32; 2]),
     Size(NodeId, [f32; 2]),
@@ -446,6 +469,55 @@ enum Undo {
     DropWorkspace(NodeId),
     /// Recreate a workspace that was removed, with all its nodes and wiring.
     RecreateWorkspace(Box<WsSnapshot>),
+    /// A [`Command::Batch`]'s inverses, in the order they were recorded;
+    /// undone newest-first as one step.
+    Batch(Vec<Undo>),
+    /// Restore a bind's mount path (`None` = the default). Like the two
+    /// below, only recorded inside a batch, so a failed batch can put back
+    /// what an earlier step changed; on their own these edits aren't undone.
+    MountPath(NodeId, NodeId, Option<String>),
+    /// Restore a serve wire's container port (`None` = forward verbatim).
+    ServePort(NodeId, NodeId, Option<u16>),
+    /// Start (`true`) or stop a node's guest, as it was before a run/stop.
+    Running(NodeId, bool),
+    /// Restore a node's 3D pose (`None` = on the default layout cylinder).
+    /// This and the rest below put back what an update changed, batch-only.
+    Pose(NodeId, Option<[f32; 4]>),
+    /// Repoint a BindMount at its previous host path.
+    HostPath(NodeId, PathBuf),
+    /// Reopen a MidiIn node's previous device.
+    MidiDevice(NodeId, String),
+    /// Restore whether a Volume persists its bytes.
+    Persist(NodeId, bool),
+    /// Restore a HostService's fabric name and host target.
+    Service(NodeId, String, String),
+    /// Restore an app node's restart policy (`None` = never re-run).
+    Restart(NodeId, Option<Restart>),
+    /// Restore an app node's resource limits, on its live node too.
+    Limits(NodeId, Limits),
+    /// Restore an app node's whole environment, on its live node too.
+    Env(NodeId, Vec<(String, String)>),
+}
+
+impl Undo {
+    /// Whether this entry only rolls back a failed batch (see
+    /// [`Server::rollback`]) and is left out of the batch's undo step.
+    fn batch_only(&self) -> bool {
+        matches!(
+            self,
+            Undo::MountPath(..)
+                | Undo::ServePort(..)
+                | Undo::Running(..)
+                | Undo::Pose(..)
+                | Undo::HostPath(..)
+                | Undo::MidiDevice(..)
+                | Undo::Persist(..)
+                | Undo::Service(..)
+                | Undo::Restart(..)
+                | Undo::Limits(..)
+                | Undo::Env(..)
+        )
+    }
 }
 
 /// Everything needed to bring a removed workspace tab back exactly as it was.
@@ -531,6 +603,15 @@ pub struct Graph {
     pub nodes: HashMap<NodeId, NodeRec>,
     /// Per-node launch args (argv after the program name). Side table keyed by id.
     pub node_args: HashMap<NodeId, Vec<String>>,
+    /// App nodes' own environment variables, layered over their image's ENV
+    /// when the guest is spawned. Side table: only nodes given some have entries.
+    pub node_env: HashMap<NodeId, Vec<(String, String)>>,
+    /// App nodes' restart policies. Side table: only nodes with a policy
+    /// other than `no` have entries.
+    pub node_restart: HashMap<NodeId, Restart>,
+    /// App nodes' own resource limits. Side table: only nodes with some
+    /// limit have entries.
+    pub node_limits: HashMap<NodeId, Limits>,
     /// Free 3D poses (`[x, y, z, yaw]`, world units) for nodes placed off the
     /// default layout cylinder. Side table: only posed nodes have entries.
     pub pos3d: HashMap<NodeId, [f32; 4]>,
@@ -587,6 +668,29 @@ pub struct Graph {
     pub available: Vec<Dependency>,
 }
 
+/// An app node's progress under its restart policy (see
+/// [`Server::sync_restarts`]).
+#[derive(Default)]
+struct Restarting {
+    /// Re-runs since the guest last stayed up for [`RESTART_RESET`] — what
+    /// the snapshot reports as the node's `restarts`, which stops at
+    /// `u16::MAX`. As wide as `max_retries`, so any limit is reached.
+    attempts: u32,
+    /// When the pending re-run fires; `None` until the guest has exited in a
+    /// way its policy restarts.
+    due: Option<Instant>,
+    /// A re-run is waiting in `pending_run` for the component to compile; it
+    /// becomes an attempt once it starts.
+    queued: bool,
+    /// When the current run started, to tell a run that stayed up from one
+    /// that crashed straight away.
+    started: Option<Instant>,
+}
+
+/// How long a re-run guest has to stay up before its restart count (and so
+/// its backoff) starts over.
+const RESTART_RESET: Duration = Duration::from_secs(10);
+
 /// A HostService node's configuration: a host TCP service published into a
 /// Network as a named fabric peer. `target` is the host `addr:port` bridged
 /// to; the fabric listener uses the target's port, so `name:port` inside the
@@ -662,6 +766,10 @@ pub struct Server {
     /// The tick loop starts each one as soon as it's ready — so clicking play on
     /// a big container that's mid-compile is never lost to a race.
     pending_run: HashSet<NodeId>,
+    /// Where each app node with a restart policy stands under it: how many
+    /// re-runs in a row, and when the next is due. Reconciled by
+    /// `sync_restarts`; an explicit `Run` starts the count over.
+    restarting: HashMap<NodeId, Restarting>,
     /// HostPorts whose last bind attempt failed — keyed by HostPort node id, the
     /// value a human message (e.g. the localhost port is already in use by
     /// another process). Surfaced in the snapshot so `wk ps`/the UI can warn;
@@ -712,7 +820,16 @@ pub struct Server {
     auth_cache: HashMap<(NodeId, &'static str, NodeId, &'static str), (u64, bool)>,
 
     /// Inverse-command history for [`Command::Undo`].
-    undo: Vec<Undo>,
+    undo: Vec<Step>,
+    /// Steps undone since the last mutation, newest last, for
+    /// [`Command::Redo`]. Any new undoable mutation clears it.
+    redo: Vec<Step>,
+    /// Clients subscribed to state changes, and the state their deltas are
+    /// relative to. Published at the end of every [`Self::tick`].
+    events: crate::events::EventHub,
+    /// A client-minted id for the node the current create places (see
+    /// [`Resource::Node`]); consumed by [`Self::alloc_id`].
+    next_id: Option<NodeId>,
 
     next_port: u16,
     file_seq: u32,
@@ -751,6 +868,7 @@ impl Server {
             host_service_serves: HashMap::new(),
             api_conn_server: None,
             pending_run: HashSet::new(),
+            restarting: HashMap::new(),
             port_errors: HashMap::new(),
             uplinks: HashMap::new(),
             capture_feeds: HashMap::new(),
@@ -762,6 +880,9 @@ impl Server {
             node_auth: None,
             auth_cache: HashMap::new(),
             undo: Vec::new(),
+            redo: Vec::new(),
+            events: crate::events::EventHub::default(),
+            next_id: None,
             next_port: 8080,
             file_seq: 0,
             host_seq: 0,
@@ -885,8 +1006,10 @@ impl Server {
         self.kind_of(id) == Some(Kind::Gateway)
     }
 
+    /// The id for a node being placed: the create's client-minted one if it
+    /// carried one, else fresh.
     fn alloc_id(&mut self) -> NodeId {
-        NodeId::new()
+        self.next_id.take().unwrap_or_else(NodeId::new)
     }
 
     /// Every live canvas node id (app, file, port, network), for a client to
@@ -905,24 +1028,112 @@ impl Server {
             .cloned()
     }
 
+    /// What committing app node `id` starts from, for `wk commit`: the live
+    /// node, and the image it was started from with the node's own settings
+    /// folded into the config — its layers as mounted, its environment over
+    /// the image's, and its own args (if it has any) as the new CMD.
+    pub fn commit_base(
+        &self,
+        id: NodeId,
+    ) -> Result<(SharedNode, crate::images::ImageManifest), String> {
+        let node = self.app_node(id).ok_or("no such node")?;
+        let mut image = self
+            .graph
+            .available
+            .iter()
+            .find(|d| d.name == node.name)
+            .and_then(Dependency::image)
+            .filter(|_| !node.layers.is_empty())
+            .ok_or_else(|| format!("{} was not started from an image", node.name))?;
+        image.layers = node.layers.clone();
+        image.env = node.environment();
+        if let Some(args) = self.graph.node_args.get(&id).filter(|a| !a.is_empty()) {
+            if image.entrypoint.is_empty() {
+                image.entrypoint = image.cmd.first().cloned().into_iter().collect();
+            }
+            image.entrypoint.truncate(1);
+            image.cmd = args.clone();
+        }
+        Ok((node, image))
+    }
+
+    /// Each app node's resource use, for `wk stats`: the counters its stores
+    /// keep (see [`crate::stats`]) alongside what its filesystem and fabric
+    /// stack hold.
+    pub fn node_stats(&self) -> Vec<wk_protocol::ipc::NodeStats> {
+        let nodes: Vec<SharedNode> = self.node_reg.lock().unwrap().clone();
+        nodes
+            .iter()
+            .map(|n| {
+                let (fs_nodes, fs_bytes) = n.fs.lock().unwrap().usage();
+                let net = n.net_stack().map(|stack| {
+                    let stack = stack.lock().unwrap();
+                    let t = stack.device.traffic;
+                    wk_protocol::ipc::NetStats {
+                        rx_bytes: t.rx_bytes,
+                        rx_packets: t.rx_packets,
+                        tx_bytes: t.tx_bytes,
+                        tx_packets: t.tx_packets,
+                        sockets: stack.open_sockets() as u64,
+                    }
+                });
+                wk_protocol::ipc::NodeStats {
+                    id: n.id,
+                    name: n.name.clone(),
+                    memory: n.stats.memory.load(Ordering::Relaxed),
+                    fs_nodes: fs_nodes as u64,
+                    fs_bytes,
+                    net,
+                    midi_sent: n.stats.midi_sent.load(Ordering::Relaxed),
+                    frames: n.stats.frames.load(Ordering::Relaxed),
+                    exec_children: n.stats.exec_children.load(Ordering::Relaxed),
+                }
+            })
+            .collect()
+    }
+
     /// Launch a dependency as a new app node at `pos` in workspace `ws`.
-    fn launch(&mut self, dep: &Dependency, pos: [f32; 2], ws: NodeId) {
+    /// `args` replaces the dependency's default launch args and `env` layers
+    /// over its image ENV (names that aren't [`is_env_name`] are dropped);
+    /// both are in place before the guest first runs.
+    fn launch(
+        &mut self,
+        dep: &Dependency,
+        pos: [f32; 2],
+        ws: NodeId,
+        args: Option<Vec<String>>,
+        mut env: Vec<(String, String)>,
+    ) {
+        env.retain(|(k, _)| is_env_name(k));
+        let wasm = match dep.local_path() {
+            Ok(wasm) => wasm,
+            Err(e) => {
+                eprintln!("failed to launch {}: {e}", dep.name);
+                return;
+            }
+        };
         let id = self.alloc_id();
         if let Err(e) = self.host.spawn(
-            &dep.local_path(),
+            &wasm,
             &dep.name,
             id,
-            &dep.effective_args(),
+            &args.clone().unwrap_or_else(|| dep.effective_args()),
             self.registry.clone(),
             self.node_reg.clone(),
             Vec::new(),
             dep.container(),
+            &env,
         ) {
             eprintln!("failed to launch {}: {e:#}", dep.name);
             return;
         }
         self.place(id, Kind::App, ws, pos, [360.0, 260.0]);
-        self.graph.node_args.insert(id, dep.args.clone());
+        self.graph
+            .node_args
+            .insert(id, args.unwrap_or_else(|| dep.args.clone()));
+        if !env.is_empty() {
+            self.graph.node_env.insert(id, env);
+        }
         self.write_token_file(id);
     }
 
@@ -1141,7 +1352,8 @@ impl Server {
     }
 
     /// Duplicate a node into the same workspace at an offset. App nodes are
-    /// relaunched with their current args + knob settings; wiring isn't copied.
+    /// relaunched with their current args, env + knob settings; wiring isn't
+    /// copied.
     fn duplicate(&mut self, id: NodeId) {
         let Some(&NodeRec { ws, pos, size, .. }) = self.graph.nodes.get(&id) else {
             return;
@@ -1165,9 +1377,17 @@ impl Server {
                 .cloned()
                 .unwrap_or_else(|| dep.effective_args());
             let options = node.options.lock().unwrap().clone();
+            let env = self.graph.node_env.get(&id).cloned().unwrap_or_default();
+            let wasm = match dep.local_path() {
+                Ok(wasm) => wasm,
+                Err(e) => {
+                    eprintln!("failed to duplicate {}: {e}", dep.name);
+                    return;
+                }
+            };
             let new_id = self.alloc_id();
             if let Err(e) = self.host.spawn(
-                &dep.local_path(),
+                &wasm,
                 &dep.name,
                 new_id,
                 &args,
@@ -1175,12 +1395,22 @@ impl Server {
                 self.node_reg.clone(),
                 options,
                 dep.container(),
+                &env,
             ) {
                 eprintln!("failed to duplicate {}: {e:#}", dep.name);
                 return;
             }
             self.place(new_id, Kind::App, ws, off, size);
             self.graph.node_args.insert(new_id, args);
+            if !env.is_empty() {
+                self.graph.node_env.insert(new_id, env);
+            }
+            if let Some(&restart) = self.graph.node_restart.get(&id) {
+                self.graph.node_restart.insert(new_id, restart);
+            }
+            if let Some(&limits) = self.graph.node_limits.get(&id) {
+                self.set_limits(new_id, limits);
+            }
             return;
         }
 
@@ -1314,10 +1544,11 @@ impl Server {
         self.graph.workspaces.retain(|&w| w != id);
     }
 
-    /// (Re)run an idle or exited node's guest with its current args.
-    fn run_node(&mut self, id: NodeId) {
+    /// (Re)run an idle or exited node's guest with its current args. Whether
+    /// it started now: not while it's still compiling, nor if it failed to.
+    fn run_node(&mut self, id: NodeId) -> bool {
         let Some(node) = self.app_node(id) else {
-            return;
+            return false;
         };
         // Still compiling? Remember the intent and start it the moment its
         // component (and container rootfs) is ready — the tick loop drains
@@ -1325,7 +1556,7 @@ impl Server {
         // a lost no-op: `host.run_node` would silently do nothing here.
         if node.is_loading() {
             self.pending_run.insert(id);
-            return;
+            return false;
         }
         self.pending_run.remove(&id);
         // A container's rootfs is mounted on the compile thread (see
@@ -1334,8 +1565,12 @@ impl Server {
         // now, on top of the rootfs, right before the guest reads its filesystem.
         self.reapply_binds(id);
         let args = self.graph.node_args.get(&id).cloned().unwrap_or_default();
-        if let Err(e) = self.host.run_node(&node, &args) {
-            eprintln!("failed to run {}: {e:#}", node.name);
+        match self.host.run_node(&node, &args) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("failed to run {}: {e:#}", node.name);
+                false
+            }
         }
     }
 
@@ -1354,7 +1589,13 @@ impl Server {
                         self.pending_run.remove(&id);
                     }
                 }
-                Some(_) => self.run_node(id), // ready → run (clears pending)
+                // Ready → run (clears pending).
+                Some(_) => {
+                    let queued = self.restarting.get(&id).is_some_and(|r| r.queued);
+                    if self.run_node(id) && queued {
+                        self.count_restart(id);
+                    }
+                }
                 None => {
                     self.pending_run.remove(&id); // node gone
                 }
@@ -1362,6 +1603,137 @@ impl Server {
         }
     }
 
+    /// Set an app node's restart policy. Guarded to app nodes; `no` drops the
+    /// entry (and any re-run already scheduled).
+    fn set_restart(&mut self, id: NodeId, restart: Restart) {
+        if self.kind_of(id) != Some(Kind::App) {
+            return;
+        }
+        if restart.policy == RestartPolicy::No {
+            self.graph.node_restart.remove(&id);
+            self.restarting.remove(&id);
+        } else {
+            self.graph.node_restart.insert(id, restart);
+        }
+    }
+
+    /// Set an app node's resource limits, all of them, and apply them to its
+    /// live node. Guarded to app nodes; no limits drops the entry.
+    fn set_limits(&mut self, id: NodeId, limits: Limits) {
+        if self.kind_of(id) != Some(Kind::App) {
+            return;
+        }
+        if let Some(node) = self.app_node(id) {
+            node.set_limits(&limits);
+        }
+        if limits.is_empty() {
+            self.graph.node_limits.remove(&id);
+        } else {
+            self.graph.node_limits.insert(id, limits);
+        }
+    }
+
+    /// Set and unset variables in an app node's own environment, by name
+    /// (`None` unsets), and hand the result to its live node for the next
+    /// run. Guarded to app nodes; names that aren't [`is_env_name`] are
+    /// ignored. An empty environment drops the entry.
+    fn set_env(&mut self, id: NodeId, vars: Vec<(String, Option<String>)>) {
+        if self.kind_of(id) != Some(Kind::App) {
+            return;
+        }
+        let mut env = self.graph.node_env.remove(&id).unwrap_or_default();
+        for (name, value) in vars.into_iter().filter(|(k, _)| is_env_name(k)) {
+            match (env.iter().position(|(k, _)| *k == name), value) {
+                (Some(i), Some(v)) => env[i].1 = v,
+                (Some(i), None) => {
+                    env.remove(i);
+                }
+                (None, Some(v)) => env.push((name, v)),
+                (None, None) => {}
+            }
+        }
+        self.put_env(id, env);
+    }
+
+    /// Make `env` app node `id`'s whole environment, on its live node too.
+    fn put_env(&mut self, id: NodeId, env: Vec<(String, String)>) {
+        if let Some(node) = self.app_node(id) {
+            *node.env.lock().unwrap() = env.clone();
+        }
+        if env.is_empty() {
+            self.graph.node_env.remove(&id);
+        } else {
+            self.graph.node_env.insert(id, env);
+        }
+    }
+
+    /// Re-run app nodes whose guest exited in a way their restart policy
+    /// covers. The first re-run waits the policy's backoff and each
+    /// consecutive one twice as long, until the retry limit; a run that stays
+    /// up for [`RESTART_RESET`] starts the count over. A re-run counts toward
+    /// the limit once its guest starts — one still compiling waits in
+    /// `pending_run` and counts when that starts it. A guest the server
+    /// stopped is left alone, as is one that has never run.
+    fn sync_restarts(&mut self) {
+        if self.graph.node_restart.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        let mut due = Vec::new();
+        for (&id, restart) in &self.graph.node_restart {
+            let Some(node) = self.app_node(id) else {
+                continue;
+            };
+            let state = self.restarting.entry(id).or_default();
+            if node.running.load(Ordering::Relaxed) {
+                state.due = None;
+                if state.started.is_some_and(|t| now - t >= RESTART_RESET) {
+                    state.attempts = 0;
+                    state.started = None;
+                }
+                continue;
+            }
+            let exit = node.exit.lock().unwrap().clone();
+            if self.pending_run.contains(&id)
+                || !exit.is_some_and(|e| restart.wants(&e))
+                || !restart.allows(state.attempts)
+            {
+                state.due = None;
+                continue;
+            }
+            let at = *state.due.get_or_insert_with(|| {
+                now + Duration::from_millis(restart.delay_ms(state.attempts))
+            });
+            if now >= at {
+                state.due = None;
+                due.push(id);
+            }
+        }
+        for id in due {
+            if self.run_node(id) {
+                self.count_restart(id);
+            } else if let Some(state) = self.restarting.get_mut(&id) {
+                state.queued = self.pending_run.contains(&id);
+            }
+        }
+    }
+
+    /// Count a re-run of `id` under its restart policy that has now started.
+    fn count_restart(&mut self, id: NodeId) {
+        let Some(state) = self.restarting.get_mut(&id) else {
+            return;
+        };
+        state.attempts = state.attempts.saturating_add(1);
+        state.queued = false;
+        state.started = Some(Instant::now());
+        let attempt = state.attempts;
+        let name = self
+            .app_node(id)
+            .map(|n| n.name.clone())
+            .unwrap_or_default();
+        eprintln!("[restart] {name} ({id}): re-running (attempt {attempt})");
+    }
+
     /// Re-mount every bind wired into app `id` at its current path, over whatever
     /// is already in the app's fs (its container rootfs). Idempotent: unmounts
     /// any stale mount for the pair first. Ensures a guest sees its mounts even
@@ -2364,6 +2736,10 @@ impl Server {
         self.graph.nodes.remove(&id);
         self.graph.pos3d.remove(&id);
         self.graph.node_args.remove(&id);
+        self.graph.node_env.remove(&id);
+        self.graph.node_restart.remove(&id);
+        self.restarting.remove(&id);
+        self.graph.node_limits.remove(&id);
         self.graph.file_nodes.remove(&id);
         self.graph.host_ports.remove(&id);
         self.graph.node_tokens.remove(&id);
@@ -2451,6 +2827,7 @@ impl Server {
         // before reconciling serves so a just-started node gets published this
         // same tick.
         self.drain_pending_run();
+        self.sync_restarts();
         self.sync_pending_app_wires();
         self.sync_mounts();
         self.sync_midi();
@@ -2460,6 +2837,11 @@ impl Server {
         self.sync_serves();
         self.sync_apis();
         self.sync_host_services();
+        // Last, so subscribers see this step's reconciled state.
+        if self.events.is_active() {
+            let snap = self.ipc_snapshot();
+            self.events.publish(snap);
+        }
     }
 
     /// Kill a node and drop everything referencing it (its wiring, geometry, and
@@ -2633,11 +3015,45 @@ impl Server {
 
     /// Apply a client [`Command`], recording an inverse for [`Command::Undo`]
     /// where the mutation is undoable. The single entry point for mutations.
-    pub fn apply(&mut self, cmd: Command) {
+    /// Returns what a create made (the new node ids, or the inferred wire) so
+    /// a client can target it without re-reading the state; empty otherwise.
+    /// A batch that fails is logged and leaves the document unchanged — use
+    /// [`Self::try_apply`] to get its error.
+    pub fn apply(&mut self, cmd: Command) -> Created {
+        self.try_apply(cmd).unwrap_or_else(|e| {
+            eprintln!("wk: batch rejected: {e}");
+            Created::default()
+        })
+    }
+
+    /// [`Self::apply`], reporting why a [`Command::Batch`] was rolled back.
+    /// A plain command never fails: one aimed at a missing target is a no-op,
+    /// as it always was.
+    pub fn try_apply(&mut self, cmd: Command) -> Result<Created, String> {
+        let cmd = match cmd {
+            Command::Batch(cmds) => return self.apply_batch(cmds),
+            cmd => cmd,
+        };
+        // Which step an undo/redo reverses, read under the same lock that
+        // applies it, so no other edit can slip in between.
+        let undone = match cmd {
+            Command::Undo => self.undo.last(),
+            Command::Redo => self.redo.last(),
+            _ => None,
+        }
+        .map(|s| s.label.clone());
         match &cmd {
             // Node creates: run, then record removal of whatever node appeared.
             Command::Create(Resource::Node { .. } | Resource::HostMount { .. })
             | Command::Duplicate(_) => {
+                // An app's args/env are applied at spawn (see `launch`); the
+                // rest of the initial configuration once the node exists.
+                let init = match &cmd {
+                    Command::Create(Resource::Node { args, size, .. }) => {
+                        Some((args.clone(), *size))
+                    }
+                    _ => None,
+                };
                 let before: HashSet<NodeId> = self.graph.nodes.keys().copied().collect();
                 self.dispatch(cmd);
                 let created: Vec<NodeId> = self
@@ -2647,17 +3063,29 @@ impl Server {
                     .copied()
                     .filter(|id| !before.contains(id))
                     .collect();
-                for id in created {
+                for &id in &created {
+                    if let Some((args, size)) = &init {
+                        if let Some(size) = *size {
+                            self.set_node_size(id, size);
+                        }
+                        if let (Some(args), false) = (args, self.app_node(id).is_some()) {
+                            self.set_node_args(id, &args.join(" "));
+                        }
+                    }
                     self.record(Undo::Uncreate(id));
                 }
-                return;
+                return Ok(Created {
+                    nodes: created,
+                    ..Default::default()
+                });
             }
             Command::Create(Resource::Wire { a, b }) => {
+                let wire = wiring::classify(*a, *b, self.class_of(*a), self.class_of(*b));
                 // Only record when the create will actually connect.
                 if !self.wired(*a, *b) {
                     // Net/serve wires are "one per source": connecting may
                     // displace an existing link, which undo must restore.
-                    match wiring::classify(*a, *b, self.class_of(*a), self.class_of(*b)) {
+                    match wire {
                         Some(Wire::Net(app, net)) => {
                             let old_dst = self
                                 .graph
@@ -2687,6 +3115,13 @@ impl Server {
                         _ => self.record(Undo::Wire(*a, *b)),
                     }
                 }
+                self.dispatch(cmd);
+                // Report the wire only if it's actually in place (the kinds may
+                // not wire, or the create may have raced a delete).
+                return Ok(Created {
+                    wire: wire.filter(|&w| self.wire_exists(w)),
+                    ..Default::default()
+                });
             }
             Command::Create(Resource::Workspace { id }) => {
                 if !self.graph.workspaces.contains(id) {
@@ -2708,7 +3143,7 @@ impl Server {
                     let old = self.graph.node_args.get(id).cloned().unwrap_or_default();
                     self.record(Undo::Args(*id, old));
                 }
-                if patch.port_delta.is_some() {
+                if patch.port_delta.is_some() || patch.port_set.is_some() {
                     if let Some(&p) = self.graph.host_ports.get(id) {
                         self.record(Undo::Port(*id, p));
                     }
@@ -2742,40 +3177,260 @@ impl Server {
                     self.record(Undo::Token(*id, self.graph.node_tokens.get(id).cloned()));
                 }
             }
-            // Not undoable: run, mount-path / serve-port edits, and undo itself.
+            // Not undoable: run, mount-path / serve-port edits, undo/redo.
             Command::SetMount { .. }
             | Command::SetServePort { .. }
             | Command::Run(_)
             | Command::Stop(_)
-            | Command::Undo => {}
+            | Command::Undo
+            | Command::Redo => {}
+            Command::Batch(_) => unreachable!("batches are applied above"),
         }
         self.dispatch(cmd);
+        Ok(Created {
+            undone,
+            ..Default::default()
+        })
     }
 
-    /// Perform a command's mutation (no undo recording).
-    fn dispatch(&mut self, cmd: Command) {
+    /// Apply a batch all-or-nothing as one undo step. The inner commands
+    /// record into a scratch history; if one can't be applied, that history
+    /// is unwound (rolling the document back) and the error returned,
+    /// otherwise it becomes a single [`Undo::Batch`] entry. Run/stop,
+    /// mount-path/serve-port edits and the node fields an update changes
+    /// that aren't undoable on their own record [`Self::rollback`] entries
+    /// that a failure replays but the batch's undo step leaves out.
+    fn apply_batch(&mut self, cmds: Vec<Command>) -> Result<Created, String> {
+        let outer = std::mem::take(&mut self.undo);
+        let redo = std::mem::take(&mut self.redo);
+        let mut made = Created::default();
+        let mut failed = None;
+        for (i, cmd) in cmds.into_iter().enumerate() {
+            let step = match self.check(&cmd) {
+                Ok(()) => {
+                    for u in self.rollback(&cmd) {
+                        self.record(u);
+                    }
+                    self.try_apply(cmd.clone())
+                        .and_then(|c| self.confirm(&cmd, c))
+                }
+                Err(e) => Err(e),
+            };
+            match step {
+                Ok(c) => {
+                    made.nodes.extend(c.nodes);
+                    made.wire = c.wire.or(made.wire);
+                }
+                Err(e) => {
+                    failed = Some(format!("step {}: {e}", i + 1));
+                    break;
+                }
+            }
+        }
+        let mut entries = std::mem::replace(&mut self.undo, outer);
+        if let Some(e) = failed {
+            // Nothing changed after all: the redo history still applies.
+            self.redo = redo;
+            self.apply_undo(Undo::Batch(entries.into_iter().map(|s| s.undo).collect()));
+            return Err(e);
+        }
+        entries.retain(|s| !s.undo.batch_only());
+        if entries.is_empty() {
+            self.redo = redo;
+        } else {
+            let labels: Vec<String> = entries.iter().map(|s| s.label.clone()).collect();
+            self.push_step(Step {
+                label: format!("batch: {}", labels.join(", ")),
+                undo: Undo::Batch(entries.into_iter().map(|s| s.undo).collect()),
+            });
+        }
+        Ok(made)
+    }
+
+    /// What puts back a batch step that isn't undoable on its own, read
+    /// before it applies: the bind's mount path, the serve wire's container
+    /// port, whether the node's guest was running (or waiting to), or each
+    /// field an update sets that [`Command::Update`]'s own undo doesn't cover.
+    fn rollback(&self, cmd: &Command) -> Vec<Undo> {
+        match cmd {
+            Command::SetMount { volume, app, .. } => vec![Undo::MountPath(
+                *volume,
+                *app,
+                self.graph.mount_paths.get(&(*volume, *app)).cloned(),
+            )],
+            Command::SetServePort {
+                served, hostport, ..
+            } => vec![Undo::ServePort(
+                *served,
+                *hostport,
+                self.graph.serve_ports.get(&(*served, *hostport)).copied(),
+            )],
+            Command::Run(id) | Command::Stop(id) => vec![Undo::Running(*id, self.runs(*id))],
+            Command::Update { id, patch } => {
+                let id = *id;
+                let mut undo = Vec::new();
+                if patch.pos3d.is_some() && self.node_exists(id) {
+                    undo.push(Undo::Pose(id, self.graph.pos3d.get(&id).copied()));
+                }
+                if patch.host_path.is_some() {
+                    if let Some(FileNode::Bind(f)) = self.graph.file_nodes.get(&id) {
+                        undo.push(Undo::HostPath(id, f.path.clone()));
+                    }
+                }
+                if patch.midi_device.is_some() {
+                    if let Some(device) = self.graph.midi_ins.get(&id) {
+                        undo.push(Undo::MidiDevice(id, device.clone()));
+                    }
+                }
+                if patch.persist.is_some() {
+                    if let Some(FileNode::Volume(v)) = self.graph.file_nodes.get(&id) {
+                        undo.push(Undo::Persist(id, v.persist));
+                    }
+                }
+                if patch.service_name.is_some() || patch.service_target.is_some() {
+                    if let Some(svc) = self.graph.host_services.get(&id) {
+                        undo.push(Undo::Service(id, svc.name.clone(), svc.target.clone()));
+                    }
+                }
+                if patch.restart.is_some() && self.kind_of(id) == Some(Kind::App) {
+                    undo.push(Undo::Restart(id, self.graph.node_restart.get(&id).copied()));
+                }
+                if patch.limits.is_some() && self.kind_of(id) == Some(Kind::App) {
+                    let limits = self.graph.node_limits.get(&id).copied().unwrap_or_default();
+                    undo.push(Undo::Limits(id, limits));
+                }
+                if patch.env.is_some() && self.kind_of(id) == Some(Kind::App) {
+                    let env = self.graph.node_env.get(&id).cloned().unwrap_or_default();
+                    undo.push(Undo::Env(id, env));
+                }
+                undo
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether node `id`'s guest is running, or queued to once it compiles.
+    fn runs(&self, id: NodeId) -> bool {
+        self.pending_run.contains(&id)
+            || self
+                .app_node(id)
+                .is_some_and(|n| n.running.load(Ordering::Relaxed))
+    }
+
+    /// Whether `cmd` can be applied to the current document — the up-front
+    /// half of a batch's all-or-nothing check (its targets exist, the pair
+    /// wires, a client-minted id is free). Plain commands skip this and stay
+    /// lenient.
+    fn check(&self, cmd: &Command) -> Result<(), String> {
+        let node = |id: &NodeId| {
+            if self.node_exists(*id) {
+                Ok(())
+            } else {
+                Err(format!("no node {id}"))
+            }
+        };
+        let ws = |id: &NodeId| {
+            if self.graph.workspaces.contains(id) {
+                Ok(())
+            } else {
+                Err(format!("no workspace {id}"))
+            }
+        };
         match cmd {
-            Command::Create(Resource::Node { kind, pos, ws }) => match kind {
-                NodeKind::App { dep } => {
-                    if let Some(dep) = self.graph.available.get(dep).cloned() {
-                        self.launch(&dep, pos, ws);
+            Command::Create(Resource::Node {
+                kind, ws: w, id, ..
+            }) => {
+                ws(w)?;
+                if let NodeKind::App { dep } = kind {
+                    if *dep >= self.graph.available.len() {
+                        return Err(format!("no dependency #{dep}"));
                     }
                 }
-                NodeKind::Volume => self.add_virtual_file(pos, ws),
-                NodeKind::BindMount => self.add_host_mapped_file(pos, ws),
-                NodeKind::Port => self.add_host_port(pos, ws),
-                NodeKind::Network => {
-                    self.add_net_node(pos, ws);
+                match id {
+                    Some(id) if self.node_exists(*id) => Err(format!("node {id} already exists")),
+                    _ => Ok(()),
                 }
-                NodeKind::Gateway => self.add_gateway_node(pos, ws),
-                NodeKind::Iroh => self.add_iroh_node(pos, ws),
-                NodeKind::Veilid => self.add_veilid_node(pos, ws),
-                NodeKind::Note => self.add_note(pos, ws),
-                NodeKind::Capture => self.add_capture_node(pos, ws),
-                NodeKind::Api => self.add_api_node(pos, ws),
-                NodeKind::MidiIn => self.add_midi_in_node(pos, ws),
-                NodeKind::HostService => self.add_host_service(pos, ws),
+            }
+            Command::Create(Resource::HostMount { ws: w, .. }) => ws(w),
+            Command::Create(Resource::Wire { a, b }) => {
+                node(a)?;
+                node(b)?;
+                match wiring::classify(*a, *b, self.class_of(*a), self.class_of(*b)) {
+                    Some(_) => Ok(()),
+                    None => Err(format!("{a} and {b} can't be wired to each other")),
+                }
+            }
+            Command::Create(Resource::Workspace { .. }) => Ok(()),
+            Command::Update { id, .. }
+            | Command::Delete(ResourceRef::Node(id))
+            | Command::Run(id)
+            | Command::Stop(id)
+            | Command::Duplicate(id) => node(id),
+            Command::Delete(ResourceRef::Wire(w)) => {
+                if self.wire_exists(*w) {
+                    Ok(())
+                } else {
+                    Err(format!("no such wire {w:?}"))
+                }
+            }
+            Command::Delete(ResourceRef::Workspace(id)) => {
+                ws(id)?;
+                if self.graph.workspaces.len() > 1 {
+                    Ok(())
+                } else {
+                    Err("can't delete the last workspace".into())
+                }
+            }
+            Command::SetMount { volume, app, .. } => node(volume).and(node(app)),
+            Command::SetServePort {
+                served, hostport, ..
+            } => node(served).and(node(hostport)),
+            Command::SetToken { id, .. } => match self.kind_of(*id) {
+                Some(Kind::App) => Ok(()),
+                Some(_) => Err(format!("node {id} is not an app")),
+                None => Err(format!("no node {id}")),
             },
+            // Undo inside a batch would pop the batch's own scratch history.
+            Command::Undo => Err("undo can't be part of a batch".into()),
+            Command::Redo => Err("redo can't be part of a batch".into()),
+            Command::Batch(_) => Ok(()),
+        }
+    }
+
+    /// The after-the-fact half of a batch step's check: a create that placed
+    /// nothing (a failed launch) or a wire that didn't form fails the batch.
+    fn confirm(&self, cmd: &Command, made: Created) -> Result<Created, String> {
+        match cmd {
+            Command::Create(Resource::Node { .. } | Resource::HostMount { .. })
+            | Command::Duplicate(_)
+                if made.nodes.is_empty() =>
+            {
+                Err("the node could not be created".into())
+            }
+            Command::Create(Resource::Wire { a, b }) if made.wire.is_none() => {
+                Err(format!("{a} and {b} did not wire"))
+            }
+            _ => Ok(made),
+        }
+    }
+
+    /// Perform a command's mutation (no undo recording).
+    fn dispatch(&mut self, cmd: Command) {
+        match cmd {
+            Command::Create(Resource::Node {
+                kind,
+                pos,
+                ws,
+                id,
+                args,
+                env,
+                ..
+            }) => {
+                // A client-minted id that's already taken is ignored (fresh).
+                self.next_id = id.filter(|id| !self.node_exists(*id));
+                self.create_node(kind, pos, ws, args, env);
+                self.next_id = None;
+            }
             // Create is create only: a wire that already exists is left alone
             // (removal is Delete, so a create-only token can never disconnect).
             Command::Create(Resource::HostMount { path, pos, ws }) => {
@@ -2835,6 +3490,17 @@ impl Server {
                         v.persist = persist;
                     }
                 }
+                if let Some(restart) = patch.restart {
+                    self.set_restart(id, restart);
+                }
+                if let Some(patch) = patch.limits {
+                    let mut limits = self.graph.node_limits.get(&id).copied().unwrap_or_default();
+                    limits.merge(*patch);
+                    self.set_limits(id, limits);
+                }
+                if let Some(vars) = patch.env {
+                    self.set_env(id, vars);
+                }
             }
             Command::Delete(ResourceRef::Node(id)) => self.remove_any(id),
             Command::Delete(ResourceRef::Wire(w)) => self.disconnect_wire(w),
@@ -2846,21 +3512,70 @@ impl Server {
                 container,
             } => self.set_serve_port(served, hostport, container),
             Command::SetToken { id, token } => self.set_node_token(id, token),
-            Command::Run(id) => self.run_node(id),
+            Command::Run(id) => {
+                // Run by hand: the restart count starts over.
+                self.restarting.remove(&id);
+                self.run_node(id);
+            }
             Command::Stop(id) => self.stop_node(id),
             Command::Duplicate(id) => self.duplicate(id),
             Command::Undo => {
-                if let Some(u) = self.undo.pop() {
-                    self.apply_undo(u);
+                if let Some(Step { label, undo }) = self.undo.pop() {
+                    let redo = self.apply_undo(undo);
+                    push_capped(&mut self.redo, Step { label, undo: redo });
+                }
+            }
+            Command::Redo => {
+                if let Some(Step { label, undo }) = self.redo.pop() {
+                    let undo = self.apply_undo(undo);
+                    push_capped(&mut self.undo, Step { label, undo });
+                }
+            }
+            Command::Batch(cmds) => {
+                for cmd in cmds {
+                    self.dispatch(cmd);
                 }
             }
         }
     }
 
+    /// Place a new node of `kind` (the create half of [`Self::dispatch`]).
+    fn create_node(
+        &mut self,
+        kind: NodeKind,
+        pos: [f32; 2],
+        ws: NodeId,
+        args: Option<Vec<String>>,
+        env: Vec<(String, String)>,
+    ) {
+        match kind {
+            NodeKind::App { dep } => {
+                if let Some(dep) = self.graph.available.get(dep).cloned() {
+                    self.launch(&dep, pos, ws, args, env);
+                }
+            }
+            NodeKind::Volume => self.add_virtual_file(pos, ws),
+            NodeKind::BindMount => self.add_host_mapped_file(pos, ws),
+            NodeKind::Port => self.add_host_port(pos, ws),
+            NodeKind::Network => {
+                self.add_net_node(pos, ws);
+            }
+            NodeKind::Gateway => self.add_gateway_node(pos, ws),
+            NodeKind::Iroh => self.add_iroh_node(pos, ws),
+            NodeKind::Veilid => self.add_veilid_node(pos, ws),
+            NodeKind::Note => self.add_note(pos, ws),
+            NodeKind::Capture => self.add_capture_node(pos, ws),
+            NodeKind::Api => self.add_api_node(pos, ws),
+            NodeKind::MidiIn => self.add_midi_in_node(pos, ws),
+            NodeKind::HostService => self.add_host_service(pos, ws),
+        }
+    }
+
     /// Push an inverse onto the undo stack, coalescing a run of same-node
-    /// move/resize/args edits (e.g. a drag) into a single entry.
+    /// move/resize/args edits (e.g. a drag) into a single entry. Called before
+    /// the mutation is dispatched, so the label sees the node as it was.
     fn record(&mut self, u: Undo) {
-        let coalesce = match (self.undo.last(), &u) {
+        let coalesce = match (self.undo.last().map(|s| &s.undo), &u) {
             (Some(Undo::Pos(a, _)), Undo::Pos(b, _)) => a == b,
             (Some(Undo::Size(a, _)), Undo::Size(b, _)) => a == b,
             (Some(Undo::Args(a, _)), Undo::Args(b, _)) => a == b,
@@ -2868,21 +3583,116 @@ impl Server {
             _ => false,
         };
         if coalesce {
+            self.redo.clear();
             return;
         }
-        self.undo.push(u);
-        if self.undo.len() > UNDO_CAP {
-            self.undo.remove(0);
+        let label = self.describe(&u);
+        self.push_step(Step { label, undo: u });
+    }
+
+    /// Push a new mutation's step: it starts a new branch of history, so what
+    /// was undone before it can no longer be redone.
+    fn push_step(&mut self, step: Step) {
+        self.redo.clear();
+        push_capped(&mut self.undo, step);
+    }
+
+    /// The history label for the mutation `u` reverses — see [`Step`].
+    fn describe(&self, u: &Undo) -> String {
+        match u {
+            Undo::Pos(id, _) => format!("move {}", self.node_label(*id)),
+            Undo::Size(id, _) => format!("resize {}", self.node_label(*id)),
+            Undo::Args(id, _) => format!("set args of {}", self.node_label(*id)),
+            Undo::Port(id, _) => format!("change port of {}", self.node_label(*id)),
+            Undo::Text(id, _) => format!("edit {}", self.node_label(*id)),
+            // Recorded before the toggle: an existing wire is being removed.
+            Undo::Wire(a, b) => format!(
+                "{} {} and {}",
+                if self.wired(*a, *b) { "unwire" } else { "wire" },
+                self.node_label(*a),
+                self.node_label(*b)
+            ),
+            Undo::RewireUnique { src, new_dst, .. } => format!(
+                "wire {} and {}",
+                self.node_label(*src),
+                self.node_label(*new_dst)
+            ),
+            Undo::Token(id, _) => format!("set token of {}", self.node_label(*id)),
+            Undo::Uncreate(id) => format!("create {}", self.node_label(*id)),
+            Undo::Recreate(s) => format!("delete {}", self.node_label(s.node.id)),
+            Undo::DropWorkspace(_) => "add workspace".into(),
+            Undo::RecreateWorkspace(s) => format!("delete workspace ({} nodes)", s.nodes.len()),
+            Undo::Batch(entries) => format!("batch of {}", entries.len()),
+            Undo::MountPath(volume, app, _) => format!(
+                "set mount of {} in {}",
+                self.node_label(*volume),
+                self.node_label(*app)
+            ),
+            Undo::ServePort(served, _, _) => {
+                format!("set serve port of {}", self.node_label(*served))
+            }
+            Undo::Running(id, true) => format!("stop {}", self.node_label(*id)),
+            Undo::Running(id, false) => format!("run {}", self.node_label(*id)),
+            Undo::Pose(id, _) => format!("place {}", self.node_label(*id)),
+            Undo::HostPath(id, _) => format!("set host path of {}", self.node_label(*id)),
+            Undo::MidiDevice(id, _) => format!("set device of {}", self.node_label(*id)),
+            Undo::Persist(id, _) => format!("set persistence of {}", self.node_label(*id)),
+            Undo::Service(id, ..) => format!("set service of {}", self.node_label(*id)),
+            Undo::Restart(id, _) => format!("set restart policy of {}", self.node_label(*id)),
+            Undo::Limits(id, _) => format!("set limits of {}", self.node_label(*id)),
+            Undo::Env(id, _) => format!("set env of {}", self.node_label(*id)),
+        }
+    }
+
+    /// How a history label names a node: its kind, its app or file name when
+    /// it has one, and the short id `wk ps` prints.
+    fn node_label(&self, id: NodeId) -> String {
+        let kind = match self.kind_of(id) {
+            Some(Kind::File)
+                if self
+                    .graph
+                    .file_nodes
+                    .get(&id)
+                    .is_some_and(|f| matches!(f, FileNode::Bind(_))) =>
+            {
+                "bindmount".to_string()
+            }
+            Some(Kind::File) => "volume".to_string(),
+            Some(Kind::Port) => "hostport".to_string(),
+            Some(k) => format!("{k:?}").to_lowercase(),
+            None => "node".to_string(),
+        };
+        let name = match self.app_node(id) {
+            Some(n) => Some(n.name.clone()),
+            None => self.graph.file_nodes.get(&id).map(|f| f.name().to_string()),
+        };
+        let short: String = id.to_string().chars().take(12).collect();
+        match name {
+            Some(name) if !name.is_empty() => format!("{kind} {name} ({short})"),
+            _ => format!("{kind} {short}"),
         }
     }
 
+    /// The undo and redo histories' labels, each with the step the next
+    /// undo/redo applies last — see
+    /// [`wk_protocol::ipc::ServerMsg::History`].
+    pub fn history(&self) -> (Vec<String>, Vec<String>) {
+        let labels =
+            |steps: &[Step]| -> Vec<String> { steps.iter().map(|s| s.label.clone()).collect() };
+        (labels(&self.undo), labels(&self.redo))
+    }
+
     /// Whether a node with this id currently exists (any kind).
     fn node_exists(&self, id: NodeId) -> bool {
         self.graph.nodes.contains_key(&id)
     }
 
-    /// Apply one recorded inverse. Guards against nodes that have since gone.
-    fn apply_undo(&mut self, u: Undo) {
+    /// Apply one recorded inverse, returning *its* inverse — the entry that
+    /// re-does what was just undone (an undone redo undoes again). Guards
+    /// against nodes that have since gone; an inverse that found nothing to
+    /// act on returns an empty batch.
+    fn apply_undo(&mut self, u: Undo) -> Undo {
+        let inverse = self.inverse(&u);
         match u {
             Undo::Pos(id, p) => self.set_node_pos(id, p),
             Undo::Size(id, s) => self.set_node_size(id, s),
@@ -2946,6 +3756,159 @@ impl Server {
             Undo::Recreate(s) => self.recreate(*s),
             Undo::DropWorkspace(id) => self.remove_workspace(id),
             Undo::RecreateWorkspace(s) => self.recreate_workspace(*s),
+            Undo::MountPath(volume, app, path) => {
+                self.set_mount(volume, app, path.unwrap_or_default())
+            }
+            Undo::ServePort(served, hostport, port) => {
+                self.set_serve_port(served, hostport, port.unwrap_or(0))
+            }
+            Undo::Running(id, true) => {
+                if !self.runs(id) {
+                    self.run_node(id);
+                }
+            }
+            Undo::Running(id, false) => {
+                self.pending_run.remove(&id);
+                self.stop_node(id);
+            }
+            Undo::Pose(id, pose) => match pose {
+                Some(pose) => self.set_node_pos3d(id, pose),
+                None => {
+                    self.graph.pos3d.remove(&id);
+                }
+            },
+            Undo::HostPath(id, path) => self.set_bind_path(id, path.to_string_lossy().into_owned()),
+            Undo::MidiDevice(id, device) => self.set_midi_device(id, device),
+            Undo::Persist(id, persist) => {
+                if let Some(FileNode::Volume(v)) = self.graph.file_nodes.get_mut(&id) {
+                    v.persist = persist;
+                }
+            }
+            Undo::Service(id, name, target) => {
+                if let Some(svc) = self.graph.host_services.get_mut(&id) {
+                    svc.name = name;
+                    svc.target = target;
+                }
+            }
+            Undo::Restart(id, restart) => self.set_restart(id, restart.unwrap_or_default()),
+            Undo::Limits(id, limits) => self.set_limits(id, limits),
+            Undo::Env(id, env) => {
+                if self.kind_of(id) == Some(Kind::App) {
+                    self.put_env(id, env);
+                }
+            }
+            Undo::Batch(entries) => {
+                // Each entry's inverse depends on the state its newer siblings
+                // left, so invert while applying. Collected newest-first, the
+                // redo batch then replays them oldest-first.
+                let redo = entries
+                    .into_iter()
+                    .rev()
+                    .map(|u| self.apply_undo(u))
+                    .collect();
+                return Undo::Batch(redo);
+            }
+        }
+        inverse
+    }
+
+    /// The entry that reverses applying `u` to the current state (read
+    /// before `u` is applied). Batches are inverted step by step in
+    /// [`Self::apply_undo`] instead.
+    fn inverse(&self, u: &Undo) -> Undo {
+        let none = || Undo::Batch(Vec::new());
+        let rec = |id: &NodeId| self.graph.nodes.get(id);
+        match u {
+            Undo::Pos(id, _) => rec(id).map_or_else(none, |r| Undo::Pos(*id, r.pos)),
+            Undo::Size(id, _) => rec(id).map_or_else(none, |r| Undo::Size(*id, r.size)),
+            Undo::Args(id, _) => match rec(id) {
+                Some(_) => Undo::Args(
+                    *id,
+                    self.graph.node_args.get(id).cloned().unwrap_or_default(),
+                ),
+                None => none(),
+            },
+            Undo::Text(id, _) => match self.graph.note_text.get(id) {
+                Some(t) => Undo::Text(*id, t.clone()),
+                None => none(),
+            },
+            Undo::Port(id, _) => match self.graph.host_ports.get(id) {
+                Some(&p) => Undo::Port(*id, p),
+                None => none(),
+            },
+            Undo::Wire(a, b) => Undo::Wire(*a, *b),
+            // Undoing put `src` back on `old_dst`; redoing moves it to
+            // `new_dst` again, displacing that restored link.
+            Undo::RewireUnique {
+                src,
+                new_dst,
+                old_dst,
+            } => match old_dst {
+                Some(old) => Undo::RewireUnique {
+                    src: *src,
+                    new_dst: *old,
+                    old_dst: Some(*new_dst),
+                },
+                None => Undo::Wire(*src, *new_dst),
+            },
+            Undo::Token(id, _) => match rec(id) {
+                Some(_) => Undo::Token(*id, self.graph.node_tokens.get(id).cloned()),
+                None => none(),
+            },
+            Undo::Uncreate(id) => self
+                .snapshot(*id)
+                .map_or_else(none, |s| Undo::Recreate(Box::new(s))),
+            Undo::Recreate(s) => Undo::Uncreate(s.node.id),
+            Undo::DropWorkspace(id) => {
+                if self.graph.workspaces.len() > 1 && self.graph.workspaces.contains(id) {
+                    self.snapshot_workspace(*id)
+                        .map_or_else(none, |s| Undo::RecreateWorkspace(Box::new(s)))
+                } else {
+                    none()
+                }
+            }
+            Undo::RecreateWorkspace(s) => Undo::DropWorkspace(s.id),
+            Undo::Batch(_) => none(),
+            Undo::MountPath(volume, app, _) => Undo::MountPath(
+                *volume,
+                *app,
+                self.graph.mount_paths.get(&(*volume, *app)).cloned(),
+            ),
+            Undo::ServePort(served, hostport, _) => Undo::ServePort(
+                *served,
+                *hostport,
+                self.graph.serve_ports.get(&(*served, *hostport)).copied(),
+            ),
+            Undo::Running(id, run) => Undo::Running(*id, !run),
+            Undo::Pose(id, _) => match rec(id) {
+                Some(_) => Undo::Pose(*id, self.graph.pos3d.get(id).copied()),
+                None => none(),
+            },
+            Undo::HostPath(id, _) => match self.graph.file_nodes.get(id) {
+                Some(FileNode::Bind(f)) => Undo::HostPath(*id, f.path.clone()),
+                _ => none(),
+            },
+            Undo::MidiDevice(id, _) => match self.graph.midi_ins.get(id) {
+                Some(device) => Undo::MidiDevice(*id, device.clone()),
+                None => none(),
+            },
+            Undo::Persist(id, _) => match self.graph.file_nodes.get(id) {
+                Some(FileNode::Volume(v)) => Undo::Persist(*id, v.persist),
+                _ => none(),
+            },
+            Undo::Service(id, ..) => match self.graph.host_services.get(id) {
+                Some(svc) => Undo::Service(*id, svc.name.clone(), svc.target.clone()),
+                None => none(),
+            },
+            Undo::Restart(id, _) => Undo::Restart(*id, self.graph.node_restart.get(id).copied()),
+            Undo::Limits(id, _) => Undo::Limits(
+                *id,
+                self.graph.node_limits.get(id).copied().unwrap_or_default(),
+            ),
+            Undo::Env(id, _) => Undo::Env(
+                *id,
+                self.graph.node_env.get(id).cloned().unwrap_or_default(),
+            ),
         }
     }
 
@@ -2971,6 +3934,14 @@ impl Server {
                         .node_tokens
                         .get(&id)
                         .map(|t| crate::workspace::bytes_hex(t)),
+                    restart: self
+                        .graph
+                        .node_restart
+                        .get(&id)
+                        .copied()
+                        .unwrap_or_default(),
+                    limits: self.graph.node_limits.get(&id).copied().unwrap_or_default(),
+                    env: self.graph.node_env.get(&id).cloned().unwrap_or_default(),
                 }
             }
             Kind::File => match self.graph.file_nodes.get(&id)? {
@@ -3101,6 +4072,9 @@ impl Server {
                 options,
                 args,
                 token,
+                restart,
+                limits,
+                env,
             } => {
                 let Some(dep) = self
                     .graph
@@ -3120,8 +4094,15 @@ impl Server {
                 } else {
                     args.clone()
                 };
+                let wasm = match dep.local_path() {
+                    Ok(wasm) => wasm,
+                    Err(e) => {
+                        eprintln!("failed to restore {}: {e}", dep.name);
+                        return;
+                    }
+                };
                 if let Err(e) = self.host.spawn(
-                    &dep.local_path(),
+                    &wasm,
                     &dep.name,
                     s.id,
                     &args,
@@ -3129,12 +4110,18 @@ impl Server {
                     self.node_reg.clone(),
                     options.clone(),
                     dep.container(),
+                    env,
                 ) {
                     eprintln!("failed to restore {}: {e:#}", dep.name);
                     return;
                 }
                 self.place(s.id, Kind::App, ws, s.pos, s.size);
                 self.graph.node_args.insert(s.id, args);
+                self.set_restart(s.id, *restart);
+                self.set_limits(s.id, *limits);
+                if !env.is_empty() {
+                    self.graph.node_env.insert(s.id, env.clone());
+                }
                 // Restore a custom capability token. One that doesn't verify
                 // (the key file was lost, or the .wk moved to another machine)
                 // is dropped — the node falls back to the default token.
@@ -3451,19 +4438,31 @@ impl Server {
         }
     }
 
-    /// Mark (or clear) a node as externally attached by a CLI client. Returns
-    /// whether it is a terminal node the client can actually stream (so an
-    /// attach to a non-terminal node is rejected without leaving it flagged).
-    pub fn set_attached(&mut self, id: NodeId, on: bool) -> bool {
-        let is_terminal = self.app_node(id).is_some_and(|n| n.is_command());
-        if on {
-            if is_terminal {
-                self.attached.insert(id);
-            }
-        } else {
+    /// Mark (or clear) a node as externally attached by a CLI client. Only a
+    /// terminal node can be attached, and only by one client at a time: two
+    /// would split its output between them, and the first to detach would
+    /// clear the flag for both.
+    pub fn set_attached(&mut self, id: NodeId, on: bool) -> Result<(), String> {
+        if !on {
             self.attached.remove(&id);
+            return Ok(());
         }
-        is_terminal
+        match self.app_node(id) {
+            None => Err("no such node".into()),
+            Some(n) if !n.is_command() => Err("node is not a terminal".into()),
+            Some(_) if !self.attached.insert(id) => {
+                Err("node is already attached by another client".into())
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Subscribe to state changes: returns the baseline snapshot and a channel
+    /// of [`wk_protocol::ipc::Event`]s relative to it, published each tick
+    /// until the receiver is dropped.
+    pub fn subscribe(&mut self) -> crate::events::Subscription {
+        let current = self.ipc_snapshot();
+        self.events.subscribe(current)
     }
 
     /// A serializable projection of the state for a remote (CLI) client — the
@@ -3525,7 +4524,11 @@ impl Server {
                     runnable: app.as_ref().map(|n| n.is_runnable()).unwrap_or(false),
                     terminal: app.as_ref().map(|n| n.is_command()).unwrap_or(false),
                     attached: self.attached.contains(&id),
-                    error: v.port_errors.get(&id).cloned(),
+                    error: v
+                        .port_errors
+                        .get(&id)
+                        .cloned()
+                        .or_else(|| app.as_ref().and_then(|n| n.limit_error())),
                     // The node's *effective* token: its custom one, else the
                     // workspace default — what `wk token` inspects/attenuates.
                     token: app.as_ref().and_then(|_| {
@@ -3541,6 +4544,17 @@ impl Server {
                     // only ever printed to the server's stderr at startup.
                     ticket: v.uplinks.get(&id).map(|u| u.ticket.clone()),
                     peers: v.uplinks.get(&id).map(|u| u.peers),
+                    exit: app.as_ref().and_then(|n| n.exit.lock().unwrap().clone()),
+                    restart: self
+                        .graph
+                        .node_restart
+                        .get(&id)
+                        .map(|r| r.policy)
+                        .unwrap_or_default(),
+                    restarts: self
+                        .restarting
+                        .get(&id)
+                        .map_or(0, |r| u16::try_from(r.attempts).unwrap_or(u16::MAX)),
                 }
             })
             .collect();
@@ -3674,6 +4688,10 @@ mod model_tests {
             kind: NodeKind::Volume,
             pos: [0.0, 0.0],
             ws,
+            id: None,
+            args: None,
+            env: Vec::new(),
+            size: None,
         }));
         let vol = *s.graph.file_nodes.keys().next().expect("volume placed");
         let net = NodeId::new();
@@ -3830,12 +4848,20 @@ mod model_tests {
                 kind: NodeKind::Network,
                 pos: [0.0, 0.0],
                 ws,
+                id: None,
+                args: None,
+                env: Vec::new(),
+                size: None,
             }));
         };
         s.apply(Command::Create(Resource::Node {
             kind: NodeKind::Iroh,
             pos: [0.0, 0.0],
             ws,
+            id: None,
+            args: None,
+            env: Vec::new(),
+            size: None,
         }));
         add_net(&mut s);
         add_net(&mut s);
@@ -3879,6 +4905,10 @@ mod model_tests {
                 kind,
                 pos: [0.0, 0.0],
                 ws,
+                id: None,
+                args: None,
+                env: Vec::new(),
+                size: None,
             }));
         };
         add(&mut s, NodeKind::Port, ws1);
@@ -3927,11 +4957,14 @@ mod model_tests {
                 running: Arc::new(AtomicBool::new(false)),
                 kill: Arc::new(AtomicBool::new(false)),
                 setup: std::sync::OnceLock::new(),
-                env: Vec::new(),
+                image_env: Vec::new(),
+                env: Default::default(),
                 layers: Vec::new(),
                 capture_src: crate::capture::new_src(),
                 exec_permit: crate::exec::new_permit(true),
                 fs_serve: wk_vfs::ProviderConn::new(),
+                stats: crate::stats::new_counters(),
+                exit: Arc::new(Mutex::new(None)),
             });
             let _ = node.setup.set(NodeSetup {
                 net_stack: None,
@@ -3987,11 +5020,14 @@ mod model_tests {
                 running: Arc::new(AtomicBool::new(false)),
                 kill: Arc::new(AtomicBool::new(false)),
                 setup: std::sync::OnceLock::new(),
-                env: Vec::new(),
+                image_env: Vec::new(),
+                env: Default::default(),
                 layers: Vec::new(),
                 capture_src: crate::capture::new_src(),
                 exec_permit: crate::exec::new_permit(true),
                 fs_serve: wk_vfs::ProviderConn::new(),
+                stats: crate::stats::new_counters(),
+                exit: Arc::new(Mutex::new(None)),
             })
         };
         let published = |node: &Arc<Node>, fs_provider: bool| {
@@ -4046,6 +5082,293 @@ mod model_tests {
         assert!(s.graph.midi_links.is_empty());
     }
 
+    /// `node_stats` reports each app node's counters and filesystem; a node
+    /// without a fabric stack has no network row.
+    #[test]
+    fn node_stats_report_counters_and_fs_usage() {
+        use crate::plugin::Node;
+        let s = fresh_server();
+        let id = NodeId::new();
+        let node = Arc::new(Node {
+            id,
+            name: "synth".to_string(),
+            term_io: crate::terminal::TermIo::new(),
+            fs: crate::vfs::new_fs(),
+            midi_in: crate::midi::new_inbox(),
+            options: crate::options::new_options(Vec::new()),
+            finished: Arc::new(AtomicBool::new(false)),
+            running: Arc::new(AtomicBool::new(false)),
+            kill: Arc::new(AtomicBool::new(false)),
+            setup: std::sync::OnceLock::new(),
+            image_env: Vec::new(),
+            env: Default::default(),
+            layers: Vec::new(),
+            capture_src: crate::capture::new_src(),
+            exec_permit: crate::exec::new_permit(true),
+            fs_serve: wk_vfs::ProviderConn::new(),
+            stats: crate::stats::new_counters(),
+            exit: Arc::new(Mutex::new(None)),
+        });
+        node.fs
+            .lock()
+            .unwrap()
+            .put_file_at("/patch.syx", vec![0xf0; 32]);
+        node.stats.midi_sent.store(7, Ordering::Relaxed);
+        node.stats.frames.store(120, Ordering::Relaxed);
+        s.node_reg.lock().unwrap().push(node);
+
+        let stats = s.node_stats();
+        assert_eq!(stats.len(), 1);
+        let row = &stats[0];
+        assert_eq!((row.id, row.name.as_str()), (id, "synth"));
+        assert_eq!((row.fs_nodes, row.fs_bytes), (2, 32));
+        assert_eq!((row.midi_sent, row.frames, row.memory), (7, 120, 0));
+        assert!(row.net.is_none());
+    }
+
+    /// A placed app node that never finishes compiling — enough for the
+    /// server's bookkeeping around a guest, without running one.
+    fn stub_node(s: &mut Server, name: &str) -> crate::plugin::SharedNode {
+        let ws = s.graph.workspaces[0];
+        let id = NodeId::new();
+        let node = Arc::new(crate::plugin::Node {
+            id,
+            name: name.to_string(),
+            term_io: crate::terminal::TermIo::new(),
+            fs: crate::vfs::new_fs(),
+            midi_in: crate::midi::new_inbox(),
+            options: crate::options::new_options(Vec::new()),
+            finished: Arc::new(AtomicBool::new(false)),
+            running: Arc::new(AtomicBool::new(false)),
+            kill: Arc::new(AtomicBool::new(false)),
+            setup: std::sync::OnceLock::new(),
+            image_env: Vec::new(),
+            env: Default::default(),
+            layers: Vec::new(),
+            capture_src: crate::capture::new_src(),
+            exec_permit: crate::exec::new_permit(true),
+            fs_serve: wk_vfs::ProviderConn::new(),
+            stats: crate::stats::new_counters(),
+            exit: Arc::new(Mutex::new(None)),
+        });
+        s.node_reg.lock().unwrap().push(node.clone());
+        s.place(id, Kind::App, ws, [0.0, 0.0], [360.0, 260.0]);
+        node
+    }
+
+    #[test]
+    fn commit_needs_a_node_started_from_an_image() {
+        let mut s = fresh_server();
+        let node = stub_node(&mut s, "php");
+        let err = s.commit_base(node.id).err().unwrap();
+        assert_eq!(err, "php was not started from an image");
+        assert_eq!(s.commit_base(NodeId::new()).err().unwrap(), "no such node");
+    }
+
+    /// A guest that failed is re-run under `on-failure` until the retry
+    /// limit; a clean exit isn't, and running it by hand starts the count
+    /// over. The stub never finishes compiling, so each re-run stays queued
+    /// in `pending_run` and isn't counted as an attempt until it starts.
+    #[test]
+    fn restart_policy_reruns_failed_guests_up_to_the_limit() {
+        use wk_protocol::ipc::ExitInfo;
+        let mut s = fresh_server();
+        let node = stub_node(&mut s, "php");
+        let id = node.id;
+        s.apply(Command::Update {
+            id,
+            patch: NodePatch {
+                restart: Some(Restart {
+                    policy: RestartPolicy::OnFailure,
+                    max_retries: 2,
+                    backoff_ms: 0,
+                }),
+                ..Default::default()
+            },
+        });
+        let exit = |code| {
+            *node.exit.lock().unwrap() = Some(ExitInfo {
+                code: Some(code),
+                trap: None,
+                backtrace: None,
+                at_ms: 0,
+            })
+        };
+        let attempts = |s: &Server| s.restarting.get(&id).map_or(0, |r| r.attempts);
+
+        // Never run: nothing to restart.
+        s.sync_restarts();
+        assert!(s.pending_run.is_empty());
+
+        exit(1);
+        s.sync_restarts();
+        assert!(s.pending_run.contains(&id), "a failed guest is re-run");
+        s.sync_restarts();
+        s.sync_restarts();
+        assert_eq!(attempts(&s), 0, "a re-run counts once it starts");
+        assert!(s.restarting[&id].queued);
+
+        // Re-runs that did start use up the limit.
+        s.pending_run.clear();
+        s.restarting.get_mut(&id).unwrap().attempts = 2;
+        s.sync_restarts();
+        assert!(s.pending_run.is_empty(), "no more than max-retries");
+
+        s.apply(Command::Run(id));
+        assert_eq!(attempts(&s), 0, "a manual run starts over");
+        exit(0);
+        s.sync_restarts();
+        assert_eq!(attempts(&s), 0, "a clean exit isn't a failure");
+
+        let info = s.ipc_snapshot();
+        let info = info.nodes.iter().find(|n| n.id == id).unwrap();
+        assert_eq!(info.restart, RestartPolicy::OnFailure);
+
+        // A limit past what the snapshot counts to is still reached.
+        s.pending_run.clear();
+        s.graph.node_restart.get_mut(&id).unwrap().max_retries = 100_000;
+        s.restarting.get_mut(&id).unwrap().attempts = 70_000;
+        exit(1);
+        s.sync_restarts();
+        assert!(s.pending_run.contains(&id), "under the limit");
+        let info = s.ipc_snapshot();
+        let info = info.nodes.iter().find(|n| n.id == id).unwrap();
+        assert_eq!(info.restarts, u16::MAX);
+        s.pending_run.clear();
+        s.restarting.get_mut(&id).unwrap().attempts = 100_000;
+        s.sync_restarts();
+        assert!(s.pending_run.is_empty(), "the limit holds");
+    }
+
+    /// Limits patched onto a node reach its live counters and filesystem,
+    /// merge with what it had, and are saved; what it runs into is its error.
+    #[test]
+    fn limits_apply_to_the_live_node_and_report_what_was_hit() {
+        let mut s = fresh_server();
+        let node = stub_node(&mut s, "php");
+        let id = node.id;
+        let patch = |s: &mut Server, limits| {
+            s.apply(Command::Update {
+                id,
+                patch: NodePatch {
+                    limits: Some(Box::new(limits)),
+                    ..Default::default()
+                },
+            })
+        };
+        patch(
+            &mut s,
+            Limits {
+                memory: Some(64 << 20),
+                fs_bytes: Some(1 << 20),
+                ..Limits::default()
+            },
+        );
+        patch(
+            &mut s,
+            Limits {
+                cpu: Some(50),
+                fs_bytes: Some(0),
+                ..Limits::default()
+            },
+        );
+        let want = Limits {
+            memory: Some(64 << 20),
+            cpu: Some(50),
+            ..Limits::default()
+        };
+        assert_eq!(s.graph.node_limits.get(&id), Some(&want));
+        assert_eq!(node.stats.memory_limit.load(Ordering::Relaxed), 64 << 20);
+        assert_eq!(node.stats.cpu_limit.load(Ordering::Relaxed), 50);
+        assert_eq!(
+            node.fs.lock().unwrap().quota(),
+            crate::vfs::Quota::default()
+        );
+        match s.node_snap(id).map(|n| n.kind) {
+            Some(SnapKind::App { limits, .. }) => assert_eq!(limits, want),
+            other => panic!("not an app snap: {other:?}"),
+        }
+
+        let error = |s: &mut Server| {
+            let snap = s.ipc_snapshot();
+            snap.nodes
+                .iter()
+                .find(|n| n.id == id)
+                .unwrap()
+                .error
+                .clone()
+        };
+        assert_eq!(error(&mut s), None);
+        *node.stats.exceeded.lock().unwrap() = Some("memory limit reached (64MiB)".into());
+        assert_eq!(
+            error(&mut s).as_deref(),
+            Some("memory limit reached (64MiB)")
+        );
+
+        patch(
+            &mut s,
+            Limits {
+                memory: Some(0),
+                cpu: Some(0),
+                ..Limits::default()
+            },
+        );
+        assert!(
+            !s.graph.node_limits.contains_key(&id),
+            "no limits, no entry"
+        );
+        assert_eq!(error(&mut s), None, "changing the limits clears the error");
+    }
+
+    /// Env patches set and unset a node's own variables by name (ignoring
+    /// ones that aren't names at all), reach its live node for the next
+    /// run, and are saved.
+    #[test]
+    fn env_patches_set_and_unset_variables_by_name() {
+        let mut s = fresh_server();
+        let node = stub_node(&mut s, "php");
+        let id = node.id;
+        let patch = |s: &mut Server, vars: &[(&str, Option<&str>)]| {
+            s.apply(Command::Update {
+                id,
+                patch: NodePatch {
+                    env: Some(
+                        vars.iter()
+                            .map(|(k, v)| (k.to_string(), v.map(str::to_string)))
+                            .collect(),
+                    ),
+                    ..Default::default()
+                },
+            })
+        };
+        let kv = |k: &str, v: &str| (k.to_string(), v.to_string());
+        patch(
+            &mut s,
+            &[
+                ("APP_ENV", Some("prod")),
+                ("DEBUG", Some("1")),
+                ("A=B", Some("x")),
+            ],
+        );
+        patch(&mut s, &[("APP_ENV", Some("staging")), ("DEBUG", None)]);
+        assert_eq!(
+            s.graph.node_env.get(&id),
+            Some(&vec![kv("APP_ENV", "staging")])
+        );
+        assert_eq!(node.environment(), vec![kv("APP_ENV", "staging")]);
+        match s.node_snap(id).map(|n| n.kind) {
+            Some(SnapKind::App { env, .. }) => assert_eq!(env, vec![kv("APP_ENV", "staging")]),
+            other => panic!("not an app snap: {other:?}"),
+        }
+
+        patch(&mut s, &[("APP_ENV", None)]);
+        assert!(
+            !s.graph.node_env.contains_key(&id),
+            "no variables, no entry"
+        );
+        assert!(node.environment().is_empty());
+    }
+
     /// Dropping a file from the OS creates a BindMount already pointed at
     /// the path (Resource::HostMount), named by its basename, in one
     /// undoable step.
@@ -4076,6 +5399,313 @@ mod model_tests {
         assert!(s.graph.file_nodes.is_empty(), "undo uncreates the mount");
     }
 
+    /// A create reports exactly the node it placed, already configured with
+    /// the initial size/args it carried (undone as one step); a wire create
+    /// reports the wire inferred from the two kinds, and nothing when the kinds
+    /// don't wire.
+    #[test]
+    fn create_replies_with_the_created_ids_and_wire() {
+        let mut s = fresh_server();
+        let ws = s.graph.workspaces[0];
+        let create = |kind, size| {
+            Command::Create(Resource::Node {
+                kind,
+                pos: [0.0, 0.0],
+                ws,
+                id: None,
+                args: None,
+                env: Vec::new(),
+                size,
+            })
+        };
+        let made = s.apply(create(NodeKind::Network, Some([200.0, 120.0])));
+        assert_eq!(made.nodes.len(), 1, "one node placed");
+        assert!(made.wire.is_none());
+        let net = made.nodes[0];
+        assert_eq!(s.graph.nodes[&net].size, [200.0, 120.0]);
+        assert_eq!(s.graph.nodes[&net].kind, Kind::Network);
+
+        // An app (a stand-in record — wiring reads the graph) joins the net.
+        let app = NodeId::new();
+        s.place(app, Kind::App, ws, [0.0, 0.0], [100.0, 100.0]);
+        let wired = s.apply(Command::Create(Resource::Wire { a: net, b: app }));
+        assert_eq!(wired.wire, Some(Wire::Net(app, net)));
+        assert!(wired.nodes.is_empty());
+        // Already wired: the create is a no-op but still names the wire.
+        let again = s.apply(Command::Create(Resource::Wire { a: app, b: net }));
+        assert_eq!(again.wire, Some(Wire::Net(app, net)));
+
+        // Two networks never wire.
+        let other = s.apply(create(NodeKind::Network, None)).nodes[0];
+        let none = s.apply(Command::Create(Resource::Wire { a: net, b: other }));
+        assert!(none.wire.is_none());
+
+        // A duplicate reports its copy; the initial size was part of the one
+        // undoable create.
+        let dup = s.apply(Command::Duplicate(net)).nodes;
+        assert_eq!(dup.len(), 1);
+        assert_ne!(dup[0], net);
+        assert!(
+            s.apply(Command::Undo).nodes.is_empty(),
+            "undo reports nothing"
+        );
+        assert!(!s.graph.nodes.contains_key(&dup[0]));
+    }
+
+    /// A batch builds a small topology in one step — later commands target
+    /// nodes by the ids minted for them earlier in the batch — and a single
+    /// undo takes all of it back. A batch with a bad step leaves nothing
+    /// behind, and the history untouched.
+    #[test]
+    fn batches_apply_all_or_nothing_as_one_undo_step() {
+        let mut s = fresh_server();
+        let ws = s.graph.workspaces[0];
+        let create = |kind, id| {
+            Command::Create(Resource::Node {
+                kind,
+                pos: [0.0, 0.0],
+                ws,
+                id: Some(id),
+                args: None,
+                env: Vec::new(),
+                size: None,
+            })
+        };
+        let (net, port, note) = (NodeId::new(), NodeId::new(), NodeId::new());
+        let made = s
+            .try_apply(Command::Batch(vec![
+                create(NodeKind::Network, net),
+                create(NodeKind::Port, port),
+                Command::Update {
+                    id: port,
+                    patch: NodePatch {
+                        port_set: Some(4000),
+                        pos: Some([50.0, 60.0]),
+                        ..Default::default()
+                    },
+                },
+                create(NodeKind::Note, note),
+            ]))
+            .expect("the batch applies");
+        assert_eq!(made.nodes, vec![net, port, note]);
+        assert_eq!(s.graph.host_ports.get(&port).copied(), Some(4000));
+        assert_eq!(s.graph.nodes[&port].pos, [50.0, 60.0]);
+        assert_eq!(s.undo.len(), 1, "one history entry for the whole batch");
+
+        s.apply(Command::Undo);
+        assert!(s.graph.nodes.is_empty(), "one undo reverts the batch");
+        assert!(s.undo.is_empty());
+
+        // The third step targets a node that doesn't exist: the two creates
+        // before it are rolled back, and nothing is recorded.
+        let err = s
+            .try_apply(Command::Batch(vec![
+                create(NodeKind::Network, net),
+                create(NodeKind::Port, port),
+                Command::Create(Resource::Wire {
+                    a: port,
+                    b: NodeId::new(),
+                }),
+            ]))
+            .unwrap_err();
+        assert!(err.starts_with("step 3"), "{err}");
+        assert!(s.graph.nodes.is_empty(), "rolled back");
+        assert!(s.graph.host_ports.is_empty());
+        assert!(s.undo.is_empty(), "a failed batch leaves no history");
+
+        // Undo can't hide inside a batch; a minted id can't be reused.
+        assert!(s.try_apply(Command::Batch(vec![Command::Undo])).is_err());
+        s.apply(create(NodeKind::Note, note));
+        assert!(s
+            .try_apply(Command::Batch(vec![create(NodeKind::Port, note)]))
+            .is_err());
+        assert_eq!(s.graph.nodes.len(), 1);
+    }
+
+    /// A mount path, a run and an update's 3D pose, persistence, restart
+    /// policy, limits or env aren't undoable on their own, but a batch whose later step fails still puts
+    /// them back.
+    #[test]
+    fn a_failed_batch_puts_back_mount_paths_and_runs() {
+        let mut s = fresh_server();
+        let ws = s.graph.workspaces[0];
+        let node = stub_node(&mut s, "php");
+        let app = node.id;
+        let vol = s
+            .apply(Command::Create(Resource::Node {
+                kind: NodeKind::Volume,
+                pos: [0.0, 0.0],
+                ws,
+                id: None,
+                args: None,
+                env: Vec::new(),
+                size: None,
+            }))
+            .nodes[0];
+        s.graph.connections.push((vol, app));
+        let steps = || {
+            vec![
+                Command::SetMount {
+                    volume: vol,
+                    app,
+                    path: "/data/notes.txt".into(),
+                },
+                Command::Run(app),
+                Command::Update {
+                    id: vol,
+                    patch: NodePatch {
+                        persist: Some(true),
+                        ..Default::default()
+                    },
+                },
+                Command::Update {
+                    id: app,
+                    patch: NodePatch {
+                        pos3d: Some([1.0, 2.0, 3.0, 0.5]),
+                        restart: Some(Restart {
+                            policy: RestartPolicy::Always,
+                            max_retries: 0,
+                            backoff_ms: 0,
+                        }),
+                        limits: Some(Box::new(Limits {
+                            cpu: Some(50),
+                            ..Limits::default()
+                        })),
+                        env: Some(vec![("APP_ENV".into(), Some("staging".into()))]),
+                        ..Default::default()
+                    },
+                },
+            ]
+        };
+        let persists = |s: &Server| match s.graph.file_nodes.get(&vol) {
+            Some(FileNode::Volume(v)) => v.persist,
+            _ => false,
+        };
+
+        let mut failing = steps();
+        failing.push(Command::Create(Resource::Wire {
+            a: app,
+            b: NodeId::new(),
+        }));
+        let err = s.try_apply(Command::Batch(failing)).unwrap_err();
+        assert!(err.starts_with("step 5"), "{err}");
+        assert!(!s.graph.mount_paths.contains_key(&(vol, app)));
+        assert!(!s.pending_run.contains(&app), "the run is taken back");
+        assert!(!persists(&s));
+        assert!(!s.graph.pos3d.contains_key(&app));
+        assert!(!s.graph.node_restart.contains_key(&app));
+        assert!(!s.graph.node_limits.contains_key(&app));
+        assert!(!s.graph.node_env.contains_key(&app));
+        assert!(node.env.lock().unwrap().is_empty(), "the live node's too");
+        assert_eq!(s.undo.len(), 1, "only the volume's create");
+
+        // Applied, they stay out of the undo history, as on their own.
+        s.try_apply(Command::Batch(steps())).unwrap();
+        assert_eq!(s.mount_path_for(vol, app), "/data/notes.txt");
+        assert!(s.pending_run.contains(&app), "still compiling: queued");
+        assert!(persists(&s));
+        assert_eq!(s.graph.pos3d.get(&app), Some(&[1.0, 2.0, 3.0, 0.5]));
+        assert_eq!(s.graph.node_restart[&app].policy, RestartPolicy::Always);
+        assert_eq!(s.graph.node_limits[&app].cpu, Some(50));
+        assert_eq!(
+            s.graph.node_env[&app],
+            vec![("APP_ENV".to_string(), "staging".to_string())]
+        );
+        assert_eq!(s.undo.len(), 1);
+    }
+
+    /// Redo re-applies what undo reverted — a create comes back under the
+    /// same id, a whole batch comes back as one step — and is labeled for
+    /// `wk history`. A new mutation forgets what could have been redone.
+    #[test]
+    fn redo_reapplies_undone_steps_until_a_new_mutation() {
+        let mut s = fresh_server();
+        let ws = s.graph.workspaces[0];
+        let port = s
+            .apply(Command::Create(Resource::Node {
+                kind: NodeKind::Port,
+                pos: [0.0, 0.0],
+                ws,
+                id: None,
+                args: None,
+                env: Vec::new(),
+                size: None,
+            }))
+            .nodes[0];
+        let moved = |x| Command::Update {
+            id: port,
+            patch: NodePatch {
+                pos: Some([x, 0.0]),
+                ..Default::default()
+            },
+        };
+        s.apply(moved(40.0));
+        let (undo, redo) = s.history();
+        assert_eq!(undo.len(), 2);
+        assert!(undo[0].starts_with("create hostport"), "{undo:?}");
+        assert!(undo[1].starts_with("move hostport"), "{undo:?}");
+        assert!(redo.is_empty());
+
+        // Undo both, then redo both: same node, same place.
+        s.apply(Command::Undo);
+        s.apply(Command::Undo);
+        assert!(s.graph.nodes.is_empty());
+        assert_eq!(s.history().1.len(), 2);
+        s.apply(Command::Redo);
+        assert!(s.graph.nodes.contains_key(&port), "recreated under its id");
+        s.apply(Command::Redo);
+        assert_eq!(s.graph.nodes[&port].pos, [40.0, 0.0]);
+        assert_eq!(s.history(), (undo, Vec::new()));
+
+        // Undo/redo alternate indefinitely, each saying what it reversed.
+        let last = s.history().0.pop();
+        let undid = s.apply(Command::Undo).undone;
+        assert!(undid.is_some());
+        assert_eq!(undid, last);
+        assert_eq!(s.graph.nodes[&port].pos, [0.0, 0.0]);
+        assert_eq!(s.apply(Command::Redo).undone, undid);
+        assert_eq!(s.graph.nodes[&port].pos, [40.0, 0.0]);
+
+        // A batch redoes as one step.
+        let (net, note) = (NodeId::new(), NodeId::new());
+        let node = |kind, id| {
+            Command::Create(Resource::Node {
+                kind,
+                pos: [0.0, 0.0],
+                ws,
+                id: Some(id),
+                args: None,
+                env: Vec::new(),
+                size: None,
+            })
+        };
+        s.try_apply(Command::Batch(vec![
+            node(NodeKind::Network, net),
+            node(NodeKind::Note, note),
+        ]))
+        .unwrap();
+        assert!(s
+            .history()
+            .0
+            .last()
+            .unwrap()
+            .starts_with("batch: create network"));
+        s.apply(Command::Undo);
+        assert_eq!(s.graph.nodes.len(), 1);
+        s.apply(Command::Redo);
+        assert!(s.graph.nodes.contains_key(&net) && s.graph.nodes.contains_key(&note));
+
+        // A new mutation after an undo clears the redo history.
+        s.apply(Command::Undo);
+        s.apply(moved(80.0));
+        assert!(s.history().1.is_empty());
+        s.apply(Command::Redo);
+        assert!(!s.graph.nodes.contains_key(&net), "nothing left to redo");
+
+        // Redo can't hide inside a batch either.
+        assert!(s.try_apply(Command::Batch(vec![Command::Redo])).is_err());
+    }
+
     /// A HostPort's localhost port can be set absolutely via `port_set` (what
     /// `wk create port <n>` / `wk node set --port` use).
     #[test]
@@ -4086,6 +5716,10 @@ mod model_tests {
             kind: NodeKind::Port,
             pos: [0.0, 0.0],
             ws,
+            id: None,
+            args: None,
+            env: Vec::new(),
+            size: None,
         }));
         let id = *s.graph.host_ports.keys().next().expect("a hostport");
         s.apply(Command::Update {
@@ -4139,6 +5773,10 @@ mod model_tests {
             kind: NodeKind::Volume,
             pos: [0.0, 0.0],
             ws,
+            id: None,
+            args: None,
+            env: Vec::new(),
+            size: None,
         }));
         let vol = *s
             .graph
@@ -4183,6 +5821,10 @@ mod model_tests {
             kind: NodeKind::BindMount,
             pos: [0.0, 0.0],
             ws,
+            id: None,
+            args: None,
+            env: Vec::new(),
+            size: None,
         }));
         let id = *s.graph.file_nodes.keys().next().expect("a bind mount");
         s.apply(Command::Update {
@@ -4218,6 +5860,10 @@ mod model_tests {
                 kind: NodeKind::Volume,
                 pos: [0.0, 0.0],
                 ws,
+                id: None,
+                args: None,
+                env: Vec::new(),
+                size: None,
             }));
             let vol = *s.graph.file_nodes.keys().next().expect("a volume");
             s.apply(Command::Update {
@@ -4265,6 +5911,10 @@ mod model_tests {
             kind: NodeKind::Volume,
             pos: [0.0, 0.0],
             ws,
+            id: None,
+            args: None,
+            env: Vec::new(),
+            size: None,
         }));
         let vol = *s.graph.file_nodes.keys().next().expect("a volume");
         s.apply(Command::Update {
@@ -4309,6 +5959,10 @@ mod model_tests {
             kind: NodeKind::Volume,
             pos: [0.0, 0.0],
             ws,
+            id: None,
+            args: None,
+            env: Vec::new(),
+            size: None,
         }));
         let vol = *s.graph.file_nodes.keys().next().expect("a volume");
         let app = NodeId::new();
@@ -4363,6 +6017,9 @@ mod model_tests {
                             options: vec![1.0, 2.0],
                             args: vec!["hello".into()],
                             token: None,
+                            restart: "always".parse().unwrap(),
+                            limits: Limits::default(),
+                            env: Vec::new(),
                         },
                     },
                     NodeSnap {
@@ -4407,6 +6064,9 @@ mod model_tests {
                 options: vec![1.0, 2.0],
                 args: vec!["hello".into()],
                 token: None,
+                restart: "always".parse().unwrap(),
+                limits: Limits::default(),
+                env: Vec::new(),
             }
         );
         assert!(
@@ -4429,11 +6089,19 @@ mod model_tests {
                 kind: NodeKind::Iroh,
                 pos: [0.0, 0.0],
                 ws,
+                id: None,
+                args: None,
+                env: Vec::new(),
+                size: None,
             }));
             s.apply(Command::Create(Resource::Node {
                 kind: NodeKind::Network,
                 pos: [100.0, 0.0],
                 ws,
+                id: None,
+                args: None,
+                env: Vec::new(),
+                size: None,
             }));
             let iroh = *s.graph.iroh_secrets.keys().next().expect("iroh node");
             let net = s
@@ -4491,11 +6159,19 @@ mod model_tests {
                 kind: NodeKind::Veilid,
                 pos: [0.0, 0.0],
                 ws,
+                id: None,
+                args: None,
+                env: Vec::new(),
+                size: None,
             }));
             s.apply(Command::Create(Resource::Node {
                 kind: NodeKind::Network,
                 pos: [100.0, 0.0],
                 ws,
+                id: None,
+                args: None,
+                env: Vec::new(),
+                size: None,
             }));
             let uplink = *s.graph.veilid_ids.keys().next().expect("veilid node");
             let net = s
@@ -4563,6 +6239,7 @@ mod model_tests {
         /// geometry.
         UpdateGhost(u128),
         Undo,
+        Redo,
     }
 
     fn op_strat() -> impl Strategy<Value = Op> {
@@ -4580,6 +6257,7 @@ mod model_tests {
             any::<usize>().prop_map(Op::Duplicate),
             any::<u128>().prop_map(Op::UpdateGhost),
             Just(Op::Undo),
+            Just(Op::Redo),
         ]
     }
 
@@ -4590,13 +6268,25 @@ mod model_tests {
                 kind,
                 pos: [10.0, 20.0],
                 ws,
+                id: None,
+                args: None,
+                env: Vec::new(),
+                size: None,
             })
         };
         match op {
-            Op::CreateFile => s.apply(create(NodeKind::Volume)),
-            Op::CreatePort => s.apply(create(NodeKind::Port)),
-            Op::CreateNet => s.apply(create(NodeKind::Network)),
-            Op::CreateGateway => s.apply(create(NodeKind::Gateway)),
+            Op::CreateFile => {
+                s.apply(create(NodeKind::Volume));
+            }
+            Op::CreatePort => {
+                s.apply(create(NodeKind::Port));
+            }
+            Op::CreateNet => {
+                s.apply(create(NodeKind::Network));
+            }
+            Op::CreateGateway => {
+                s.apply(create(NodeKind::Gateway));
+            }
             Op::Move(i, x, y) => {
                 if let Some(id) = nth_live(s, *i) {
                     s.apply(Command::Update {
@@ -4640,24 +6330,37 @@ mod model_tests {
                     s.apply(Command::Duplicate(id));
                 }
             }
-            Op::UpdateGhost(n) => s.apply(Command::Update {
-                id: NodeId::from_u128(*n),
-                patch: NodePatch {
-                    pos3d: None,
-                    pos: Some([1.0, 2.0]),
-                    size: Some([3.0, 4.0]),
-                    args: Some("ghost".into()),
-                    port_delta: None,
-                    port_set: None,
-                    text: None,
-                    host_path: None,
-                    midi_device: None,
-                    persist: None,
-                    service_name: None,
-                    service_target: None,
-                },
-            }),
-            Op::Undo => s.apply(Command::Undo),
+            Op::UpdateGhost(n) => {
+                s.apply(Command::Update {
+                    id: NodeId::from_u128(*n),
+                    patch: NodePatch {
+                        pos3d: None,
+                        pos: Some([1.0, 2.0]),
+                        size: Some([3.0, 4.0]),
+                        args: Some("ghost".into()),
+                        port_delta: None,
+                        port_set: None,
+                        text: None,
+                        host_path: None,
+                        midi_device: None,
+                        persist: None,
+                        service_name: None,
+                        service_target: None,
+                        restart: Some("always".parse().unwrap()),
+                        limits: Some(Box::new(Limits {
+                            cpu: Some(25),
+                            ..Limits::default()
+                        })),
+                        env: Some(vec![("GHOST".into(), Some("1".into()))]),
+                    },
+                });
+            }
+            Op::Undo => {
+                s.apply(Command::Undo);
+            }
+            Op::Redo => {
+                s.apply(Command::Redo);
+            }
         }
     }
 
@@ -4675,6 +6378,15 @@ mod model_tests {
         for id in s.graph.node_args.keys() {
             prop_assert!(base.contains(id), "orphan node_args entry");
         }
+        for id in s.graph.node_env.keys() {
+            prop_assert!(base.contains(id), "orphan node_env entry");
+        }
+        for id in s.graph.node_restart.keys() {
+            prop_assert!(base.contains(id), "orphan node_restart entry");
+        }
+        for id in s.graph.node_limits.keys() {
+            prop_assert!(base.contains(id), "orphan node_limits entry");
+        }
         for id in s.graph.file_nodes.keys() {
             prop_assert!(base.contains(id), "orphan file_nodes entry");
         }
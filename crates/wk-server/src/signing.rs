@@ -0,0 +1,140 @@
+//! Plugin signatures: who published a dependency's component, checked against
+//! a key the `.wk` file pins.
+//!
+//! `wk publish --sign <keyfile>` signs the pushed component's digest
+//! (`sha256:<hex>`) with an ed25519 key and attaches the [`Signature`] to the
+//! artifact as an OCI referrer (see `crate::oci`). A dependency that declares
+//! `trusted-key "ed25519:<hex>"` runs only if its pulled component carries a
+//! valid signature by that key over its exact digest — an unsigned component,
+//! one signed by another key, or one whose bytes don't match what was signed
+//! fails with an error saying which.
+
+use std::path::Path;
+
+use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::workspace::{bytes_hex, hex_bytes, secret_bytes, secret_hex};
+
+/// One signature over a component digest, as published and as cached.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Signature {
+    /// What was signed: the component's digest, `sha256:<hex>`.
+    pub digest: String,
+    /// The signer's public key, `ed25519:<hex>`.
+    pub key: String,
+    /// The ed25519 signature over `digest`'s bytes, hex.
+    pub signature: String,
+}
+
+/// A public key in its `.wk` form, `ed25519:<hex>`.
+pub fn key_string(key: &VerifyingKey) -> String {
+    format!("ed25519:{}", secret_hex(key.as_bytes()))
+}
+
+/// Parse a `trusted-key` value.
+pub fn parse_key(s: &str) -> Result<VerifyingKey, String> {
+    let bytes = s
+        .strip_prefix("ed25519:")
+        .and_then(secret_bytes)
+        .ok_or_else(|| format!("bad trusted-key {s:?}: expected ed25519:<64 hex digits>"))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("bad trusted-key {s:?}: {e}"))
+}
+
+/// The signing key in `path` (its 32-byte secret, hex), created if the file
+/// doesn't exist yet — readable only by its owner.
+pub fn load_or_create(path: &Path) -> Result<SigningKey, String> {
+    if let Ok(text) = std::fs::read_to_string(path) {
+        let secret = secret_bytes(text.trim())
+            .ok_or_else(|| format!("{}: not an ed25519 signing key", path.display()))?;
+        return Ok(SigningKey::from_bytes(&secret));
+    }
+    let mut secret = [0u8; 32];
+    getrandom::fill(&mut secret).map_err(|e| format!("generate signing key: {e}"))?;
+    let mut open = std::fs::OpenOptions::new();
+    open.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut open, 0o600);
+    open.open(path)
+        .and_then(|mut f| std::io::Write::write_all(&mut f, secret_hex(&secret).as_bytes()))
+        .map_err(|e| format!("write {}: {e}", path.display()))?;
+    Ok(SigningKey::from_bytes(&secret))
+}
+
+/// Sign the component digest `digest` (`sha256:<hex>`).
+pub fn sign(key: &SigningKey, digest: &str) -> Signature {
+    Signature {
+        digest: digest.to_string(),
+        key: key_string(&key.verifying_key()),
+        signature: bytes_hex(&key.sign(digest.as_bytes()).to_bytes()),
+    }
+}
+
+/// Check that one of `signatures` is `trusted`'s, over `digest`. `what` names
+/// the dependency in the error.
+pub fn verify(
+    what: &str,
+    digest: &str,
+    signatures: &[Signature],
+    trusted: &str,
+) -> Result<(), String> {
+    let key = parse_key(trusted)?;
+    if signatures.is_empty() {
+        return Err(format!(
+            "{what} is unsigned, but its dependency trusts only {trusted}"
+        ));
+    }
+    let Some(sig) = signatures.iter().find(|s| s.key == key_string(&key)) else {
+        let by: Vec<&str> = signatures.iter().map(|s| s.key.as_str()).collect();
+        return Err(format!(
+            "{what} is signed by {}, not by its trusted key {trusted}",
+            by.join(", ")
+        ));
+    };
+    let valid = hex_bytes(&sig.signature)
+        .and_then(|b| <[u8; 64]>::try_from(b).ok())
+        .map(|b| ed25519_dalek::Signature::from_bytes(&b))
+        .is_some_and(|s| key.verify(sig.digest.as_bytes(), &s).is_ok());
+    if !valid || sig.digest != digest {
+        return Err(format!(
+            "{what}'s signature by {trusted} doesn't match its content ({digest})"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_trusted_key_over_the_exact_digest_verifies() {
+        let dir = std::env::temp_dir().join("wk-signing");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let ours = load_or_create(&dir.join("ours.key")).unwrap();
+        let again = load_or_create(&dir.join("ours.key")).unwrap();
+        assert_eq!(ours.to_bytes(), again.to_bytes(), "a keyfile is reused");
+        let theirs = load_or_create(&dir.join("theirs.key")).unwrap();
+        let trusted = key_string(&ours.verifying_key());
+        assert_eq!(parse_key(&trusted).unwrap(), ours.verifying_key());
+        assert!(parse_key("ed25519:abc").is_err());
+
+        let digest = "sha256:1111";
+        let sig = sign(&ours, digest);
+        verify("synth", digest, std::slice::from_ref(&sig), &trusted).unwrap();
+
+        let err = verify("synth", digest, &[], &trusted).unwrap_err();
+        assert!(err.contains("unsigned"), "{err}");
+        let err = verify("synth", digest, &[sign(&theirs, digest)], &trusted).unwrap_err();
+        assert!(err.contains("not by its trusted key"), "{err}");
+        let err = verify("synth", "sha256:2222", std::slice::from_ref(&sig), &trusted).unwrap_err();
+        assert!(err.contains("doesn't match"), "{err}");
+        let forged = Signature {
+            digest: "sha256:2222".into(),
+            ..sig
+        };
+        let err = verify("synth", "sha256:2222", &[forged], &trusted).unwrap_err();
+        assert!(err.contains("doesn't match"), "{err}");
+    }
+}
@@ -0,0 +1,357 @@
+//! Per-node runtime counters — what `wk stats` reports.
+//!
+//! Most of a node's footprint can be read off state the server already holds
+//! (its filesystem, its network stack), but some of it only exists inside a
+//! running store: how much linear memory the guest has grown to, how many
+//! frames it has presented, how much MIDI it has sent, how many `wk:exec`
+//! children it has live. Those are counted here, into atomics the node and
+//! every store running on its behalf share, so the server can read them
+//! without stopping the guest or reaching into its thread.
+//!
+//! The counters are cumulative where that is what a reader wants (frames,
+//! MIDI) and gauges where it isn't (memory, children): a gauge is given back
+//! when what it counts goes away, so a node that restarts doesn't report its
+//! previous run's memory on top of its current one.
+//!
+//! The node's memory and CPU limits live here too, for the same reason: every
+//! store running on its behalf enforces them ([`Meter`] refuses memory growth
+//! past the ceiling, [`Slicer`] pauses a guest that keeps a core busy), and
+//! notes what it held back in [`Counters::exceeded`] for the node's error.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// One node's live counters.
+#[derive(Default)]
+pub struct Counters {
+    /// Bytes of wasm linear memory held by the node's guest and its `wk:exec`
+    /// children (gauge).
+    pub memory: AtomicU64,
+    /// MIDI messages the node's output delivered, one per destination reached.
+    pub midi_sent: AtomicU64,
+    /// Frames the guest presented on its surfaces.
+    pub frames: AtomicU64,
+    /// `wk:exec` children currently running (gauge).
+    pub exec_children: AtomicU64,
+    /// Bytes of linear memory the node's stores may hold between them
+    /// (`0` = no limit).
+    pub memory_limit: AtomicU64,
+    /// Percent of one core the node's stores may keep busy between them
+    /// (`0` = no limit).
+    pub cpu_limit: AtomicU64,
+    /// Stores running on the node's behalf, which split its CPU limit
+    /// (gauge).
+    pub cpu_stores: AtomicU64,
+    /// The limit a store last held the guest to, as the node's error —
+    /// cleared when the limits change or the guest runs again.
+    pub exceeded: Mutex<Option<String>>,
+}
+
+impl Counters {
+    /// Set the node's memory and CPU limits (`None` = no limit).
+    pub fn set_limits(&self, memory: Option<u64>, cpu: Option<u32>) {
+        self.memory_limit
+            .store(memory.unwrap_or(0), Ordering::Relaxed);
+        self.cpu_limit
+            .store(cpu.map_or(0, u64::from), Ordering::Relaxed);
+        self.exceeded.lock().unwrap().take();
+    }
+
+    fn note_exceeded(&self, what: String) {
+        *self.exceeded.lock().unwrap() = Some(what);
+    }
+}
+
+/// A limit's byte count as its error shows it: whole binary units where it
+/// has them (`64MiB`), bytes otherwise.
+pub(crate) fn limit_size(n: u64) -> String {
+    for (shift, unit) in [(30, "GiB"), (20, "MiB"), (10, "KiB")] {
+        if n >= 1 << shift && n.trailing_zeros() >= shift {
+            return format!("{}{unit}", n >> shift);
+        }
+    }
+    format!("{n} bytes")
+}
+
+pub type SharedCounters = Arc<Counters>;
+
+pub fn new_counters() -> SharedCounters {
+    Arc::new(Counters::default())
+}
+
+/// A store's handle on its node's counters, and its
+/// [`ResourceLimiter`](wasmtime::ResourceLimiter) — watching linear memory
+/// grow to add it to the node's gauge, and refusing growth that would take
+/// the gauge past the node's memory limit. Installed with
+/// `store.limiter(|s| &mut s.meter)`. Whatever it added is taken back when
+/// the store (and so the meter) is dropped, which is when the memory is freed.
+///
+/// wasmtime's own `StoreLimits` won't do: it caps each memory of one store on
+/// its own, while a node's limit covers its guest and every `wk:exec` child
+/// together, can be patched while they run, and has to say what it refused
+/// for the node's error.
+pub struct Meter {
+    counters: SharedCounters,
+    grown: u64,
+}
+
+impl Meter {
+    pub fn new(counters: SharedCounters) -> Self {
+        Self { counters, grown: 0 }
+    }
+
+    /// The guest presented a frame.
+    pub(crate) fn frame(&self) {
+        self.counters.frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The guest's MIDI output delivered a message to `n` destinations.
+    pub(crate) fn midi_routed(&self, n: usize) {
+        self.counters
+            .midi_sent
+            .fetch_add(n as u64, Ordering::Relaxed);
+    }
+}
+
+impl wasmtime::ResourceLimiter for Meter {
+    fn memory_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        // Instantiation reports each memory growing from zero, so the sum of
+        // the deltas is the whole of what the store holds.
+        let delta = desired.saturating_sub(current) as u64;
+        let limit = self.counters.memory_limit.load(Ordering::Relaxed);
+        if limit > 0 && self.counters.memory.load(Ordering::Relaxed) + delta > limit {
+            self.counters
+                .note_exceeded(format!("memory limit reached ({})", limit_size(limit)));
+            return Ok(false);
+        }
+        self.grown += delta;
+        self.counters.memory.fetch_add(delta, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        _desired: usize,
+        _maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        Ok(true)
+    }
+}
+
+impl Drop for Meter {
+    fn drop(&mut self) {
+        self.counters
+            .memory
+            .fetch_sub(self.grown, Ordering::Relaxed);
+    }
+}
+
+/// The longest gap between two epoch deadlines that still counts as the guest
+/// running throughout; a longer one means it sat in a host call in between.
+const BUSY_GAP: Duration = Duration::from_millis(100);
+/// The longest one pause, so even a tiny share keeps the guest moving.
+const MAX_PAUSE: Duration = Duration::from_secs(1);
+/// How often a pause looks at the kill switch.
+const PAUSE_STEP: Duration = Duration::from_millis(10);
+
+/// Holds a store's guest to its node's CPU limit by epoch slicing. Called from
+/// the store's epoch-deadline callback, which runs once per epoch tick
+/// (~16ms) while the guest is executing wasm: the time since the last call
+/// is a slice the guest spent running, and after it the guest is paused long
+/// enough to bring it down to its share — at 25%, three slices' worth. A
+/// guest that mostly waits never fills a slice and is never paused.
+///
+/// The limit is the node's, guest and `wk:exec` children together, so each
+/// live store gets an even share of it: at 50% with one child running, the
+/// guest and the child are each held to 25%, whether or not the other is
+/// busy.
+pub(crate) struct Slicer {
+    counters: SharedCounters,
+    kill: Arc<AtomicBool>,
+    last: Option<Instant>,
+}
+
+impl Slicer {
+    pub(crate) fn new(counters: SharedCounters, kill: Arc<AtomicBool>) -> Self {
+        counters.cpu_stores.fetch_add(1, Ordering::Relaxed);
+        Self {
+            counters,
+            kill,
+            last: None,
+        }
+    }
+
+    /// An epoch deadline passed: pause if the guest has been busy past its
+    /// share. Returns early if it is killed meanwhile.
+    pub(crate) fn tick(&mut self) {
+        let now = Instant::now();
+        let last = self.last.replace(now);
+        let pct = self.counters.cpu_limit.load(Ordering::Relaxed);
+        // This store's share is `pct / stores` percent of a core.
+        let stores = self.counters.cpu_stores.load(Ordering::Relaxed).max(1);
+        let ran = match last {
+            Some(last) if (1..100 * stores).contains(&pct) => now - last,
+            _ => return,
+        };
+        if ran > BUSY_GAP {
+            return;
+        }
+        self.counters
+            .note_exceeded(format!("held to its CPU limit ({pct}%)"));
+        let pause = (ran * (100 * stores - pct) as u32 / pct as u32).min(MAX_PAUSE);
+        let end = now + pause;
+        while !self.kill.load(Ordering::Relaxed) {
+            let left = end.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            std::thread::sleep(left.min(PAUSE_STEP));
+        }
+        self.last = Some(Instant::now());
+    }
+}
+
+impl Drop for Slicer {
+    fn drop(&mut self) {
+        self.counters.cpu_stores.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Holds one count in a node's `exec_children` for as long as a child runs:
+/// made when it is spawned, dropped when its thread ends.
+pub(crate) struct Live(SharedCounters);
+
+impl Live {
+    pub(crate) fn new(counters: SharedCounters) -> Self {
+        counters.exec_children.fetch_add(1, Ordering::Relaxed);
+        Self(counters)
+    }
+}
+
+impl Drop for Live {
+    fn drop(&mut self) {
+        self.0.exec_children.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmtime::ResourceLimiter;
+
+    /// Growth adds to the node's gauge; dropping the store's meter gives it
+    /// all back, and a second store on the same node counts alongside.
+    #[test]
+    fn meter_tracks_growth_until_dropped() {
+        let c = new_counters();
+        let mut a = Meter::new(c.clone());
+        a.memory_growing(0, 65536, None).unwrap();
+        a.memory_growing(65536, 3 * 65536, None).unwrap();
+        let mut b = Meter::new(c.clone());
+        b.memory_growing(0, 65536, None).unwrap();
+        assert_eq!(c.memory.load(Ordering::Relaxed), 4 * 65536);
+        drop(a);
+        assert_eq!(c.memory.load(Ordering::Relaxed), 65536);
+        drop(b);
+        assert_eq!(c.memory.load(Ordering::Relaxed), 0);
+    }
+
+    /// Growth past the node's limit is refused — counting every store on the
+    /// node — and noted; lifting the limit clears the note.
+    #[test]
+    fn meter_refuses_growth_past_the_memory_limit() {
+        let c = new_counters();
+        c.set_limits(Some(2 * 65536), None);
+        let mut a = Meter::new(c.clone());
+        assert!(a.memory_growing(0, 65536, None).unwrap());
+        let mut b = Meter::new(c.clone());
+        assert!(b.memory_growing(0, 65536, None).unwrap());
+        assert!(!a.memory_growing(65536, 2 * 65536, None).unwrap());
+        assert_eq!(c.memory.load(Ordering::Relaxed), 2 * 65536);
+        assert_eq!(
+            c.exceeded.lock().unwrap().as_deref(),
+            Some("memory limit reached (128KiB)")
+        );
+        c.set_limits(None, None);
+        assert!(c.exceeded.lock().unwrap().is_none());
+        assert!(a.memory_growing(65536, 2 * 65536, None).unwrap());
+    }
+
+    /// A busy guest (deadlines one slice apart) is paused for its share; one
+    /// that waited between deadlines isn't.
+    #[test]
+    fn slicer_pauses_a_busy_guest_to_its_share() {
+        let c = new_counters();
+        c.set_limits(None, Some(50));
+        let mut s = Slicer::new(c.clone(), Arc::new(AtomicBool::new(false)));
+        s.tick();
+        std::thread::sleep(Duration::from_millis(20));
+        let t = Instant::now();
+        s.tick();
+        assert!(
+            t.elapsed() >= Duration::from_millis(20),
+            "{:?}",
+            t.elapsed()
+        );
+        assert_eq!(
+            c.exceeded.lock().unwrap().as_deref(),
+            Some("held to its CPU limit (50%)")
+        );
+
+        c.set_limits(None, Some(50));
+        std::thread::sleep(BUSY_GAP + Duration::from_millis(10));
+        let t = Instant::now();
+        s.tick();
+        assert!(t.elapsed() < Duration::from_millis(20), "{:?}", t.elapsed());
+        assert!(c.exceeded.lock().unwrap().is_none());
+    }
+
+    /// A second store on the node halves each one's share: a busy slice at
+    /// 50% between two stores is followed by three slices' pause, not one.
+    #[test]
+    fn slicers_split_the_node_limit() {
+        let c = new_counters();
+        c.set_limits(None, Some(50));
+        let kill = Arc::new(AtomicBool::new(false));
+        let mut s = Slicer::new(c.clone(), kill.clone());
+        let other = Slicer::new(c.clone(), kill);
+        assert_eq!(c.cpu_stores.load(Ordering::Relaxed), 2);
+        s.tick();
+        std::thread::sleep(Duration::from_millis(20));
+        let t = Instant::now();
+        s.tick();
+        assert!(
+            t.elapsed() >= Duration::from_millis(60),
+            "{:?}",
+            t.elapsed()
+        );
+        drop(other);
+        assert_eq!(c.cpu_stores.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn limit_sizes_read_in_whole_units() {
+        assert_eq!(limit_size(64 << 20), "64MiB");
+        assert_eq!(limit_size(3 << 30), "3GiB");
+        assert_eq!(limit_size(1536), "1536 bytes");
+        assert_eq!(limit_size(1000), "1000 bytes");
+    }
+
+    #[test]
+    fn live_counts_running_children() {
+        let c = new_counters();
+        let one = Live::new(c.clone());
+        let two = Live::new(c.clone());
+        assert_eq!(c.exec_children.load(Ordering::Relaxed), 2);
+        drop(one);
+        drop(two);
+        assert_eq!(c.exec_children.load(Ordering::Relaxed), 0);
+    }
+}
@@ -17,10 +17,10 @@
 //! }
 //! ```
 
-use kdl::{KdlDocument, KdlEntry, KdlEntryFormat, KdlNode, KdlValue};
+use kdl::{KdlDocument, KdlEntry, KdlEntryFormat, KdlIdentifier, KdlNode, KdlValue};
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
-use wk_protocol::NodeId;
+use wk_protocol::{is_env_name, Limits, NodeId, Restart, RestartPolicy};
 
 /// A KDL entry for a string value that always serializes *quoted*.
 ///
@@ -42,6 +42,15 @@ fn str_entry(s: &str) -> KdlEntry {
     e
 }
 
+/// A node name written as a quoted string, so names KDL would read as
+/// something else (`true`, `null`, `1A`) or can't take bare (`A B`) still
+/// parse back as themselves.
+fn quoted_name(s: &str) -> KdlIdentifier {
+    let mut name = KdlIdentifier::from(s);
+    name.set_repr(kdl_quote(s));
+    name
+}
+
 /// Escape a string into a KDL quoted-string literal.
 fn kdl_quote(s: &str) -> String {
     let mut out = String::with_capacity(s.len() + 2);
@@ -210,28 +219,79 @@ impl Source {
         }
     }
 
+    /// The stored image this source resolves to — a built `docker://`, a
+    /// pulled container image, an `image://` ref — if it is one.
+    pub fn image_id(&self) -> Option<String> {
+        match self {
+            Source::Dockerfile(p) => crate::images::aliased_image(p).map(|(id, _)| id),
+            // A pulled container image stores under its sanitized reference; a
+            // plain wasm artifact has no stored image and mounts nothing.
+            Source::Oci(reference) => {
+                let id = crate::oci::sanitize(reference);
+                crate::images::load_image(&id).map(|_| id)
+            }
+            Source::Image(reference) => crate::images::resolve_ref(reference),
+            Source::Path(_) => None,
+        }
+    }
+
     /// Make the source runnable: pull + cache an OCI artifact, (re)build a
     /// Dockerfile image, or verify a local `image://` ref resolves. A no-op for
-    /// local paths.
-    pub fn ensure(&self) -> Result<(), String> {
+    /// local paths. With a pin from the `.wk` file's lock, the result must be
+    /// what was pinned: an OCI source pulls the pinned manifest rather than wherever
+    /// its tag points now, and anything else that resolves differently is
+    /// refused — except a local path, which is only warned about.
+    pub fn ensure(&self, pin: Option<&crate::lock::Pin>) -> Result<(), String> {
         match self {
             Source::Oci(reference) => {
-                if crate::oci::cached_artifact(reference).is_none() {
-                    println!("pulling {reference} ...");
-                    crate::oci::pull_into_cache(reference)?;
+                let pinned = pin.and_then(|p| Some((p.manifest.as_deref()?, &p.lock_file)));
+                let stale = pin.is_some_and(|p| {
+                    p.wasm.is_some() && p.wasm != crate::lock::wasm_digest(&self.local_path())
+                });
+                match pinned {
+                    Some((digest, lock_file)) if stale => {
+                        println!(
+                            "pulling {reference} at {digest} ({}) ...",
+                            lock_file.display()
+                        );
+                        crate::oci::pull_pinned(reference, digest)?;
+                    }
+                    _ if crate::oci::cached_artifact(reference).is_none() => {
+                        println!("pulling {reference} ...");
+                        crate::oci::pull_into_cache(reference)?;
+                    }
+                    _ => {}
                 }
-                Ok(())
             }
             Source::Dockerfile(p) => {
                 let id = crate::images::build_and_alias(p, false)?;
                 println!("built {} -> {id}", p.display());
-                Ok(())
             }
-            Source::Image(reference) => crate::images::resolve_ref(reference)
-                .map(|_| ())
-                .ok_or_else(|| format!("no local image {reference:?} (see `wk images list`)")),
-            Source::Path(_) => Ok(()),
+            Source::Image(reference) => {
+                crate::images::resolve_ref(reference).ok_or_else(|| {
+                    format!("no local image {reference:?} (see `wk images list`)")
+                })?;
+            }
+            Source::Path(_) => {}
+        }
+        let Some(pin) = pin else {
+            return Ok(());
+        };
+        let differences = pin.differences(&crate::lock::Pin::current(self));
+        if differences.is_empty() {
+            return Ok(());
         }
+        let msg = format!(
+            "{} differs from {} ({}); `wk lock --update` accepts it",
+            self.to_kdl(),
+            pin.lock_file.display(),
+            differences.join(", ")
+        );
+        if matches!(self, Source::Path(_)) {
+            eprintln!("warning: {msg}");
+            return Ok(());
+        }
+        Err(msg)
     }
 }
 
@@ -244,33 +304,53 @@ pub struct Dependency {
     pub args: Vec<String>,
     /// An optional one-line description, shown in the command palette.
     pub description: Option<String>,
+    /// The only key (`ed25519:<hex>`) whose signature lets this dependency
+    /// run; see `crate::signing`. `oci://` sources only.
+    pub trusted_key: Option<String>,
 }
 
 impl Dependency {
-    pub fn local_path(&self) -> PathBuf {
-        self.source.local_path()
+    /// The wasm to run. For a dependency with a trusted key, only a pulled
+    /// component signed by that key; anything else is refused, saying why.
+    pub fn local_path(&self) -> Result<PathBuf, String> {
+        match (&self.source, &self.trusted_key) {
+            (Source::Oci(reference), Some(key)) => crate::oci::verified_artifact(reference, key),
+            _ => Ok(self.source.local_path()),
+        }
+    }
+
+    /// See [`Source::ensure`]; `lock` is the workspace's, if it has one. A
+    /// dependency with a trusted key must then carry that key's signature.
+    pub fn ensure(&self, lock: Option<&crate::lock::Lock>) -> Result<(), String> {
+        self.source.ensure(lock.and_then(|l| l.pin(self)))?;
+        let Some(key) = &self.trusted_key else {
+            return Ok(());
+        };
+        let Source::Oci(reference) = &self.source else {
+            return Err(format!(
+                "trusted-key applies to oci:// dependencies, not {}",
+                self.source.to_kdl()
+            ));
+        };
+        if crate::oci::verified_artifact(reference, key).is_err() {
+            // Pulled before its signature was published, perhaps.
+            crate::oci::refresh_signature(reference)?;
+        }
+        crate::oci::verified_artifact(reference, key).map(|_| ())
     }
 
-    pub fn ensure(&self) -> Result<(), String> {
-        self.source.ensure()
+    /// The stored image behind a container source — a built `docker://`, a
+    /// pulled container image, an `image://` ref — if this dependency is one.
+    pub fn image(&self) -> Option<crate::images::ImageManifest> {
+        self.source
+            .image_id()
+            .and_then(|id| crate::images::load_image(&id))
     }
 
     /// The built image behind a `docker://` source — the layers to mount and
     /// the guest env — if this dependency is one (and it has been built).
     pub fn container(&self) -> Option<crate::images::ContainerSetup> {
-        match &self.source {
-            Source::Dockerfile(p) => {
-                crate::images::aliased_image(p).map(|(_, m)| m.container_setup())
-            }
-            // A pulled container image stores under its sanitized reference; a
-            // plain wasm artifact has no stored image and mounts nothing.
-            Source::Oci(reference) => crate::images::load_image(&crate::oci::sanitize(reference))
-                .map(|m| m.container_setup()),
-            Source::Image(reference) => crate::images::resolve_ref(reference)
-                .and_then(|id| crate::images::load_image(&id))
-                .map(|m| m.container_setup()),
-            Source::Path(_) => None,
-        }
+        self.image().map(|m| m.container_setup())
     }
 
     /// The dependency's default launch args: its own, or — for an image with
@@ -279,19 +359,7 @@ impl Dependency {
         if !self.args.is_empty() {
             return self.args.clone();
         }
-        match &self.source {
-            Source::Dockerfile(p) => crate::images::aliased_image(p)
-                .map(|(_, m)| m.default_args())
-                .unwrap_or_default(),
-            Source::Oci(reference) => crate::images::load_image(&crate::oci::sanitize(reference))
-                .map(|m| m.default_args())
-                .unwrap_or_default(),
-            Source::Image(reference) => crate::images::resolve_ref(reference)
-                .and_then(|id| crate::images::load_image(&id))
-                .map(|m| m.default_args())
-                .unwrap_or_default(),
-            Source::Path(_) => Vec::new(),
-        }
+        self.image().map(|m| m.default_args()).unwrap_or_default()
     }
 }
 
@@ -327,6 +395,17 @@ pub enum SnapKind {
         /// `wk token`. Absent = the workspace's default node token, which is
         /// minted fresh each run and never persisted.
         token: Option<String>,
+        /// Whether the server re-runs the guest after it exits. Written as
+        /// `restart "on-failure" max-retries=5 backoff-ms=2000`, and only when
+        /// the policy isn't `no`.
+        restart: Restart,
+        /// Its own resource limits. Written as `limits memory=67108864 cpu=50
+        /// fs-bytes=1048576 fs-nodes=1000` with only the limits it has, and
+        /// only when it has some.
+        limits: Limits,
+        /// Its own environment variables, layered over its image's ENV.
+        /// Written as an `env { KEY "value" }` block, one child per variable.
+        env: Vec<(String, String)>,
     },
     /// An in-memory named volume. Its *bytes* are runtime state — undo carries
     /// them alongside the snap; the `.wk` file persists them (to a sidecar) only
@@ -609,17 +688,19 @@ impl Document {
                                     .collect()
                             })
                             .unwrap_or_default();
-                        let description = n
-                            .children()
-                            .and_then(|ch| ch.get("description"))
-                            .and_then(|d| d.get(0))
-                            .and_then(|v| v.as_string())
-                            .map(str::to_string);
+                        let child_str = |key: &str| {
+                            n.children()
+                                .and_then(|ch| ch.get(key))
+                                .and_then(|d| d.get(0))
+                                .and_then(|v| v.as_string())
+                                .map(str::to_string)
+                        };
                         Some(Dependency {
                             name,
                             source: Source::parse(source),
                             args,
-                            description,
+                            description: child_str("description"),
+                            trusted_key: child_str("trusted-key"),
                         })
                     })
                     .collect()
@@ -691,6 +772,11 @@ impl Document {
                 desc_node.push(str_entry(d));
                 sub.nodes_mut().push(desc_node);
             }
+            if let Some(k) = &dep.trusted_key {
+                let mut key_node = KdlNode::new("trusted-key");
+                key_node.push(str_entry(k));
+                sub.nodes_mut().push(key_node);
+            }
             if !dep.args.is_empty() {
                 let mut args_node = KdlNode::new("args");
                 for a in &dep.args {
@@ -786,6 +872,59 @@ fn uint(v: &KdlValue) -> Option<u64> {
     v.as_integer().map(|i| i as u64)
 }
 
+/// Parse an app's `restart "<policy>" max-retries=N backoff-ms=N` node. An
+/// unknown policy reads as `no`, like any other unrecognized entry.
+fn restart(n: &KdlNode) -> Restart {
+    let policy = n
+        .get(0)
+        .and_then(|v| v.as_string())
+        .and_then(RestartPolicy::parse);
+    let Some(policy) = policy else {
+        return Restart::default();
+    };
+    let default = Restart::default();
+    Restart {
+        policy,
+        max_retries: n
+            .get("max-retries")
+            .and_then(uint)
+            .and_then(|n| u32::try_from(n).ok())
+            .unwrap_or(default.max_retries),
+        backoff_ms: n
+            .get("backoff-ms")
+            .and_then(uint)
+            .unwrap_or(default.backoff_ms),
+    }
+}
+
+/// Parse an app's `limits memory=N cpu=N fs-bytes=N fs-nodes=N` node. A
+/// missing or zero entry is no limit.
+fn limits(n: &KdlNode) -> Limits {
+    let get = |key: &str| n.get(key).and_then(uint).filter(|&v| v > 0);
+    Limits {
+        memory: get("memory"),
+        cpu: get("cpu").and_then(|v| u32::try_from(v).ok()),
+        fs_bytes: get("fs-bytes"),
+        fs_nodes: get("fs-nodes"),
+    }
+}
+
+/// Parse an app's `env { KEY "value" ... }` block, in order. A child that
+/// isn't a variable name with a string value is skipped.
+fn env(n: &KdlNode) -> Vec<(String, String)> {
+    let Some(vars) = n.children() else {
+        return Vec::new();
+    };
+    vars.nodes()
+        .iter()
+        .filter(|v| is_env_name(v.name().value()))
+        .filter_map(|v| {
+            let value = v.get(0)?.as_string()?;
+            Some((v.name().value().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
 /// Parse a node id from its Crockford base32 string form.
 fn node_id(v: &KdlValue) -> Option<NodeId> {
     v.as_string()?.parse().ok()
@@ -910,6 +1049,9 @@ fn parse_snap(n: &KdlNode) -> Option<NodeSnap> {
                 options,
                 args,
                 token: text("token"),
+                restart: ch.get("restart").map(restart).unwrap_or_default(),
+                limits: ch.get("limits").map(limits).unwrap_or_default(),
+                env: ch.get("env").map(env).unwrap_or_default(),
             }
         }
         // `virtualfile`/`hostfile` are the legacy names, still accepted on read.
@@ -1061,6 +1203,9 @@ fn snap_kdl(s: &NodeSnap) -> KdlNode {
         options,
         args,
         token,
+        restart,
+        limits,
+        env,
         ..
     } = &s.kind
     {
@@ -1083,6 +1228,46 @@ fn snap_kdl(s: &NodeSnap) -> KdlNode {
             t.push(str_entry(tok));
             ch.nodes_mut().push(t);
         }
+        if restart.policy != RestartPolicy::No {
+            let mut r = KdlNode::new("restart");
+            r.push(str_entry(restart.policy.as_str()));
+            if restart.max_retries > 0 {
+                r.push(KdlEntry::new_prop(
+                    "max-retries",
+                    restart.max_retries as i128,
+                ));
+            }
+            if restart.backoff_ms != Restart::DEFAULT_BACKOFF_MS {
+                r.push(KdlEntry::new_prop("backoff-ms", restart.backoff_ms as i128));
+            }
+            ch.nodes_mut().push(r);
+        }
+        if !limits.is_empty() {
+            let mut l = KdlNode::new("limits");
+            let entries = [
+                ("memory", limits.memory),
+                ("cpu", limits.cpu.map(u64::from)),
+                ("fs-bytes", limits.fs_bytes),
+                ("fs-nodes", limits.fs_nodes),
+            ];
+            for (key, v) in entries {
+                if let Some(v) = v {
+                    l.push(KdlEntry::new_prop(key, v as i128));
+                }
+            }
+            ch.nodes_mut().push(l);
+        }
+        if !env.is_empty() {
+            let mut vars = KdlDocument::new();
+            for (k, v) in env.iter().filter(|(k, _)| is_env_name(k)) {
+                let mut var = KdlNode::new(quoted_name(k));
+                var.push(str_entry(v));
+                vars.nodes_mut().push(var);
+            }
+            let mut e = KdlNode::new("env");
+            e.set_children(vars);
+            ch.nodes_mut().push(e);
+        }
     }
     node.set_children(ch);
     node
@@ -1144,7 +1329,7 @@ pub fn add(target: String, path: &Path) -> Result<(), String> {
             .unwrap_or("image")
             .to_string(),
     };
-    source.ensure()?;
+    source.ensure(None)?;
     if doc.dependencies.iter().any(|d| d.name == name) {
         println!("dependency already present: {name}");
         return Ok(());
@@ -1154,8 +1339,10 @@ pub fn add(target: String, path: &Path) -> Result<(), String> {
         source,
         args: Vec::new(),
         description: None,
+        trusted_key: None,
     });
     doc.save(path)?;
+    crate::prune::remember(path);
     println!("added dependency: {name}");
     Ok(())
 }
@@ -1201,6 +1388,7 @@ fn refs_to_pull(doc: &Document, target: Option<&str>) -> Result<Vec<String>, Str
 pub fn pull(target: Option<String>, path: &Path) -> Result<(), String> {
     let doc = Document::load_resolved(path).unwrap_or_else(|_| Document::empty());
     let refs = refs_to_pull(&doc, target.as_deref())?;
+    crate::prune::remember(path);
     if refs.is_empty() {
         println!("(no oci:// dependencies to pull)");
         return Ok(());
@@ -1212,6 +1400,10 @@ pub fn pull(target: Option<String>, path: &Path) -> Result<(), String> {
         let after = crate::oci::cached_artifact(&reference);
         match (before, after) {
             (Some(a), Some(b)) if a == b => println!("{reference}: up to date"),
+            (Some(_), Some(_)) if crate::lock::path_for(path).is_file() => println!(
+                "{reference}: updated (still pinned by {}; `wk lock --update` moves it)",
+                crate::lock::path_for(path).display()
+            ),
             (Some(_), Some(_)) => println!("{reference}: updated"),
             _ => println!("{reference}: pulled"),
         }
@@ -1221,16 +1413,39 @@ pub fn pull(target: Option<String>, path: &Path) -> Result<(), String> {
 
 /// Publish a local plugin to an OCI registry as a Wasm OCI Artifact. `plugin` is
 /// a dependency name (resolved to its local wasm) or a `.wasm` path; `reference`
-/// is the target, e.g. `localhost:5000/triangle:1.0`.
-pub fn publish(plugin: String, reference: String, path: &Path) -> Result<(), String> {
+/// is the target, e.g. `localhost:5000/triangle:1.0`. With `sign`, a keyfile
+/// (created if missing), the component's digest is signed and the signature
+/// attached to the artifact (see `crate::signing`).
+pub fn publish(
+    plugin: String,
+    reference: String,
+    path: &Path,
+    sign: Option<&Path>,
+) -> Result<(), String> {
     let wasm = Document::load_resolved(path)
         .ok()
         .and_then(|d| d.dependencies.into_iter().find(|d| d.name == plugin))
         .map(|d| d.local_path())
+        .transpose()?
         .unwrap_or_else(|| PathBuf::from(&plugin));
-    let bytes = std::fs::read(&wasm).map_err(|e| format!("reading {}: {e}", wasm.display()))?;
+    let mut bytes = std::fs::read(&wasm).map_err(|e| format!("reading {}: {e}", wasm.display()))?;
+    let Some(keyfile) = sign else {
+        crate::oci::push(&reference, &bytes)?;
+        println!("published {} -> oci://{reference}", wasm.display());
+        return Ok(());
+    };
+    let key = crate::signing::load_or_create(keyfile)?;
+    // Sign what a pull will store: a core module is componentized here, not
+    // there, so the signed digest is the pulled component's.
+    bytes = crate::oci::ensure_component(&bytes)?;
+    let digest = crate::images::oci_digest(&crate::oci::digest(&bytes));
     crate::oci::push(&reference, &bytes)?;
-    println!("published {} -> oci://{reference}", wasm.display());
+    crate::oci::push_signature(&reference, &crate::signing::sign(&key, &digest))?;
+    println!(
+        "published {} -> oci://{reference}, signed by {}",
+        wasm.display(),
+        crate::signing::key_string(&key.verifying_key())
+    );
     Ok(())
 }
 
@@ -1282,6 +1497,7 @@ mod tests {
             source,
             args: Vec::new(),
             description: None,
+            trusted_key: None,
         };
         doc.dependencies
             .push(dep("foo", Source::Oci("ghcr.io/org/foo:1.0".to_string())));
@@ -1616,12 +1832,14 @@ mod tests {
                     source: Source::Path("plugins/triangle.wasm".into()),
                     args: Vec::new(),
                     description: Some("spinning demo triangle".into()),
+                    trusted_key: None,
                 },
                 Dependency {
                     name: "fetch".into(),
                     source: Source::Oci("ghcr.io/o/fetch:1".into()),
                     args: vec!["example.com".into(), "80".into()],
                     description: None,
+                    trusted_key: Some(format!("ed25519:{}", "ab".repeat(32))),
                 },
             ],
             workspaces: vec![
@@ -1638,6 +1856,16 @@ mod tests {
                                 options: vec![8.0, 0.6, 0.0, 1.0],
                                 args: vec!["netserve".into(), "80".into()],
                                 token: Some("c0ffee".into()),
+                                restart: "on-failure:5".parse().unwrap(),
+                                limits: Limits {
+                                    memory: Some(64 << 20),
+                                    cpu: Some(50),
+                                    ..Limits::default()
+                                },
+                                env: vec![
+                                    ("APP_ENV".into(), "staging".into()),
+                                    ("GREETING".into(), "hi \"there\"".into()),
+                                ],
                             },
                         },
                         NodeSnap {
@@ -1878,13 +2106,17 @@ mod tests {
             source(),
             prop::collection::vec(value_str(), 0..3),
             prop::option::of(value_str()),
+            prop::option::of("ed25519:[0-9a-f]{64}"),
         )
-            .prop_map(|(name, source, args, description)| Dependency {
-                name,
-                source,
-                args,
-                description,
-            })
+            .prop_map(
+                |(name, source, args, description, trusted_key)| Dependency {
+                    name,
+                    source,
+                    args,
+                    description,
+                    trusted_key,
+                },
+            )
     }
 
     fn uplink_fields() -> impl Strategy<Value = (Option<String>, Option<String>)> {
@@ -1897,6 +2129,47 @@ mod tests {
         )
     }
 
+    /// A restart policy as the file can hold it: `no` carries nothing else.
+    fn restart() -> impl Strategy<Value = Restart> {
+        prop_oneof![
+            Just(Restart::default()),
+            (any::<bool>(), any::<u32>(), any::<u32>()).prop_map(
+                |(always, max_retries, backoff)| {
+                    Restart {
+                        policy: if always {
+                            RestartPolicy::Always
+                        } else {
+                            RestartPolicy::OnFailure
+                        },
+                        max_retries,
+                        backoff_ms: backoff as u64,
+                    }
+                }
+            ),
+        ]
+    }
+
+    /// Limits as the file can hold them: a zero is no limit.
+    fn limits() -> impl Strategy<Value = Limits> {
+        let some = |v: u64| (v > 0).then_some(v);
+        (any::<u64>(), any::<u32>(), any::<u64>(), any::<u64>()).prop_map(
+            move |(memory, cpu, fs_bytes, fs_nodes)| Limits {
+                memory: some(memory),
+                cpu: some(cpu.into()).map(|v| v as u32),
+                fs_bytes: some(fs_bytes),
+                fs_nodes: some(fs_nodes),
+            },
+        )
+    }
+
+    /// A node's own environment as the file can hold it: shell names, ones
+    /// KDL reserves or can't write bare, and any string values.
+    fn env() -> impl Strategy<Value = Vec<(String, String)>> {
+        let name = "[A-Za-z_][A-Za-z0-9_]{0,8}|[0-9A-Za-z _.\\-\"]{1,8}|true|false|null|inf|nan"
+            .prop_filter("a name", |n: &String| is_env_name(n));
+        prop::collection::vec((name, value_str()), 0..3)
+    }
+
     fn snap_kind() -> impl Strategy<Value = SnapKind> {
         prop_oneof![
             (
@@ -1906,12 +2179,20 @@ mod tests {
                 prop::option::of(
                     prop::collection::vec(any::<u8>(), 1..48).prop_map(|b| bytes_hex(&b))
                 ),
+                restart(),
+                limits(),
+                env(),
             )
-                .prop_map(|(name, options, args, token)| SnapKind::App {
-                    name,
-                    options,
-                    args,
-                    token
+                .prop_map(|(name, options, args, token, restart, limits, env)| {
+                    SnapKind::App {
+                        name,
+                        options,
+                        args,
+                        token,
+                        restart,
+                        limits,
+                        env,
+                    }
                 }),
             (value_str(), any::<bool>())
                 .prop_map(|(name, persist)| SnapKind::Volume { name, persist }),
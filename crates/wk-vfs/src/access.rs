@@ -0,0 +1,680 @@
+//! A node's filesystem from *outside* the guest: what an API client reaches
+//! through `wk fs` and `wk cp`. Every operation takes a path in the node's
+//! tree and crosses provider mounts like the guest's own calls do, so a
+//! client sees exactly what the node sees — image layers, canvas mounts and
+//! served subtrees alike.
+//!
+//! The rules are the guest's too: a read-only mount refuses writes, and a
+//! provider call may block up to its timeout, so these run on a connection's
+//! thread, never a render loop. Errors are one line each, naming the path.
+//!
+//! [`archive`] and [`extract`] move whole trees as tar (what `docker cp`
+//! streams), built on the same operations.
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use super::{
+    components, list_dir_forwarded, resolve, resolve_place, DirEntry, FsEntryKind, FsError, FsOp,
+    FsReplyData, Node, PathKind, ProviderConn, Resolved, SharedFs, FILE_READ_CHUNK, MAX_FILE_SIZE,
+    ROOT,
+};
+
+/// The entries directly under directory `path`, directories first.
+pub fn list(fs: &SharedFs, path: &str) -> Result<Vec<DirEntry>, String> {
+    list_dir_forwarded(fs, path).ok_or_else(|| format!("{path}: not a directory"))
+}
+
+/// What `path` is: its entry as its parent's listing would show it (the root
+/// is named `/`).
+pub fn stat(fs: &SharedFs, path: &str) -> Result<DirEntry, String> {
+    let name = components(path).last().copied().unwrap_or("/").to_string();
+    let remote = {
+        let g = fs.lock().unwrap();
+        match resolve_place(&g, ROOT, path, true) {
+            Resolved::Local(id) => return Ok(g.entry(&name, id)),
+            Resolved::Remote { conn, path, .. } => (conn, path),
+            Resolved::Missing => return Err(format!("{path}: {}", describe(FsError::NoEntry))),
+        }
+    };
+    let (conn, rpath) = remote;
+    match conn.call(FsOp::Getattr { path: rpath }) {
+        Ok(FsReplyData::Attr(st)) => {
+            let is_dir = st.kind == FsEntryKind::Dir;
+            Ok(DirEntry {
+                name,
+                is_dir,
+                size: if is_dir { 0 } else { st.size as usize },
+                origin: if is_dir {
+                    PathKind::Dir
+                } else {
+                    PathKind::Mounted
+                },
+            })
+        }
+        Ok(_) => Err(format!("{path}: {}", describe(FsError::Io))),
+        Err(e) => Err(format!("{path}: {}", describe(e))),
+    }
+}
+
+/// The whole content of the file at `path`, read to its end. A file over the
+/// filesystem's size cap, or a read that fails part way, is an error rather
+/// than a short result.
+pub fn read_file(fs: &SharedFs, path: &str) -> Result<Vec<u8>, String> {
+    let g = fs.lock().unwrap();
+    let (conn, rpath) = match resolve_place(&g, ROOT, path, true) {
+        Resolved::Remote { conn, path, .. } => (conn, path),
+        Resolved::Local(id) => {
+            return match g.nodes.get(&id) {
+                Some(Node::Host(p)) => {
+                    let p = p.clone();
+                    drop(g);
+                    read_host(&p).map_err(|e| format!("{path}: {e}"))
+                }
+                Some(Node::Dir(_) | Node::Provider(_)) => {
+                    Err(format!("{path}: {}", describe(FsError::IsDir)))
+                }
+                _ => g
+                    .read_file(path, MAX_FILE_SIZE)
+                    .ok_or_else(|| format!("{path}: {}", describe(FsError::NoEntry))),
+            }
+        }
+        Resolved::Missing => return Err(format!("{path}: {}", describe(FsError::NoEntry))),
+    };
+    drop(g);
+    read_remote(&conn, &rpath).map_err(|e| format!("{path}: {e}"))
+}
+
+/// Replace the content of the file at `path` with `data`, creating it if its
+/// directory exists. An existing entry keeps its kind: a volume mount's
+/// shared bytes change for every node it's wired to, a host-mapped file is
+/// written on disk, and a layer file is copied up. A new file or a private
+/// one's growth is held to the node's [`Quota`](crate::Quota), as its guest's
+/// own writes are.
+pub fn write_file(fs: &SharedFs, path: &str, data: &[u8]) -> Result<(), String> {
+    if data.len() > MAX_FILE_SIZE {
+        return Err(format!("{path}: {}", describe(FsError::TooLarge)));
+    }
+    let mut g = fs.lock().unwrap();
+    let id = match resolve_place(&g, ROOT, path, true) {
+        Resolved::Remote {
+            conn,
+            path: rpath,
+            readonly,
+        } => {
+            drop(g);
+            if readonly {
+                return Err(format!("{path}: read-only mount"));
+            }
+            return write_remote(&conn, &rpath, data).map_err(|e| format!("{path}: {e}"));
+        }
+        Resolved::Local(id) => id,
+        Resolved::Missing => {
+            let comps = components(path);
+            let Some((name, dirs)) = comps.split_last() else {
+                return Err(format!("{path}: {}", describe(FsError::IsDir)));
+            };
+            let parent = resolve(&g, ROOT, &dirs.join("/"))
+                .filter(|p| matches!(g.nodes.get(p), Some(Node::Dir(_))))
+                .ok_or_else(|| format!("{path}: no such directory"))?;
+            if g.refuse_create() {
+                return Err(format!("{path}: the filesystem is full"));
+            }
+            // Checked before it's linked, so a refusal leaves nothing behind.
+            let id = g.alloc(Node::File(Vec::new()));
+            if !g.fits(id, data.len() as u64) {
+                g.nodes.remove(&id);
+                return Err(format!("{path}: the filesystem is full"));
+            }
+            g.nodes.insert(id, Node::File(data.to_vec()));
+            if let Some(Node::Dir(children)) = g.nodes.get_mut(&parent) {
+                children.insert((*name).to_string(), id);
+            }
+            return Ok(());
+        }
+    };
+    if g.readonly.contains(&id) {
+        return Err(format!("{path}: read-only mount"));
+    }
+    // Copied up: the layer's bytes stay shared and untouched, and come back
+    // if the copy doesn't fit.
+    let layer = match g.nodes.get_mut(&id) {
+        Some(node @ Node::RoFile(_)) => Some(std::mem::replace(node, Node::File(Vec::new()))),
+        _ => None,
+    };
+    if !g.fits(id, data.len() as u64) {
+        if let Some(node) = layer {
+            g.nodes.insert(id, node);
+        }
+        return Err(format!("{path}: the filesystem is full"));
+    }
+    match g.nodes.get_mut(&id) {
+        Some(Node::File(bytes)) => *bytes = data.to_vec(),
+        Some(Node::Shared(shared)) => *shared.lock().unwrap() = data.to_vec(),
+        Some(Node::Host(p)) => std::fs::write(p, data).map_err(|e| format!("{path}: {e}"))?,
+        Some(Node::Null | Node::Zero | Node::Random) => {}
+        Some(Node::Dir(_) | Node::Provider(_)) => {
+            return Err(format!("{path}: {}", describe(FsError::IsDir)))
+        }
+        // `resolve_place` follows links, so one reached here dangles; a
+        // layer file was copied up above.
+        Some(Node::Symlink(_) | Node::RoFile(_)) | None => {
+            return Err(format!("{path}: {}", describe(FsError::NoEntry)))
+        }
+    }
+    Ok(())
+}
+
+/// Create the directory at `path` and any missing parents (`mkdir -p`). An
+/// existing directory is fine.
+pub fn mkdir(fs: &SharedFs, path: &str) -> Result<(), String> {
+    let mut g = fs.lock().unwrap();
+    match resolve_place(&g, ROOT, path, true) {
+        Resolved::Local(id) => match g.nodes.get(&id) {
+            Some(Node::Dir(_) | Node::Provider(_)) => Ok(()),
+            _ => Err(format!("{path}: {}", describe(FsError::Exist))),
+        },
+        // One level at a time, each a create held to the node's quota.
+        Resolved::Missing => {
+            let comps = components(path);
+            for depth in 1..=comps.len() {
+                let dir = comps[..depth].join("/");
+                if resolve(&g, ROOT, &dir).is_some() {
+                    continue;
+                }
+                if g.refuse_create() {
+                    return Err(format!("{path}: the filesystem is full"));
+                }
+                g.ensure_dir_path(&dir)
+                    .ok_or_else(|| format!("{path}: can't create a directory there"))?;
+            }
+            Ok(())
+        }
+        Resolved::Remote {
+            conn,
+            path: rpath,
+            readonly,
+        } => {
+            drop(g);
+            if readonly {
+                return Err(format!("{path}: read-only mount"));
+            }
+            // The provider makes one level at a time.
+            let comps = components(&rpath);
+            for depth in 1..=comps.len() {
+                let dir = comps[..depth].join("/");
+                match conn.call(FsOp::Getattr { path: dir.clone() }) {
+                    Ok(FsReplyData::Attr(st)) if st.kind == FsEntryKind::Dir => continue,
+                    Ok(_) => return Err(format!("{path}: {}", describe(FsError::Exist))),
+                    Err(FsError::NoEntry) => {}
+                    Err(e) => return Err(format!("{path}: {}", describe(e))),
+                }
+                conn.call(FsOp::Mkdir { path: dir })
+                    .map_err(|e| format!("{path}: {}", describe(e)))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Remove the entry at `path`, and everything under it if it's a directory
+/// (`rm -r`). Removing a mount detaches it; the root and a provider's mount
+/// point can't be removed.
+pub fn remove(fs: &SharedFs, path: &str) -> Result<(), String> {
+    let mut g = fs.lock().unwrap();
+    // The final component is the entry itself, not what a link points at.
+    match resolve_place(&g, ROOT, path, false) {
+        Resolved::Remote {
+            conn,
+            path: rpath,
+            readonly,
+        } => {
+            drop(g);
+            if rpath.is_empty() {
+                return Err(format!("{path}: a mount point can't be removed"));
+            }
+            if readonly {
+                return Err(format!("{path}: read-only mount"));
+            }
+            remove_remote(&conn, &rpath).map_err(|e| format!("{path}: {e}"))
+        }
+        Resolved::Missing => Err(format!("{path}: {}", describe(FsError::NoEntry))),
+        Resolved::Local(ROOT) => Err(format!("{path}: the root can't be removed")),
+        Resolved::Local(id) => {
+            if g.readonly.contains(&id) {
+                return Err(format!("{path}: read-only mount"));
+            }
+            let comps = components(path);
+            let (name, dirs) = comps.split_last().expect("not the root");
+            let parent = resolve(&g, ROOT, &dirs.join("/")).expect("the entry's parent resolves");
+            g.remove_path_in(parent, name);
+            Ok(())
+        }
+    }
+}
+
+/// Write `data` as the whole of a provider file: open (creating, truncating),
+/// write in chunks, release.
+fn write_remote(conn: &Arc<ProviderConn>, path: &str, data: &[u8]) -> Result<(), String> {
+    let opened = match conn.call(FsOp::Open {
+        path: path.to_string(),
+        create: true,
+        truncate: true,
+        exclusive: false,
+    }) {
+        Ok(FsReplyData::Opened(o)) => o,
+        Ok(_) => return Err(describe(FsError::Io)),
+        Err(e) => return Err(describe(e)),
+    };
+    let handle = opened.handle;
+    let result = if opened.kind == FsEntryKind::Dir {
+        Err(describe(FsError::IsDir))
+    } else {
+        data.chunks(FILE_READ_CHUNK)
+            .enumerate()
+            .try_for_each(|(i, chunk)| {
+                conn.call(FsOp::Write {
+                    handle,
+                    offset: (i * FILE_READ_CHUNK) as u64,
+                    data: chunk.to_vec(),
+                })
+                .map(|_| ())
+                .map_err(describe)
+            })
+    };
+    conn.cast(FsOp::Release { handle });
+    result
+}
+
+/// The whole of a host-mapped file, up to the size cap.
+fn read_host(path: &std::path::Path) -> Result<Vec<u8>, String> {
+    let f = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut data = Vec::new();
+    f.take(MAX_FILE_SIZE as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| e.to_string())?;
+    if data.len() > MAX_FILE_SIZE {
+        return Err(describe(FsError::TooLarge));
+    }
+    Ok(data)
+}
+
+/// The whole of a provider file: open, read in chunks to its end, release.
+fn read_remote(conn: &Arc<ProviderConn>, path: &str) -> Result<Vec<u8>, String> {
+    let opened = match conn.call(FsOp::Open {
+        path: path.to_string(),
+        create: false,
+        truncate: false,
+        exclusive: false,
+    }) {
+        Ok(FsReplyData::Opened(o)) => o,
+        Ok(_) => return Err(describe(FsError::Io)),
+        Err(e) => return Err(describe(e)),
+    };
+    let handle = opened.handle;
+    let mut data = Vec::new();
+    let result = if opened.kind == FsEntryKind::Dir {
+        Err(describe(FsError::IsDir))
+    } else {
+        loop {
+            match conn.call(FsOp::Read {
+                handle,
+                offset: data.len() as u64,
+                len: FILE_READ_CHUNK as u32,
+            }) {
+                Ok(FsReplyData::Data { bytes, eof }) => {
+                    data.extend_from_slice(&bytes);
+                    if data.len() > MAX_FILE_SIZE {
+                        break Err(describe(FsError::TooLarge));
+                    }
+                    if bytes.is_empty() || eof {
+                        break Ok(());
+                    }
+                }
+                Ok(_) => break Err(describe(FsError::Io)),
+                Err(e) => break Err(describe(e)),
+            }
+        }
+    };
+    conn.cast(FsOp::Release { handle });
+    result.map(|()| data)
+}
+
+/// Remove a provider entry, emptying a directory first.
+fn remove_remote(conn: &Arc<ProviderConn>, path: &str) -> Result<(), String> {
+    let removed = match conn.call(FsOp::Getattr {
+        path: path.to_string(),
+    }) {
+        Ok(FsReplyData::Attr(st)) if st.kind == FsEntryKind::Dir => {
+            let entries = match conn.call(FsOp::Readdir {
+                path: path.to_string(),
+            }) {
+                Ok(FsReplyData::Entries(list)) => list,
+                Ok(_) => return Err(describe(FsError::Io)),
+                Err(e) => return Err(describe(e)),
+            };
+            for e in entries {
+                remove_remote(conn, &format!("{path}/{}", e.name))?;
+            }
+            conn.call(FsOp::Rmdir {
+                path: path.to_string(),
+            })
+        }
+        Ok(_) => conn.call(FsOp::Unlink {
+            path: path.to_string(),
+        }),
+        Err(e) => Err(e),
+    };
+    removed.map(|_| ()).map_err(describe)
+}
+
+/// Write the tree at `path` to `out` as a tar archive, entries named from its
+/// last component (`/etc` archives as `etc/...`; the root as its children),
+/// and hand `out` back. Only one file's bytes are held at a time.
+pub fn archive<W: Write>(fs: &SharedFs, path: &str, out: W) -> Result<W, String> {
+    let top = stat(fs, path)?;
+    let mut tar = tar::Builder::new(out);
+    let name = if components(path).is_empty() {
+        String::new()
+    } else {
+        top.name.clone()
+    };
+    append(fs, &mut tar, path, &name, top.is_dir)?;
+    tar.into_inner().map_err(|e| e.to_string())
+}
+
+/// Add `path` (a directory if `is_dir`) to `tar` as `name`, recursively. A
+/// link goes in as a link, not what it points at.
+fn append<W: Write>(
+    fs: &SharedFs,
+    tar: &mut tar::Builder<W>,
+    path: &str,
+    name: &str,
+    is_dir: bool,
+) -> Result<(), String> {
+    let mut header = tar::Header::new_gnu();
+    let link = fs.lock().unwrap().read_symlink(path);
+    if let Some(target) = link {
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o777);
+        return tar
+            .append_link(&mut header, name, target)
+            .map_err(|e| format!("{path}: {e}"));
+    }
+    if !is_dir {
+        let data = read_file(fs, path)?;
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        return tar
+            .append_data(&mut header, name, data.as_slice())
+            .map_err(|e| format!("{path}: {e}"));
+    }
+    if !name.is_empty() {
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mode(0o755);
+        tar.append_data(&mut header, name, std::io::empty())
+            .map_err(|e| format!("{path}: {e}"))?;
+    }
+    for entry in list(fs, path)? {
+        let child = format!("{}/{}", path.trim_end_matches('/'), entry.name);
+        let child_name = if name.is_empty() {
+            entry.name.clone()
+        } else {
+            format!("{name}/{}", entry.name)
+        };
+        append(fs, tar, &child, &child_name, entry.is_dir)?;
+    }
+    Ok(())
+}
+
+/// Unpack the tar archive read from `data` into the directory `dir`, creating
+/// it and any directories the archive names; files overwrite what's there as
+/// [`write_file`] does. A symlink is made only outside provider mounts
+/// (providers serve plain trees). The archive is read an entry at a time.
+pub fn extract(fs: &SharedFs, dir: &str, data: impl Read) -> Result<(), String> {
+    mkdir(fs, dir)?;
+    let mut archive = tar::Archive::new(data);
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let name = entry
+            .path()
+            .map_err(|e| e.to_string())?
+            .to_string_lossy()
+            .into_owned();
+        // Nothing escapes `dir`: no absolute paths, no `..`.
+        if name.split('/').any(|c| c == "..") {
+            return Err(format!("{name}: the archive reaches outside the target"));
+        }
+        let comps = components(&name);
+        if comps.is_empty() {
+            continue;
+        }
+        let path = format!("{}/{}", dir.trim_end_matches('/'), comps.join("/"));
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => mkdir(fs, &path)?,
+            tar::EntryType::Regular | tar::EntryType::Continuous => {
+                if entry.size() > MAX_FILE_SIZE as u64 {
+                    return Err(format!("{path}: {}", describe(FsError::TooLarge)));
+                }
+                let mut bytes = Vec::new();
+                entry
+                    .read_to_end(&mut bytes)
+                    .map_err(|e| format!("{name}: {e}"))?;
+                write_file(fs, &path, &bytes)?;
+            }
+            tar::EntryType::Symlink => {
+                let target = entry
+                    .link_name()
+                    .map_err(|e| e.to_string())?
+                    .ok_or_else(|| format!("{name}: a link without a target"))?;
+                let mut g = fs.lock().unwrap();
+                if matches!(
+                    resolve_place(&g, ROOT, &path, false),
+                    Resolved::Remote { .. }
+                ) {
+                    return Err(format!("{path}: a provider mount can't hold a link"));
+                }
+                if g.refuse_create() {
+                    return Err(format!("{path}: the filesystem is full"));
+                }
+                g.put_symlink_at(&path, target.to_string_lossy().into_owned());
+            }
+            // Hard links, devices and the like have no place in a node's tree.
+            _ => return Err(format!("{name}: unsupported archive entry")),
+        }
+    }
+    Ok(())
+}
+
+/// A provider error as the one-line reason a client sees.
+fn describe(e: FsError) -> String {
+    match e {
+        FsError::NoEntry => "no such file or directory",
+        FsError::NotDir => "not a directory",
+        FsError::IsDir => "is a directory",
+        FsError::Exist => "already exists",
+        FsError::NotPermitted => "not permitted",
+        FsError::Io => "I/O error",
+        FsError::TooLarge => "too large",
+        FsError::Unsupported => "not supported",
+        FsError::Dead => "the serving node isn't running",
+        FsError::Timeout => "the serving node didn't answer",
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::tests::spawn_memfs_provider;
+    use crate::{mount_file, mount_provider, new_fs, Quota, QuotaHit};
+
+    /// Every operation on a node's own tree: write/read/stat/list, mkdir -p,
+    /// rm -r, and a read-only mount refusing writes.
+    #[test]
+    fn local_paths_read_write_and_remove() {
+        let fs = new_fs();
+        mkdir(&fs, "/data/logs").unwrap();
+        write_file(&fs, "/data/logs/a.txt", b"one").unwrap();
+        write_file(&fs, "/data/logs/a.txt", b"two").unwrap();
+        assert_eq!(read_file(&fs, "/data/logs/a.txt").unwrap(), b"two");
+        let st = stat(&fs, "/data/logs/a.txt").unwrap();
+        assert_eq!((st.name.as_str(), st.is_dir, st.size), ("a.txt", false, 3));
+        assert!(stat(&fs, "/").unwrap().is_dir);
+        assert_eq!(list(&fs, "/data").unwrap()[0].name, "logs");
+        assert!(write_file(&fs, "/nowhere/b.txt", b"x")
+            .unwrap_err()
+            .contains("no such directory"));
+
+        let shared = Arc::new(std::sync::Mutex::new(b"ro".to_vec()));
+        mount_file(&fs, "/etc/conf", shared.clone(), false);
+        assert!(write_file(&fs, "/etc/conf", b"rw")
+            .unwrap_err()
+            .contains("read-only"));
+        assert!(remove(&fs, "/etc/conf").is_err());
+        assert_eq!(*shared.lock().unwrap(), b"ro");
+
+        remove(&fs, "/data").unwrap();
+        assert!(stat(&fs, "/data/logs/a.txt").is_err());
+        assert!(remove(&fs, "/").is_err());
+    }
+
+    /// Writes from outside are held to the node's quota like its guest's own:
+    /// a refused file is never created, and a refused copy-up leaves the
+    /// layer file as it was.
+    #[test]
+    fn writes_are_held_to_the_quota() {
+        let fs = new_fs();
+        let layer = Arc::new(crate::layers::LayerBytes::eager(Arc::new(
+            b"layer".to_vec(),
+        )));
+        fs.lock().unwrap().put_ro_file_at("/base.txt", layer);
+        fs.lock().unwrap().set_quota(Quota {
+            nodes: None,
+            bytes: Some(8),
+        });
+
+        write_file(&fs, "/a.txt", b"12345").unwrap();
+        assert!(write_file(&fs, "/b.txt", b"12345")
+            .unwrap_err()
+            .contains("full"));
+        assert!(stat(&fs, "/b.txt").is_err());
+        assert_eq!(fs.lock().unwrap().quota_hit(), Some(QuotaHit::Bytes));
+        assert!(write_file(&fs, "/base.txt", b"1234").is_err());
+        assert_eq!(read_file(&fs, "/base.txt").unwrap(), b"layer");
+        write_file(&fs, "/a.txt", b"12345678").unwrap();
+
+        let nodes = fs.lock().unwrap().nodes.len();
+        fs.lock().unwrap().set_quota(Quota {
+            nodes: Some(nodes + 1),
+            bytes: None,
+        });
+        assert!(mkdir(&fs, "/x/y").unwrap_err().contains("full"));
+        assert_eq!(fs.lock().unwrap().quota_hit(), Some(QuotaHit::Nodes));
+        assert!(stat(&fs, "/x").unwrap().is_dir);
+    }
+
+    /// A tree archived from one filesystem unpacks into another, links
+    /// included.
+    #[test]
+    fn archives_round_trip_a_tree() {
+        let from = new_fs();
+        write_file(&from, "/a.txt", b"top").unwrap();
+        mkdir(&from, "/src/sub").unwrap();
+        write_file(&from, "/src/sub/b.txt", b"deep").unwrap();
+        from.lock()
+            .unwrap()
+            .put_symlink_at("/src/latest", "sub".into());
+        let tar = archive(&from, "/src", Vec::new()).unwrap();
+
+        let to = new_fs();
+        extract(&to, "/copy", tar.as_slice()).unwrap();
+        assert_eq!(read_file(&to, "/copy/src/sub/b.txt").unwrap(), b"deep");
+        assert_eq!(
+            to.lock()
+                .unwrap()
+                .read_symlink("/copy/src/latest")
+                .as_deref(),
+            Some("sub"),
+            "a link to a directory goes across as the link"
+        );
+        assert!(stat(&to, "/copy/a.txt").is_err(), "only the named tree");
+
+        let mut b = tar::Builder::new(Vec::new());
+        let mut h = tar::Header::new_gnu();
+        h.set_entry_type(tar::EntryType::Symlink);
+        h.set_size(0);
+        b.append_link(&mut h, "ls", "coreutils").unwrap();
+        // The tar crate won't write a `..` path, so spell it by hand.
+        let mut h = tar::Header::new_old();
+        h.as_old_mut().name[..9].copy_from_slice(b"../escape");
+        h.set_entry_type(tar::EntryType::Regular);
+        h.set_size(1);
+        h.set_cksum();
+        b.append(&h, &b"x"[..]).unwrap();
+        let err = extract(&to, "/bin", b.into_inner().unwrap().as_slice()).unwrap_err();
+        assert!(err.contains("outside"), "{err}");
+        assert_eq!(
+            to.lock().unwrap().read_symlink("/bin/ls").as_deref(),
+            Some("coreutils")
+        );
+    }
+
+    /// The same operations reach through a provider mount to the serving
+    /// node.
+    #[test]
+    fn provider_mounts_are_reached_through() {
+        let conn = ProviderConn::new();
+        let (t, stop) = spawn_memfs_provider(conn.clone(), &[("hello.txt", b"served")]);
+        let fs = new_fs();
+        mount_provider(&fs, "/mnt/p", conn.clone(), true);
+
+        assert_eq!(stat(&fs, "/mnt/p/hello.txt").unwrap().size, 6);
+        write_file(&fs, "/mnt/p/hello.txt", b"rewritten").unwrap();
+        assert_eq!(read_file(&fs, "/mnt/p/hello.txt").unwrap(), b"rewritten");
+        mkdir(&fs, "/mnt/p/new/dir").unwrap();
+        assert!(stat(&fs, "/mnt/p/new/dir").unwrap().is_dir);
+        remove(&fs, "/mnt/p/hello.txt").unwrap();
+        assert!(stat(&fs, "/mnt/p/hello.txt").is_err());
+        assert!(remove(&fs, "/mnt/p").is_err(), "the mount point stays");
+
+        stop.store(true, Ordering::Relaxed);
+        t.join().unwrap();
+    }
+
+    /// A read that fails part way — the serving node dies after the first
+    /// chunk — is an error, not the bytes read so far.
+    #[test]
+    fn a_read_cut_short_is_an_error() {
+        let conn = ProviderConn::new();
+        conn.begin_serving();
+        let serving = conn.clone();
+        let t = std::thread::spawn(move || {
+            let wait = std::time::Duration::from_secs(5);
+            let (id, _open) = serving.next_request(wait).unwrap();
+            let opened = crate::FsOpened {
+                handle: 1,
+                kind: FsEntryKind::File,
+                size: 2 * FILE_READ_CHUNK as u64,
+            };
+            serving.reply(id, Ok(FsReplyData::Opened(opened)));
+            let (id, _read) = serving.next_request(wait).unwrap();
+            let first = FsReplyData::Data {
+                bytes: vec![b'x'; FILE_READ_CHUNK],
+                eof: false,
+            };
+            serving.reply(id, Ok(first));
+            serving.end_serving();
+        });
+        let fs = new_fs();
+        mount_provider(&fs, "/mnt/p", conn, true);
+        let err = read_file(&fs, "/mnt/p/big.bin").unwrap_err();
+        assert!(err.contains("isn't running"), "{err}");
+        t.join().unwrap();
+    }
+}
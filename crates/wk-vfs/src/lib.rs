@@ -12,6 +12,7 @@
 //! bytes once. The host embeds the filesystem by implementing [`VfsView`] and
 //! calling [`add_to_linker`].
 
+pub mod access;
 pub mod layers;
 pub mod p3;
 pub mod provider;
@@ -20,6 +21,7 @@ pub use provider::{
     FsDirent, FsEntryKind, FsError, FsOp, FsOpened, FsReplyData, FsStat, ProviderConn,
 };
 
+use std::cell::Cell;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
@@ -100,7 +102,8 @@ pub enum PathKind {
     LayerFile,
     /// A privately written file: created or copied-up since the last layer.
     PrivateFile,
-    /// A canvas file mount (shared/host) — not part of any image.
+    /// A canvas mount (a shared/host file, or a provider's tree) — not part
+    /// of any image.
     Mounted,
     /// A symbolic link, and what it points at.
     ///
@@ -170,6 +173,32 @@ pub struct Fs {
     /// immediately, then keep writing and seeking the open fd. Freed only when
     /// both the link count (dir entries) and this count reach zero.
     open_fds: HashMap<u64, u32>,
+    /// This node's own limits, below the hard caps every fs has.
+    quota: Quota,
+    /// Private file bytes as last counted, plus the growth allowed since: the
+    /// cheap figure a byte quota is checked against, recounted before a write
+    /// is refused. `None` until a byte quota first needs it.
+    counted_bytes: Option<u64>,
+    /// Which quota a guest last ran into, until the quota changes.
+    quota_hit: Cell<Option<QuotaHit>>,
+}
+
+/// Limits on one [`Fs`], set per node. They bound what its guest may add — a
+/// create or a write past them fails with `insufficient-space` — and count
+/// what the fs holds however it got there: `nodes` every entry, image layers'
+/// included; `bytes` private file bytes (what [`Fs::usage`] reports), not
+/// the layer bytes shared with other nodes. `None` = only the hard caps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quota {
+    pub nodes: Option<usize>,
+    pub bytes: Option<u64>,
+}
+
+/// Which [`Quota`] a guest ran into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuotaHit {
+    Nodes,
+    Bytes,
 }
 
 impl Default for Fs {
@@ -181,10 +210,24 @@ impl Default for Fs {
             next: 1,
             readonly: HashSet::new(),
             open_fds: HashMap::new(),
+            quota: Quota::default(),
+            counted_bytes: None,
+            quota_hit: Cell::new(None),
         }
     }
 }
 
+/// The bytes held in private files — written or copied up, not shared.
+fn private_bytes(nodes: &BTreeMap<u64, Node>) -> u64 {
+    nodes
+        .values()
+        .map(|n| match n {
+            Node::File(data) => data.len() as u64,
+            _ => 0,
+        })
+        .sum()
+}
+
 /// Largest number of nodes (files + directories) one app's in-memory fs may
 /// hold. Bounds host memory: a guest can otherwise `open`/`mkdir` in a loop and
 /// allocate unbounded entries (each a file up to [`MAX_FILE_SIZE`]).
@@ -196,6 +239,62 @@ impl Fs {
         self.nodes.len() >= MAX_FS_NODES
     }
 
+    /// Whether a guest's create must be refused: the fs is at its node cap or
+    /// its node quota (noted as hit).
+    fn refuse_create(&self) -> bool {
+        if self.quota.nodes.is_some_and(|max| self.nodes.len() >= max) {
+            self.quota_hit.set(Some(QuotaHit::Nodes));
+            return true;
+        }
+        self.at_capacity()
+    }
+
+    /// Whether private file `id` may grow to `len` bytes under the byte
+    /// quota. Checked against the running count, and recounted before
+    /// refusing, so a count left high by deletes never refuses a write that
+    /// fits. Call after [`Self::copy_up`].
+    fn fits(&mut self, id: u64, len: u64) -> bool {
+        let Some(max) = self.quota.bytes else {
+            return true;
+        };
+        let current = match self.nodes.get(&id) {
+            Some(Node::File(data)) => data.len() as u64,
+            _ => return true,
+        };
+        let growth = len.saturating_sub(current);
+        if growth == 0 {
+            return true;
+        }
+        let counted = match self.counted_bytes {
+            Some(n) if n + growth <= max => n,
+            _ => private_bytes(&self.nodes),
+        };
+        if counted + growth > max {
+            self.counted_bytes = Some(counted);
+            self.quota_hit.set(Some(QuotaHit::Bytes));
+            return false;
+        }
+        self.counted_bytes = Some(counted + growth);
+        true
+    }
+
+    /// Set this fs's [`Quota`]. What it already holds stays; only what a
+    /// guest adds from now on is refused.
+    pub fn set_quota(&mut self, quota: Quota) {
+        self.quota = quota;
+        self.counted_bytes = None;
+        self.quota_hit.set(None);
+    }
+
+    pub fn quota(&self) -> Quota {
+        self.quota
+    }
+
+    /// The quota a guest last ran into, if any since it was set.
+    pub fn quota_hit(&self) -> Option<QuotaHit> {
+        self.quota_hit.get()
+    }
+
     fn alloc(&mut self, node: Node) -> u64 {
         let id = self.next;
         self.next += 1;
@@ -248,6 +347,9 @@ impl Fs {
             // copy needs the real content, and later readers of the layer
             // share the materialization anyway.
             let private = bytes.bytes().as_ref().clone();
+            if let Some(n) = &mut self.counted_bytes {
+                *n += private.len() as u64;
+            }
             self.nodes.insert(id, Node::File(private));
         }
     }
@@ -386,11 +488,14 @@ impl Fs {
                     Some(Node::Dir(_)) => PathKind::Dir,
                     Some(Node::RoFile(_)) => PathKind::LayerFile,
                     Some(Node::File(_)) => PathKind::PrivateFile,
-                    Some(Node::Shared(_) | Node::Host(_)) => PathKind::Mounted,
+                    // A provider's tree is reported but not walked: it is
+                    // served from elsewhere, and listing it here would make
+                    // whatever it covers look deleted.
+                    Some(Node::Shared(_) | Node::Host(_) | Node::Provider(_)) => PathKind::Mounted,
                     Some(Node::Symlink(target)) => PathKind::Symlink(target.clone()),
-                    // Device nodes and provider mounts are provisioned at
-                    // runtime, not build content — keep them out of layer diffs.
-                    Some(Node::Null | Node::Zero | Node::Random | Node::Provider(_)) => continue,
+                    // Device nodes are provisioned at runtime, not build
+                    // content — keep them out of layer diffs.
+                    Some(Node::Null | Node::Zero | Node::Random) => continue,
                     None => continue,
                 };
                 let is_dir = kind == PathKind::Dir;
@@ -457,6 +562,24 @@ impl Fs {
         }
     }
 
+    /// The listing entry for node `id`, named `name`.
+    fn entry(&self, name: &str, id: u64) -> DirEntry {
+        let origin = match self.nodes.get(&id) {
+            Some(Node::Dir(_)) => PathKind::Dir,
+            Some(Node::RoFile(_)) => PathKind::LayerFile,
+            Some(Node::Shared(_) | Node::Host(_) | Node::Provider(_)) => PathKind::Mounted,
+            _ => PathKind::PrivateFile,
+        };
+        let is_dir =
+            origin == PathKind::Dir || matches!(self.nodes.get(&id), Some(Node::Provider(_)));
+        DirEntry {
+            name: name.to_string(),
+            is_dir,
+            size: self.file_len(id),
+            origin,
+        }
+    }
+
     /// List the entries directly under directory `path` (root = `""` or `"/"`),
     /// directories first then files, each group sorted by name. `None` if the
     /// path doesn't resolve to a directory. Read-only; for UI inspection.
@@ -467,27 +590,19 @@ impl Fs {
         };
         let mut out: Vec<DirEntry> = children
             .iter()
-            .map(|(name, &cid)| {
-                let origin = match self.nodes.get(&cid) {
-                    Some(Node::Dir(_)) => PathKind::Dir,
-                    Some(Node::RoFile(_)) => PathKind::LayerFile,
-                    Some(Node::Shared(_) | Node::Host(_) | Node::Provider(_)) => PathKind::Mounted,
-                    _ => PathKind::PrivateFile,
-                };
-                let is_dir = origin == PathKind::Dir
-                    || matches!(self.nodes.get(&cid), Some(Node::Provider(_)));
-                DirEntry {
-                    name: name.clone(),
-                    is_dir,
-                    size: self.file_len(cid),
-                    origin,
-                }
-            })
+            .map(|(name, &cid)| self.entry(name, cid))
             .collect();
         out.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
         Some(out)
     }
 
+    /// What this fs holds, for `wk stats`: its node count (files, directories,
+    /// links and mounts alike, against the cap of 100 000) and the bytes in its
+    /// private files — the memory it costs beyond the layers it shares.
+    pub fn usage(&self) -> (usize, u64) {
+        (self.nodes.len(), private_bytes(&self.nodes))
+    }
+
     /// Place a symlink at `path` pointing at `target`, creating parents and
     /// replacing any existing entry (how a layer materialises its links).
     pub fn put_symlink_at(&mut self, path: &str, target: String) {
@@ -1138,6 +1253,9 @@ impl OutputStream for VfsOutputStream {
     fn write(&mut self, bytes: Bytes) -> std::result::Result<(), StreamError> {
         let mut fs = self.fs.lock().unwrap();
         fs.copy_up(self.node);
+        if !fs.fits(self.node, self.offset.saturating_add(bytes.len() as u64)) {
+            return Err(StreamError::Closed);
+        }
         match fs.nodes.get_mut(&self.node) {
             Some(Node::File(data)) => {
                 write_at(data, self.offset, &bytes).map_err(|_| StreamError::Closed)?;
@@ -1834,6 +1952,9 @@ impl<T: VfsView> wasi::filesystem::types::HostDescriptor for VfsImpl<T> {
             Kind::File | Kind::Ro(_) => {
                 let mut g = fs.lock().unwrap();
                 g.copy_up(node);
+                if !g.fits(node, offset.saturating_add(buf.len() as u64)) {
+                    return err(ErrorCode::InsufficientSpace);
+                }
                 let Some(Node::File(data)) = g.nodes.get_mut(&node) else {
                     return err(ErrorCode::NoEntry);
                 };
@@ -1953,7 +2074,7 @@ impl<T: VfsView> wasi::filesystem::types::HostDescriptor for VfsImpl<T> {
                 return err(ErrorCode::Exist);
             }
         }
-        if g.at_capacity() {
+        if g.refuse_create() {
             return err(ErrorCode::InsufficientSpace);
         }
         let id = g.alloc(Node::Dir(BTreeMap::new()));
@@ -2095,6 +2216,9 @@ impl<T: VfsView> wasi::filesystem::types::HostDescriptor for VfsImpl<T> {
             Kind::File | Kind::Ro(_) => {
                 let mut g = fs.lock().unwrap();
                 g.copy_up(node);
+                if !g.fits(node, size as u64) {
+                    return err(ErrorCode::InsufficientSpace);
+                }
                 if let Some(Node::File(data)) = g.nodes.get_mut(&node) {
                     data.resize(size, 0);
                 }
@@ -2195,7 +2319,7 @@ impl<T: VfsView> wasi::filesystem::types::HostDescriptor for VfsImpl<T> {
                     let Some((parent, name)) = resolve_parent(&g, start, &path) else {
                         return err(ErrorCode::NoEntry);
                     };
-                    if g.at_capacity() {
+                    if g.refuse_create() {
                         return err(ErrorCode::InsufficientSpace);
                     }
                     let id = g.alloc(Node::File(Vec::new()));
@@ -2506,7 +2630,7 @@ impl<T: VfsView> wasi::filesystem::types::HostDescriptor for VfsImpl<T> {
         } else {
             return err(ErrorCode::NotDirectory);
         }
-        if g.at_capacity() {
+        if g.refuse_create() {
             return err(ErrorCode::InsufficientSpace);
         }
         let id = g.alloc(Node::Symlink(src_path));
@@ -2682,6 +2806,80 @@ mod tests {
         Arc::new(layers::LayerBytes::eager(Arc::new(data.to_vec())))
     }
 
+    /// `usage` counts every node but only the bytes this fs owns: a layer
+    /// file is shared, so it costs a node and nothing more.
+    #[test]
+    fn usage_counts_nodes_and_private_bytes() {
+        let mut fs = Fs::default();
+        assert_eq!(fs.usage(), (1, 0));
+        fs.put_file_at("/etc/app.toml", b"debug = true".to_vec());
+        fs.put_ro_file_at("/etc/base.toml", ro_bytes(b"from a layer"));
+        assert_eq!(fs.usage(), (4, 12));
+    }
+
+    /// A guest may not write or create past its fs's quota — the error is
+    /// `insufficient-space` and the hit is noted — but a write that fits once
+    /// deletes are counted goes through, and what the host places is not
+    /// refused.
+    #[test]
+    fn quota_refuses_guest_growth_past_it() {
+        use wasi::filesystem::types::HostDescriptor;
+
+        let fs = new_fs();
+        fs.lock().unwrap().put_file_at("/big", vec![0; 100]);
+        let nodes = fs.lock().unwrap().usage().0;
+        fs.lock().unwrap().set_quota(Quota {
+            nodes: Some(nodes + 1),
+            bytes: Some(150),
+        });
+        let mut store = VfsImpl(TestStore {
+            table: ResourceTable::new(),
+            fs: fs.clone(),
+        });
+        let root = store
+            .0
+            .table
+            .push(Descriptor::open(fs.clone(), ROOT))
+            .unwrap();
+        let root_fd = || Resource::<Descriptor>::new_own(root.rep());
+        let create = |store: &mut VfsImpl<TestStore>, path: &str| {
+            HostDescriptor::open_at(
+                store,
+                root_fd(),
+                PathFlags::SYMLINK_FOLLOW,
+                path.into(),
+                OpenFlags::CREATE,
+                DescriptorFlags::READ | DescriptorFlags::WRITE,
+            )
+            .unwrap()
+        };
+
+        let fd = create(&mut store, "small").expect("one more entry fits");
+        assert_eq!(
+            create(&mut store, "another").unwrap_err(),
+            ErrorCode::InsufficientSpace
+        );
+        assert_eq!(fs.lock().unwrap().quota_hit(), Some(QuotaHit::Nodes));
+        let write = |store: &mut VfsImpl<TestStore>, len: usize| {
+            HostDescriptor::write(store, Resource::new_own(fd.rep()), vec![1; len], 0).unwrap()
+        };
+        assert_eq!(
+            write(&mut store, 60).unwrap_err(),
+            ErrorCode::InsufficientSpace
+        );
+        assert_eq!(fs.lock().unwrap().quota_hit(), Some(QuotaHit::Bytes));
+        write(&mut store, 50).expect("exactly at the quota");
+
+        HostDescriptor::unlink_file_at(&mut store, root_fd(), "big".into())
+            .unwrap()
+            .unwrap();
+        write(&mut store, 140).expect("room freed by the delete");
+
+        fs.lock().unwrap().set_quota(Quota::default());
+        assert_eq!(fs.lock().unwrap().quota_hit(), None);
+        fs.lock().unwrap().put_file_at("/host", vec![0; 1000]);
+    }
+
     /// A minimal store so tests can drive the generated `wasi:filesystem`
     /// methods (the same surface a guest hits) without a live wasm instance.
     struct TestStore {
@@ -2702,7 +2900,7 @@ mod tests {
     /// A minimal in-memory provider: a map of path -> bytes served over a
     /// `ProviderConn` on its own thread, the way a wk:fs guest node would.
     /// Returns the join handle and a stop flag.
-    fn spawn_memfs_provider(
+    pub(crate) fn spawn_memfs_provider(
         conn: Arc<ProviderConn>,
         seed: &[(&str, &[u8])],
     ) -> (
@@ -343,6 +343,9 @@ impl VfsWriteConsumer {
             WriteDst::Local { fs, node } => {
                 let mut g = fs.lock().unwrap();
                 g.copy_up(*node);
+                if !g.fits(*node, self.offset.saturating_add(bytes.len() as u64)) {
+                    return Err(ErrorCode::InsufficientSpace);
+                }
                 match g.nodes.get_mut(node) {
                     Some(crate::Node::File(data)) => crate::write_at(data, self.offset, bytes)
                         .map_err(|_| ErrorCode::FileTooLarge)?,
@@ -6,14 +6,15 @@
 //! to the node, its output streams back, and **Ctrl-P Ctrl-Q** detaches without
 //! stopping it. Non-interactive (piped): input is forwarded until EOF and output
 //! is printed — so `echo cmd | wk attach <repl>` works in a script.
+//!
+//! `wk exec -it` drives its program's terminal with the same loop.
 
 use std::io::{BufReader, Read, Write};
-use std::os::unix::net::UnixStream;
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use wk_protocol::ipc::{read_msg, write_msg, ClientMsg, ServerMsg};
+use wk_protocol::ipc::{read_msg, write_msg, write_msg_as, ClientMsg, ServerMsg};
 
 use crate::cli;
 
@@ -87,10 +88,22 @@ pub fn attach(workspace: &Path, node_ref: &str) -> Result<(), String> {
         Some(ServerMsg::Error(e)) => return Err(e),
         other => return Err(format!("unexpected reply: {other:?}")),
     }
+    terminal(stream, reader, Some(&name)).map(|_| ())
+}
 
+/// Drive an attached terminal stream until it ends: the local terminal goes
+/// raw (if it is one), keystrokes and size go up, output comes down. `node`
+/// names the attached node for the banners; `None` is `wk exec -it`'s own
+/// terminal, which ends when its program does — whose exit status, or trap,
+/// is what this returns.
+pub(crate) fn terminal(
+    mut stream: cli::Conn,
+    mut reader: BufReader<cli::Conn>,
+    node: Option<&str>,
+) -> Result<Option<i32>, String> {
     let raw = RawMode::enable();
     let interactive = raw.is_some();
-    if interactive {
+    if let (true, Some(name)) = (interactive, node) {
         eprintln!("wk: attached to {name} — detach with Ctrl-P Ctrl-Q\r");
     }
 
@@ -129,14 +142,16 @@ pub fn attach(workspace: &Path, node_ref: &str) -> Result<(), String> {
     };
 
     // Piped (non-interactive): the node never sees our stdin EOF (detach doesn't
-    // close its stdin), so read its output until it goes idle, then exit.
-    if !interactive {
+    // close its stdin), so read its output until it goes idle, then exit. An
+    // exec's stream ends by itself.
+    if !interactive && node.is_some() {
         let _ = reader
-            .get_ref()
+            .get_mut()
             .set_read_timeout(Some(std::time::Duration::from_millis(1200)));
     }
 
     let mut stdout = std::io::stdout();
+    let mut status = Ok(None);
     while !done.load(Ordering::Relaxed) {
         match read_msg::<_, ServerMsg>(&mut reader) {
             Ok(Some(ServerMsg::Term(bytes))) => {
@@ -144,6 +159,9 @@ pub fn attach(workspace: &Path, node_ref: &str) -> Result<(), String> {
                     break;
                 }
             }
+            // How an exec'd program ended; its detach follows.
+            Ok(Some(ServerMsg::Exited(code))) => status = Ok(Some(code)),
+            Ok(Some(ServerMsg::Error(e))) => status = Err(e),
             // The node exited, or we detached.
             Ok(Some(ServerMsg::Detached)) | Ok(None) => break,
             Ok(Some(_)) => {}
@@ -158,16 +176,16 @@ pub fn attach(workspace: &Path, node_ref: &str) -> Result<(), String> {
     if let Some(r) = resizer {
         let _ = r.join();
     }
-    if interactive {
+    if let (true, Some(name)) = (interactive, node) {
         eprintln!("\r\nwk: detached from {name}");
     }
-    Ok(())
+    status
 }
 
 /// Read local stdin and forward it as [`ClientMsg::Input`]. In interactive mode,
 /// the docker detach sequence **Ctrl-P Ctrl-Q** ends the attach instead of being
 /// sent. Stops when `done` is set, on EOF, or on a write error.
-fn forward_stdin(stream: &mut UnixStream, interactive: bool, done: &AtomicBool) {
+fn forward_stdin(stream: &mut cli::Conn, interactive: bool, done: &AtomicBool) {
     const CTRL_P: u8 = 0x10;
     const CTRL_Q: u8 = 0x11;
     let mut stdin = std::io::stdin();
@@ -200,7 +218,8 @@ fn forward_stdin(stream: &mut UnixStream, interactive: bool, done: &AtomicBool)
             }
             out.push(b);
         }
-        if !out.is_empty() && write_msg(stream, &ClientMsg::Input(out)).is_err() {
+        let bytes = stream.byte_encoding();
+        if !out.is_empty() && write_msg_as(stream, &ClientMsg::Input(out), bytes).is_err() {
             break;
         }
         if done.load(Ordering::Relaxed) {
@@ -1,34 +1,215 @@
 //! The `wk` CLI as a *client* of a running server — wk's `docker`/`docker
 //! compose` command surface. These subcommands don't start a server; they
 //! connect to one already started by `wk run` (windowed or headless) over its
-//! per-workspace Unix socket and drive it live.
+//! per-workspace Unix socket and drive it live — or, with `--remote`, to one
+//! on another machine over TCP + TLS (`wk run --listen`). The local socket is
+//! admin; `--token` drops a connection to that token's authority instead.
 
-use std::io::BufReader;
+use std::io::{self, BufReader, Read, Write};
 use std::os::unix::net::UnixStream;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
 use wk_api::ipc::socket_path;
-use wk_protocol::ipc::{read_msg, write_msg, ClientMsg, ServerMsg, Snapshot};
-use wk_protocol::{Command, NodeKind, NodePatch, Resource, ResourceRef};
+use wk_protocol::ipc::{
+    read_msg, write_msg, Auth, ByteEncoding, ClientMsg, Created, Event, Hello, ServerMsg, Snapshot,
+    PROTOCOL_VERSION,
+};
+use wk_protocol::{
+    Command, Limits, NodeKind, NodePatch, Resource, ResourceRef, Restart, RestartPolicy,
+};
 
-/// Connect to the running server for `workspace`, or a helpful error if none.
-pub(crate) fn connect(workspace: &Path) -> Result<UnixStream, String> {
-    let sock = socket_path(workspace);
-    UnixStream::connect(&sock).map_err(|_| {
-        format!(
-            "no running wk server for {} — start one with `wk run{}`",
-            workspace.display(),
-            if workspace == Path::new(wk_server::workspace::DEFAULT_WORKSPACE) {
-                String::new()
-            } else {
-                format!(" {}", workspace.display())
+/// A remote server to drive instead of the local socket (`wk --remote`).
+pub(crate) struct Remote {
+    /// `host:port` of its `wk run --listen`.
+    pub addr: String,
+    /// The token to present, hex.
+    pub token: String,
+    /// PEM certificate (or CA) to verify the server against.
+    pub ca: PathBuf,
+}
+
+/// The `--remote` target, set once at startup; unset, commands use the local
+/// socket.
+static REMOTE: OnceLock<Remote> = OnceLock::new();
+
+/// Send every command that connects to `remote` instead of the local socket.
+pub(crate) fn set_remote(remote: Remote) {
+    let _ = REMOTE.set(remote);
+}
+
+/// The `--token` to bear over the local socket in place of its admin
+/// authority (hex); unset, local connections are admin.
+static TOKEN: OnceLock<String> = OnceLock::new();
+
+/// Authenticate every local connection with `token` (see [`TOKEN`]).
+pub(crate) fn set_token(token: String) {
+    let _ = TOKEN.set(token);
+}
+
+/// The token connections bear, if one was given — to `--remote`, or over the
+/// local socket.
+pub(crate) fn token() -> Option<&'static str> {
+    match REMOTE.get() {
+        Some(remote) => Some(&remote.token),
+        None => TOKEN.get().map(String::as_str),
+    }
+}
+
+/// A connection to a running server: its local socket, or a TLS session with
+/// a remote one. Both carry the same [`wk_protocol::ipc`] messages; clones
+/// share the connection (a reader and writers on separate threads).
+pub(crate) struct Conn {
+    stream: Stream,
+    /// What the server said it supports, in its [`Hello`].
+    features: Arc<Vec<String>>,
+}
+
+enum Stream {
+    Local(UnixStream),
+    Remote(wk_api::tcp::ClientStream),
+}
+
+impl Conn {
+    /// Another handle to the same connection.
+    pub(crate) fn try_clone(&self) -> io::Result<Conn> {
+        let stream = match &self.stream {
+            Stream::Local(s) => Stream::Local(s.try_clone()?),
+            Stream::Remote(s) => Stream::Remote(s.clone()),
+        };
+        Ok(Conn {
+            stream,
+            features: self.features.clone(),
+        })
+    }
+
+    /// Give up a read after `timeout` with no data (`None` waits forever).
+    pub(crate) fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        match &mut self.stream {
+            Stream::Local(s) => s.set_read_timeout(timeout),
+            Stream::Remote(s) => {
+                s.set_read_timeout(timeout);
+                Ok(())
             }
-        )
-    })
+        }
+    }
+
+    /// Fail with a clear "server too old" error unless the server supports
+    /// `feature` (one of [`wk_protocol::ipc::FEATURES`]), which `what` needs.
+    pub(crate) fn require(&self, feature: &str, what: &str) -> Result<(), String> {
+        if self.features.iter().any(|f| f == feature) {
+            return Ok(());
+        }
+        Err(format!(
+            "the server is too old for {what} (it lacks the {feature:?} feature) — restart \
+             it with this version's `wk run`"
+        ))
+    }
+
+    /// How to write byte payloads ([`ClientMsg::Input`]) to this server:
+    /// base64 if it said it reads them, since our hello says we do too.
+    pub(crate) fn byte_encoding(&self) -> ByteEncoding {
+        if self.features.iter().any(|f| f == "base64") {
+            ByteEncoding::Base64
+        } else {
+            ByteEncoding::Array
+        }
+    }
+}
+
+impl Read for Conn {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.stream {
+            Stream::Local(s) => s.read(buf),
+            Stream::Remote(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Conn {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.stream {
+            Stream::Local(s) => s.write(buf),
+            Stream::Remote(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.stream {
+            Stream::Local(s) => s.flush(),
+            Stream::Remote(s) => s.flush(),
+        }
+    }
+}
+
+/// Connect to the running server for `workspace` (or the `--remote` one), or
+/// a helpful error if none. The connection has said hello, so it knows what
+/// the server supports, and bears `--token` if one was given.
+pub(crate) fn connect(workspace: &Path) -> Result<Conn, String> {
+    let stream = match REMOTE.get() {
+        Some(remote) => {
+            let tls = wk_api::tcp::client_config(&remote.ca)?;
+            wk_api::tcp::connect(&remote.addr, &remote.token, tls).map(Stream::Remote)?
+        }
+        None => UnixStream::connect(socket_path(workspace))
+            .map(Stream::Local)
+            .map_err(|_| {
+                format!(
+                    "no running wk server for {} — start one with `wk run{}`",
+                    workspace.display(),
+                    if workspace == Path::new(wk_server::workspace::DEFAULT_WORKSPACE) {
+                        String::new()
+                    } else {
+                        format!(" {}", workspace.display())
+                    }
+                )
+            })?,
+    };
+    let mut conn = Conn {
+        stream,
+        features: Arc::default(),
+    };
+    conn.features = Arc::new(hello(&mut conn)?.features);
+    if let (None, Some(token)) = (REMOTE.get(), TOKEN.get()) {
+        conn.require("authenticate", "--token")?;
+        let auth = Auth {
+            token: token.trim().to_string(),
+        };
+        write_msg(&mut conn, &ClientMsg::Authenticate(auth)).map_err(|e| e.to_string())?;
+        let mut r = BufReader::new(conn.try_clone().map_err(|e| e.to_string())?);
+        match read_msg::<_, ServerMsg>(&mut r).map_err(|e| e.to_string())? {
+            Some(ServerMsg::Ok) => {}
+            Some(ServerMsg::Error(e)) => return Err(format!("the server refused the token: {e}")),
+            other => return Err(format!("unexpected reply: {other:?}")),
+        }
+    }
+    Ok(conn)
+}
+
+/// Exchange [`Hello`]s: the server's, if it speaks this protocol version. A
+/// server from before the handshake can't parse ours and hangs up.
+fn hello(conn: &mut Conn) -> Result<Hello, String> {
+    write_msg(conn, &ClientMsg::Hello(Hello::ours())).map_err(|e| e.to_string())?;
+    let mut r = BufReader::new(conn.try_clone().map_err(|e| e.to_string())?);
+    match read_msg::<_, ServerMsg>(&mut r) {
+        Ok(Some(ServerMsg::Hello(h))) if h.version == PROTOCOL_VERSION => Ok(h),
+        Ok(Some(ServerMsg::Hello(h))) => Err(format!(
+            "the server speaks protocol version {} and this wk version {PROTOCOL_VERSION} — run \
+             the same wk on both sides",
+            h.version
+        )),
+        Ok(None) | Err(_) => Err(
+            "the server is too old for this wk (it predates the protocol handshake) — restart \
+             it with this version's `wk run`"
+                .into(),
+        ),
+        Ok(Some(other)) => Err(format!("unexpected reply: {other:?}")),
+    }
 }
 
 /// Fetch a fresh snapshot from the connected server.
-pub(crate) fn get_snapshot(stream: &mut UnixStream) -> Result<Snapshot, String> {
+pub(crate) fn get_snapshot(stream: &mut Conn) -> Result<Snapshot, String> {
     write_msg(stream, &ClientMsg::GetSnapshot).map_err(|e| e.to_string())?;
     let mut r = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
     match read_msg::<_, ServerMsg>(&mut r).map_err(|e| e.to_string())? {
@@ -39,12 +220,13 @@ pub(crate) fn get_snapshot(stream: &mut UnixStream) -> Result<Snapshot, String>
 }
 
 /// A short, docker-style node id (first 12 chars of the 26-char ULID).
-fn short(id: wk_protocol::NodeId) -> String {
+pub(crate) fn short(id: wk_protocol::NodeId) -> String {
     id.to_string().chars().take(12).collect()
 }
 
-/// Send one command and wait for the server's ack.
-fn send_command(stream: &mut UnixStream, cmd: Command) -> Result<(), String> {
+/// Send one command and wait for the server's verdict: it replies once the
+/// command is applied, so one the token doesn't grant comes back as an error.
+pub(crate) fn send_command(stream: &mut Conn, cmd: Command) -> Result<(), String> {
     write_msg(stream, &ClientMsg::Command(cmd)).map_err(|e| e.to_string())?;
     let mut r = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
     match read_msg::<_, ServerMsg>(&mut r).map_err(|e| e.to_string())? {
@@ -54,6 +236,18 @@ fn send_command(stream: &mut UnixStream, cmd: Command) -> Result<(), String> {
     }
 }
 
+/// Send a create (or duplicate) and wait for what it made. The server replies
+/// once the command is applied, so the returned ids are live.
+pub(crate) fn send_create(stream: &mut Conn, cmd: Command) -> Result<Created, String> {
+    write_msg(stream, &ClientMsg::Command(cmd)).map_err(|e| e.to_string())?;
+    let mut r = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    match read_msg::<_, ServerMsg>(&mut r).map_err(|e| e.to_string())? {
+        Some(ServerMsg::Created(made)) => Ok(made),
+        Some(ServerMsg::Error(e)) => Err(e),
+        other => Err(format!("unexpected reply: {other:?}")),
+    }
+}
+
 /// Resolve a user-supplied node reference to exactly one node. A reference
 /// matches by node name, or by any substring of its id (so a short id prefix
 /// *or* the distinguishing suffix both work). Ambiguous or absent → error.
@@ -86,7 +280,18 @@ pub(crate) fn resolve<'a>(
 /// `wk node add <name> [args...]`: launch a dependency as a new node.
 pub fn add(workspace: &Path, name: &str, args: &[String]) -> Result<(), String> {
     let mut stream = connect(workspace)?;
-    let snap = get_snapshot(&mut stream)?;
+    let id = add_on(&mut stream, name, args)?;
+    println!("added {name} ({})", short(id));
+    Ok(())
+}
+
+/// Launch dependency `name` as a new node over an open connection; its id.
+pub(crate) fn add_on(
+    stream: &mut Conn,
+    name: &str,
+    args: &[String],
+) -> Result<wk_protocol::NodeId, String> {
+    let snap = get_snapshot(stream)?;
     let dep = snap
         .available
         .iter()
@@ -101,42 +306,31 @@ pub fn add(workspace: &Path, name: &str, args: &[String]) -> Result<(), String>
     // Cascade new nodes so they don't stack exactly on top of each other.
     let n = snap.nodes.len() as f32;
     let pos = [60.0 + 24.0 * (n % 8.0), 60.0 + 24.0 * (n % 8.0)];
-    send_command(
-        &mut stream,
+    // Launch args ride the create (none = the dependency's defaults), so the
+    // guest's first run already sees them.
+    let made = send_create(
+        stream,
         Command::Create(Resource::Node {
             kind: NodeKind::App { dep },
             pos,
             ws,
+            id: None,
+            args: (!args.is_empty()).then(|| args.to_vec()),
+            env: Vec::new(),
+            size: None,
         }),
     )?;
-    // Set launch args, if given (a second command; empty falls back to default).
-    if !args.is_empty() {
-        // The new node's id isn't in our stale snapshot; re-fetch and target the
-        // newest node of this dependency without args yet.
-        let snap = get_snapshot(&mut stream)?;
-        if let Some(node) = snap
-            .nodes
-            .iter()
-            .rfind(|nd| nd.name == name && nd.args.is_empty())
-        {
-            send_command(
-                &mut stream,
-                Command::Update {
-                    id: node.id,
-                    patch: NodePatch {
-                        args: Some(args.join(" ")),
-                        ..Default::default()
-                    },
-                },
-            )?;
-        }
-    }
-    println!("added {name}");
-    Ok(())
+    made.nodes
+        .first()
+        .copied()
+        .ok_or_else(|| format!("failed to launch {name} (see the server log)"))
 }
 
 /// Fetch a node's effective capability token: (node id, token bytes).
-fn node_token(workspace: &Path, node: &str) -> Result<(wk_protocol::NodeId, Vec<u8>), String> {
+pub(crate) fn node_token(
+    workspace: &Path,
+    node: &str,
+) -> Result<(wk_protocol::NodeId, Vec<u8>), String> {
     let mut stream = connect(workspace)?;
     let snap = get_snapshot(&mut stream)?;
     let n = resolve(&snap, node)?;
@@ -162,21 +356,29 @@ fn node_token(workspace: &Path, node: &str) -> Result<(wk_protocol::NodeId, Vec<
 /// currency `token set` accepts).
 pub fn token_show(workspace: &Path, node: &str) -> Result<(), String> {
     let (id, bytes) = node_token(workspace, node)?;
-    let tok =
-        biscuit_auth::UnverifiedBiscuit::from(&bytes).map_err(|e| format!("parse token: {e}"))?;
     println!("node {}", short(id));
+    print!("{}", token_blocks(&bytes)?);
+    println!("hex: {}", wk_server::workspace::bytes_hex(&bytes));
+    Ok(())
+}
+
+/// A token's Datalog, block by block (the authority, then any attenuations),
+/// as `wk token show` prints it.
+pub(crate) fn token_blocks(bytes: &[u8]) -> Result<String, String> {
+    let tok =
+        biscuit_auth::UnverifiedBiscuit::from(bytes).map_err(|e| format!("parse token: {e}"))?;
+    let mut out = String::new();
     for i in 0..tok.block_count() {
         let source = tok
             .print_block_source(i)
             .map_err(|e| format!("block {i}: {e}"))?;
         let label = if i == 0 { "authority" } else { "attenuation" };
-        println!("block {i} ({label}):");
+        out.push_str(&format!("block {i} ({label}):\n"));
         for line in source.lines().filter(|l| !l.trim().is_empty()) {
-            println!("  {}", line.trim());
+            out.push_str(&format!("  {}\n", line.trim()));
         }
     }
-    println!("hex: {}", wk_server::workspace::bytes_hex(&bytes));
-    Ok(())
+    Ok(out)
 }
 
 /// `wk token attenuate <ref> <block>`: append an attenuation block (Datalog
@@ -243,8 +445,8 @@ pub fn token_reset(workspace: &Path, node: &str) -> Result<(), String> {
 /// steal the live stream the way `attach` does). `follow` streams new output
 /// until the node exits or Ctrl-C.
 pub fn logs(workspace: &Path, node: &str, follow: bool) -> Result<(), String> {
-    use std::io::Write;
     let mut stream = connect(workspace)?;
+    stream.require("logs", "`wk logs`")?;
     let snap = get_snapshot(&mut stream)?;
     let id = resolve(&snap, node)?.id;
     write_msg(&mut stream, &ClientMsg::Logs { node: id, follow }).map_err(|e| e.to_string())?;
@@ -264,6 +466,119 @@ pub fn logs(workspace: &Path, node: &str, follow: bool) -> Result<(), String> {
     Ok(())
 }
 
+/// `wk events`: subscribe to the running workspace and print each state
+/// change as one JSON line, as it happens, until the server goes away or
+/// Ctrl-C. Meant for piping into `jq` or a script instead of polling `wk ps`.
+pub fn events(workspace: &Path) -> Result<(), String> {
+    let mut stream = connect(workspace)?;
+    stream.require("subscribe", "`wk events`")?;
+    write_msg(&mut stream, &ClientMsg::Subscribe).map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    let mut stdout = std::io::stdout();
+    loop {
+        match read_msg::<_, ServerMsg>(&mut reader).map_err(|e| e.to_string())? {
+            Some(ServerMsg::Event(ev)) => {
+                let line = serde_json::to_string(&ev).map_err(|e| e.to_string())?;
+                if writeln!(stdout, "{line}")
+                    .and_then(|_| stdout.flush())
+                    .is_err()
+                {
+                    break; // stdout closed (e.g. `| head`)
+                }
+            }
+            // The baseline: events are relative to it, nothing to print.
+            Some(ServerMsg::Snapshot(_)) => {}
+            Some(ServerMsg::Error(e)) => return Err(e),
+            Some(_) => {}
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+/// `wk wait <ref>`: block until an app node's guest ends, then print its exit
+/// status and exit with it — so a script can run a CLI node to completion
+/// and check the result. Like `docker wait`, a node that isn't running
+/// answers at once: with how it last ended, or an error if it never ran. A
+/// trap or a stop has no status: it is reported on stderr and is a failure.
+pub fn wait(workspace: &Path, node: &str) -> Result<(), String> {
+    let mut stream = connect(workspace)?;
+    stream.require("subscribe", "`wk wait`")?;
+    write_msg(&mut stream, &ClientMsg::Subscribe).map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    // The baseline resolves the node and says whether it has already ended;
+    // the events after it are relative to it, so an exit can't slip between.
+    let (id, mut ended) = match read_msg::<_, ServerMsg>(&mut reader).map_err(|e| e.to_string())? {
+        Some(ServerMsg::Snapshot(snap)) => {
+            let n = resolve(&snap, node)?;
+            if n.kind != "app" {
+                return Err(format!("{node}: not an app node; only a guest exits"));
+            }
+            let done = !n.running && !n.compiling;
+            if done && n.exit.is_none() {
+                return Err(format!("{node} isn't running"));
+            }
+            (n.id, n.exit.clone().filter(|_| done))
+        }
+        Some(ServerMsg::Error(e)) => return Err(e),
+        other => return Err(format!("unexpected reply: {other:?}")),
+    };
+    let exit = loop {
+        if let Some(exit) = ended.take() {
+            break exit;
+        }
+        match read_msg::<_, ServerMsg>(&mut reader).map_err(|e| e.to_string())? {
+            Some(ServerMsg::Event(Event::NodeExited { id: n, exit })) if n == id => ended = exit,
+            Some(ServerMsg::Event(Event::NodeDeleted { id: n })) if n == id => {
+                return Err(format!("{node} was removed before it exited"));
+            }
+            Some(ServerMsg::Error(e)) => return Err(e),
+            Some(_) => {}
+            None => return Err("the server hung up before the node exited".into()),
+        }
+    };
+    match (exit.code, &exit.trap) {
+        (Some(code), _) => {
+            println!("{code}");
+            if code != 0 {
+                std::process::exit(code);
+            }
+            Ok(())
+        }
+        (None, Some(trap)) => {
+            if let Some(bt) = &exit.backtrace {
+                eprintln!("{bt}");
+            }
+            Err(format!("{node} trapped: {trap}"))
+        }
+        (None, None) => Err(format!("{node} was stopped before it exited")),
+    }
+}
+
+/// `wk commit <ref> <tag>`: keep what a node has written since it started —
+/// files it created or changed, files it deleted — as a new image tagged
+/// `tag` in the local store (like `docker commit`). The node keeps running.
+pub fn commit(workspace: &Path, node: &str, tag: &str) -> Result<(), String> {
+    let mut stream = connect(workspace)?;
+    stream.require("commit", "`wk commit`")?;
+    let snap = get_snapshot(&mut stream)?;
+    let id = resolve(&snap, node)?.id;
+    let msg = ClientMsg::Commit {
+        node: id,
+        tag: tag.to_string(),
+    };
+    write_msg(&mut stream, &msg).map_err(|e| e.to_string())?;
+    let mut r = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    match read_msg::<_, ServerMsg>(&mut r).map_err(|e| e.to_string())? {
+        Some(ServerMsg::Committed(image)) => {
+            println!("committed {} as {tag} ({image})", short(id));
+            Ok(())
+        }
+        Some(ServerMsg::Error(e)) => Err(e),
+        other => Err(format!("unexpected reply: {other:?}")),
+    }
+}
+
 /// One connection of an inspected node: the wire kind and the peer it joins.
 #[derive(serde::Serialize)]
 struct Connection {
@@ -278,9 +593,18 @@ struct NodeReport<'a> {
     id: String,
     kind: &'a str,
     name: &'a str,
-    status: &'static str,
+    status: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<&'a str>,
+    /// How its guest last ended: status, trap and backtrace, and when.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit: Option<&'a wk_protocol::ipc::ExitInfo>,
+    /// An app's restart policy, and how many times in a row it has been
+    /// re-run under it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    restart: Option<&'static str>,
+    #[serde(skip_serializing_if = "is_zero")]
+    restarts: u16,
     running: bool,
     runnable: bool,
     terminal: bool,
@@ -298,6 +622,10 @@ struct NodeReport<'a> {
     connections: Vec<Connection>,
 }
 
+fn is_zero(n: &u16) -> bool {
+    *n == 0
+}
+
 /// The first `n` characters of `s`, for long opaque strings (uplink tickets)
 /// that would otherwise wrap a table. Char-wise, so it can't split a UTF-8
 /// sequence.
@@ -306,19 +634,30 @@ fn ellipsis(s: &str, n: usize) -> String {
 }
 
 /// Status label for an inspected node, matching `wk ps`'s vocabulary.
-fn status_of(n: &wk_protocol::ipc::NodeInfo) -> &'static str {
+fn status_of(n: &wk_protocol::ipc::NodeInfo) -> String {
     if n.error.is_some() {
-        "error"
+        "error".into()
     } else if n.compiling {
-        "compiling"
+        "compiling".into()
     } else if n.attached {
-        "attached"
+        "attached".into()
     } else if !n.runnable {
-        "-"
+        "-".into()
     } else if n.running {
-        "running"
+        "running".into()
+    } else if let Some(exit) = &n.exit {
+        exit_status(exit)
     } else {
-        "idle"
+        "idle".into()
+    }
+}
+
+/// An ended node's status: `exited <code>`, `trapped` or `stopped`. The trap
+/// itself is too long for a status column; `wk ps` shows it alongside.
+fn exit_status(exit: &wk_protocol::ipc::ExitInfo) -> String {
+    match exit.trap {
+        Some(_) => "trapped".into(),
+        None => exit.summary(),
     }
 }
 
@@ -350,6 +689,9 @@ fn node_report<'a>(snap: &'a Snapshot, node: &'a wk_protocol::ipc::NodeInfo) ->
         name: &node.name,
         status: status_of(node),
         error: node.error.as_deref(),
+        exit: node.exit.as_ref(),
+        restart: (node.restart != RestartPolicy::No).then(|| node.restart.as_str()),
+        restarts: node.restarts,
         running: node.running,
         runnable: node.runnable,
         terminal: node.terminal,
@@ -461,6 +803,62 @@ pub fn restart(workspace: &Path, node: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Fetch the undo/redo history's labels (see [`ServerMsg::History`]).
+fn get_history(stream: &mut Conn) -> Result<(Vec<String>, Vec<String>), String> {
+    stream.require("history", "the undo history")?;
+    write_msg(stream, &ClientMsg::History).map_err(|e| e.to_string())?;
+    let mut r = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    match read_msg::<_, ServerMsg>(&mut r).map_err(|e| e.to_string())? {
+        Some(ServerMsg::History { undo, redo }) => Ok((undo, redo)),
+        Some(ServerMsg::Error(e)) => Err(e),
+        other => Err(format!("unexpected reply: {other:?}")),
+    }
+}
+
+/// `wk undo`: undo the running workspace's last mutation (like Cmd/Ctrl+Z in
+/// the window) and say what it was.
+pub fn undo(workspace: &Path) -> Result<(), String> {
+    let mut stream = connect(workspace)?;
+    stream.require("history", "`wk undo`")?;
+    match send_create(&mut stream, Command::Undo)?.undone {
+        Some(label) => println!("undid: {label}"),
+        None => println!("nothing to undo"),
+    }
+    Ok(())
+}
+
+/// `wk redo`: re-apply the last undone mutation.
+pub fn redo(workspace: &Path) -> Result<(), String> {
+    let mut stream = connect(workspace)?;
+    stream.require("history", "`wk redo`")?;
+    match send_create(&mut stream, Command::Redo)?.undone {
+        Some(label) => println!("redid: {label}"),
+        None => println!("nothing to redo"),
+    }
+    Ok(())
+}
+
+/// `wk history`: list the undoable mutations, oldest first (the last is what
+/// `wk undo` reverses next), then what `wk redo` would re-apply, in order.
+pub fn history(workspace: &Path) -> Result<(), String> {
+    let mut stream = connect(workspace)?;
+    let (undo, redo) = get_history(&mut stream)?;
+    if undo.is_empty() && redo.is_empty() {
+        println!("no history");
+        return Ok(());
+    }
+    for (i, label) in undo.iter().enumerate() {
+        println!("{:>4}  {label}", i + 1);
+    }
+    if !redo.is_empty() {
+        println!("undone (wk redo re-applies, top first):");
+        for label in redo.iter().rev() {
+            println!("      {label}");
+        }
+    }
+    Ok(())
+}
+
 /// `wk down`: stop every running app node in the workspace (leaves them placed;
 /// bring them back with `wk up`).
 pub fn down(workspace: &Path) -> Result<(), String> {
@@ -488,8 +886,10 @@ pub fn up(workspace: &Path) -> Result<(), String> {
     Ok(())
 }
 
-/// `wk node set <ref> [--args "..."] [--host-path P]`: reconfigure a node's
-/// launch args and/or (for a BindMount) the host file/folder it exposes.
+/// `wk node set <ref> [--args "..."] [--host-path P] [--restart R] [--env K=V]
+/// [--memory N] ...`: reconfigure a node's launch args, (for a BindMount) the
+/// host file/folder it exposes, and/or (for an app) its restart policy,
+/// environment and resource limits.
 #[allow(clippy::too_many_arguments)]
 pub fn set_node(
     workspace: &Path,
@@ -498,11 +898,37 @@ pub fn set_node(
     host_path: Option<&str>,
     persist: Option<bool>,
     port: Option<u16>,
+    restart: Option<Restart>,
+    limits: Limits,
+    env: &[String],
+    unset_env: &[String],
 ) -> Result<(), String> {
-    if args.is_none() && host_path.is_none() && persist.is_none() && port.is_none() {
-        return Err("nothing to set — pass --args, --host-path, --persist, and/or --port".into());
+    let limits = (!limits.is_empty()).then(|| Box::new(limits));
+    let env = Some(env_patch(env, unset_env)?).filter(|vars| !vars.is_empty());
+    if args.is_none()
+        && host_path.is_none()
+        && persist.is_none()
+        && port.is_none()
+        && restart.is_none()
+        && limits.is_none()
+        && env.is_none()
+    {
+        return Err(
+            "nothing to set — pass --args, --host-path, --persist, --port, --restart, \
+             --env/--unset-env, and/or a limit (--memory, --cpu, --fs-bytes, --fs-nodes)"
+                .into(),
+        );
     }
     let mut stream = connect(workspace)?;
+    if restart.is_some() {
+        stream.require("restart", "`--restart`")?;
+    }
+    if limits.is_some() {
+        stream.require("limits", "resource limits")?;
+    }
+    if env.is_some() {
+        stream.require("env", "`--env`")?;
+    }
     let snap = get_snapshot(&mut stream)?;
     let id = resolve(&snap, node)?.id;
     send_command(
@@ -514,6 +940,9 @@ pub fn set_node(
                 host_path: host_path.map(str::to_string),
                 persist,
                 port_set: port,
+                restart,
+                limits,
+                env,
                 ..Default::default()
             },
         },
@@ -522,8 +951,33 @@ pub fn set_node(
     Ok(())
 }
 
+/// `wk node set`'s `--env KEY=VALUE`s and `--unset-env KEY`s as one
+/// [`NodePatch::env`].
+fn env_patch(set: &[String], unset: &[String]) -> Result<Vec<(String, Option<String>)>, String> {
+    let name = |k: &str| {
+        if wk_protocol::is_env_name(k) {
+            Ok(k.to_string())
+        } else {
+            Err(format!(
+                "{k:?} isn't a variable name (not empty, no = or control characters)"
+            ))
+        }
+    };
+    let mut vars = Vec::new();
+    for kv in set {
+        let (k, v) = kv
+            .split_once('=')
+            .ok_or_else(|| format!("{kv}: set a variable as KEY=VALUE"))?;
+        vars.push((name(k)?, Some(v.to_string())));
+    }
+    for k in unset {
+        vars.push((name(k)?, None));
+    }
+    Ok(vars)
+}
+
 /// A short human label for a creatable node kind (for CLI output).
-fn kind_label(kind: NodeKind) -> &'static str {
+pub(crate) fn kind_label(kind: NodeKind) -> &'static str {
     match kind {
         NodeKind::App { .. } => "app",
         NodeKind::Volume => "volume",
@@ -551,37 +1005,41 @@ pub fn create(
     persist: bool,
 ) -> Result<(), String> {
     let mut stream = connect(workspace)?;
-    let snap = get_snapshot(&mut stream)?;
+    let id = create_on(&mut stream, kind, value, persist)?;
+    println!("created {} ({})", short(id), kind_label(kind));
+    Ok(())
+}
+
+/// Create a non-app node over an open connection, seeding its config from
+/// `value` as [`create`] describes; its id.
+pub(crate) fn create_on(
+    stream: &mut Conn,
+    kind: NodeKind,
+    value: Option<&str>,
+    persist: bool,
+) -> Result<wk_protocol::NodeId, String> {
+    let snap = get_snapshot(stream)?;
     let ws = *snap.workspaces.first().ok_or("the workspace has no tabs")?;
-    let before: std::collections::HashSet<_> = snap.nodes.iter().map(|n| n.id).collect();
     // Cascade so successive nodes don't stack exactly on top of each other.
     let n = snap.nodes.len() as f32;
     let pos = [60.0 + 24.0 * (n % 8.0), 60.0 + 24.0 * (n % 8.0)];
-    send_command(
-        &mut stream,
-        Command::Create(Resource::Node { kind, pos, ws }),
+    let made = send_create(
+        stream,
+        Command::Create(Resource::Node {
+            kind,
+            pos,
+            ws,
+            id: None,
+            args: None,
+            env: Vec::new(),
+            size: None,
+        }),
     )?;
-
-    // The create is applied asynchronously on the server's tick, so poll until
-    // the new node (the id absent from the prior snapshot) appears before
-    // seeding its config.
-    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(3);
-    let id = loop {
-        let snap = get_snapshot(&mut stream)?;
-        if let Some(id) = snap
-            .nodes
-            .iter()
-            .map(|n| n.id)
-            .find(|id| !before.contains(id))
-        {
-            break id;
-        }
-        if std::time::Instant::now() >= deadline {
-            println!("created a {} node", kind_label(kind));
-            return Ok(());
-        }
-        std::thread::sleep(std::time::Duration::from_millis(20));
-    };
+    let id = *made
+        .nodes
+        .first()
+        .ok_or_else(|| format!("failed to create a {} node", kind_label(kind)))?;
+    // Seed the kind's config on the node the create reported.
     let patch = match kind {
         NodeKind::BindMount => NodePatch {
             host_path: value.map(str::to_string),
@@ -622,10 +1080,9 @@ pub fn create(
     };
     // Only send a follow-up if there's actually something to configure.
     if !is_empty_patch(&patch) {
-        send_command(&mut stream, Command::Update { id, patch })?;
+        send_command(stream, Command::Update { id, patch })?;
     }
-    println!("created {} ({})", short(id), kind_label(kind));
-    Ok(())
+    Ok(id)
 }
 
 /// Whether a patch would change nothing (all fields `None`/absent).
@@ -640,6 +1097,7 @@ fn is_empty_patch(p: &NodePatch) -> bool {
         && p.size.is_none()
         && p.service_name.is_none()
         && p.service_target.is_none()
+        && p.restart.is_none()
 }
 
 /// `wk mount <volume> <app> [path]`: set where a volume bind mounts inside an
@@ -722,10 +1180,13 @@ pub fn wire(workspace: &Path, a: &str, b: &str) -> Result<(), String> {
     let mut stream = connect(workspace)?;
     let snap = get_snapshot(&mut stream)?;
     let (ida, idb) = (resolve(&snap, a)?.id, resolve(&snap, b)?.id);
-    send_command(
+    let made = send_create(
         &mut stream,
         Command::Create(Resource::Wire { a: ida, b: idb }),
     )?;
+    if made.wire.is_none() {
+        return Err(format!("{a:?} and {b:?} can't be wired to each other"));
+    }
     println!("wired {} <-> {}", short(ida), short(idb));
     Ok(())
 }
@@ -787,21 +1248,26 @@ pub fn ps(workspace: &Path) -> Result<(), String> {
             "-".to_string()
         } else if n.running {
             "running".to_string()
+        } else if let Some(exit) = &n.exit {
+            exit_status(exit)
         } else {
             "idle".to_string()
         };
         let name = if n.name.is_empty() { "-" } else { &n.name };
         // The trailing column carries the error message when there is one (a
-        // HostPort has no args of its own), else the launch args. An uplink's
-        // args are a ~200-char ticket, which would wrap the whole table — it
-        // is elided here and printed in full by `wk inspect`.
-        let detail = match (&n.error, n.peers.is_some()) {
-            (Some(e), _) => e.clone(),
-            (None, true) => match n.args.join(" ") {
+        // HostPort has no args of its own), then what a trapped guest trapped
+        // on (the full trace is `wk inspect`'s), else the launch args. An
+        // uplink's args are a ~200-char ticket, which would wrap the whole
+        // table — it is elided here and printed in full by `wk inspect`.
+        let trap = n.exit.as_ref().and_then(|e| e.trap.as_deref());
+        let detail = match (&n.error, trap, n.peers.is_some()) {
+            (Some(e), _, _) => e.clone(),
+            (None, Some(t), _) => t.lines().next().unwrap_or_default().to_string(),
+            (None, None, true) => match n.args.join(" ") {
                 t if t.is_empty() => String::new(),
                 t => format!("dials {}…", ellipsis(&t, 16)),
             },
-            (None, false) => n.args.join(" "),
+            (None, None, false) => n.args.join(" "),
         };
         println!(
             "{:<12}  {:<11}  {:<16}  {:<9}  {}",
@@ -839,6 +1305,9 @@ mod tests {
             token: None,
             ticket: None,
             peers: None,
+            exit: None,
+            restart: RestartPolicy::No,
+            restarts: 0,
         }
     }
 
@@ -896,6 +1365,40 @@ mod tests {
         assert!(!plain.contains("peers"), "{plain}");
     }
 
+    /// An ended node's status and the detail of how it ended reach `wk
+    /// inspect`; a node that never ran carries no `exit` at all.
+    #[test]
+    fn inspect_reports_how_a_node_ended() {
+        let mut crashed = node(0xE5, "job");
+        crashed.exit = Some(wk_protocol::ipc::ExitInfo {
+            code: None,
+            trap: Some("wasm `unreachable` instruction executed".into()),
+            backtrace: Some("0: 0x1a2b - job!main".into()),
+            at_ms: 1_700_000_000_000,
+        });
+        crashed.restart = RestartPolicy::OnFailure;
+        crashed.restarts = 3;
+        let s = snap(vec![crashed, node(0xF6, "idle")]);
+
+        let report = node_report(&s, &s.nodes[0]);
+        assert_eq!(report.status, "trapped");
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains("job!main"), "backtrace in output: {json}");
+        assert!(json.contains("1700000000000"), "{json}");
+        assert!(
+            json.contains(r#""restart":"on-failure","restarts":3"#),
+            "{json}"
+        );
+
+        let idle = node_report(&s, &s.nodes[1]);
+        assert_eq!(idle.status, "idle");
+        let idle = serde_json::to_string(&idle).unwrap();
+        assert!(
+            !idle.contains("exit") && !idle.contains("restart"),
+            "{idle}"
+        );
+    }
+
     #[test]
     fn inspect_report_resolves_a_nodes_connections() {
         use wk_protocol::ipc::WireInfo;
@@ -934,4 +1437,23 @@ mod tests {
         assert!(err.contains("ambiguous"), "{err}");
         assert!(resolve(&s, "nope").unwrap_err().contains("no node"));
     }
+
+    #[test]
+    fn env_patch_sets_and_unsets_by_name() {
+        let strings = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let patch = env_patch(&strings(&["A=1", "B=x=y", "C="]), &strings(&["D"])).unwrap();
+        assert_eq!(
+            patch,
+            vec![
+                ("A".into(), Some("1".into())),
+                ("B".into(), Some("x=y".into())),
+                ("C".into(), Some(String::new())),
+                ("D".into(), None),
+            ]
+        );
+        assert_eq!(env_patch(&[], &[]), Ok(Vec::new()));
+        assert!(env_patch(&strings(&["NOEQUALS"]), &[]).is_err());
+        assert!(env_patch(&strings(&["1X=1"]), &[]).is_err());
+        assert!(env_patch(&[], &strings(&["A-B"])).is_err());
+    }
 }
@@ -0,0 +1,86 @@
+//! `wk exec <node> <program> [argv…]` — run a program from a node's own
+//! filesystem against that live filesystem, the way `docker exec` runs one
+//! in a container. It is what a node's guest gets from `wk:exec`, offered to
+//! the CLI: debug a container node with the tools its image ships, without
+//! rebuilding it.
+//!
+//! The arguments after the program are its argv *in full*, as `wk:exec`
+//! takes it, so a multicall binary is told what to be:
+//! `wk exec web /bin/coreutils.wasm ls -l /data`. With none, argv is just the
+//! program's path.
+//!
+//! Plain, the program has no stdin and its stdout and stderr stream to ours;
+//! `wk exec` exits with its status. With `-it` it gets a terminal of its own,
+//! driven like `wk attach` drives a node's.
+
+use std::io::{BufReader, Write};
+use std::path::Path;
+
+use wk_protocol::ipc::{read_msg, write_msg, ClientMsg, ServerMsg};
+
+use crate::attach;
+use crate::cli;
+
+/// `wk exec`: run `program` in `node` and stream it until it exits, then exit
+/// with its status.
+pub fn exec(
+    workspace: &Path,
+    node: &str,
+    program: &str,
+    argv: &[String],
+    env: &[String],
+    tty: bool,
+) -> Result<(), String> {
+    let env = env
+        .iter()
+        .map(|kv| match kv.split_once('=') {
+            Some((k, v)) if !k.is_empty() => Ok((k.to_string(), v.to_string())),
+            _ => Err(format!("{kv}: set a variable as KEY=VALUE")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut stream = cli::connect(workspace)?;
+    stream.require("exec", "`wk exec`")?;
+    let snap = cli::get_snapshot(&mut stream)?;
+    let node = cli::resolve(&snap, node)?.id;
+
+    let msg = ClientMsg::Exec {
+        node,
+        path: program.to_string(),
+        argv: argv.to_vec(),
+        env,
+        tty,
+    };
+    write_msg(&mut stream, &msg).map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    let code = match read_msg::<_, ServerMsg>(&mut reader).map_err(|e| e.to_string())? {
+        Some(ServerMsg::Attached { .. }) if tty => attach::terminal(stream, reader, None)?,
+        Some(ServerMsg::Ok) if !tty => Some(output(&mut reader)?),
+        Some(ServerMsg::Error(e)) => return Err(e),
+        other => return Err(format!("unexpected reply: {other:?}")),
+    };
+    match code {
+        Some(0) => Ok(()),
+        Some(code) => std::process::exit(code),
+        // Detached before it finished (which ends it).
+        None => Ok(()),
+    }
+}
+
+/// Copy a piped exec's output to ours until it exits; its status.
+fn output(reader: &mut BufReader<cli::Conn>) -> Result<i32, String> {
+    let (mut stdout, mut stderr) = (std::io::stdout(), std::io::stderr());
+    loop {
+        match read_msg::<_, ServerMsg>(reader).map_err(|e| e.to_string())? {
+            Some(ServerMsg::Stdout(bytes)) => {
+                let _ = stdout.write_all(&bytes).and_then(|()| stdout.flush());
+            }
+            Some(ServerMsg::Stderr(bytes)) => {
+                let _ = stderr.write_all(&bytes);
+            }
+            Some(ServerMsg::Exited(code)) => return Ok(code),
+            Some(ServerMsg::Error(e)) => return Err(e),
+            Some(_) => {}
+            None => return Err("the server hung up before the program exited".into()),
+        }
+    }
+}
@@ -0,0 +1,408 @@
+//! `wk fs`, `wk cp` and `wk diff` — a running node's filesystem from the CLI,
+//! the way `docker cp`, `docker exec ls` and `docker diff` reach into a
+//! container. Paths are the node's own view: its image layers, mounted
+//! volumes and served (provider) subtrees alike.
+//!
+//! `wk cp` streams trees as tar archives both ways, with `docker cp`'s naming:
+//! copying onto an existing directory puts the source inside it, anything
+//! else copies it *as* the destination, and `-` streams the archive on
+//! stdout (or reads one from stdin) instead.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use wk_protocol::ipc::{read_msg, write_msg_as, ClientMsg, ServerMsg, FS_CHUNK};
+use wk_protocol::NodeId;
+
+use crate::cli::{self, Conn};
+
+/// Connect and resolve `node`, checking the server serves filesystems.
+fn open(workspace: &Path, node: &str) -> Result<(Conn, NodeId), String> {
+    let mut stream = cli::connect(workspace)?;
+    stream.require("fs", "node filesystem access")?;
+    let snap = cli::get_snapshot(&mut stream)?;
+    let id = cli::resolve(&snap, node)?.id;
+    Ok((stream, id))
+}
+
+/// Send one `Fs` request and read its reply; a refusal is the error.
+fn ask(stream: &mut Conn, msg: ClientMsg) -> Result<ServerMsg, String> {
+    let bytes = stream.byte_encoding();
+    write_msg_as(stream, &msg, bytes).map_err(|e| e.to_string())?;
+    let mut r = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    match read_msg::<_, ServerMsg>(&mut r).map_err(|e| e.to_string())? {
+        Some(ServerMsg::Error(e)) => Err(e),
+        Some(reply) => Ok(reply),
+        None => Err("the server hung up".into()),
+    }
+}
+
+/// Expect [`ServerMsg::Ok`].
+fn done(reply: ServerMsg) -> Result<(), String> {
+    match reply {
+        ServerMsg::Ok => Ok(()),
+        other => Err(format!("unexpected reply: {other:?}")),
+    }
+}
+
+/// Expect [`ServerMsg::FsData`].
+fn data(reply: ServerMsg) -> Result<Vec<u8>, String> {
+    match reply {
+        ServerMsg::FsData(bytes) => Ok(bytes),
+        other => Err(format!("unexpected reply: {other:?}")),
+    }
+}
+
+/// `wk diff <node>`: what the node has added (`A`), changed (`C`) and
+/// deleted (`D`) relative to its image, one path per line as `docker diff`
+/// prints them.
+pub fn diff(workspace: &Path, node: &str) -> Result<(), String> {
+    let mut stream = cli::connect(workspace)?;
+    stream.require("diff", "`wk diff`")?;
+    let snap = cli::get_snapshot(&mut stream)?;
+    let node = cli::resolve(&snap, node)?.id;
+    let changes = match ask(&mut stream, ClientMsg::Diff { node })? {
+        ServerMsg::Changes(list) => list,
+        other => return Err(format!("unexpected reply: {other:?}")),
+    };
+    for c in changes {
+        println!("{} {}", c.kind.letter(), c.path);
+    }
+    Ok(())
+}
+
+/// `wk fs ls <node> [path]`: list a directory, directories first, each with
+/// its size.
+pub fn ls(workspace: &Path, node: &str, path: &str) -> Result<(), String> {
+    let (mut stream, node) = open(workspace, node)?;
+    let path = path.to_string();
+    let entries = match ask(&mut stream, ClientMsg::FsList { node, path })? {
+        ServerMsg::FsEntries(list) => list,
+        other => return Err(format!("unexpected reply: {other:?}")),
+    };
+    for e in entries {
+        let slash = if e.dir { "/" } else { "" };
+        println!("{:>10}  {}{slash}", e.size, e.name);
+    }
+    Ok(())
+}
+
+/// `wk fs cat <node> <path>`: print a file.
+pub fn cat(workspace: &Path, node: &str, path: &str) -> Result<(), String> {
+    let (mut stream, node) = open(workspace, node)?;
+    let path = path.to_string();
+    let bytes = data(ask(&mut stream, ClientMsg::FsRead { node, path })?)?;
+    let mut stdout = std::io::stdout();
+    stdout
+        .write_all(&bytes)
+        .and_then(|()| stdout.flush())
+        .map_err(|e| e.to_string())
+}
+
+/// `wk fs put <node> <path> [local]`: write a file from `local`, or stdin.
+pub fn put(workspace: &Path, node: &str, path: &str, local: Option<&Path>) -> Result<(), String> {
+    let data = match local {
+        Some(local) => {
+            std::fs::read(local).map_err(|e| format!("can't read {}: {e}", local.display()))?
+        }
+        None => {
+            let mut buf = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut buf)
+                .map_err(|e| e.to_string())?;
+            buf
+        }
+    };
+    let (mut stream, node) = open(workspace, node)?;
+    let path = path.to_string();
+    done(ask(&mut stream, ClientMsg::FsWrite { node, path, data })?)
+}
+
+/// `wk fs mkdir <node> <path>`: make a directory and its parents.
+pub fn mkdir(workspace: &Path, node: &str, path: &str) -> Result<(), String> {
+    let (mut stream, node) = open(workspace, node)?;
+    let path = path.to_string();
+    done(ask(&mut stream, ClientMsg::FsMkdir { node, path })?)
+}
+
+/// `wk fs rm <node> <path>`: remove a file, or a directory and its contents.
+pub fn rm(workspace: &Path, node: &str, path: &str) -> Result<(), String> {
+    let (mut stream, node) = open(workspace, node)?;
+    let path = path.to_string();
+    done(ask(&mut stream, ClientMsg::FsRemove { node, path })?)
+}
+
+/// One side of a `wk cp`: `<node>:<path>` in a node, or a local path (`-` for
+/// a tar stream on stdin/stdout).
+enum Side<'a> {
+    Node(&'a str, &'a str),
+    Local(&'a str),
+}
+
+impl<'a> Side<'a> {
+    /// A `<node>:` prefix makes it a node path; a local path containing a
+    /// colon can be written `./a:b`.
+    fn parse(arg: &'a str) -> Side<'a> {
+        match arg.split_once(':') {
+            Some((node, path)) if !node.is_empty() && !node.contains('/') => {
+                Side::Node(node, if path.is_empty() { "/" } else { path })
+            }
+            _ => Side::Local(arg),
+        }
+    }
+}
+
+/// `wk cp <src> <dest>`: copy between a node and the local machine, one side
+/// each.
+pub fn cp(workspace: &Path, src: &str, dest: &str) -> Result<(), String> {
+    match (Side::parse(src), Side::parse(dest)) {
+        (Side::Node(node, path), Side::Local(local)) => cp_out(workspace, node, path, local),
+        (Side::Local(local), Side::Node(node, path)) => cp_in(workspace, local, node, path),
+        (Side::Node(..), Side::Node(..)) => {
+            Err("copy between nodes through a local directory (one side must be local)".into())
+        }
+        (Side::Local(_), Side::Local(_)) => {
+            Err("one side must be a node path, written <node>:<path>".into())
+        }
+    }
+}
+
+/// Node → local: fetch the tree as tar, and stream it or unpack it as it
+/// arrives.
+fn cp_out(workspace: &Path, node: &str, path: &str, local: &str) -> Result<(), String> {
+    let (mut stream, node) = open(workspace, node)?;
+    let msg = ClientMsg::FsArchive {
+        node,
+        path: path.to_string(),
+    };
+    let bytes = stream.byte_encoding();
+    write_msg_as(&mut stream, &msg, bytes).map_err(|e| e.to_string())?;
+    let mut tar = Download {
+        r: BufReader::new(stream.try_clone().map_err(|e| e.to_string())?),
+        chunk: Vec::new(),
+        at: 0,
+        ended: false,
+    };
+    if local == "-" {
+        let mut stdout = std::io::stdout();
+        return std::io::copy(&mut tar, &mut stdout)
+            .and_then(|_| stdout.flush())
+            .map_err(|e| e.to_string());
+    }
+    unpack(tar, path, Path::new(local))
+}
+
+/// The archive a [`ClientMsg::FsArchive`] streams back, read as it arrives.
+/// The server's refusal, up front or part way, is the read's error.
+struct Download<R> {
+    r: R,
+    chunk: Vec<u8>,
+    at: usize,
+    ended: bool,
+}
+
+impl<R: BufRead> Read for Download<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.at == self.chunk.len() {
+            if self.ended {
+                return Ok(0);
+            }
+            match read_msg::<_, ServerMsg>(&mut self.r)? {
+                Some(ServerMsg::FsChunk(chunk)) => (self.chunk, self.at) = (chunk, 0),
+                Some(ServerMsg::FsEnd) => self.ended = true,
+                Some(ServerMsg::Error(e)) => return Err(std::io::Error::other(e)),
+                Some(other) => {
+                    return Err(std::io::Error::other(format!(
+                        "unexpected reply: {other:?}"
+                    )))
+                }
+                None => return Err(std::io::Error::other("the server hung up")),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.at);
+        buf[..n].copy_from_slice(&self.chunk[self.at..self.at + n]);
+        self.at += n;
+        Ok(n)
+    }
+}
+
+/// A writer sending what it's given as the [`ClientMsg::FsChunk`]s of a
+/// [`ClientMsg::FsExtract`], each up to [`FS_CHUNK`] bytes. `flush` sends the
+/// last, short one.
+struct Upload<'a> {
+    stream: &'a mut Conn,
+    buf: Vec<u8>,
+}
+
+impl Write for Upload<'_> {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        let n = data.len().min(FS_CHUNK - self.buf.len());
+        self.buf.extend_from_slice(&data[..n]);
+        if self.buf.len() == FS_CHUNK {
+            self.flush()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let bytes = self.stream.byte_encoding();
+        let chunk = ClientMsg::FsChunk(std::mem::take(&mut self.buf));
+        write_msg_as(self.stream, &chunk, bytes)
+    }
+}
+
+/// Unpack `tar`, the archive of a node's `path`, at `local`. The archive
+/// names its entries from the source's last component (none for the root):
+/// onto an existing directory they land inside it, else the top one is
+/// renamed to `local`.
+fn unpack(tar: impl Read, path: &str, local: &Path) -> Result<(), String> {
+    let root = !path.split('/').any(|c| !c.is_empty() && c != ".");
+    let into = local.is_dir() || (root && std::fs::create_dir_all(local).is_ok());
+    let mut archive = tar::Archive::new(tar);
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.path().map_err(|e| e.to_string())?.into_owned();
+        if name
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return Err(format!("{}: unsafe path in archive", name.display()));
+        }
+        let rest: PathBuf = name.components().skip(1).collect();
+        let rel = if into { name.as_path() } else { rest.as_path() };
+        if through_link(local, rel) {
+            return Err(format!(
+                "{}: the archive reaches through a link",
+                name.display()
+            ));
+        }
+        let target = match (into, rest.as_os_str().is_empty()) {
+            (true, _) => local.join(&name),
+            (false, true) => local.to_path_buf(),
+            (false, false) => local.join(&rest),
+        };
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        entry
+            .unpack(&target)
+            .map_err(|e| format!("{}: {e}", target.display()))?;
+    }
+    Ok(())
+}
+
+/// Whether a directory on the way from `base` to `rel` is a link: one an
+/// earlier entry of the archive made could point anywhere, and nothing is
+/// written through it.
+fn through_link(base: &Path, rel: &Path) -> bool {
+    let mut dir = base.to_path_buf();
+    for c in rel.parent().into_iter().flat_map(Path::components) {
+        dir.push(c);
+        match std::fs::symlink_metadata(&dir) {
+            Ok(m) if m.file_type().is_symlink() => return true,
+            Ok(_) => {}
+            Err(_) => return false,
+        }
+    }
+    false
+}
+
+/// Local → node: archive the local path (or pass on stdin's archive) to the
+/// node, which unpacks it as the chunks arrive.
+fn cp_in(workspace: &Path, local: &str, node: &str, path: &str) -> Result<(), String> {
+    let (mut stream, node) = open(workspace, node)?;
+    let stat = ask(
+        &mut stream,
+        ClientMsg::FsStat {
+            node,
+            path: path.to_string(),
+        },
+    );
+    let onto_dir = matches!(stat, Ok(ServerMsg::FsEntry(ref e)) if e.dir);
+    // A piped archive unpacks into the destination as it is.
+    let (dir, source) = if local == "-" {
+        (path.to_string(), None)
+    } else {
+        let source = Path::new(local);
+        let (dir, name) = if onto_dir {
+            let name = source
+                .file_name()
+                .ok_or_else(|| format!("{local}: name a file or directory to copy"))?;
+            (path.to_string(), PathBuf::from(name))
+        } else {
+            let trimmed = path.trim_end_matches('/');
+            let (parent, name) = trimmed.rsplit_once('/').unwrap_or(("", trimmed));
+            if name.is_empty() {
+                return Err(format!("{path}: name where to copy to"));
+            }
+            let parent = if parent.is_empty() { "/" } else { parent };
+            (parent.to_string(), PathBuf::from(name))
+        };
+        (dir, Some((source, name)))
+    };
+    done(ask(&mut stream, ClientMsg::FsExtract { node, path: dir })?)?;
+    let mut up = Upload {
+        stream: &mut stream,
+        buf: Vec::new(),
+    };
+    match source {
+        None => std::io::copy(&mut std::io::stdin().lock(), &mut up)
+            .map(|_| ())
+            .map_err(|e| e.to_string())?,
+        Some((source, name)) => {
+            pack(source, &name, &mut up)?;
+        }
+    }
+    up.flush().map_err(|e| e.to_string())?;
+    done(ask(&mut stream, ClientMsg::FsEnd)?)
+}
+
+/// Write `source` (a file or a directory tree) to `out` as a tar archive, its
+/// top entry named `name`, and hand `out` back.
+fn pack<W: Write>(source: &Path, name: &Path, out: W) -> Result<W, String> {
+    let mut tar = tar::Builder::new(out);
+    // Copy what links point at: a node's tree gets the content.
+    tar.follow_symlinks(true);
+    let meta = std::fs::metadata(source).map_err(|e| format!("{}: {e}", source.display()))?;
+    let added = if meta.is_dir() {
+        tar.append_dir_all(name, source)
+    } else {
+        tar.append_path_with_name(source, name)
+    };
+    added
+        .and_then(|()| tar.into_inner())
+        .map_err(|e| format!("{}: {e}", source.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An archive that plants a link and then writes beneath it is refused
+    /// before anything lands where the link points.
+    #[test]
+    fn unpacking_never_writes_through_a_link() {
+        let base = std::env::temp_dir().join(format!("wk-cp-test-{}", std::process::id()));
+        let (outside, dest) = (base.join("outside"), base.join("dest"));
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::create_dir_all(&dest).unwrap();
+        let mut b = tar::Builder::new(Vec::new());
+        let mut h = tar::Header::new_gnu();
+        h.set_entry_type(tar::EntryType::Symlink);
+        h.set_size(0);
+        b.append_link(&mut h, "app/escape", &outside).unwrap();
+        let mut h = tar::Header::new_gnu();
+        h.set_size(1);
+        h.set_mode(0o644);
+        b.append_data(&mut h, "app/escape/planted", &b"x"[..])
+            .unwrap();
+
+        let err = unpack(b.into_inner().unwrap().as_slice(), "/app", &dest).unwrap_err();
+        assert!(err.contains("through a link"), "{err}");
+        assert!(!outside.join("planted").exists());
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -14,6 +14,10 @@ use std::path::{Path, PathBuf};
 
 mod attach;
 mod cli;
+mod exec;
+mod fs;
+mod mcp;
+mod stats;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -30,6 +34,19 @@ struct Cli {
         default_value = workspace::DEFAULT_WORKSPACE
     )]
     file: PathBuf,
+    /// Drive a server started with `wk run --listen` on another machine
+    /// (`host:port`) instead of the local workspace's socket
+    #[arg(long, global = true, value_name = "HOST:PORT", requires_all = ["token", "ca"])]
+    remote: Option<String>,
+    /// The token to bear (hex, e.g. from `wk token mint` on the server's
+    /// workspace): presented to `--remote`, or over the local socket in place
+    /// of its admin authority
+    #[arg(long, global = true, value_name = "HEX")]
+    token: Option<String>,
+    /// The server's certificate (or the CA that issued it) to verify
+    /// `--remote` against, as PEM
+    #[arg(long, global = true, value_name = "PEM", requires = "remote")]
+    ca: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -51,12 +68,25 @@ enum Commands {
         target: Option<String>,
     },
 
+    /// Check the workspace's lock (`<file>.lock`) against what its
+    /// dependencies resolve to, or rewrite it with --update
+    Lock {
+        /// Pull and build every dependency, then pin what each resolved to
+        /// (manifest digest, wasm sha256, image id)
+        #[arg(long)]
+        update: bool,
+    },
+
     /// Publish a plugin to an OCI registry as a Wasm OCI Artifact
     Publish {
         /// Dependency name or local `.wasm` path
         plugin: String,
         /// Target OCI reference, e.g. localhost:5000/triangle:1.0
         reference: String,
+        /// Sign the component with the ed25519 key in this file (created if
+        /// missing) and attach the signature, for `trusted-key` dependencies
+        #[arg(long, value_name = "KEYFILE")]
+        sign: Option<PathBuf>,
     },
 
     /// List the project's dependencies
@@ -127,6 +157,28 @@ enum Commands {
         node: String,
     },
 
+    /// Run a program from a node's filesystem against it (like `docker
+    /// exec`): `wk exec web /bin/coreutils.wasm ls -l /data`. Exits with the
+    /// program's status
+    Exec {
+        /// Give the program a terminal and connect ours to it (together, `-it`)
+        #[arg(short, long)]
+        interactive: bool,
+        /// See `-i`: the program's stdin is its terminal, so the two go together
+        #[arg(short, long)]
+        tty: bool,
+        /// Set a variable over the node's environment (repeatable)
+        #[arg(short, long = "env", value_name = "KEY=VALUE")]
+        env: Vec<String>,
+        /// Node reference: its name, or any part of its id
+        node: String,
+        /// Path of the wasm program in the node's filesystem
+        program: String,
+        /// The program's argv in full, argv[0] included (default: the path)
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        argv: Vec<String>,
+    },
+
     /// Show a node's output log (like `docker logs`)
     Logs {
         /// Node reference: its name, or any part of its id
@@ -136,6 +188,43 @@ enum Commands {
         follow: bool,
     },
 
+    /// Browse and change a running node's filesystem
+    Fs {
+        #[command(subcommand)]
+        cmd: FsCmd,
+    },
+
+    /// Copy files between a node and the local machine (like `docker cp`):
+    /// `wk cp vim:/data ./data`, `wk cp ./notes.txt vim:/data/`. A `-` side
+    /// streams a tar archive on stdout or stdin instead
+    Cp {
+        /// Source: `<node>:<path>` or a local path
+        src: String,
+        /// Destination: `<node>:<path>` or a local path
+        dest: String,
+    },
+
+    /// List what a node has added (A), changed (C) and deleted (D) in its
+    /// filesystem relative to its image (like `docker diff`)
+    Diff {
+        /// Node reference: its name, or any part of its id
+        node: String,
+    },
+
+    /// Show each app node's live resource use — memory, files, network,
+    /// MIDI, frames, exec'd programs — refreshed every second (like `docker
+    /// stats`)
+    #[command(visible_alias = "top")]
+    Stats {
+        /// Print the table once and exit
+        #[arg(long)]
+        no_stream: bool,
+    },
+
+    /// Stream a running workspace's changes as JSON lines (nodes created,
+    /// deleted, started, exited; wires; tokens; errors) until Ctrl-C
+    Events,
+
     /// Show a node's or image's full detail as JSON (like `docker inspect`)
     Inspect {
         /// A node reference (name / id part) or an image id in the local store
@@ -148,12 +237,51 @@ enum Commands {
     /// Restart a node (stop, then start)
     Restart { node: String },
 
+    /// Block until a node's guest exits, then print its exit status and exit
+    /// with it (like `docker wait`); a trap or a stop fails
+    Wait { node: String },
+
+    /// Save what a node has written to its filesystem as a new image in the
+    /// local store (like `docker commit`); the node must run an image
+    Commit {
+        /// Node reference: its name, or any part of its id
+        node: String,
+        /// Tag for the new image, e.g. `py-with-numpy` (`:latest` if untagged)
+        tag: String,
+    },
+
     /// Stop every running node in the workspace
     Down,
 
     /// Start every idle runnable node in the workspace
     Up,
 
+    /// Undo the running workspace's last change (like Cmd/Ctrl+Z)
+    Undo,
+
+    /// Re-apply the last undone change (like Shift+Cmd/Ctrl+Z)
+    Redo,
+
+    /// List the changes `wk undo` and `wk redo` would reverse
+    History,
+
+    /// Serve the running workspace to an agent as a Model Context Protocol
+    /// server on stdio, authorized by `--token` (required)
+    Mcp,
+
+    /// Store credentials for an OCI registry, used by every pull and push
+    /// from it (a `docker login` is picked up without this)
+    Login {
+        /// Registry host, e.g. ghcr.io or localhost:5001
+        registry: String,
+        /// User name (prompted for if omitted)
+        #[arg(short, long)]
+        username: Option<String>,
+        /// Read the password or token from stdin instead of prompting
+        #[arg(long)]
+        password_stdin: bool,
+    },
+
     /// Manage wk's local OCI image store
     Images {
         #[command(subcommand)]
@@ -182,6 +310,23 @@ enum Commands {
         /// alive, and exit on Ctrl-C. No rendering or OS input.
         #[arg(long)]
         headless: bool,
+        /// Also serve the API over TCP + TLS on this address (e.g.
+        /// `0.0.0.0:7070`) for `wk --remote`. Each client presents a token;
+        /// mint one with `wk token mint`
+        #[arg(long, value_name = "ADDR")]
+        listen: Option<String>,
+        /// PEM certificate for `--listen` (default: a self-signed one beside
+        /// the workspace, `<file>.cert.pem`, generated on first use)
+        #[arg(long, value_name = "PEM", requires_all = ["listen", "tls_key"])]
+        tls_cert: Option<PathBuf>,
+        /// PEM private key for `--tls-cert`
+        #[arg(long, value_name = "PEM", requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+        /// Also serve the API over WebSocket on this address, or on this port
+        /// of 127.0.0.1 (e.g. `7071`), for browser tooling. Each connection
+        /// presents a token as `Authorization: Bearer <hex>` or `?token=<hex>`
+        #[arg(long, value_name = "ADDR|PORT")]
+        ws: Option<String>,
     },
 }
 
@@ -202,7 +347,7 @@ enum NodeCmd {
     /// (Re)start an idle/exited node's guest
     Start { node: String },
     /// Reconfigure a node: launch args, a BindMount's host path, a Volume's
-    /// persistence, or a HostPort's localhost port
+    /// persistence, a HostPort's localhost port, or an app's restart policy
     Set {
         node: String,
         /// The full argument string (quote it)
@@ -217,6 +362,36 @@ enum NodeCmd {
         /// For a HostPort: set its localhost port
         #[arg(long)]
         port: Option<u16>,
+        /// For an app: re-run its guest when it exits — no, on-failure or
+        /// always, optionally with a retry limit (on-failure:5)
+        #[arg(long)]
+        restart: Option<wk_protocol::Restart>,
+        /// With --restart: milliseconds before the first re-run (doubling
+        /// with each consecutive one)
+        #[arg(long, requires = "restart")]
+        restart_backoff: Option<u64>,
+        /// For an app: cap its linear memory (e.g. 64M; 0 lifts the limit)
+        #[arg(long, value_parser = wk_protocol::Limits::parse_bytes)]
+        memory: Option<u64>,
+        /// For an app: percent of one core it may keep busy (0 lifts the
+        /// limit)
+        #[arg(long, value_parser = clap::value_parser!(u32).range(0..=100))]
+        cpu: Option<u32>,
+        /// For an app: bytes its filesystem may hold in its own files (e.g.
+        /// 100M; 0 lifts the limit)
+        #[arg(long, value_parser = wk_protocol::Limits::parse_bytes)]
+        fs_bytes: Option<u64>,
+        /// For an app: files and directories its filesystem may hold (0
+        /// lifts the limit)
+        #[arg(long)]
+        fs_nodes: Option<u64>,
+        /// For an app: set a variable in its environment, over its image's
+        /// (repeatable; applies from its next run)
+        #[arg(short, long = "env", value_name = "KEY=VALUE")]
+        env: Vec<String>,
+        /// For an app: remove a variable it set with --env (repeatable)
+        #[arg(long, value_name = "KEY")]
+        unset_env: Vec<String>,
     },
 }
 
@@ -261,6 +436,31 @@ impl CreateKind {
     }
 }
 
+#[derive(Subcommand)]
+enum FsCmd {
+    /// List a directory
+    Ls {
+        /// Node reference: its name, or any part of its id
+        node: String,
+        #[arg(default_value = "/")]
+        path: String,
+    },
+    /// Print a file
+    Cat { node: String, path: String },
+    /// Write a file from a local one, or from stdin
+    Put {
+        node: String,
+        /// Path in the node
+        path: String,
+        /// Local file to upload (default: stdin)
+        local: Option<PathBuf>,
+    },
+    /// Make a directory (and its parents)
+    Mkdir { node: String, path: String },
+    /// Remove a file, or a directory and everything in it
+    Rm { node: String, path: String },
+}
+
 #[derive(Subcommand)]
 enum MidiCmd {
     /// List connected hardware MIDI input devices (their port names)
@@ -302,7 +502,8 @@ enum TokenCmd {
 enum ImagesCmd {
     /// List stored images (tags, id, entrypoint, layers)
     List,
-    /// Remove a stored image by tag or id (layer tars stay; they're shared)
+    /// Remove a stored image by tag or id (layer tars stay; they're shared
+    /// — `wk images prune` reclaims unused ones)
     Rm {
         /// Image reference: a tag (name:tag) or an id / id-prefix
         image: String,
@@ -319,6 +520,40 @@ enum ImagesCmd {
         #[arg(long)]
         network: bool,
     },
+    /// Write a stored image to an OCI image-layout tar (like `docker save`),
+    /// to move it to another machine without a registry
+    Save {
+        /// Image reference: a tag (name:tag) or an id / id-prefix
+        image: String,
+        /// The archive to write
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// Store the images in an OCI image-layout tar (like `docker load`),
+    /// restoring the tags it names
+    Load {
+        /// The archive to read
+        input: PathBuf,
+    },
+    /// Push a stored image — layers, config and manifest — to an OCI
+    /// registry, where `wk pull` (or any OCI tool) fetches it as the same image
+    Push {
+        /// Image reference: a tag (name:tag) or an id / id-prefix
+        image: String,
+        /// Target OCI reference, e.g. localhost:5001/wk-shell:1.0
+        reference: String,
+    },
+    /// Delete images, layers and pulled blobs nothing uses any more: by
+    /// default untagged images and unreferenced layers and blobs
+    Prune {
+        /// Also remove tagged, built and pulled images that no known `.wk`
+        /// file depends on
+        #[arg(long)]
+        all: bool,
+        /// Report what would be removed without removing it
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Name a stored image so it can be referenced as image://<tag>
     Tag {
         /// Existing image: a tag or an id / id-prefix
@@ -328,7 +563,7 @@ enum ImagesCmd {
     },
 }
 
-fn images_cmd(cmd: &ImagesCmd) -> Result<(), String> {
+fn images_cmd(file: &Path, cmd: &ImagesCmd) -> Result<(), String> {
     use wk_server::images;
     match cmd {
         ImagesCmd::List => {
@@ -376,6 +611,51 @@ fn images_cmd(cmd: &ImagesCmd) -> Result<(), String> {
             }
             Ok(())
         }
+        ImagesCmd::Save { image, output } => {
+            let id = wk_server::layout::save(image, output)?;
+            println!("saved {image} ({id}) to {}", output.display());
+            Ok(())
+        }
+        ImagesCmd::Load { input } => {
+            for (id, tag) in wk_server::layout::load(input)? {
+                match tag {
+                    Some(tag) => println!("loaded {tag} ({id})"),
+                    None => println!("loaded {id}"),
+                }
+            }
+            Ok(())
+        }
+        ImagesCmd::Push { image, reference } => {
+            let id = images::resolve_ref(image).ok_or_else(|| format!("no image {image:?}"))?;
+            let manifest = images::load_image(&id).ok_or_else(|| format!("no image {image:?}"))?;
+            println!("pushing {id} ({} layers) ...", manifest.layers.len());
+            wk_server::oci::push_image(reference, &manifest)?;
+            println!("pushed {image} -> oci://{reference}");
+            Ok(())
+        }
+        ImagesCmd::Prune { all, dry_run } => {
+            let pruned = wk_server::prune::prune(&[file.to_path_buf()], *all, *dry_run)?;
+            let verb = if *dry_run { "would delete" } else { "deleted" };
+            for id in &pruned.images {
+                println!("{verb} image {id}");
+            }
+            for reference in &pruned.refs {
+                println!("{verb} oci://{reference}");
+            }
+            for key in &pruned.blobs {
+                println!("{verb} {key}");
+            }
+            for key in &pruned.signatures {
+                println!("{verb} the signatures for {key}");
+            }
+            let verb = if *dry_run {
+                "would reclaim"
+            } else {
+                "reclaimed"
+            };
+            println!("{verb} {}", stats::size(pruned.bytes));
+            Ok(())
+        }
         ImagesCmd::Tag { image, tag } => {
             let id = images::resolve_ref(image).ok_or_else(|| format!("no image {image:?}"))?;
             images::set_tag(tag, &id)?;
@@ -385,19 +665,70 @@ fn images_cmd(cmd: &ImagesCmd) -> Result<(), String> {
     }
 }
 
+/// `wk login`: ask for whatever wasn't given, then store it.
+fn login(registry: &str, username: Option<&str>, password_stdin: bool) -> Result<(), String> {
+    use std::io::{BufRead, IsTerminal, Write};
+    use std::process::Command;
+    let read_line = |prompt: &str, hide: bool| -> Result<String, String> {
+        let tty = std::io::stdin().is_terminal();
+        if tty {
+            eprint!("{prompt}");
+            let _ = std::io::stderr().flush();
+        }
+        // Keep the password off the screen; `stty` is everywhere a terminal is.
+        let hide = hide && tty && Command::new("stty").arg("-echo").status().is_ok();
+        let mut line = String::new();
+        let read = std::io::stdin().lock().read_line(&mut line);
+        if hide {
+            let _ = Command::new("stty").arg("echo").status();
+            eprintln!();
+        }
+        read.map_err(|e| format!("read: {e}"))?;
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    };
+    let username = match username {
+        Some(u) => u.to_string(),
+        None if password_stdin => return Err("--password-stdin needs --username".into()),
+        None => read_line("Username: ", false)?,
+    };
+    let password = read_line("Password: ", !password_stdin)?;
+    if username.is_empty() || password.is_empty() {
+        return Err("a user name and a password are both needed".into());
+    }
+    wk_server::credentials::store(
+        registry,
+        &wk_server::credentials::Login { username, password },
+    )?;
+    println!("logged in to {registry}");
+    Ok(())
+}
+
 fn main() -> Result<(), String> {
     env_logger::init();
 
     let cli = Cli::parse();
 
     let file = &cli.file;
+    if let (Some(addr), Some(token), Some(ca)) = (&cli.remote, &cli.token, &cli.ca) {
+        cli::set_remote(cli::Remote {
+            addr: addr.clone(),
+            token: token.clone(),
+            ca: ca.clone(),
+        });
+    } else if let Some(token) = &cli.token {
+        cli::set_token(token.clone());
+    }
     match &cli.command {
         Some(Commands::Init) => workspace::init(file),
         Some(Commands::Add { target }) => workspace::add(target.clone(), file),
         Some(Commands::Pull { target }) => workspace::pull(target.clone(), file),
-        Some(Commands::Publish { plugin, reference }) => {
-            workspace::publish(plugin.clone(), reference.clone(), file)
-        }
+        Some(Commands::Lock { update: true }) => wk_server::lock::update(file),
+        Some(Commands::Lock { update: false }) => wk_server::lock::check(file),
+        Some(Commands::Publish {
+            plugin,
+            reference,
+            sign,
+        }) => workspace::publish(plugin.clone(), reference.clone(), file, sign.as_deref()),
         Some(Commands::List) => workspace::list(file),
         Some(Commands::Ps) => cli::ps(file),
         Some(Commands::Node { cmd }) => match cmd {
@@ -410,6 +741,14 @@ fn main() -> Result<(), String> {
                 host_path,
                 persist,
                 port,
+                restart,
+                restart_backoff,
+                memory,
+                cpu,
+                fs_bytes,
+                fs_nodes,
+                env,
+                unset_env,
             } => cli::set_node(
                 file,
                 node,
@@ -417,6 +756,18 @@ fn main() -> Result<(), String> {
                 host_path.as_deref(),
                 *persist,
                 *port,
+                restart.map(|r| wk_protocol::Restart {
+                    backoff_ms: restart_backoff.unwrap_or(r.backoff_ms),
+                    ..r
+                }),
+                wk_protocol::Limits {
+                    memory: *memory,
+                    cpu: *cpu,
+                    fs_bytes: *fs_bytes,
+                    fs_nodes: *fs_nodes,
+                },
+                env,
+                unset_env,
             ),
         },
         Some(Commands::Create {
@@ -435,13 +786,55 @@ fn main() -> Result<(), String> {
             container,
         }) => cli::port(file, served, hostport, *container),
         Some(Commands::Attach { node }) => attach::attach(file, node),
+        Some(Commands::Exec {
+            interactive,
+            tty,
+            env,
+            node,
+            program,
+            argv,
+        }) => match (interactive, tty) {
+            (true, false) | (false, true) => {
+                Err("pass -i and -t together (-it): the program's stdin is its terminal".into())
+            }
+            _ => exec::exec(file, node, program, argv, env, *interactive),
+        },
         Some(Commands::Logs { node, follow }) => cli::logs(file, node, *follow),
+        Some(Commands::Fs { cmd }) => match cmd {
+            FsCmd::Ls { node, path } => fs::ls(file, node, path),
+            FsCmd::Cat { node, path } => fs::cat(file, node, path),
+            FsCmd::Put { node, path, local } => fs::put(file, node, path, local.as_deref()),
+            FsCmd::Mkdir { node, path } => fs::mkdir(file, node, path),
+            FsCmd::Rm { node, path } => fs::rm(file, node, path),
+        },
+        Some(Commands::Cp { src, dest }) => fs::cp(file, src, dest),
+        Some(Commands::Diff { node }) => fs::diff(file, node),
+        Some(Commands::Stats { no_stream }) => stats::stats(file, *no_stream),
+        Some(Commands::Events) => cli::events(file),
         Some(Commands::Inspect { target }) => cli::inspect(file, target),
         Some(Commands::Stop { node }) => cli::stop(file, node),
         Some(Commands::Restart { node }) => cli::restart(file, node),
+        Some(Commands::Wait { node }) => cli::wait(file, node),
+        Some(Commands::Commit { node, tag }) => cli::commit(file, node, tag),
         Some(Commands::Down) => cli::down(file),
+        Some(Commands::Undo) => cli::undo(file),
+        Some(Commands::Redo) => cli::redo(file),
+        Some(Commands::History) => cli::history(file),
+        Some(Commands::Mcp) => match cli::token() {
+            Some(_) => mcp::serve(file),
+            None => Err(
+                "`wk mcp` bears a scoped capability: pass --token <hex> (mint one with \
+                 `wk token mint`)"
+                    .into(),
+            ),
+        },
         Some(Commands::Up) => cli::up(file),
-        Some(Commands::Images { cmd }) => images_cmd(cmd),
+        Some(Commands::Login {
+            registry,
+            username,
+            password_stdin,
+        }) => login(registry, username.as_deref(), *password_stdin),
+        Some(Commands::Images { cmd }) => images_cmd(file, cmd),
         Some(Commands::Midi { cmd }) => {
             let MidiCmd::Devices = cmd;
             let devices = wk_server::midihw::input_devices();
@@ -471,7 +864,22 @@ fn main() -> Result<(), String> {
         Some(Commands::Run {
             file: run_file,
             headless,
-        }) => run(run_file.as_deref().unwrap_or(file), *headless),
+            listen,
+            tls_cert,
+            tls_key,
+            ws,
+        }) => {
+            let listen = listen.as_deref().map(|addr| Listen {
+                addr,
+                tls: tls_cert.as_deref().zip(tls_key.as_deref()),
+            });
+            run(
+                run_file.as_deref().unwrap_or(file),
+                *headless,
+                listen,
+                ws.as_deref(),
+            )
+        }
         None => {
             Cli::command().print_help().map_err(|e| e.to_string())?;
             Ok(())
@@ -487,17 +895,100 @@ fn key_path(file: &Path) -> PathBuf {
     PathBuf::from(s)
 }
 
+/// Where `wk run --listen` serves the API, and with which certificate + key
+/// (`None`: the workspace's self-signed pair).
+struct Listen<'a> {
+    addr: &'a str,
+    tls: Option<(&'a Path, &'a Path)>,
+}
+
+/// Where a workspace's self-signed TLS certificate and its key persist, beside
+/// the `.wk` file like the token key (`workspace.wk.cert.pem` / `.cert.key`).
+fn cert_paths(file: &Path) -> (PathBuf, PathBuf) {
+    let with = |ext: &str| {
+        let mut s = file.to_path_buf().into_os_string();
+        s.push(ext);
+        PathBuf::from(s)
+    };
+    (with(".cert.pem"), with(".cert.key"))
+}
+
+/// This machine's host name, for the self-signed certificate's names.
+fn host_name() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer outlives the call and its length is passed.
+    let rc = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+    if rc != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Some(String::from_utf8_lossy(&buf[..len]).into_owned()).filter(|h| !h.is_empty())
+}
+
+/// Start the networked API listener for `wk run --listen`. Without an explicit
+/// certificate, one is generated (once) for `localhost`, this host's name and
+/// the listen address, and its path printed: clients pass it as `--ca`.
+fn listen_tcp(
+    runtime: &ServerRuntime,
+    file: &Path,
+    listen: Listen,
+) -> Result<wk_api::tcp::TcpServer, String> {
+    let (cert, key) = match listen.tls {
+        Some((cert, key)) => (cert.to_path_buf(), key.to_path_buf()),
+        None => {
+            let (cert, key) = cert_paths(file);
+            let mut names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+            names.extend(host_name());
+            if let Some((host, _)) = listen.addr.rsplit_once(':') {
+                let host = host.trim_start_matches('[').trim_end_matches(']');
+                let unspecified = host
+                    .parse::<std::net::IpAddr>()
+                    .is_ok_and(|ip| ip.is_unspecified());
+                if !host.is_empty() && !unspecified && !names.iter().any(|n| n == host) {
+                    names.push(host.to_string());
+                }
+            }
+            wk_api::tcp::ensure_self_signed(&cert, &key, names)?;
+            (cert, key)
+        }
+    };
+    let tls = wk_api::tcp::server_config(&cert, &key)?;
+    // A token-less handle: each connection is served with the token it presents.
+    let server = wk_api::tcp::TcpServer::start(runtime.handle(), listen.addr, tls)
+        .map_err(|e| format!("can't listen on {}: {e}", listen.addr))?;
+    eprintln!(
+        "wk: remote API on {} (certificate {}; clients need a token from `wk token mint`)",
+        server.local_addr(),
+        cert.display()
+    );
+    Ok(server)
+}
+
 /// Open the given `.wk` workspace. The server runs independently on its own
 /// thread; a windowed run attaches the local UI client, a headless run attaches
-/// none and just keeps the server alive until Ctrl-C.
-fn run(file: &Path, headless: bool) -> Result<(), String> {
+/// none and just keeps the server alive until Ctrl-C. With `listen`, the API is
+/// also served over the network; with `ws`, over WebSocket.
+fn run(
+    file: &Path,
+    headless: bool,
+    listen: Option<Listen>,
+    ws: Option<&str>,
+) -> Result<(), String> {
     // Resolve `import`s into one merged document to run (the CLI edit commands
     // use the raw single-file `load` instead).
     let doc = workspace::Document::load_resolved(file)?;
+    wk_server::prune::remember(file);
     // Pull any OCI-artifact dependencies into the local cache before launching.
+    // A locked workspace starts exactly what its lock pins, or not at all; a
+    // dependency with a trusted key runs signed, or not at all.
+    let lock = wk_server::lock::Lock::load(file)?;
     for dep in &doc.dependencies {
-        if let Err(e) = dep.ensure() {
-            eprintln!("warning: dependency {:?} unavailable: {e}", dep.name);
+        match dep.ensure(lock.as_ref()) {
+            Err(e) if lock.is_some() || dep.trusted_key.is_some() => {
+                return Err(format!("dependency {:?}: {e}", dep.name))
+            }
+            Err(e) => eprintln!("warning: dependency {:?} unavailable: {e}", dep.name),
+            Ok(()) => {}
         }
     }
     // Three-way auth split, wired up locally:
@@ -541,6 +1032,20 @@ fn run(file: &Path, headless: bool) -> Result<(), String> {
             None
         }
     };
+    let _tcp = listen.map(|l| listen_tcp(&runtime, file, l)).transpose()?;
+    // Like the TCP listener, token-less: each upgrade presents its own.
+    let _ws = match ws {
+        Some(addr) => {
+            let server = wk_api::ws::WsServer::start(runtime.handle(), addr)
+                .map_err(|e| format!("can't listen on {addr}: {e}"))?;
+            eprintln!(
+                "wk: WebSocket API on ws://{} (clients need a token from `wk token mint`)",
+                server.local_addr()
+            );
+            Some(server)
+        }
+        None => None,
+    };
     if headless {
         // No client attached; run the server until Ctrl-C, then save + stop.
         runtime.block_until_ctrl_c();
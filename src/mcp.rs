@@ -0,0 +1,469 @@
+//! `wk mcp` — the running workspace as a [Model Context Protocol] server on
+//! stdio, so an agent can drive it through a scoped capability rather than the
+//! admin socket.
+//!
+//! MCP is JSON-RPC 2.0, one message per line: the agent's host starts
+//! `wk --token <hex> mcp` and writes requests to its stdin; replies come back
+//! on stdout (which therefore carries nothing else — diagnostics go to
+//! stderr). The bridge answers `initialize`, `ping`, `tools/list` and
+//! `tools/call`, and offers one tool per thing an agent needs to do: read the
+//! snapshot, create and wire nodes, run and stop them, type into a terminal
+//! node and read what it printed, read logs, and inspect tokens.
+//!
+//! Each tool call is ordinary client-protocol traffic on a fresh connection
+//! (see [`cli::connect`]) — a snapshot read, a [`Command`], an attach or a log
+//! read — bearing the token the bridge was started with. The server authorizes
+//! every one of them against that token, so the agent can do exactly what the
+//! token grants; a refusal comes back as a tool error the agent can read, not
+//! a protocol failure.
+//!
+//! [Model Context Protocol]: https://modelcontextprotocol.io
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use wk_protocol::ipc::{read_msg, write_msg, write_msg_as, ClientMsg, ServerMsg};
+use wk_protocol::{Command, Resource, Wire};
+
+use crate::cli;
+
+/// The MCP revisions this bridge speaks, newest first. A client asking for
+/// one of these gets it; anything else is offered the newest.
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// JSON-RPC error codes.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// How much of a log (from its end) `logs` returns, so a chatty node can't
+/// flood the agent's context.
+const LOG_CAP: usize = 64 * 1024;
+
+/// `send_input`'s default and longest wait for a terminal to go quiet.
+const QUIET: Duration = Duration::from_millis(500);
+const QUIET_MAX: Duration = Duration::from_secs(10);
+
+/// A tool: its name and description, the JSON Schema of its arguments, and
+/// what it does — its text result, or an error for the agent.
+struct Tool {
+    name: &'static str,
+    description: &'static str,
+    schema: fn() -> Value,
+    run: fn(&Path, &Value) -> Result<String, String>,
+}
+
+/// Every tool the bridge offers, in `tools/list` order.
+const TOOLS: &[Tool] = &[
+    Tool {
+        name: "snapshot",
+        description: "The running workspace as JSON: its workspaces (tabs), \
+            nodes (id, kind, name, status, args), wires, and the dependencies \
+            an app can be created from.",
+        schema: || json!({ "type": "object", "properties": {} }),
+        run: snapshot,
+    },
+    Tool {
+        name: "create",
+        description: "Create a node. kind \"app\" launches one of the \
+            workspace's dependencies (see the snapshot's `available`) with \
+            optional args; any other kind is a resource node, whose `value` \
+            seeds its config (a bind's host path, a port number, a note's text).",
+        schema: || {
+            let mut kinds = vec!["app".to_string()];
+            kinds.extend(
+                crate::CreateKind::value_variants()
+                    .iter()
+                    .filter_map(|k| k.to_possible_value().map(|v| v.get_name().to_string())),
+            );
+            json!({
+                "type": "object",
+                "properties": {
+                    "kind": { "type": "string", "enum": kinds },
+                    "dependency": {
+                        "type": "string",
+                        "description": "For an app: the dependency to launch."
+                    },
+                    "args": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "For an app: its launch arguments."
+                    },
+                    "value": { "type": "string" },
+                    "persist": {
+                        "type": "boolean",
+                        "description": "For a volume: keep its contents."
+                    }
+                },
+                "required": ["kind"]
+            })
+        },
+        run: create,
+    },
+    Tool {
+        name: "wire",
+        description: "Connect two nodes; the kind of wire (mount, MIDI, serve, \
+            network, capture, api) is inferred from the nodes' kinds.",
+        schema: two_nodes,
+        run: wire,
+    },
+    Tool {
+        name: "run",
+        description: "Start an idle or exited node's guest.",
+        schema: one_node,
+        run: run_node,
+    },
+    Tool {
+        name: "stop",
+        description: "Stop a running node's guest (it stays placed, and can \
+            be run again).",
+        schema: one_node,
+        run: stop_node,
+    },
+    Tool {
+        name: "send_input",
+        description: "Attach to a terminal node, type `input` into it \
+            (include \"\\n\" to submit a line), and return what it prints \
+            until its output goes quiet. The node keeps running.",
+        schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "node": { "type": "string", "description": NODE_REF },
+                    "input": { "type": "string" },
+                    "quiet_ms": {
+                        "type": "integer",
+                        "description": "How long the output must be quiet \
+                            before returning (default 500, at most 10000)."
+                    }
+                },
+                "required": ["node", "input"]
+            })
+        },
+        run: send_input,
+    },
+    Tool {
+        name: "logs",
+        description: "A node's output log so far (its last 64 KiB).",
+        schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "node": { "type": "string", "description": NODE_REF },
+                    "tail": {
+                        "type": "integer",
+                        "description": "Only the last this many lines."
+                    }
+                },
+                "required": ["node"]
+            })
+        },
+        run: logs,
+    },
+    Tool {
+        name: "token",
+        description: "Show a capability token's Datalog, block by block: a \
+            node's, or without `node`, the one this bridge bears (what every \
+            call here is authorized against).",
+        schema: || {
+            json!({
+                "type": "object",
+                "properties": { "node": { "type": "string", "description": NODE_REF } }
+            })
+        },
+        run: token,
+    },
+];
+
+/// How a tool's `node` argument is matched, for the schemas.
+const NODE_REF: &str = "A node's name, or any part of its id.";
+
+fn one_node() -> Value {
+    json!({
+        "type": "object",
+        "properties": { "node": { "type": "string", "description": NODE_REF } },
+        "required": ["node"]
+    })
+}
+
+fn two_nodes() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "a": { "type": "string", "description": NODE_REF },
+            "b": { "type": "string", "description": NODE_REF }
+        },
+        "required": ["a", "b"]
+    })
+}
+
+/// `wk mcp`: answer MCP requests on stdin until it closes.
+pub fn serve(workspace: &Path) -> Result<(), String> {
+    let mut stdout = io::stdout();
+    for line in io::stdin().lock().lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<Value>(&line) {
+            Ok(msg) => handle(workspace, &msg),
+            Err(e) => Some(failure(
+                Value::Null,
+                PARSE_ERROR,
+                format!("parse error: {e}"),
+            )),
+        };
+        if let Some(reply) = reply {
+            writeln!(stdout, "{reply}")
+                .and_then(|_| stdout.flush())
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// The reply to one JSON-RPC message, or `None` for a notification (no `id`),
+/// which gets none.
+fn handle(workspace: &Path, msg: &Value) -> Option<Value> {
+    let id = msg.get("id")?.clone();
+    let method = msg.get("method").and_then(Value::as_str).unwrap_or("");
+    let params = msg.get("params").cloned().unwrap_or_else(|| json!({}));
+    let result = match method {
+        "initialize" => Ok(initialize(&params)),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({
+            "tools": TOOLS
+                .iter()
+                .map(|t| json!({
+                    "name": t.name,
+                    "description": t.description,
+                    "inputSchema": (t.schema)(),
+                }))
+                .collect::<Vec<_>>()
+        })),
+        "tools/call" => call(workspace, &params),
+        _ => Err((METHOD_NOT_FOUND, format!("no method {method:?}"))),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => failure(id, code, message),
+    })
+}
+
+/// A JSON-RPC error reply.
+fn failure(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// The `initialize` result: the protocol revision, and that we offer tools.
+fn initialize(params: &Value) -> Value {
+    let asked = params.get("protocolVersion").and_then(Value::as_str);
+    let version = PROTOCOL_VERSIONS
+        .iter()
+        .find(|v| Some(**v) == asked)
+        .unwrap_or(&PROTOCOL_VERSIONS[0]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": { "name": "wk", "version": env!("CARGO_PKG_VERSION") },
+        "instructions": "Drive a running wk workspace. Start with `snapshot` \
+            to see its nodes; tools take a node by name or any part of its id. \
+            Everything is authorized by the bridge's token (see `token`).",
+    })
+}
+
+/// Run a `tools/call`. An unknown tool is a protocol error; the tool's own
+/// failure (a refusal, a bad node reference) is a result flagged `isError`.
+fn call(workspace: &Path, params: &Value) -> Result<Value, (i64, String)> {
+    let name = params
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or((INVALID_PARAMS, "tools/call needs a tool name".to_string()))?;
+    let tool = TOOLS
+        .iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| (INVALID_PARAMS, format!("no tool {name:?}")))?;
+    let args = params
+        .get("arguments")
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+    let (text, is_error) = match (tool.run)(workspace, &args) {
+        Ok(text) => (text, false),
+        Err(e) => (e, true),
+    };
+    Ok(json!({ "content": [{ "type": "text", "text": text }], "isError": is_error }))
+}
+
+/// A required string argument.
+fn arg<'a>(args: &'a Value, key: &str) -> Result<&'a str, String> {
+    args.get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("missing string argument {key:?}"))
+}
+
+/// Resolve the node reference in argument `key` on an open connection.
+fn node_arg(
+    stream: &mut cli::Conn,
+    args: &Value,
+    key: &str,
+) -> Result<wk_protocol::NodeId, String> {
+    let snap = cli::get_snapshot(stream)?;
+    cli::resolve(&snap, arg(args, key)?).map(|n| n.id)
+}
+
+fn snapshot(workspace: &Path, _: &Value) -> Result<String, String> {
+    let snap = cli::get_snapshot(&mut cli::connect(workspace)?)?;
+    serde_json::to_string_pretty(&snap).map_err(|e| e.to_string())
+}
+
+fn create(workspace: &Path, args: &Value) -> Result<String, String> {
+    let mut stream = cli::connect(workspace)?;
+    let kind = arg(args, "kind")?;
+    let (id, label) = if kind == "app" {
+        let dep = arg(args, "dependency")?;
+        let argv: Vec<String> = match args.get("args") {
+            Some(v) => serde_json::from_value(v.clone())
+                .map_err(|_| "\"args\" must be an array of strings".to_string())?,
+            None => Vec::new(),
+        };
+        (cli::add_on(&mut stream, dep, &argv)?, dep)
+    } else {
+        let kind = crate::CreateKind::from_str(kind, true)
+            .map_err(|_| format!("no node kind {kind:?}"))?
+            .node_kind();
+        let value = args.get("value").and_then(Value::as_str);
+        let persist = args.get("persist").and_then(Value::as_bool) == Some(true);
+        let id = cli::create_on(&mut stream, kind, value, persist)?;
+        (id, cli::kind_label(kind))
+    };
+    Ok(format!("created {id} ({label})"))
+}
+
+fn wire(workspace: &Path, args: &Value) -> Result<String, String> {
+    let mut stream = cli::connect(workspace)?;
+    let (a, b) = (
+        node_arg(&mut stream, args, "a")?,
+        node_arg(&mut stream, args, "b")?,
+    );
+    let made = cli::send_create(&mut stream, Command::Create(Resource::Wire { a, b }))?;
+    let kind = match made.wire {
+        Some(Wire::Bind(..)) => "bind",
+        Some(Wire::Midi(..)) => "midi",
+        Some(Wire::Serve(..)) => "serve",
+        Some(Wire::Net(..)) => "net",
+        Some(Wire::Capture(..)) => "capture",
+        Some(Wire::Api(..)) => "api",
+        None => return Err(format!("{a} and {b} can't be wired to each other")),
+    };
+    Ok(format!("wired {a} <-> {b} ({kind})"))
+}
+
+fn run_node(workspace: &Path, args: &Value) -> Result<String, String> {
+    let mut stream = cli::connect(workspace)?;
+    let id = node_arg(&mut stream, args, "node")?;
+    cli::send_command(&mut stream, Command::Run(id))?;
+    Ok(format!("started {id}"))
+}
+
+fn stop_node(workspace: &Path, args: &Value) -> Result<String, String> {
+    let mut stream = cli::connect(workspace)?;
+    let id = node_arg(&mut stream, args, "node")?;
+    cli::send_command(&mut stream, Command::Stop(id))?;
+    Ok(format!("stopped {id}"))
+}
+
+fn send_input(workspace: &Path, args: &Value) -> Result<String, String> {
+    let mut stream = cli::connect(workspace)?;
+    let id = node_arg(&mut stream, args, "node")?;
+    let input = arg(args, "input")?;
+    let quiet = args
+        .get("quiet_ms")
+        .and_then(Value::as_u64)
+        .map_or(QUIET, Duration::from_millis)
+        .min(QUIET_MAX);
+
+    write_msg(&mut stream, &ClientMsg::Attach { node: id }).map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    match read_msg::<_, ServerMsg>(&mut reader).map_err(|e| e.to_string())? {
+        Some(ServerMsg::Attached { .. }) => {}
+        Some(ServerMsg::Error(e)) => return Err(e),
+        other => return Err(format!("unexpected reply: {other:?}")),
+    }
+    let bytes = stream.byte_encoding();
+    write_msg_as(
+        &mut stream,
+        &ClientMsg::Input(input.as_bytes().to_vec()),
+        bytes,
+    )
+    .map_err(|e| e.to_string())?;
+
+    // Read until the output has been quiet for `quiet` (a read times out), the
+    // node exits, or the overall cap — like a piped `wk attach`.
+    let _ = reader.get_mut().set_read_timeout(Some(quiet));
+    let deadline = Instant::now() + QUIET_MAX;
+    let mut out = Vec::new();
+    while Instant::now() < deadline {
+        match read_msg::<_, ServerMsg>(&mut reader) {
+            Ok(Some(ServerMsg::Term(bytes))) => out.extend_from_slice(&bytes),
+            Ok(Some(ServerMsg::Detached)) | Ok(None) | Err(_) => break,
+            Ok(Some(_)) => {}
+        }
+    }
+    // Hand the node back to the UI.
+    let _ = write_msg(&mut stream, &ClientMsg::Detach);
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+fn logs(workspace: &Path, args: &Value) -> Result<String, String> {
+    let mut stream = cli::connect(workspace)?;
+    stream.require("logs", "reading logs")?;
+    let id = node_arg(&mut stream, args, "node")?;
+    write_msg(
+        &mut stream,
+        &ClientMsg::Logs {
+            node: id,
+            follow: false,
+        },
+    )
+    .map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    let mut log = Vec::new();
+    loop {
+        match read_msg::<_, ServerMsg>(&mut reader).map_err(|e| e.to_string())? {
+            Some(ServerMsg::LogChunk(bytes)) => log.extend_from_slice(&bytes),
+            Some(ServerMsg::LogEnd) | None => break,
+            Some(ServerMsg::Error(e)) => return Err(e),
+            Some(_) => {}
+        }
+    }
+    let log = String::from_utf8_lossy(&log[log.len().saturating_sub(LOG_CAP)..]).into_owned();
+    Ok(match args.get("tail").and_then(Value::as_u64) {
+        Some(n) => {
+            let lines: Vec<&str> = log.lines().collect();
+            lines[lines.len().saturating_sub(n as usize)..].join("\n")
+        }
+        None => log,
+    })
+}
+
+fn token(workspace: &Path, args: &Value) -> Result<String, String> {
+    match args.get("node").and_then(Value::as_str) {
+        Some(node) => {
+            let (id, bytes) = cli::node_token(workspace, node)?;
+            Ok(format!("node {id}\n{}", cli::token_blocks(&bytes)?))
+        }
+        None => {
+            let hex = cli::token().ok_or("this bridge bears no token")?;
+            let bytes = wk_server::workspace::hex_bytes(hex.trim())
+                .filter(|b| !b.is_empty())
+                .ok_or("the bridge's token is not hex")?;
+            Ok(format!(
+                "this bridge's token\n{}",
+                cli::token_blocks(&bytes)?
+            ))
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! `wk stats` — each app node's live resource use, refreshed in place the way
+//! `docker stats` shows containers': the wasm memory its guest holds, what its
+//! filesystem holds, its fabric traffic and open sockets, the MIDI it has
+//! sent, the frames it has drawn, and the `wk:exec` programs running in it.
+//!
+//! Memory and filesystem bytes are what the node holds now; traffic, MIDI and
+//! frames count up from when the server started it.
+
+use std::io::{BufReader, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use wk_protocol::ipc::{read_msg, write_msg, ClientMsg, NodeStats, ServerMsg};
+
+use crate::cli::{self, Conn};
+
+/// How often the table redraws.
+const REFRESH: Duration = Duration::from_secs(1);
+
+/// `wk stats`: print the table, then (unless `once`) redraw it every second
+/// until Ctrl-C.
+pub fn stats(workspace: &Path, once: bool) -> Result<(), String> {
+    let mut stream = cli::connect(workspace)?;
+    stream.require("stats", "`wk stats`")?;
+    loop {
+        let rows = fetch(&mut stream)?;
+        let mut out = std::io::stdout().lock();
+        if !once {
+            // Home the cursor and clear, so each refresh replaces the last.
+            let _ = write!(out, "\x1b[H\x1b[2J");
+        }
+        let _ = out.write_all(table(&rows).as_bytes());
+        let _ = out.flush();
+        if once {
+            return Ok(());
+        }
+        std::thread::sleep(REFRESH);
+    }
+}
+
+/// Ask for one round of stats.
+fn fetch(stream: &mut Conn) -> Result<Vec<NodeStats>, String> {
+    write_msg(stream, &ClientMsg::Stats).map_err(|e| e.to_string())?;
+    let mut r = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    match read_msg::<_, ServerMsg>(&mut r).map_err(|e| e.to_string())? {
+        Some(ServerMsg::Stats(rows)) => Ok(rows),
+        Some(ServerMsg::Error(e)) => Err(e),
+        other => Err(format!("unexpected reply: {other:?}")),
+    }
+}
+
+/// The table, one row per node, docker-stats-like. A node without
+/// networking shows `-` in the network columns.
+fn table(rows: &[NodeStats]) -> String {
+    let mut s = format!(
+        "{:<12}  {:<16}  {:>9}  {:>7}  {:>9}  {:>19}  {:>15}  {:>5}  {:>7}  {:>8}  {:>4}\n",
+        "ID", "NAME", "MEM", "FILES", "FS", "NET I/O", "PACKETS", "SOCKS", "MIDI", "FRAMES", "EXEC"
+    );
+    if rows.is_empty() {
+        s.push_str("(no app nodes)\n");
+    }
+    for n in rows {
+        let name = if n.name.is_empty() { "-" } else { &n.name };
+        let (io, packets, sockets) = match &n.net {
+            Some(net) => (
+                format!("{} / {}", size(net.rx_bytes), size(net.tx_bytes)),
+                format!("{} / {}", net.rx_packets, net.tx_packets),
+                net.sockets.to_string(),
+            ),
+            None => ("-".into(), "-".into(), "-".into()),
+        };
+        s.push_str(&format!(
+            "{:<12}  {:<16}  {:>9}  {:>7}  {:>9}  {:>19}  {:>15}  {:>5}  {:>7}  {:>8}  {:>4}\n",
+            cli::short(n.id),
+            name,
+            size(n.memory),
+            n.fs_nodes,
+            size(n.fs_bytes),
+            io,
+            packets,
+            sockets,
+            n.midi_sent,
+            n.frames,
+            n.exec_children
+        ));
+    }
+    s
+}
+
+/// A byte count in binary units, one decimal above a KiB: `512B`, `1.5KiB`,
+/// `64.0MiB`.
+pub(crate) fn size(n: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if n < 1024 {
+        return format!("{n}B");
+    }
+    let mut v = n as f64 / 1024.0;
+    let mut unit = 0;
+    while v >= 1024.0 && unit < UNITS.len() - 1 {
+        v /= 1024.0;
+        unit += 1;
+    }
+    format!("{v:.1}{}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_read_in_binary_units() {
+        assert_eq!(size(0), "0B");
+        assert_eq!(size(1023), "1023B");
+        assert_eq!(size(1536), "1.5KiB");
+        assert_eq!(size(64 << 20), "64.0MiB");
+        assert_eq!(size(3 << 30), "3.0GiB");
+    }
+
+    /// A node without a fabric stack dashes its network columns rather than
+    /// reporting zero traffic it could never have had.
+    #[test]
+    fn table_dashes_network_for_offline_nodes() {
+        let rows = vec![NodeStats {
+            name: "synth".into(),
+            memory: 2 << 20,
+            frames: 60,
+            ..NodeStats::default()
+        }];
+        let t = table(&rows);
+        let row = t.lines().nth(1).unwrap();
+        assert!(row.contains("synth") && row.contains("2.0MiB"), "{row}");
+        assert!(row.contains(" - "), "{row}");
+    }
+}
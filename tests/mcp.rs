@@ -0,0 +1,165 @@
+//! End-to-end for the MCP bridge: a server with its CLI socket in this
+//! process, and a `wk --token <hex> mcp` process spoken to over stdio the way
+//! an agent's host would — with what it may do decided by that token.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use serde_json::{json, Value};
+use wk_api::ipc::IpcServer;
+use wk_protocol::{Action, ResourceKind};
+use wk_server::runtime::ServerRuntime;
+use wk_server::workspace::{bytes_hex, Document};
+use wk_token_service::TokenService;
+
+/// A running `wk mcp` and its stdio.
+struct Bridge {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next: u64,
+}
+
+impl Bridge {
+    fn start(file: &std::path::Path, token: &str) -> Bridge {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_wk"))
+            .arg("--file")
+            .arg(file)
+            .args(["--token", token, "mcp"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("run wk mcp");
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Bridge {
+            child,
+            stdin,
+            stdout,
+            next: 1,
+        }
+    }
+
+    /// Send a JSON-RPC request and read its reply.
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next;
+        self.next += 1;
+        let msg = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        writeln!(self.stdin, "{msg}").unwrap();
+        let mut line = String::new();
+        self.stdout.read_line(&mut line).unwrap();
+        let reply: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(reply["id"], id);
+        reply
+    }
+
+    /// Call a tool: its text, and whether it failed.
+    fn call(&mut self, tool: &str, arguments: Value) -> (String, bool) {
+        let reply = self.request(
+            "tools/call",
+            json!({ "name": tool, "arguments": arguments }),
+        );
+        let result = &reply["result"];
+        (
+            result["content"][0]["text"].as_str().unwrap().to_string(),
+            result["isError"].as_bool().unwrap(),
+        )
+    }
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn wk_mcp_drives_the_workspace_with_its_token() {
+    let dir = std::env::temp_dir().join(format!("wk-mcp-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("workspace.wk");
+    std::fs::write(
+        &path,
+        "// vim: set filetype=kdl :\nworkspace \"01KXKGZ000000000000000WS00\" {\n}\n",
+    )
+    .unwrap();
+
+    let doc = Document::load_resolved(&path).unwrap();
+    let tokens = TokenService::new();
+    let runtime = ServerRuntime::spawn(
+        &doc,
+        path.clone(),
+        tokens.public_key(),
+        tokens.mint_node_base().unwrap(),
+    )
+    .unwrap();
+    let ipc = IpcServer::start(
+        runtime.handle().with_token(tokens.mint_admin().unwrap()),
+        &path,
+    )
+    .unwrap();
+
+    // A token that may read the document and create nodes, nothing more.
+    let token = tokens
+        .mint(&[
+            (ResourceKind::Document, Action::Read),
+            (ResourceKind::Node, Action::Create),
+        ])
+        .unwrap();
+    let mut bridge = Bridge::start(&path, &bytes_hex(&token));
+
+    let init = bridge.request("initialize", json!({ "protocolVersion": "2025-06-18" }));
+    assert_eq!(init["result"]["protocolVersion"], "2025-06-18");
+    let tools = bridge.request("tools/list", json!({}));
+    let names: Vec<&str> = tools["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap())
+        .collect();
+    for tool in [
+        "snapshot",
+        "create",
+        "wire",
+        "run",
+        "stop",
+        "send_input",
+        "logs",
+        "token",
+    ] {
+        assert!(names.contains(&tool), "{tool} missing from {names:?}");
+    }
+
+    let (text, failed) = bridge.call("create", json!({ "kind": "note" }));
+    assert!(!failed, "{text}");
+    let (text, failed) = bridge.call("snapshot", json!({}));
+    assert!(!failed, "{text}");
+    let snap: Value = serde_json::from_str(&text).unwrap();
+    let note = snap["nodes"][0]["id"].as_str().unwrap().to_string();
+    assert_eq!(snap["nodes"][0]["kind"], "note");
+
+    // Stopping a node takes a right the token doesn't carry.
+    let (text, failed) = bridge.call("stop", json!({ "node": note }));
+    assert!(failed);
+    assert!(text.contains("does not grant"), "{text}");
+
+    let (text, failed) = bridge.call("token", json!({}));
+    assert!(!failed, "{text}");
+    assert!(text.contains(r#"right("node", "create")"#), "{text}");
+
+    drop(bridge);
+    // Without a token there is no bridge.
+    let out = Command::new(env!("CARGO_BIN_EXE_wk"))
+        .arg("--file")
+        .arg(&path)
+        .arg("mcp")
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+
+    ipc.shutdown();
+    runtime.shutdown();
+    let _ = std::fs::remove_dir_all(&dir);
+}
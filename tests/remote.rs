@@ -0,0 +1,87 @@
+//! End-to-end over the network: a server listening on TCP + TLS in this
+//! process, driven by a separate `wk --remote` process — the "headless build
+//! box, laptop client" setup, on loopback.
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use wk_api::tcp::{ensure_self_signed, server_config, TcpServer};
+use wk_server::runtime::ServerRuntime;
+use wk_server::workspace::{bytes_hex, Document};
+use wk_token_service::TokenService;
+
+/// Run the `wk` binary against `addr` with `token`, verifying with `ca`.
+fn wk(addr: &str, token: &str, ca: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_wk"))
+        .args(["--remote", addr, "--token", token, "--ca"])
+        .arg(ca)
+        .args(args)
+        .output()
+        .expect("run wk")
+}
+
+#[test]
+fn wk_remote_drives_a_listening_server() {
+    let dir = std::env::temp_dir().join(format!("wk-remote-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("workspace.wk");
+    std::fs::write(
+        &path,
+        "// vim: set filetype=kdl :\nworkspace \"01KXKGZ000000000000000WS00\" {\n}\n",
+    )
+    .unwrap();
+
+    let doc = Document::load_resolved(&path).unwrap();
+    let tokens = TokenService::new();
+    let runtime = ServerRuntime::spawn(
+        &doc,
+        path.clone(),
+        tokens.public_key(),
+        tokens.mint_node_base().unwrap(),
+    )
+    .unwrap();
+    let (cert, key) = (dir.join("cert.pem"), dir.join("cert.key"));
+    ensure_self_signed(&cert, &key, vec!["localhost".into()]).unwrap();
+    let server = TcpServer::start(
+        runtime.handle(),
+        "127.0.0.1:0",
+        server_config(&cert, &key).unwrap(),
+    )
+    .unwrap();
+    let addr = format!("localhost:{}", server.local_addr().port());
+    let admin = bytes_hex(&tokens.mint_admin().unwrap());
+
+    // Create a node from the other process, then list it.
+    let out = wk(&addr, &admin, &cert, &["create", "note", "from afar"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let out = wk(&addr, &admin, &cert, &["ps"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(String::from_utf8_lossy(&out.stdout).contains("note"));
+
+    // The server saw it.
+    let snap = runtime
+        .handle()
+        .with_token(tokens.mint_admin().unwrap())
+        .snapshot()
+        .unwrap();
+    assert_eq!(snap.nodes.len(), 1);
+
+    // A token from another key is turned away at the handshake.
+    let stranger = bytes_hex(&TokenService::new().mint_admin().unwrap());
+    let out = wk(&addr, &stranger, &cert, &["ps"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("refused the token"));
+
+    server.shutdown();
+    runtime.shutdown();
+    let _ = std::fs::remove_dir_all(&dir);
+}